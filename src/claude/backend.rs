@@ -0,0 +1,130 @@
+//! Agent backends
+//!
+//! The loop engine talks to the coding agent through [`AgentBackend`]: a
+//! backend takes a prompt and a [`ClaudeConfig`] and yields a stream of
+//! [`ClaudeEvent`]s plus the [`ExecutionUsage`] of the run. [`ClaudeRunner`]
+//! runs the local `claude` CLI, `SandboxRunner` runs it inside a Docker
+//! container and [`ReplayBackend`](super::ReplayBackend) plays back recorded
+//! stream-json output for offline tests.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+
+use super::runner::{ClaudeConfig, ClaudeEvent, ClaudeRunner, ExecutionUsage};
+
+/// Future that resolves when a run should be stopped
+pub type CancelSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Event stream and usage handle of a running agent
+pub type AgentExecution = (
+    mpsc::Receiver<ClaudeEvent>,
+    tokio::task::JoinHandle<Result<ExecutionUsage>>,
+);
+
+/// A single agent invocation
+#[derive(Debug, Clone)]
+pub struct AgentRequest {
+    /// Prompt for this run
+    pub prompt: String,
+    /// Model, tools and permission settings for this run
+    pub config: ClaudeConfig,
+    /// Task ID used for logging
+    pub task_id: Option<String>,
+    /// File to record the raw stream-json output to
+    pub transcript: Option<PathBuf>,
+}
+
+impl AgentRequest {
+    pub fn new(prompt: impl Into<String>, config: ClaudeConfig) -> Self {
+        Self {
+            prompt: prompt.into(),
+            config,
+            task_id: None,
+            transcript: None,
+        }
+    }
+
+    pub fn with_task_id(mut self, task_id: String) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    pub fn with_transcript(mut self, path: PathBuf) -> Self {
+        self.transcript = Some(path);
+        self
+    }
+}
+
+/// Backend that executes agent runs
+#[async_trait::async_trait]
+pub trait AgentBackend: Send + Sync {
+    /// Start a run, stopping it early when `cancel` resolves
+    ///
+    /// Usage collected before cancellation is still returned.
+    async fn execute(&self, request: AgentRequest, cancel: CancelSignal) -> Result<AgentExecution>;
+
+    /// Get backend name for logging
+    fn name(&self) -> &str;
+}
+
+/// Agent CLI binary and extra arguments (`[agent]` in doodoori.toml)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AgentCommand {
+    /// Path or name of the Claude Code binary
+    pub binary: PathBuf,
+    /// Extra arguments passed before the prompt
+    pub extra_args: Vec<String>,
+}
+
+impl Default for AgentCommand {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("claude"),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentBackend for ClaudeRunner {
+    async fn execute(&self, request: AgentRequest, cancel: CancelSignal) -> Result<AgentExecution> {
+        let mut runner = self.clone().with_config(request.config);
+        if let Some(task_id) = request.task_id {
+            runner = runner.with_task_id(task_id);
+        }
+        if let Some(path) = request.transcript {
+            runner = runner.with_transcript(path);
+        }
+        runner.execute_with_cancel(&request.prompt, cancel).await
+    }
+
+    fn name(&self) -> &str {
+        "claude"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_command_from_toml() {
+        let command: AgentCommand = toml::from_str(
+            r#"
+binary = "/opt/claude/bin/claude"
+extra_args = ["--add-dir", "../shared"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(command.binary, PathBuf::from("/opt/claude/bin/claude"));
+        assert_eq!(command.extra_args, vec!["--add-dir", "../shared"]);
+
+        let command: AgentCommand = toml::from_str("").unwrap();
+        assert_eq!(command, AgentCommand::default());
+    }
+}
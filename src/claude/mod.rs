@@ -1,9 +1,23 @@
+mod backend;
 mod models;
+mod replay;
 mod runner;
+mod tools;
+mod transcript;
 
+#[allow(unused_imports)]
+pub use backend::{AgentBackend, AgentCommand, AgentExecution, AgentRequest, CancelSignal};
 pub use models::ModelAlias;
 #[allow(unused_imports)]
+pub use replay::ReplayBackend;
+#[allow(unused_imports)]
 pub use runner::{
-    AssistantEvent, ClaudeConfig, ClaudeEvent, ClaudeRunner, ExecutionUsage, ResultEvent,
-    SystemEvent, ToolResultEvent, ToolUseEvent, UsageStats,
+    AssistantEvent, AssistantMessage, ClaudeConfig, ClaudeEvent, ClaudeRunner, ContentBlock,
+    ExecutionUsage, MessageUsage, ResultEvent, SystemEvent, ToolResultContent, ToolResultEvent,
+    ToolUseEvent, UsageStats, UserEvent, kill_running_agents,
 };
+#[allow(unused_imports)]
+pub use tools::{format_tool_stats, ToolActivity, ToolCall, ToolCallResult, ToolStats, ToolTracker};
+#[allow(unused_imports)]
+pub use transcript::{read_transcript, TranscriptLine, TranscriptStore, TranscriptWriter, TRANSCRIPTS_DIR};
+pub(crate) use runner::stream_child;
@@ -23,6 +23,24 @@ impl ModelAlias {
             ModelAlias::Opus => "claude-opus-4-5-20251101",
         }
     }
+
+    /// Relative size of the model (haiku < sonnet < opus)
+    pub fn size(&self) -> u8 {
+        match self {
+            ModelAlias::Haiku => 0,
+            ModelAlias::Sonnet => 1,
+            ModelAlias::Opus => 2,
+        }
+    }
+
+    /// Next larger model (haiku -> sonnet -> opus), None for opus
+    pub fn escalate(&self) -> Option<ModelAlias> {
+        match self {
+            ModelAlias::Haiku => Some(ModelAlias::Sonnet),
+            ModelAlias::Sonnet => Some(ModelAlias::Opus),
+            ModelAlias::Opus => None,
+        }
+    }
 }
 
 impl fmt::Display for ModelAlias {
@@ -71,6 +89,13 @@ mod tests {
         assert_eq!(ModelAlias::Opus.to_string(), "opus");
     }
 
+    #[test]
+    fn test_model_escalate() {
+        assert_eq!(ModelAlias::Haiku.escalate(), Some(ModelAlias::Sonnet));
+        assert_eq!(ModelAlias::Sonnet.escalate(), Some(ModelAlias::Opus));
+        assert_eq!(ModelAlias::Opus.escalate(), None);
+    }
+
     #[test]
     fn test_default_model() {
         assert_eq!(ModelAlias::default(), ModelAlias::Sonnet);
@@ -0,0 +1,235 @@
+//! Scripted replay backend
+//!
+//! Plays back recorded `--output-format stream-json` output instead of
+//! running Claude Code, so the loop, parallel and workflow paths can be
+//! exercised offline.
+
+#![allow(dead_code)]
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+use super::backend::{AgentBackend, AgentExecution, AgentRequest, CancelSignal};
+use super::runner::{ClaudeEvent, ExecutionUsage};
+use super::transcript::read_transcript;
+
+/// Backend that replays recorded stream-json scripts, one per run
+///
+/// Scripts are played in order; once all have been played the last one is
+/// repeated.
+#[derive(Debug, Default)]
+pub struct ReplayBackend {
+    scripts: Vec<String>,
+    next: AtomicUsize,
+    requests: Mutex<Vec<AgentRequest>>,
+}
+
+impl ReplayBackend {
+    /// Create a backend from stream-json script contents
+    pub fn new(scripts: Vec<String>) -> Self {
+        Self {
+            scripts,
+            ..Default::default()
+        }
+    }
+
+    /// Load scripts from recorded stream-json files
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self> {
+        let scripts = paths
+            .iter()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read replay script: {}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(scripts))
+    }
+
+    /// Load scripts from recorded transcripts (see [`TranscriptStore`](super::TranscriptStore))
+    pub fn from_transcripts(paths: &[PathBuf]) -> Result<Self> {
+        let scripts = paths
+            .iter()
+            .map(|path| {
+                let lines = read_transcript(path)?;
+                Ok(lines.into_iter().map(|l| l.line).collect::<Vec<_>>().join("\n"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(scripts))
+    }
+
+    /// Load all `*.jsonl` scripts in a directory, in file name order
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read replay directory: {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        paths.sort();
+        Self::from_files(&paths)
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> Vec<AgentRequest> {
+        self.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Parse a stream-json script into events, skipping unparseable lines
+    fn parse_script(script: &str) -> Vec<ClaudeEvent> {
+        script
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    tracing::warn!("Failed to parse replay event: {} - line: {}", e, line);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentBackend for ReplayBackend {
+    async fn execute(&self, request: AgentRequest, mut cancel: CancelSignal) -> Result<AgentExecution> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        let script = self
+            .scripts
+            .get(index)
+            .or(self.scripts.last())
+            .context("Replay backend has no scripts")?;
+        let events = Self::parse_script(script);
+        let mut meter = request.config.cost_meter();
+
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.push(request);
+        }
+
+        // Capacity 1 keeps playback in step with the consumer, so a cancel
+        // stops the run part-way like killing the process would
+        let (tx, rx) = mpsc::channel(1);
+        let handle = tokio::spawn(async move {
+            let mut usage = ExecutionUsage::default();
+            for event in events {
+                let permit = tokio::select! {
+                    biased;
+                    _ = &mut cancel => break,
+                    permit = tx.reserve() => match permit {
+                        Ok(permit) => permit,
+                        Err(_) => break,
+                    },
+                };
+                usage.record_event(&event);
+                meter.record_event(&event);
+                permit.send(event);
+            }
+            Ok(meter.settle(usage))
+        });
+
+        Ok((rx, handle))
+    }
+
+    fn name(&self) -> &str {
+        "replay"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::ClaudeConfig;
+
+    const SCRIPT: &str = r#"{"type":"system","subtype":"init","session_id":"sess-1"}
+{"type":"assistant","message":"Working on it"}
+
+{"type":"result","subtype":"success","total_cost_usd":0.25,"duration_ms":1200,"usage":{"input_tokens":100,"output_tokens":40}}
+"#;
+
+    async fn collect(backend: &ReplayBackend, prompt: &str) -> (Vec<ClaudeEvent>, ExecutionUsage) {
+        let request = AgentRequest::new(prompt, ClaudeConfig::default());
+        let (mut rx, handle) = backend
+            .execute(request, Box::pin(std::future::pending()))
+            .await
+            .unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        (events, handle.await.unwrap().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_replay_script() {
+        let backend = ReplayBackend::new(vec![SCRIPT.to_string()]);
+        let (events, usage) = collect(&backend, "Do it").await;
+
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], ClaudeEvent::System(_)));
+        assert_eq!(usage.total_cost_usd, 0.25);
+        assert_eq!(usage.reported_cost_usd, Some(0.25));
+        assert!(usage.calculated_cost_usd > 0.0);
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.duration_ms, 1200);
+        assert_eq!(backend.requests()[0].prompt, "Do it");
+    }
+
+    #[tokio::test]
+    async fn test_replay_calculates_unreported_cost() {
+        let script = r#"{"type":"assistant","message":{"id":"msg_1","usage":{"input_tokens":100000,"output_tokens":10000}}}
+{"type":"result","subtype":"success","usage":{"input_tokens":100000,"output_tokens":10000}}"#;
+        let backend = ReplayBackend::new(vec![script.to_string()]);
+        let (_, usage) = collect(&backend, "Do it").await;
+
+        // Sonnet: $0.30 input + $0.15 output
+        assert_eq!(usage.reported_cost_usd, None);
+        assert!((usage.total_cost_usd - 0.45).abs() < 1e-9);
+        assert_eq!(usage.total_cost_usd, usage.calculated_cost_usd);
+    }
+
+    #[tokio::test]
+    async fn test_replay_repeats_last_script() {
+        let first = r#"{"type":"assistant","message":"first"}"#.to_string();
+        let second = r#"{"type":"assistant","message":"second"}"#.to_string();
+        let backend = ReplayBackend::new(vec![first, second]);
+
+        for expected in ["first", "second", "second"] {
+            let (events, _) = collect(&backend, "prompt").await;
+            match &events[0] {
+                ClaudeEvent::Assistant(asst) => {
+                    assert_eq!(asst.message.as_ref().unwrap().as_text(), expected)
+                }
+                _ => panic!("Expected Assistant event"),
+            }
+        }
+        assert_eq!(backend.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_dir_and_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("01.jsonl"), SCRIPT).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let backend = ReplayBackend::from_dir(dir.path()).unwrap();
+        let (events, _) = collect(&backend, "prompt").await;
+        assert_eq!(events.len(), 3);
+
+        let path = dir.path().join("iteration-001.jsonl.gz");
+        let mut writer = crate::claude::TranscriptWriter::create(&path).unwrap();
+        for line in SCRIPT.lines() {
+            writer.write_line(line).unwrap();
+        }
+        writer.finish().unwrap();
+        let backend = ReplayBackend::from_transcripts(&[path]).unwrap();
+        let (events, usage) = collect(&backend, "prompt").await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(usage.total_cost_usd, 0.25);
+
+        let empty = ReplayBackend::default();
+        let request = AgentRequest::new("prompt", ClaudeConfig::default());
+        assert!(empty.execute(request, Box::pin(std::future::pending())).await.is_err());
+    }
+}
@@ -3,13 +3,18 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Mutex;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 use tokio::sync::mpsc;
 
+use super::backend::{AgentCommand, AgentExecution};
+use super::tools::{ToolActivity, ToolTracker};
+use super::transcript::TranscriptWriter;
 use super::ModelAlias;
+use crate::pricing::{CostCalculator, CostMeter};
 
 /// Configuration for Claude Code execution
 #[allow(dead_code)]
@@ -29,6 +34,12 @@ pub struct ClaudeConfig {
     pub system_prompt: Option<PathBuf>,
     /// Read-only mode
     pub readonly: bool,
+    /// Session ID of an existing conversation to resume (`--resume`)
+    pub resume_session: Option<String>,
+    /// Binary and extra arguments used to invoke Claude Code
+    pub command: AgentCommand,
+    /// price.toml used to calculate costs from tokens (embedded prices if None)
+    pub price_file: Option<PathBuf>,
 }
 
 impl Default for ClaudeConfig {
@@ -41,18 +52,30 @@ impl Default for ClaudeConfig {
             yolo_mode: false,
             system_prompt: None,
             readonly: false,
+            resume_session: None,
+            command: AgentCommand::default(),
+            price_file: None,
         }
     }
 }
 
+impl ClaudeConfig {
+    /// Meter that prices this run's tokens
+    pub fn cost_meter(&self) -> CostMeter {
+        CostMeter::with_calculator(self.model.clone(), CostCalculator::from_price_file(self.price_file.as_deref()))
+    }
+}
+
 /// Events emitted during Claude execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClaudeEvent {
     /// System initialization
     System(SystemEvent),
-    /// Assistant message (text content)
+    /// Assistant message (text, thinking and tool use blocks)
     Assistant(AssistantEvent),
+    /// User message (tool result blocks)
+    User(UserEvent),
     /// Tool use event
     ToolUse(ToolUseEvent),
     /// Tool result event
@@ -82,6 +105,15 @@ pub struct AssistantEvent {
     pub session_id: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEvent {
+    /// Message in the same shape as an assistant message
+    #[serde(default)]
+    pub message: Option<AssistantMessage>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
 /// Assistant message - can be a simple string or complex object
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -97,24 +129,39 @@ impl AssistantMessage {
     pub fn as_text(&self) -> String {
         match self {
             AssistantMessage::Text(s) => s.clone(),
-            AssistantMessage::Object(obj) => {
-                // Extract text from content blocks
-                if let Some(content) = &obj.content {
-                    content
-                        .iter()
-                        .filter_map(|block| match block {
-                            ContentBlock::Text { text } => Some(text.as_str()),
-                            ContentBlock::ToolUse { name, .. } => Some(name.as_str()),
-                            ContentBlock::Unknown => None,
-                        })
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                } else {
-                    String::new()
-                }
-            }
+            AssistantMessage::Object(_) => self
+                .blocks()
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    ContentBlock::ToolUse { name, .. } => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
         }
     }
+
+    /// Content blocks of the message (empty for plain text messages)
+    pub fn blocks(&self) -> &[ContentBlock] {
+        match self {
+            AssistantMessage::Object(obj) => obj.content.as_deref().unwrap_or_default(),
+            AssistantMessage::Text(_) => &[],
+        }
+    }
+
+    /// Extract thinking content from the message
+    pub fn thinking(&self) -> Option<String> {
+        let thinking: Vec<&str> = self
+            .blocks()
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+                _ => None,
+            })
+            .collect();
+        (!thinking.is_empty()).then(|| thinking.join("\n"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,16 +186,54 @@ pub enum ContentBlock {
     Text {
         text: String,
     },
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: Option<String>,
+    },
     ToolUse {
         id: String,
         name: String,
+        #[serde(default)]
         input: serde_json::Value,
     },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Option<ToolResultContent>,
+        #[serde(default)]
+        is_error: bool,
+    },
     #[serde(other)]
     Unknown,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Content of a `tool_result` block - a string or a list of content blocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl ToolResultContent {
+    /// Extract text content from the result
+    pub fn as_text(&self) -> String {
+        match self {
+            ToolResultContent::Text(s) => s.clone(),
+            ToolResultContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct MessageUsage {
     #[serde(default)]
     pub input_tokens: u64,
@@ -158,6 +243,29 @@ pub struct MessageUsage {
     pub cache_creation_input_tokens: u64,
     #[serde(default)]
     pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation: Option<CacheCreation>,
+}
+
+impl MessageUsage {
+    /// Tokens sent with the request (input plus cache reads and writes)
+    pub fn prompt_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// Cache writes to the 1-hour cache
+    pub fn cache_creation_1h_tokens(&self) -> u64 {
+        self.cache_creation.map(|c| c.ephemeral_1h_input_tokens).unwrap_or(0)
+    }
+}
+
+/// Cache writes split by cache lifetime
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct CacheCreation {
+    #[serde(default)]
+    pub ephemeral_5m_input_tokens: u64,
+    #[serde(default)]
+    pub ephemeral_1h_input_tokens: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -204,6 +312,8 @@ pub struct UsageStats {
     pub cache_creation_input_tokens: u64,
     #[serde(default)]
     pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation: Option<CacheCreation>,
 }
 
 /// Accumulated usage from a Claude execution
@@ -213,8 +323,15 @@ pub struct ExecutionUsage {
     pub input_tokens: u64,
     pub output_tokens: u64,
     pub cache_creation_tokens: u64,
+    /// Part of `cache_creation_tokens` written to the 1-hour cache
+    pub cache_creation_1h_tokens: u64,
     pub cache_read_tokens: u64,
+    /// Cost to record: the reported cost, or the calculated one if none was reported
     pub total_cost_usd: f64,
+    /// Cost reported by the CLI's `result` event
+    pub reported_cost_usd: Option<f64>,
+    /// Cost calculated from token usage with price.toml
+    pub calculated_cost_usd: f64,
     pub duration_ms: u64,
 }
 
@@ -223,18 +340,74 @@ impl ExecutionUsage {
         self.input_tokens += other.input_tokens;
         self.output_tokens += other.output_tokens;
         self.cache_creation_tokens += other.cache_creation_input_tokens;
+        self.cache_creation_1h_tokens += other.cache_creation.map(|c| c.ephemeral_1h_input_tokens).unwrap_or(0);
         self.cache_read_tokens += other.cache_read_input_tokens;
     }
+
+    /// Add the usage of another run (e.g. an iteration to a task total)
+    pub fn accumulate(&mut self, other: &ExecutionUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_creation_1h_tokens += other.cache_creation_1h_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.total_cost_usd += other.total_cost_usd;
+        self.reported_cost_usd = match (self.reported_cost_usd, other.reported_cost_usd) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        };
+        self.calculated_cost_usd += other.calculated_cost_usd;
+        self.duration_ms += other.duration_ms;
+    }
+
+    /// Record the cost calculated from tokens, using it as the total if
+    /// the CLI reported none
+    pub fn set_calculated_cost(&mut self, cost: f64) {
+        self.calculated_cost_usd = cost;
+        self.total_cost_usd = self.reported_cost_usd.unwrap_or(cost);
+    }
+
+    /// Extract usage information from a result event
+    pub fn record_event(&mut self, event: &ClaudeEvent) {
+        if let ClaudeEvent::Result(result) = event {
+            if let Some(ref stats) = result.usage {
+                self.add(stats);
+            }
+            if let Some(cost) = result.total_cost_usd {
+                self.total_cost_usd = cost;
+                self.reported_cost_usd = Some(cost);
+            }
+            if let Some(duration) = result.duration_ms {
+                self.duration_ms = duration;
+            }
+        }
+    }
+}
+
+/// Log file of a task: `<log_dir>/<task-id>.log` with a log directory
+/// override, otherwise `.doodoori/tasks/<task-id>/task.log`
+fn task_log_path(log_dir: Option<&Path>, task_id: &str) -> Result<PathBuf> {
+    let path = match log_dir {
+        Some(dir) => dir.join(format!("{}.log", task_id)),
+        None => crate::state::task_dir(Path::new("."), task_id).join("task.log"),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
 }
 
 /// Claude Code CLI runner
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct ClaudeRunner {
     config: ClaudeConfig,
     /// Optional task ID for logging
     task_id: Option<String>,
     /// Optional log directory override (for testing)
     log_dir: Option<PathBuf>,
+    /// Optional file to record the raw stream-json output to
+    transcript: Option<PathBuf>,
 }
 
 #[allow(dead_code)]
@@ -244,15 +417,28 @@ impl ClaudeRunner {
             config,
             task_id: None,
             log_dir: None,
+            transcript: None,
         }
     }
 
+    /// Replace the execution configuration
+    pub fn with_config(mut self, config: ClaudeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Set task ID for logging
     pub fn with_task_id(mut self, task_id: String) -> Self {
         self.task_id = Some(task_id);
         self
     }
 
+    /// Record the raw stream-json output to a transcript file
+    pub fn with_transcript(mut self, path: PathBuf) -> Self {
+        self.transcript = Some(path);
+        self
+    }
+
     /// Set custom log directory (mainly for testing)
     pub fn with_log_dir(mut self, log_dir: PathBuf) -> Self {
         self.log_dir = Some(log_dir);
@@ -289,15 +475,16 @@ impl ClaudeRunner {
         self
     }
 
+    /// Resume an existing Claude conversation instead of starting a new one
+    pub fn with_resume_session(mut self, session_id: String) -> Self {
+        self.config.resume_session = Some(session_id);
+        self
+    }
+
     /// Write a log line to the task log file
     fn write_to_log(&self, level: &str, message: &str) -> Result<()> {
         if let Some(ref task_id) = self.task_id {
-            let log_dir = self
-                .log_dir
-                .clone()
-                .unwrap_or_else(|| PathBuf::from(".doodoori/logs"));
-            fs::create_dir_all(&log_dir)?;
-            let log_path = log_dir.join(format!("{}.log", task_id));
+            let log_path = task_log_path(self.log_dir.as_deref(), task_id)?;
 
             let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
             let line = format!("[{}] [{}] {}", timestamp, level, message);
@@ -312,7 +499,7 @@ impl ClaudeRunner {
     }
 
     /// Build the command arguments for Claude Code
-    fn build_args(&self, prompt: &str) -> Vec<String> {
+    pub(crate) fn build_args(&self, prompt: &str) -> Vec<String> {
         // Build arguments for Claude Code CLI
         // Use alias (haiku, sonnet, opus) instead of full model ID
         // Claude Code CLI resolves aliases to the best available model internally
@@ -350,6 +537,13 @@ impl ClaudeRunner {
             args.push(system_prompt.to_string_lossy().to_string());
         }
 
+        if let Some(ref session_id) = self.config.resume_session {
+            args.push("--resume".to_string());
+            args.push(session_id.clone());
+        }
+
+        args.extend(self.config.command.extra_args.iter().cloned());
+
         // The prompt must be the last argument (use -p, not --prompt)
         args.push("-p".to_string());
         args.push(prompt.to_string());
@@ -358,20 +552,25 @@ impl ClaudeRunner {
     }
 
     /// Execute Claude Code with the given prompt, streaming events
-    pub async fn execute(
+    pub async fn execute(&self, prompt: &str) -> Result<AgentExecution> {
+        self.execute_with_cancel(prompt, std::future::pending()).await
+    }
+
+    /// Execute Claude Code, killing the process when `cancel` resolves
+    ///
+    /// Usage collected before cancellation is still returned.
+    pub async fn execute_with_cancel(
         &self,
         prompt: &str,
-    ) -> Result<(
-        mpsc::Receiver<ClaudeEvent>,
-        tokio::task::JoinHandle<Result<ExecutionUsage>>,
-    )> {
+        cancel: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> Result<AgentExecution> {
         let args = self.build_args(prompt);
-        tracing::debug!("Executing claude with args: {:?}", args);
+        tracing::debug!("Executing {} with args: {:?}", self.config.command.binary.display(), args);
 
         // Log start
         self.write_to_log("INFO", "Starting task...")?;
 
-        let mut cmd = Command::new("claude");
+        let mut cmd = Command::new(&self.config.command.binary);
         cmd.args(&args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -381,128 +580,240 @@ impl ClaudeRunner {
             cmd.current_dir(dir);
         }
 
-        let mut child = cmd.spawn().context("Failed to spawn claude command")?;
-
-        let stdout = child.stdout.take().context("Failed to capture stdout")?;
-        let (tx, rx) = mpsc::channel(100);
-
-        // Clone task_id and log_dir for the async task
-        let task_id = self.task_id.clone();
-        let log_dir = self.log_dir.clone();
-
-        let handle = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            let mut usage = ExecutionUsage::default();
-
-            // Helper to write logs from async context
-            let write_log = |level: &str, message: &str| -> Result<()> {
-                if let Some(ref tid) = task_id {
-                    let ld = log_dir
-                        .clone()
-                        .unwrap_or_else(|| PathBuf::from(".doodoori/logs"));
-                    fs::create_dir_all(&ld)?;
-                    let log_path = ld.join(format!("{}.log", tid));
-
-                    let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
-                    let line = format!("[{}] [{}] {}", timestamp, level, message);
-
-                    let mut file = OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(log_path)?;
-                    writeln!(file, "{}", line)?;
-                }
-                Ok(())
-            };
+        // Run in its own process group so a terminal Ctrl+C reaches doodoori
+        // only; the loop decides whether to let the iteration finish or kill it
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to spawn {} command", self.config.command.binary.display()))?;
+
+        stream_child(
+            child,
+            self.config.cost_meter(),
+            cancel,
+            self.task_id.clone(),
+            self.log_dir.clone(),
+            self.transcript.clone(),
+        )
+    }
+
+    /// Execute Claude Code and collect all output (blocking until completion)
+    pub async fn execute_and_wait(
+        &self,
+        prompt: &str,
+    ) -> Result<(Vec<ClaudeEvent>, ExecutionUsage)> {
+        let (mut rx, handle) = self.execute(prompt).await?;
+        let mut events = Vec::new();
+
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+
+        let usage = handle.await.context("Task panicked")??;
+        Ok((events, usage))
+    }
+}
+
+/// IDs of the agent processes being streamed, killed if doodoori exits abruptly
+static RUNNING_AGENTS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Kill every running agent process, along with its process group
+///
+/// For exiting without waiting for the loop: agents run in their own process
+/// group, so they would otherwise outlive doodoori.
+pub fn kill_running_agents() {
+    let pids = RUNNING_AGENTS.lock().map(|pids| pids.clone()).unwrap_or_default();
+    for pid in pids {
+        kill_process_group(pid);
+    }
+}
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                if line.trim().is_empty() {
+/// SIGKILL a process's group, or just the process if it leads none
+/// (e.g. `docker exec`)
+fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        if libc::killpg(pid as libc::pid_t, libc::SIGKILL) != 0 {
+            libc::kill(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+fn track_agent(pid: Option<u32>, running: bool) {
+    let (Some(pid), Ok(mut pids)) = (pid, RUNNING_AGENTS.lock()) else {
+        return;
+    };
+    if running {
+        pids.push(pid);
+    } else {
+        pids.retain(|&p| p != pid);
+    }
+}
+
+/// Stream stream-json events from a spawned agent process
+///
+/// The process is killed when `cancel` resolves; stdout is drained until EOF
+/// either way. The usage reported by the `result` event is returned with the
+/// cost `meter` calculated from tokens, which stands in if the CLI reported
+/// no cost. Raw lines are recorded to `transcript` if given.
+pub(crate) fn stream_child(
+    mut child: Child,
+    mut meter: CostMeter,
+    cancel: impl std::future::Future<Output = ()> + Send + 'static,
+    task_id: Option<String>,
+    log_dir: Option<PathBuf>,
+    transcript: Option<PathBuf>,
+) -> Result<AgentExecution> {
+    let stdout = child.stdout.take().context("Failed to capture stdout")?;
+    let (tx, rx) = mpsc::channel(100);
+    let pid = child.id();
+    track_agent(pid, true);
+
+    let handle = tokio::spawn(async move {
+        let reader = BufReader::new(stdout);
+        let mut lines = reader.lines();
+        let mut usage = ExecutionUsage::default();
+        let mut tools = ToolTracker::new();
+        let mut transcript = transcript.and_then(|path| match TranscriptWriter::create(&path) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                tracing::warn!("Failed to record transcript: {}", e);
+                None
+            }
+        });
+
+        // Helper to write logs from async context
+        let write_log = |level: &str, message: &str| -> Result<()> {
+            if let Some(ref tid) = task_id {
+                let log_path = task_log_path(log_dir.as_deref(), tid)?;
+
+                let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+                let line = format!("[{}] [{}] {}", timestamp, level, message);
+
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(log_path)?;
+                writeln!(file, "{}", line)?;
+            }
+            Ok(())
+        };
+
+        tokio::pin!(cancel);
+        let mut cancelled = false;
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line,
+                _ = &mut cancel, if !cancelled => {
+                    // Kill the process and keep draining stdout until EOF
+                    cancelled = true;
+                    let _ = write_log("INFO", "Task cancelled");
+                    if let Err(e) = child.start_kill() {
+                        tracing::warn!("Failed to kill claude: {}", e);
+                    }
                     continue;
                 }
+            };
+            let Ok(Some(line)) = line else {
+                break;
+            };
 
-                match serde_json::from_str::<ClaudeEvent>(&line) {
-                    Ok(event) => {
-                        // Log events
-                        match &event {
-                            ClaudeEvent::Assistant(asst) => {
-                                if let Some(msg) = &asst.message {
-                                    let text = msg.as_text();
-                                    let _ = write_log(
-                                        "CLAUDE",
-                                        &text.chars().take(200).collect::<String>(),
-                                    );
-                                }
-                            }
-                            ClaudeEvent::ToolUse(tool) => {
-                                let _ = write_log("TOOL", &tool.tool_name);
-                            }
-                            ClaudeEvent::ToolResult(result) => {
-                                if result.is_error {
-                                    let _ =
-                                        write_log("ERROR", &format!("{} failed", result.tool_name));
-                                }
-                            }
-                            ClaudeEvent::Result(result) => {
-                                if result.is_error {
-                                    let _ = write_log("ERROR", "Task failed");
-                                } else {
-                                    let _ = write_log("INFO", "Task completed");
-                                }
-                            }
-                            _ => {}
-                        }
+            if line.trim().is_empty() {
+                continue;
+            }
 
-                        // Extract usage information from result events
-                        if let ClaudeEvent::Result(ref result) = event {
-                            if let Some(ref stats) = result.usage {
-                                usage.add(stats);
+            if let Some(ref mut writer) = transcript
+                && let Err(e) = writer.write_line(&line)
+            {
+                tracing::warn!("Failed to write transcript, recording stopped: {}", e);
+                transcript = None;
+            }
+
+            match serde_json::from_str::<ClaudeEvent>(&line) {
+                Ok(event) => {
+                    // Log events
+                    if let ClaudeEvent::Assistant(asst) = &event
+                        && let Some(msg) = &asst.message
+                    {
+                        if let Some(thinking) = msg.thinking() {
+                            let _ = write_log(
+                                "THINKING",
+                                &thinking.chars().take(200).collect::<String>(),
+                            );
+                        }
+                        let text = msg.as_text();
+                        if !text.is_empty() {
+                            let _ = write_log("CLAUDE", &text.chars().take(200).collect::<String>());
+                        }
+                    }
+                    for activity in tools.observe(&event) {
+                        match activity {
+                            ToolActivity::Started(call) => {
+                                let message = match call.summary() {
+                                    Some(summary) => format!("{} {}", call.name, summary),
+                                    None => call.name,
+                                };
+                                let _ = write_log("TOOL", &message);
                             }
-                            if let Some(cost) = result.total_cost_usd {
-                                usage.total_cost_usd = cost;
+                            ToolActivity::Finished(result) if result.is_error => {
+                                let _ = write_log(
+                                    "ERROR",
+                                    &format!("{} failed ({}ms)", result.name, result.duration_ms),
+                                );
                             }
-                            if let Some(duration) = result.duration_ms {
-                                usage.duration_ms = duration;
+                            ToolActivity::Finished(result) => {
+                                let _ = write_log(
+                                    "TOOL",
+                                    &format!("{} done ({}ms)", result.name, result.duration_ms),
+                                );
                             }
                         }
-
-                        if tx.send(event).await.is_err() {
-                            break;
+                    }
+                    if let ClaudeEvent::Result(result) = &event {
+                        if result.is_error {
+                            let _ = write_log("ERROR", "Task failed");
+                        } else {
+                            let _ = write_log("INFO", "Task completed");
                         }
                     }
-                    Err(e) => {
-                        tracing::warn!("Failed to parse Claude event: {} - line: {}", e, line);
+
+                    // Extract usage information from result events
+                    usage.record_event(&event);
+                    meter.record_event(&event);
+
+                    if tx.send(event).await.is_err() {
+                        break;
                     }
                 }
+                Err(e) => {
+                    tracing::warn!("Failed to parse Claude event: {} - line: {}", e, line);
+                }
             }
+        }
 
-            // Wait for the process to complete
-            let status = child.wait().await.context("Failed to wait for claude")?;
-            if !status.success() {
-                tracing::warn!("Claude exited with status: {}", status);
-            }
-
-            Ok(usage)
-        });
-
-        Ok((rx, handle))
-    }
-
-    /// Execute Claude Code and collect all output (blocking until completion)
-    pub async fn execute_and_wait(
-        &self,
-        prompt: &str,
-    ) -> Result<(Vec<ClaudeEvent>, ExecutionUsage)> {
-        let (mut rx, handle) = self.execute(prompt).await?;
-        let mut events = Vec::new();
+        if let Some(writer) = transcript
+            && let Err(e) = writer.finish()
+        {
+            tracing::warn!("Failed to finish transcript: {}", e);
+        }
 
-        while let Some(event) = rx.recv().await {
-            events.push(event);
+        // Wait for the process to complete
+        let status = child.wait().await;
+        track_agent(pid, false);
+        let status = status.context("Failed to wait for claude")?;
+        if !status.success() && !cancelled {
+            tracing::warn!("Claude exited with status: {}", status);
         }
 
-        let usage = handle.await.context("Task panicked")??;
-        Ok((events, usage))
-    }
+        Ok(meter.settle(usage))
+    });
+
+    Ok((rx, handle))
 }
 
 #[cfg(test)]
@@ -583,6 +894,21 @@ mod tests {
         assert!(args.contains(&"Read,Grep,Glob".to_string()));
     }
 
+    #[test]
+    fn test_build_args_resume_session() {
+        let runner = ClaudeRunner::new(ClaudeConfig::default())
+            .with_resume_session("abc123".to_string());
+        let args = runner.build_args("Continue");
+
+        let pos = args.iter().position(|a| a == "--resume").unwrap();
+        assert_eq!(args[pos + 1], "abc123");
+        // The prompt must still be last
+        assert_eq!(args.last().unwrap(), "Continue");
+
+        let args = ClaudeRunner::new(ClaudeConfig::default()).build_args("Test");
+        assert!(!args.contains(&"--resume".to_string()));
+    }
+
     #[test]
     fn test_builder_pattern() {
         let runner = ClaudeRunner::new(ClaudeConfig::default())
@@ -650,6 +976,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_thinking_and_tool_result_blocks() {
+        let json = r#"{"type":"assistant","message":{"content":[
+            {"type":"thinking","thinking":"Look at the tests first","signature":"sig"},
+            {"type":"text","text":"Running tests"}
+        ]}}"#;
+        let event: ClaudeEvent = serde_json::from_str(json).unwrap();
+        match event {
+            ClaudeEvent::Assistant(asst) => {
+                let msg = asst.message.unwrap();
+                assert_eq!(msg.thinking().as_deref(), Some("Look at the tests first"));
+                assert_eq!(msg.as_text(), "Running tests");
+            }
+            _ => panic!("Expected Assistant event"),
+        }
+
+        let json = r#"{"type":"user","message":{"role":"user","content":[
+            {"type":"tool_result","tool_use_id":"toolu_1","content":"ok"},
+            {"type":"tool_result","tool_use_id":"toolu_2","content":[{"type":"text","text":"boom"}],"is_error":true}
+        ]},"session_id":"sess-1"}"#;
+        let event: ClaudeEvent = serde_json::from_str(json).unwrap();
+        match event {
+            ClaudeEvent::User(user) => {
+                let msg = user.message.unwrap();
+                assert_eq!(msg.blocks().len(), 2);
+                match &msg.blocks()[1] {
+                    ContentBlock::ToolResult { tool_use_id, content, is_error } => {
+                        assert_eq!(tool_use_id, "toolu_2");
+                        assert_eq!(content.as_ref().unwrap().as_text(), "boom");
+                        assert!(is_error);
+                    }
+                    _ => panic!("Expected tool_result block"),
+                }
+                assert!(msg.thinking().is_none());
+            }
+            _ => panic!("Expected User event"),
+        }
+    }
+
     #[test]
     fn test_parse_result_event_with_usage() {
         let json = r#"{
@@ -704,12 +1069,17 @@ mod tests {
             output_tokens: 50,
             cache_creation_input_tokens: 10,
             cache_read_input_tokens: 20,
+            cache_creation: Some(CacheCreation {
+                ephemeral_5m_input_tokens: 4,
+                ephemeral_1h_input_tokens: 6,
+            }),
         };
 
         usage.add(&stats);
         assert_eq!(usage.input_tokens, 100);
         assert_eq!(usage.output_tokens, 50);
         assert_eq!(usage.cache_creation_tokens, 10);
+        assert_eq!(usage.cache_creation_1h_tokens, 6);
         assert_eq!(usage.cache_read_tokens, 20);
 
         // Add again
@@ -798,6 +1168,69 @@ mod tests {
         assert_eq!(runner.task_id, Some("test-id".to_string()));
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_custom_binary_with_transcript() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("fake-claude");
+        std::fs::write(
+            &binary,
+            "#!/bin/sh\necho '{\"type\":\"assistant\",\"message\":\"hi\"}'\necho 'garbage'\necho '{\"type\":\"result\",\"total_cost_usd\":0.5}'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = ClaudeConfig {
+            command: AgentCommand {
+                binary,
+                extra_args: vec!["--extra".to_string()],
+            },
+            ..Default::default()
+        };
+        let transcript = dir.path().join("transcripts").join("iteration-001.jsonl.gz");
+        let runner = ClaudeRunner::new(config).with_transcript(transcript.clone());
+        assert!(runner.build_args("Test").contains(&"--extra".to_string()));
+
+        let (events, usage) = runner.execute_and_wait("Test").await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(usage.total_cost_usd, 0.5);
+
+        // Unparseable lines are still recorded
+        let lines = crate::claude::read_transcript(&transcript).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].line, "garbage");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("sleep.pid");
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()))
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        while !std::fs::read_to_string(&pid_file).is_ok_and(|pid| pid.ends_with('\n')) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let sleep_pid: u32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+
+        kill_process_group(child.id().unwrap());
+
+        // The shell and the process it started are both gone
+        let status = tokio::time::timeout(std::time::Duration::from_secs(5), child.wait()).await.unwrap().unwrap();
+        assert!(!status.success());
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while crate::state::process_alive(sleep_pid) && std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(!crate::state::process_alive(sleep_pid));
+    }
+
     #[test]
     fn test_with_log_dir_builder() {
         let log_dir = PathBuf::from("/tmp/test-logs");
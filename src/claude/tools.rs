@@ -0,0 +1,278 @@
+//! Tool call tracking
+//!
+//! Claude Code reports tool calls as `tool_use` blocks inside assistant
+//! messages and their outcomes as `tool_result` blocks inside the following
+//! user message. [`ToolTracker`] pairs the two by tool use ID, measures how
+//! long each call took and keeps per-tool totals. Older top-level
+//! `tool_use`/`tool_result` events are paired by tool name.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use super::runner::{ClaudeEvent, ContentBlock};
+
+/// Maximum length of a tool call summary
+const SUMMARY_MAX_CHARS: usize = 80;
+
+/// A tool call that has started
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+impl ToolCall {
+    /// Short description of the call's main argument (file, command, pattern...)
+    pub fn summary(&self) -> Option<String> {
+        let value = ["file_path", "notebook_path", "command", "pattern", "path", "url", "query", "description"]
+            .iter()
+            .find_map(|key| self.input.get(key).and_then(|v| v.as_str()))?;
+        let line = value.lines().next().unwrap_or_default();
+        if line.chars().count() > SUMMARY_MAX_CHARS {
+            let truncated: String = line.chars().take(SUMMARY_MAX_CHARS - 3).collect();
+            Some(format!("{}...", truncated))
+        } else {
+            Some(line.to_string())
+        }
+    }
+}
+
+/// A tool call that has finished
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallResult {
+    pub id: String,
+    pub name: String,
+    pub is_error: bool,
+    pub duration_ms: u64,
+    pub output: Option<String>,
+}
+
+/// Tool activity extracted from a stream-json event
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolActivity {
+    Started(ToolCall),
+    Finished(ToolCallResult),
+}
+
+/// Per-tool totals
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolStats {
+    pub calls: u32,
+    pub failures: u32,
+    pub duration_ms: u64,
+}
+
+/// One-line summary of tool totals, e.g. `Bash (3, 1 failed), Edit (2)`
+pub fn format_tool_stats(tools: &BTreeMap<String, ToolStats>) -> String {
+    tools
+        .iter()
+        .map(|(name, stats)| match stats.failures {
+            0 => format!("{} ({})", name, stats.calls),
+            failures => format!("{} ({}, {} failed)", name, stats.calls, failures),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Pairs tool calls with their results across a stream of events
+#[derive(Debug)]
+pub struct ToolTracker {
+    started: Instant,
+    /// Calls without a result yet: id -> (name, start offset in ms)
+    pending: HashMap<String, (String, u64)>,
+    /// Start order of pending calls, for pairing results by name
+    order: Vec<String>,
+    stats: BTreeMap<String, ToolStats>,
+    next_id: u64,
+}
+
+impl Default for ToolTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolTracker {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            pending: HashMap::new(),
+            order: Vec::new(),
+            stats: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Observe an event as it arrives
+    pub fn observe(&mut self, event: &ClaudeEvent) -> Vec<ToolActivity> {
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        self.observe_at(event, now_ms)
+    }
+
+    /// Observe an event that arrived `elapsed_ms` after tracking started
+    pub fn observe_at(&mut self, event: &ClaudeEvent, elapsed_ms: u64) -> Vec<ToolActivity> {
+        let mut activity = Vec::new();
+        match event {
+            ClaudeEvent::Assistant(asst) => {
+                for block in asst.message.iter().flat_map(|m| m.blocks()) {
+                    if let ContentBlock::ToolUse { id, name, input } = block {
+                        activity.push(self.start(id.clone(), name.clone(), input.clone(), elapsed_ms));
+                    }
+                }
+            }
+            ClaudeEvent::User(user) => {
+                for block in user.message.iter().flat_map(|m| m.blocks()) {
+                    if let ContentBlock::ToolResult { tool_use_id, content, is_error } = block {
+                        let output = content.as_ref().map(|c| c.as_text());
+                        activity.push(self.finish(tool_use_id, None, *is_error, output, elapsed_ms));
+                    }
+                }
+            }
+            ClaudeEvent::ToolUse(tool) => {
+                self.next_id += 1;
+                let id = format!("{}-{}", tool.tool_name, self.next_id);
+                let input = tool.tool_input.clone().unwrap_or_default();
+                activity.push(self.start(id, tool.tool_name.clone(), input, elapsed_ms));
+            }
+            ClaudeEvent::ToolResult(result) => {
+                let id = self
+                    .order
+                    .iter()
+                    .find(|id| self.pending.get(*id).is_some_and(|(name, _)| *name == result.tool_name))
+                    .cloned()
+                    .unwrap_or_else(|| result.tool_name.clone());
+                activity.push(self.finish(
+                    &id,
+                    Some(&result.tool_name),
+                    result.is_error,
+                    result.output.clone(),
+                    elapsed_ms,
+                ));
+            }
+            _ => {}
+        }
+        activity
+    }
+
+    fn start(&mut self, id: String, name: String, input: serde_json::Value, at_ms: u64) -> ToolActivity {
+        self.pending.insert(id.clone(), (name.clone(), at_ms));
+        self.order.push(id.clone());
+        ToolActivity::Started(ToolCall { id, name, input })
+    }
+
+    fn finish(
+        &mut self,
+        id: &str,
+        name: Option<&str>,
+        is_error: bool,
+        output: Option<String>,
+        at_ms: u64,
+    ) -> ToolActivity {
+        let (name, duration_ms) = match self.pending.remove(id) {
+            Some((name, started_ms)) => (name, at_ms.saturating_sub(started_ms)),
+            None => (name.unwrap_or("unknown").to_string(), 0),
+        };
+        self.order.retain(|pending| pending != id);
+
+        let stats = self.stats.entry(name.clone()).or_default();
+        stats.calls += 1;
+        stats.duration_ms += duration_ms;
+        if is_error {
+            stats.failures += 1;
+        }
+
+        ToolActivity::Finished(ToolCallResult {
+            id: id.to_string(),
+            name,
+            is_error,
+            duration_ms,
+            output,
+        })
+    }
+
+    /// Totals per tool name for finished calls
+    pub fn stats(&self) -> &BTreeMap<String, ToolStats> {
+        &self.stats
+    }
+
+    /// Number of calls still waiting for a result
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> ClaudeEvent {
+        serde_json::from_str(line).unwrap()
+    }
+
+    #[test]
+    fn test_pairs_nested_tool_blocks() {
+        let mut tracker = ToolTracker::new();
+        let call = parse(
+            r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Check the file"},{"type":"tool_use","id":"toolu_1","name":"Read","input":{"file_path":"src/main.rs"}},{"type":"tool_use","id":"toolu_2","name":"Bash","input":{"command":"cargo test\necho done"}}]}}"#,
+        );
+        let result = parse(
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_2","content":[{"type":"text","text":"1 failed"}],"is_error":true},{"type":"tool_result","tool_use_id":"toolu_1","content":"fn main() {}"}]}}"#,
+        );
+
+        let started = tracker.observe_at(&call, 100);
+        assert_eq!(started.len(), 2);
+        match &started[1] {
+            ToolActivity::Started(call) => {
+                assert_eq!(call.id, "toolu_2");
+                assert_eq!(call.summary().as_deref(), Some("cargo test"));
+            }
+            _ => panic!("Expected Started"),
+        }
+        assert_eq!(tracker.pending(), 2);
+
+        let finished = tracker.observe_at(&result, 350);
+        assert_eq!(
+            finished[0],
+            ToolActivity::Finished(ToolCallResult {
+                id: "toolu_2".to_string(),
+                name: "Bash".to_string(),
+                is_error: true,
+                duration_ms: 250,
+                output: Some("1 failed".to_string()),
+            })
+        );
+        match &finished[1] {
+            ToolActivity::Finished(result) => {
+                assert_eq!(result.name, "Read");
+                assert!(!result.is_error);
+                assert_eq!(result.output.as_deref(), Some("fn main() {}"));
+            }
+            _ => panic!("Expected Finished"),
+        }
+        assert_eq!(tracker.pending(), 0);
+        assert_eq!(tracker.stats()["Bash"], ToolStats { calls: 1, failures: 1, duration_ms: 250 });
+    }
+
+    #[test]
+    fn test_pairs_top_level_events_by_name() {
+        let mut tracker = ToolTracker::new();
+        tracker.observe_at(&parse(r#"{"type":"tool_use","tool_name":"Read"}"#), 0);
+        tracker.observe_at(&parse(r#"{"type":"tool_use","tool_name":"Edit"}"#), 10);
+        let finished = tracker.observe_at(&parse(r#"{"type":"tool_result","tool_name":"Read"}"#), 40);
+
+        match &finished[0] {
+            ToolActivity::Finished(result) => {
+                assert_eq!(result.id, "Read-1");
+                assert_eq!(result.duration_ms, 40);
+            }
+            _ => panic!("Expected Finished"),
+        }
+        assert_eq!(tracker.pending(), 1);
+
+        // A result without a known call is still counted
+        tracker.observe_at(&parse(r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_x"}]}}"#), 50);
+        assert_eq!(tracker.stats()["unknown"].calls, 1);
+    }
+}
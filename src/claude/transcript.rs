@@ -0,0 +1,249 @@
+//! Raw stream-json transcripts
+//!
+//! Every line Claude Code prints is kept per task and iteration under
+//! `.doodoori/tasks/<task-id>/transcripts/iteration-NNN.jsonl.gz`, together
+//! with the time it arrived, so a run can be replayed later (`doodoori replay`).
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Transcripts directory inside a task directory
+pub const TRANSCRIPTS_DIR: &str = "transcripts";
+
+/// Transcripts directory of earlier versions, relative to the project directory
+const LEGACY_TRANSCRIPTS_DIR: &str = ".doodoori/transcripts";
+
+/// One recorded stream-json line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptLine {
+    /// Milliseconds since the iteration started
+    pub elapsed_ms: u64,
+    /// The raw line as printed by Claude Code
+    pub line: String,
+}
+
+/// Writes a compressed transcript for one iteration
+pub struct TranscriptWriter {
+    encoder: GzEncoder<BufWriter<File>>,
+    started: Instant,
+}
+
+impl TranscriptWriter {
+    /// Create the transcript file (and its parent directories)
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create transcripts directory")?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create transcript: {}", path.display()))?;
+        Ok(Self {
+            encoder: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            started: Instant::now(),
+        })
+    }
+
+    /// Append a raw line
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let record = TranscriptLine {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            line: line.to_string(),
+        };
+        serde_json::to_writer(&mut self.encoder, &record)?;
+        self.encoder.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Flush and close the transcript
+    pub fn finish(self) -> Result<()> {
+        self.encoder.finish()?.flush()?;
+        Ok(())
+    }
+}
+
+/// Read a transcript file
+///
+/// A transcript cut short by a crash is read up to the last complete line.
+pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptLine>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open transcript: {}", path.display()))?;
+    let reader = BufReader::new(MultiGzDecoder::new(file));
+
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => lines.push(record),
+            Err(e) => tracing::warn!("Skipping malformed transcript line in {}: {}", path.display(), e),
+        }
+    }
+    Ok(lines)
+}
+
+/// Transcript files of a project
+#[derive(Debug, Clone)]
+pub struct TranscriptStore {
+    /// `.doodoori/tasks`
+    tasks_dir: PathBuf,
+    /// `.doodoori/transcripts`, still read for older runs
+    legacy_dir: PathBuf,
+}
+
+impl TranscriptStore {
+    pub fn for_project(project_dir: &Path) -> Self {
+        Self {
+            tasks_dir: project_dir.join(".doodoori").join(crate::state::TASKS_DIR),
+            legacy_dir: project_dir.join(LEGACY_TRANSCRIPTS_DIR),
+        }
+    }
+
+    /// Transcripts directory of a task
+    fn task_transcripts(&self, task_id: &str) -> PathBuf {
+        let dir = self.tasks_dir.join(task_id).join(TRANSCRIPTS_DIR);
+        let legacy = self.legacy_dir.join(task_id);
+        if !dir.exists() && legacy.exists() {
+            legacy
+        } else {
+            dir
+        }
+    }
+
+    /// Path of the transcript for a (1-based) iteration of a task
+    pub fn iteration_path(&self, task_id: &str, iteration: u32) -> PathBuf {
+        self.tasks_dir
+            .join(task_id)
+            .join(TRANSCRIPTS_DIR)
+            .join(format!("iteration-{:03}.jsonl.gz", iteration))
+    }
+
+    /// Task IDs with recorded transcripts, most recent first
+    pub fn list_tasks(&self) -> Result<Vec<String>> {
+        let mut tasks: Vec<(std::time::SystemTime, String)> = Vec::new();
+        let roots = [(&self.tasks_dir, Path::new(TRANSCRIPTS_DIR)), (&self.legacy_dir, Path::new(""))];
+        for (root, subdir) in roots {
+            if !root.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(root)?.filter_map(|entry| entry.ok()) {
+                let dir = entry.path().join(subdir);
+                let id = entry.file_name().to_string_lossy().to_string();
+                if !dir.is_dir() || tasks.iter().any(|(_, t)| *t == id) {
+                    continue;
+                }
+                let modified = dir
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .unwrap_or(std::time::UNIX_EPOCH);
+                tasks.push((modified, id));
+            }
+        }
+        tasks.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        Ok(tasks.into_iter().map(|(_, id)| id).collect())
+    }
+
+    /// Resolve a full task ID from an ID or unique prefix
+    pub fn resolve_task(&self, task_id: &str) -> Result<String> {
+        let matches: Vec<String> = self
+            .list_tasks()?
+            .into_iter()
+            .filter(|id| id.starts_with(task_id))
+            .collect();
+
+        match matches.as_slice() {
+            [] => anyhow::bail!("No transcripts found for task: {}", task_id),
+            [id] => Ok(id.clone()),
+            _ if matches.iter().any(|id| id == task_id) => Ok(task_id.to_string()),
+            _ => anyhow::bail!(
+                "Task ID '{}' is ambiguous ({} matches); use a longer prefix",
+                task_id,
+                matches.len()
+            ),
+        }
+    }
+
+    /// Transcript files of a task with their iteration numbers, in order
+    pub fn iterations(&self, task_id: &str) -> Result<Vec<(u32, PathBuf)>> {
+        let task_dir = self.task_transcripts(task_id);
+        let mut files: Vec<(u32, PathBuf)> = fs::read_dir(&task_dir)
+            .with_context(|| format!("Failed to read transcripts: {}", task_dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?;
+                let iteration = name
+                    .strip_prefix("iteration-")?
+                    .strip_suffix(".jsonl.gz")?
+                    .parse()
+                    .ok()?;
+                Some((iteration, path))
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TranscriptStore::for_project(dir.path());
+        let path = store.iteration_path("task-abc", 1);
+
+        let mut writer = TranscriptWriter::create(&path).unwrap();
+        writer.write_line(r#"{"type":"system","subtype":"init"}"#).unwrap();
+        writer.write_line("not json").unwrap();
+        writer.finish().unwrap();
+
+        let lines = read_transcript(&path).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, r#"{"type":"system","subtype":"init"}"#);
+        assert_eq!(lines[1].line, "not json");
+        assert!(lines[0].elapsed_ms <= lines[1].elapsed_ms);
+    }
+
+    #[test]
+    fn test_store_resolves_tasks_and_iterations() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TranscriptStore::for_project(dir.path());
+        for (task, iteration) in [("abc-1", 2), ("abc-1", 1), ("abd-2", 1)] {
+            TranscriptWriter::create(&store.iteration_path(task, iteration))
+                .unwrap()
+                .finish()
+                .unwrap();
+        }
+
+        assert_eq!(store.resolve_task("abc").unwrap(), "abc-1");
+        assert!(store.resolve_task("ab").is_err());
+        assert!(store.resolve_task("zzz").is_err());
+
+        let iterations: Vec<u32> = store
+            .iterations("abc-1")
+            .unwrap()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(iterations, vec![1, 2]);
+        assert!(store.iteration_path("abc-1", 1).starts_with(dir.path().join(".doodoori/tasks/abc-1/transcripts")));
+
+        // Transcripts recorded by earlier versions are still found
+        let legacy = dir.path().join(".doodoori/transcripts/old-task");
+        fs::create_dir_all(&legacy).unwrap();
+        TranscriptWriter::create(&legacy.join("iteration-001.jsonl.gz")).unwrap().finish().unwrap();
+        assert_eq!(store.resolve_task("old").unwrap(), "old-task");
+        assert_eq!(store.iterations("old-task").unwrap().len(), 1);
+    }
+}
@@ -1,9 +1,14 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
 use clap::Args;
 use std::path::PathBuf;
 
-use crate::output::{CostOutput, OutputFormat, OutputWriter};
-use crate::pricing::{format_cost, format_tokens, CostHistoryManager};
+use crate::output::{CostGroupOutput, CostOutput, OutputFormat, OutputWriter};
+use crate::pricing::{
+    format_cost, format_tokens, group_entries, history_of, CostFilter, CostGroupBy, CostHistoryManager,
+    GlobalCostEntry, GlobalCostLedger,
+};
+use crate::utils::truncate_str;
 
 /// View cost history and tracking
 #[derive(Args, Debug)]
@@ -28,10 +33,28 @@ pub struct CostArgs {
     #[arg(short = 'n', long, default_value = "10")]
     pub limit: usize,
 
-    /// Project directory (default: current directory)
+    /// Project directory (default: current directory); with --all-projects,
+    /// only this project (a path, or part of a path or git remote)
     #[arg(long)]
     pub project: Option<PathBuf>,
 
+    /// Report costs of all projects from the user-level cost ledger
+    #[arg(long)]
+    pub all_projects: bool,
+
+    /// Only include costs from this day on (YYYY-MM-DD, UTC)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+
+    /// Only include costs up to this day (YYYY-MM-DD, UTC)
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+
+    /// Break costs down by project, model, day or status
+    /// (default: project with --all-projects, otherwise day)
+    #[arg(long)]
+    pub group_by: Option<CostGroupBy>,
+
     /// Output format (text, json, json-pretty, yaml, markdown)
     #[arg(long, short = 'f', default_value = "text")]
     pub format: String,
@@ -43,6 +66,11 @@ pub struct CostArgs {
 
 impl CostArgs {
     pub async fn execute(self) -> Result<()> {
+        if self.all_projects {
+            let ledger = GlobalCostLedger::user().context("Could not determine the user data directory")?;
+            return self.show_breakdown(ledger.entries()?);
+        }
+
         let project_dir = self.project.clone()
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
@@ -61,6 +89,20 @@ impl CostArgs {
             return Ok(());
         }
 
+        if self.group_by.is_some() || self.since.is_some() || self.until.is_some() {
+            let entries = manager
+                .history()
+                .entries
+                .iter()
+                .map(|entry| GlobalCostEntry {
+                    entry: entry.clone(),
+                    project: project_dir.clone(),
+                    git_remote: None,
+                })
+                .collect();
+            return self.show_breakdown(entries);
+        }
+
         if let Some(ref task_id) = self.task_id {
             self.show_task_cost(&manager, task_id)?;
             return Ok(());
@@ -126,11 +168,7 @@ impl CostArgs {
                 for entry in recent {
                     let short_id = &entry.task_id[..8.min(entry.task_id.len())];
                     let desc = entry.description.as_deref().unwrap_or("-");
-                    let desc_short = if desc.len() > 30 {
-                        format!("{}...", &desc[..27])
-                    } else {
-                        desc.to_string()
-                    };
+                    let desc_short = truncate_str(desc, 30);
                     println!(
                         "  {} │ {} │ {} │ {}",
                         short_id,
@@ -164,6 +202,87 @@ impl CostArgs {
         Ok(())
     }
 
+    /// Costs grouped by project, model, day or status, within the date range
+    fn show_breakdown(&self, entries: Vec<GlobalCostEntry>) -> Result<()> {
+        let filter = CostFilter {
+            project: self
+                .project
+                .as_ref()
+                .filter(|_| self.all_projects)
+                .map(|p| p.display().to_string()),
+            since: self.since,
+            until: self.until,
+        };
+        let entries: Vec<GlobalCostEntry> = entries.into_iter().filter(|e| filter.matches(e)).collect();
+        let group_by = self.group_by.unwrap_or(if self.all_projects {
+            CostGroupBy::Project
+        } else {
+            CostGroupBy::Day
+        });
+        let groups = group_entries(&entries, group_by);
+        let history = history_of(&entries);
+
+        let output_format: OutputFormat = self.format.parse().unwrap_or_default();
+        if output_format == OutputFormat::Text {
+            let scope = if self.all_projects { "all projects" } else { "this project" };
+            println!("=== Cost by {} ({}) ===\n", group_by, scope);
+            if self.since.is_some() || self.until.is_some() {
+                let day = |d: Option<NaiveDate>| d.map(|d| d.to_string()).unwrap_or_else(|| "…".to_string());
+                println!("Period: {} to {}\n", day(self.since), day(self.until));
+            }
+            if groups.is_empty() {
+                println!("No cost entries found.");
+                if self.all_projects {
+                    println!("Costs are recorded across projects with global_ledger = true under [cost].");
+                }
+                return Ok(());
+            }
+
+            println!("{:<40} {:>10} {:>7} {:>10} {:>10}", "Group", "Cost", "Tasks", "Input", "Output");
+            println!("{}", "-".repeat(81));
+            for group in &groups {
+                println!(
+                    "{:<40} {:>10} {:>7} {:>10} {:>10}",
+                    truncate_str(&group.key, 40),
+                    format_cost(group.cost_usd),
+                    group.task_count,
+                    format_tokens(group.input_tokens),
+                    format_tokens(group.output_tokens),
+                );
+            }
+            println!("{}", "-".repeat(81));
+            println!("Total: {} ({} tasks)", format_cost(history.get_total_cost()), entries.len());
+        } else {
+            let groups = groups
+                .into_iter()
+                .map(|g| CostGroupOutput {
+                    key: g.key,
+                    cost_usd: g.cost_usd,
+                    tasks: g.task_count,
+                    input_tokens: g.input_tokens,
+                    output_tokens: g.output_tokens,
+                })
+                .collect();
+            let cost_output = CostOutput::new()
+                .with_today(history.get_today_summary().map(|t| t.total_cost_usd).unwrap_or(0.0))
+                .with_month(history.get_monthly_total())
+                .with_total(history.get_total_cost())
+                .with_task_count(entries.len() as u64)
+                .with_groups(group_by.to_string(), groups);
+
+            let writer = if let Some(ref path) = self.output {
+                OutputWriter::new(output_format).with_file(path)
+            } else {
+                OutputWriter::new(output_format)
+            };
+            if let Err(e) = writer.write_cost(&cost_output) {
+                tracing::error!("Failed to write output: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
     fn show_task_cost(&self, manager: &CostHistoryManager, task_id: &str) -> Result<()> {
         let history = manager.history();
         let entries = history.get_task_entries(task_id);
@@ -188,6 +307,27 @@ impl CostArgs {
                 format_tokens(entry.input_tokens),
                 format_tokens(entry.output_tokens),
             );
+
+            // Tasks that switched models show what each model cost
+            let by_model = entry.cost_by_model();
+            if by_model.len() > 1 {
+                for (model, cost) in by_model {
+                    let iterations = entry.iterations.iter().filter(|i| i.model == model).count();
+                    println!("    {:<8} {} ({} iteration(s))", model, format_cost(cost), iterations);
+                }
+            }
+
+            // Compare with the cost priced from tokens
+            if let Some((reported, calculated)) = entry.cost_drift() {
+                println!(
+                    "    reported {} / calculated from tokens {}",
+                    format_cost(reported),
+                    format_cost(calculated)
+                );
+            }
+            if entry.has_calculated_cost() {
+                println!("    includes cost calculated from tokens (not reported by the CLI)");
+            }
         }
 
         Ok(())
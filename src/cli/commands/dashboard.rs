@@ -54,9 +54,11 @@ pub mod tui {
     use std::path::PathBuf;
     use std::time::{Duration, Instant};
 
-    use crate::loop_engine::{ExecutionSnapshot, LiveEvent, LiveStatus};
+    use crate::claude::format_tool_stats;
+    use crate::loop_engine::{ControlState, ExecutionSnapshot, LiveEvent, LiveStatus, LoopControl};
     use crate::pricing::CostHistoryManager;
     use crate::state::{StateManager, TaskState};
+    use crate::utils::truncate_str;
 
     /// View mode for the dashboard
     #[derive(Debug, Clone, PartialEq, Eq)]
@@ -115,6 +117,10 @@ pub mod tui {
         pub live_output_buffer: Vec<String>,
         /// Live output scroll position
         pub live_output_scroll: usize,
+        /// Control handle for the live-monitored loop
+        pub live_control: Option<LoopControl>,
+        /// Signalled tasks waiting to save their own state
+        pub stopping_tasks: Vec<StoppingTask>,
     }
 
     /// Log filter options
@@ -177,6 +183,16 @@ pub mod tui {
         pub working_dir: Option<String>,
     }
 
+    /// A task sent SIGTERM, checked on each tick until it has stopped
+    #[derive(Debug, Clone)]
+    pub struct StoppingTask {
+        pub task_id: String,
+        /// Process signalled, if known
+        pub pid: Option<u32>,
+        /// When to stop waiting for the process to save its state
+        pub deadline: Instant,
+    }
+
     impl App {
         pub fn new(active_only: bool) -> Self {
             let project_dir =
@@ -211,6 +227,8 @@ pub mod tui {
                 live_monitoring_active: false,
                 live_output_buffer: Vec::new(),
                 live_output_scroll: 0,
+                live_control: None,
+                stopping_tasks: Vec::new(),
             };
 
             app.load_tasks();
@@ -234,9 +252,9 @@ pub mod tui {
             if let Some(ref state_manager) = self.state_manager {
                 let mut tasks = Vec::new();
 
-                // Load current state
-                if let Ok(Some(state)) = state_manager.load_state() {
-                    tasks.push(state);
+                // Load active tasks
+                if let Ok(active) = state_manager.list_active_tasks() {
+                    tasks.extend(active);
                 }
 
                 // Load history (last 20 tasks)
@@ -346,8 +364,7 @@ pub mod tui {
         /// Load log content for selected task
         pub fn load_log_content(&mut self) {
             if let Some(task) = self.tasks.get(self.selected_task) {
-                let log_path =
-                    PathBuf::from(".doodoori/logs").join(format!("{}.log", task.task_id));
+                let log_path = self.task_log_path(&task.task_id);
                 if log_path.exists() {
                     if let Ok(content) = fs::read_to_string(&log_path) {
                         self.log_content = content.lines().map(String::from).collect();
@@ -361,8 +378,22 @@ pub mod tui {
             }
         }
 
+        /// Log file of a task: its Claude log, the output of a detached run,
+        /// or a log written by an earlier version
+        fn task_log_path(&self, task_id: &str) -> PathBuf {
+            let legacy = PathBuf::from(".doodoori/logs").join(format!("{}.log", task_id));
+            let Some(ref state_manager) = self.state_manager else {
+                return legacy;
+            };
+            [state_manager.log_path(task_id), state_manager.output_log_path(task_id)]
+                .into_iter()
+                .find(|path| path.exists())
+                .unwrap_or(legacy)
+        }
+
         /// Refresh data (for real-time updates)
         pub fn refresh(&mut self) {
+            self.check_stopping_tasks();
             self.load_tasks();
             if self.view_mode == ViewMode::LogView {
                 self.load_log_content();
@@ -376,6 +407,10 @@ pub mod tui {
         }
 
         /// Kill the selected running task
+        ///
+        /// Sends SIGTERM to the task's doodoori process, which cancels its loop
+        /// and saves the task as interrupted. If the process exits without doing
+        /// so, the dashboard marks the task interrupted itself.
         pub fn kill_selected_task(&mut self) {
             if let Some(task) = self.tasks.get(self.selected_task) {
                 if task.status != crate::state::TaskStatus::Running {
@@ -386,22 +421,23 @@ pub mod tui {
                     return;
                 }
 
-                // Try to find and kill the process
+                // Signal the recorded process, or search for it
                 let task_id = task.task_id.clone();
-                match Self::find_and_kill_task(&task_id) {
+                let pid = self.state_manager.as_ref().and_then(|m| m.running_pid(&task_id));
+                let result = match pid {
+                    Some(pid) => Self::kill_pid(pid),
+                    None => Self::find_and_kill_task(&task_id),
+                };
+                match result {
                     Ok(killed) => {
                         if killed {
-                            // Update state to interrupted
-                            if let Some(ref state_manager) = self.state_manager {
-                                if let Ok(Some(mut state)) = state_manager.load_state() {
-                                    if state.task_id == task_id {
-                                        state.status = crate::state::TaskStatus::Interrupted;
-                                        let _ = state_manager.save_state(&state);
-                                    }
-                                }
-                            }
+                            self.stopping_tasks.push(StoppingTask {
+                                task_id: task_id.clone(),
+                                pid,
+                                deadline: Instant::now() + Duration::from_secs(2),
+                            });
                             self.status_message = Some((
-                                format!("✓ Killed task {}", &task_id[..8]),
+                                format!("✓ Stopping task {}", &task_id[..8]),
                                 Instant::now(),
                             ));
                             self.load_tasks();
@@ -422,6 +458,40 @@ pub mod tui {
             }
         }
 
+        /// Mark signalled tasks interrupted if their state still says running
+        ///
+        /// A task is given until its deadline to exit and save its own state; a
+        /// process that was killed outright never gets to. One still shutting
+        /// down at the deadline is left to save its state itself.
+        fn check_stopping_tasks(&mut self) {
+            let Some(ref state_manager) = self.state_manager else {
+                self.stopping_tasks.clear();
+                return;
+            };
+            self.stopping_tasks.retain(|stopping| {
+                let exited = stopping.pid.is_some_and(|pid| !crate::state::process_alive(pid));
+                if !exited && Instant::now() < stopping.deadline {
+                    return true;
+                }
+                if exited || stopping.pid.is_none() {
+                    let _ = state_manager.update_state(&stopping.task_id, |state| {
+                        if state.status == crate::state::TaskStatus::Running {
+                            state.interrupt();
+                        }
+                    });
+                }
+                false
+            });
+        }
+
+        /// Send SIGTERM to a process
+        fn kill_pid(pid: u32) -> Result<bool> {
+            let status = std::process::Command::new("kill")
+                .args(["-TERM", &pid.to_string()])
+                .status()?;
+            Ok(status.success())
+        }
+
         /// Find and kill a task's process
         fn find_and_kill_task(task_id: &str) -> Result<bool> {
             use std::process::Command;
@@ -473,16 +543,28 @@ pub mod tui {
             let mut pruned_count = 0;
 
             if let Some(ref state_manager) = self.state_manager {
-                // Check current state
-                if let Ok(Some(mut state)) = state_manager.load_state() {
-                    if state.status == crate::state::TaskStatus::Running {
-                        // Check if process exists
-                        if !Self::is_task_process_running(&state.task_id) {
+                let running = state_manager
+                    .list_active_tasks()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|t| t.status == crate::state::TaskStatus::Running);
+                for task in running {
+                    // Tasks record their process; older states are looked up by name
+                    let alive = match state_manager.recorded_pid(&task.task_id) {
+                        Some(pid) => crate::state::process_alive(pid),
+                        None => Self::is_task_process_running(&task.task_id),
+                    };
+                    if alive {
+                        continue;
+                    }
+                    let pruned = state_manager.update_state(&task.task_id, |state| {
+                        if state.status == crate::state::TaskStatus::Running {
                             state.status = crate::state::TaskStatus::Interrupted;
                             state.error = Some("Pruned: process not found".to_string());
-                            let _ = state_manager.save_state(&state);
-                            pruned_count += 1;
                         }
+                    });
+                    if let Ok(Some(_)) = pruned {
+                        pruned_count += 1;
                     }
                 }
             }
@@ -578,6 +660,32 @@ pub mod tui {
             self.view_mode = ViewMode::LiveMonitor;
         }
 
+        /// Attach the control handle of the live-monitored loop
+        pub fn set_live_control(&mut self, control: LoopControl) {
+            self.live_control = Some(control);
+        }
+
+        /// Cancel the live task immediately (kills the running claude process)
+        pub fn cancel_live_task(&mut self) {
+            if let Some(ref control) = self.live_control {
+                control.cancel();
+            }
+        }
+
+        /// Stop the live task after the current iteration
+        pub fn stop_live_task(&mut self) {
+            if let Some(ref control) = self.live_control {
+                control.stop();
+            }
+        }
+
+        /// Pause or resume the live task between iterations
+        pub fn toggle_live_pause(&mut self) {
+            if let Some(ref control) = self.live_control {
+                control.toggle_pause();
+            }
+        }
+
         /// Stop live monitoring
         pub fn stop_live_monitoring(&mut self) {
             self.live_monitoring_active = false;
@@ -644,6 +752,12 @@ pub mod tui {
                         self.live_output_scroll = self.live_output_buffer.len().saturating_sub(1);
                     }
                 }
+                LiveEvent::Thinking { text } => {
+                    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                        self.live_output_buffer.push(format!("💭 {}", line));
+                    }
+                    self.live_output_scroll = self.live_output_buffer.len().saturating_sub(1);
+                }
                 LiveEvent::ToolStart { tool_name, .. } => {
                     self.live_output_buffer.push(format!("▶ [TOOL] {}", tool_name));
                     self.live_output_scroll = self.live_output_buffer.len().saturating_sub(1);
@@ -1143,14 +1257,57 @@ pub mod tui {
                     ),
                 ]),
                 Line::from(""),
-                Line::from(vec![Span::styled(
-                    "Prompt:",
-                    Style::default().fg(Color::Yellow),
-                )]),
-                Line::from("─────────────────────────────────────────────────────"),
             ];
 
             let mut all_lines = text;
+
+            // Per-iteration timeline
+            if !task.iterations.is_empty() {
+                all_lines.push(Line::from(vec![Span::styled(
+                    "Timeline:",
+                    Style::default().fg(Color::Yellow),
+                )]));
+                // Keep the most recent iterations so the prompt stays visible
+                let skipped = task.iterations.len().saturating_sub(10);
+                if skipped > 0 {
+                    all_lines.push(Line::from(Span::styled(
+                        format!("  ({} earlier iteration(s))", skipped),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                for record in task.iterations.iter().skip(skipped) {
+                    let color = if record.completed {
+                        Color::Green
+                    } else if !record.errors.is_empty() {
+                        Color::Red
+                    } else {
+                        Color::White
+                    };
+                    all_lines.push(Line::from(Span::styled(
+                        format!("  {}", record.timeline_line()),
+                        Style::default().fg(color),
+                    )));
+                    if !record.tools.is_empty() {
+                        all_lines.push(Line::from(Span::styled(
+                            format!("       tools: {}", format_tool_stats(&record.tools)),
+                            Style::default().fg(Color::DarkGray),
+                        )));
+                    }
+                    for error in &record.errors {
+                        all_lines.push(Line::from(Span::styled(
+                            format!("       error: {}", truncate_str(error, 100)),
+                            Style::default().fg(Color::Red),
+                        )));
+                    }
+                }
+                all_lines.push(Line::from(""));
+            }
+
+            all_lines.push(Line::from(vec![Span::styled(
+                "Prompt:",
+                Style::default().fg(Color::Yellow),
+            )]));
+            all_lines.push(Line::from("─────────────────────────────────────────────────────"));
             for line in prompt_preview.lines() {
                 all_lines.push(Line::from(line));
             }
@@ -1364,8 +1521,14 @@ pub mod tui {
         f.render_widget(output_paragraph, chunks[1]);
 
         // Footer
+        let control_state = app.live_control.as_ref().map(|c| c.state()).unwrap_or_default();
         let footer_text = if app.live_monitoring_active {
-            "↑/↓ scroll, PgUp/PgDn pages, Esc stop & return, 'q' quit"
+            match control_state {
+                ControlState::Running => "'s' stop after iteration, 'p' pause, 'k' cancel, ↑/↓ scroll, PgUp/PgDn pages, 'q' quit",
+                ControlState::Paused => "⏸ Paused before next iteration - 'p' resume, 's' stop, 'k' cancel, 'q' quit",
+                ControlState::Stopping => "Stopping after the current iteration - 'k' cancel now, 'q' quit",
+                ControlState::Cancelled => "Cancelling...",
+            }
         } else {
             "Execution completed - Press Esc to return to task list"
         };
@@ -1724,6 +1887,7 @@ mod tests {
                 error: None,
                 final_output: None,
                 working_dir: Some(".".to_string()),
+                iterations: Vec::new(),
             };
             app.tasks = vec![task];
             app.selected_task = 0;
@@ -1794,6 +1958,7 @@ mod tests {
                 error: None,
                 final_output: None,
                 working_dir: Some(".".to_string()),
+                iterations: Vec::new(),
             };
             app.tasks = vec![task];
             app.selected_task = 0;
@@ -1832,6 +1997,7 @@ mod tests {
                 error: Some("Test error".to_string()),
                 final_output: None,
                 working_dir: Some("/project".to_string()),
+                iterations: Vec::new(),
             };
             app.tasks = vec![task];
             app.selected_task = 0;
@@ -1873,6 +2039,7 @@ mod tests {
                 error: None,
                 final_output: Some("Done".to_string()),
                 working_dir: None,
+                iterations: Vec::new(),
             };
             app.tasks = vec![task];
             app.selected_task = 0;
@@ -1960,5 +2127,37 @@ mod tests {
 
             assert_eq!(app.log_scroll, 0);
         }
+
+        #[test]
+        fn test_stopping_task_marked_interrupted() {
+            use crate::state::{StateManager, TaskState, TaskStatus};
+            use std::time::{Duration, Instant};
+
+            let dir = tempfile::tempdir().unwrap();
+            let manager = StateManager::new(dir.path()).unwrap();
+            let mut state = TaskState::new("test".to_string(), "sonnet".to_string(), 5);
+            state.start();
+            manager.save_state(&state).unwrap();
+
+            let mut app = App::new(false);
+            app.state_manager = Some(StateManager::new(dir.path()).unwrap());
+
+            // A live process is given until its deadline
+            app.stopping_tasks.push(StoppingTask {
+                task_id: state.task_id.clone(),
+                pid: Some(std::process::id()),
+                deadline: Instant::now() + Duration::from_secs(60),
+            });
+            app.refresh();
+            assert_eq!(app.stopping_tasks.len(), 1);
+            assert_eq!(manager.load_task(&state.task_id).unwrap().unwrap().status, TaskStatus::Running);
+
+            // One that never saved its state is marked interrupted
+            app.stopping_tasks[0].pid = None;
+            app.stopping_tasks[0].deadline = Instant::now();
+            app.refresh();
+            assert!(app.stopping_tasks.is_empty());
+            assert_eq!(manager.load_task(&state.task_id).unwrap().unwrap().status, TaskStatus::Interrupted);
+        }
     }
 }
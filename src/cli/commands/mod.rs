@@ -2,7 +2,9 @@ pub mod cost;
 pub mod dashboard;
 pub mod git;
 pub mod parallel;
+pub mod replay;
 pub mod resume;
+pub mod rollback;
 pub mod run;
 pub mod sandbox;
 pub mod secret;
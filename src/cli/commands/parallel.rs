@@ -8,6 +8,7 @@ use crate::executor::{
 };
 use crate::git::sanitize_branch_name;
 use crate::instructions::{SpecFile, SpecParser};
+use crate::loop_engine::{CompletionStrategy, VerificationConfig};
 use crate::output::{OutputFormat, OutputWriter, ParallelOutput, TaskOutput};
 use crate::pricing::format_cost;
 
@@ -93,6 +94,10 @@ impl ParallelArgs {
             return self.execute_dry_run().await;
         }
 
+        // Refuse to start once a daily or monthly spend cap is reached
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        doodoori_config.cost.ensure_spend_available(&std::env::current_dir()?)?;
+
         // Collect tasks from CLI arguments
         let mut tasks = Vec::new();
 
@@ -152,6 +157,7 @@ impl ParallelArgs {
             git_branch_prefix: self.branch_prefix.clone(),
             git_auto_commit: self.auto_commit,
             git_auto_pr: self.auto_pr,
+            cost_ledger: doodoori_config.cost.ledger_path(),
         };
 
         let executor = ParallelExecutor::new(config);
@@ -192,6 +198,7 @@ impl ParallelArgs {
                         TaskStatus::BudgetExceeded => "💰 Budget exceeded",
                         TaskStatus::Failed => "✗ Failed",
                         TaskStatus::Cancelled => "⊘ Cancelled",
+                        TaskStatus::Stalled => "🔁 Stalled",
                     };
                     println!("  [{}] {} ({})", short_id, status_str, format_cost(cost));
                 }
@@ -225,6 +232,7 @@ impl ParallelArgs {
                     TaskStatus::BudgetExceeded => "💰",
                     TaskStatus::Failed => "✗",
                     TaskStatus::Cancelled => "⊘",
+                    TaskStatus::Stalled => "🔁",
                 };
 
                 println!("{} [{}] {}", status_icon, short_id, task_result.prompt_summary);
@@ -391,6 +399,7 @@ impl ParallelArgs {
         // Check if this is a multi-task spec
         if spec.is_multi_task() {
             // Each TaskSpec becomes a separate TaskDefinition
+            let completion_strategy = CompletionStrategy::from_promise(&spec.effective_completion_promise());
             for task_spec in &spec.tasks {
                 let default_model = spec.effective_model();
                 let model = self.model.clone()
@@ -416,17 +425,22 @@ impl ParallelArgs {
                     prompt.push_str(&format!("## Completion Criteria\n{}\n\n", criteria));
                 }
 
-                prompt.push_str("---\n\n");
-                prompt.push_str(&format!(
-                    "When complete, output: {}\n",
-                    spec.effective_completion_promise()
-                ));
+                // Completion instructions are added by the loop engine's prompt template
 
-                let task = TaskDefinition::new(prompt)
+                let mut task = TaskDefinition::new(prompt)
                     .with_name(task_spec.id.clone())
                     .with_model(model)
                     .with_max_iterations(max_iterations)
-                    .with_yolo_mode(self.yolo);
+                    .with_yolo_mode(self.yolo)
+                    .with_completion_strategy(completion_strategy.clone());
+
+                // Task-level model policy and verification command, falling back to the spec's
+                if let Some(policy) = task_spec.model_policy.as_ref().or(spec.model_policy.as_ref()) {
+                    task = task.with_model_policy(policy.clone());
+                }
+                if let Some(cmd) = task_spec.verify_command.as_ref().or(spec.verify_command.as_ref()) {
+                    task = task.with_verification(VerificationConfig::new(cmd.clone()));
+                }
 
                 tasks.push(task);
             }
@@ -508,18 +522,28 @@ impl ParallelArgs {
             .with_name(task_name)
             .with_model(model)
             .with_max_iterations(max_iterations)
-            .with_yolo_mode(self.yolo);
+            .with_yolo_mode(self.yolo)
+            .with_completion_strategy(CompletionStrategy::from_promise(&spec.effective_completion_promise()));
 
         // Set the git branch if worktree mode is enabled
         if self.git_worktree {
             task = task.with_git_branch(branch_name);
         }
 
+        if let Some(ref policy) = spec.model_policy {
+            task = task.with_model_policy(policy.clone());
+        }
+
         // Set budget if specified in spec
         if let Some(budget) = spec.budget {
             task = task.with_budget(budget);
         }
 
+        // Set verification command if specified in spec
+        if let Some(ref cmd) = spec.verify_command {
+            task = task.with_verification(VerificationConfig::new(cmd.clone()));
+        }
+
         Ok(task)
     }
 }
@@ -0,0 +1,203 @@
+//! Replay command for re-rendering recorded Claude sessions
+
+use anyhow::Result;
+use clap::Args;
+use std::path::Path;
+use std::time::Duration;
+
+use super::run::print_claude_event;
+use crate::claude::{read_transcript, ClaudeEvent, ExecutionUsage, ToolTracker, TranscriptStore};
+use crate::output::{OutputFormat, OutputWriter, TaskOutput};
+use crate::state::{StateManager, TaskState};
+
+/// Arguments for the replay command
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Task ID to replay (can be short prefix); lists recorded tasks if omitted
+    #[arg()]
+    pub task_id: Option<String>,
+
+    /// Replay a single iteration
+    #[arg(long, short)]
+    pub iteration: Option<u32>,
+
+    /// Replay with the original timing, sped up by FACTOR (default: 1 = original speed)
+    #[arg(long, value_name = "FACTOR", num_args = 0..=1, default_missing_value = "1")]
+    pub speed: Option<f64>,
+
+    /// Output format (text, json, json-pretty, yaml, markdown)
+    #[arg(long, short = 'f', default_value = "text")]
+    pub format: String,
+
+    /// Output file path (default: stdout)
+    #[arg(long, short = 'o')]
+    pub output: Option<String>,
+}
+
+/// Totals collected while replaying a run
+#[derive(Debug, Default)]
+struct ReplaySummary {
+    usage: ExecutionUsage,
+    final_output: Option<String>,
+    error: bool,
+    tools: ToolTracker,
+    /// Recorded time of the iterations replayed so far
+    elapsed_ms: u64,
+}
+
+impl ReplayArgs {
+    pub async fn execute(self) -> Result<()> {
+        let work_dir = std::env::current_dir()?;
+        let store = TranscriptStore::for_project(&work_dir);
+
+        let Some(ref task_id) = self.task_id else {
+            return Self::list_tasks(&store);
+        };
+        let task_id = store.resolve_task(task_id)?;
+
+        let mut iterations = store.iterations(&task_id)?;
+        if let Some(number) = self.iteration {
+            iterations.retain(|(n, _)| *n == number);
+            if iterations.is_empty() {
+                anyhow::bail!("No transcript for iteration {} of task {}", number, task_id);
+            }
+        }
+
+        let output_format: OutputFormat = self.format.parse().unwrap_or_default();
+        let text = output_format == OutputFormat::Text;
+
+        let mut summary = ReplaySummary::default();
+        for (number, path) in &iterations {
+            if text {
+                println!("{}", console::style(format!("── Iteration {} ──", number)).bold());
+            }
+            self.replay_iteration(path, text, &mut summary).await?;
+            if text {
+                println!();
+            }
+        }
+
+        let state = Self::load_state(&work_dir, &task_id);
+        if text {
+            Self::print_summary(&task_id, iterations.len() as u32, &summary);
+        } else {
+            let mut task_output = TaskOutput::new(
+                task_id.clone(),
+                state.as_ref().map(|s| s.prompt.clone()).unwrap_or_default(),
+            )
+            .with_status(
+                state
+                    .as_ref()
+                    .map(|s| s.status.to_string())
+                    .unwrap_or_else(|| if summary.error { "error" } else { "success" }.to_string()),
+            )
+            .with_iterations(iterations.len() as u32)
+            .with_cost(summary.usage.total_cost_usd)
+            .with_duration(summary.usage.duration_ms)
+            .with_tokens(summary.usage.input_tokens, summary.usage.output_tokens)
+            .with_tools(summary.tools.stats());
+            if let Some(ref state) = state {
+                task_output = task_output.with_model(state.model.clone());
+            }
+            if let Some(ref output) = summary.final_output {
+                task_output = task_output.with_output(output);
+            }
+
+            let writer = if let Some(ref path) = self.output {
+                OutputWriter::new(output_format).with_file(path)
+            } else {
+                OutputWriter::new(output_format)
+            };
+            writer.write_task(&task_output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-render one iteration's transcript, optionally with its original timing
+    async fn replay_iteration(&self, path: &Path, text: bool, summary: &mut ReplaySummary) -> Result<()> {
+        let mut usage = ExecutionUsage::default();
+        let mut previous_ms = 0;
+
+        for record in read_transcript(path)? {
+            if let Some(speed) = self.speed.filter(|s| *s > 0.0) {
+                let wait_ms = record.elapsed_ms.saturating_sub(previous_ms) as f64 / speed;
+                tokio::time::sleep(Duration::from_millis(wait_ms as u64)).await;
+            }
+            previous_ms = record.elapsed_ms;
+
+            let event: ClaudeEvent = match serde_json::from_str(&record.line) {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("Failed to parse recorded event: {} - line: {}", e, record.line);
+                    continue;
+                }
+            };
+
+            usage.record_event(&event);
+            if let ClaudeEvent::Result(ref result) = event {
+                summary.error = result.is_error;
+                if result.result.is_some() {
+                    summary.final_output = result.result.clone();
+                }
+            }
+            let activity = summary.tools.observe_at(&event, summary.elapsed_ms + record.elapsed_ms);
+            if text {
+                print_claude_event(&event, &activity);
+            }
+        }
+        summary.elapsed_ms += previous_ms;
+
+        summary.usage.input_tokens += usage.input_tokens;
+        summary.usage.output_tokens += usage.output_tokens;
+        summary.usage.cache_creation_tokens += usage.cache_creation_tokens;
+        summary.usage.cache_read_tokens += usage.cache_read_tokens;
+        summary.usage.total_cost_usd += usage.total_cost_usd;
+        summary.usage.duration_ms += usage.duration_ms;
+        Ok(())
+    }
+
+    /// Task state for the replayed task, if it is still known
+    fn load_state(work_dir: &Path, task_id: &str) -> Option<TaskState> {
+        StateManager::new(work_dir).ok()?.find_task(task_id).ok().flatten()
+    }
+
+    fn list_tasks(store: &TranscriptStore) -> Result<()> {
+        let tasks = store.list_tasks()?;
+        if tasks.is_empty() {
+            println!("No recorded transcripts found.");
+            return Ok(());
+        }
+
+        println!("Recorded tasks (most recent first):");
+        println!();
+        println!("{:<38} ITERATIONS", "TASK ID");
+        println!("{}", "-".repeat(50));
+        for task_id in tasks {
+            let iterations = store.iterations(&task_id).map(|i| i.len()).unwrap_or(0);
+            println!("{:<38} {}", task_id, iterations);
+        }
+        println!();
+        println!("Use 'doodoori replay <task-id>' to replay a task");
+        Ok(())
+    }
+
+    fn print_summary(task_id: &str, iterations: u32, summary: &ReplaySummary) {
+        use console::{style, Emoji};
+
+        println!("{} {}", Emoji("📼", "[REPLAY]"), style("Replay finished").bold());
+        println!();
+        println!("  Task:          {}", task_id);
+        println!("  Iterations:    {}", iterations);
+        println!("  Input tokens:  {}", summary.usage.input_tokens);
+        println!("  Output tokens: {}", summary.usage.output_tokens);
+        let stats = summary.tools.stats();
+        if !stats.is_empty() {
+            let calls: u32 = stats.values().map(|s| s.calls).sum();
+            let failures: u32 = stats.values().map(|s| s.failures).sum();
+            println!("  Tool calls:    {} ({} failed)", calls, failures);
+        }
+        println!("  Total cost:    ${:.4}", summary.usage.total_cost_usd);
+        println!("  Duration:      {:.2}s", summary.usage.duration_ms as f64 / 1000.0);
+    }
+}
@@ -3,9 +3,13 @@
 use anyhow::{Context, Result};
 use clap::Args;
 
-use crate::claude::ModelAlias;
-use crate::loop_engine::{LoopConfig, LoopEngine, LoopEvent, LoopStatus};
+use crate::claude::{format_tool_stats, ModelAlias};
+use crate::git::checkpoint::CheckpointManager;
+use crate::git::repo::GitRepository;
+use crate::git::GitError;
+use crate::loop_engine::{spawn_signal_handler, LoopConfig, LoopEngine, LoopEvent, LoopStatus, ProgressJournal};
 use crate::state::{StateManager, TaskStatus};
+use crate::utils::truncate_str;
 
 /// Arguments for the resume command
 #[derive(Args, Debug)]
@@ -43,8 +47,9 @@ impl ResumeArgs {
             return self.resume_task(&state_manager, task_id).await;
         }
 
-        // No task ID provided, show current state or list
-        if let Some(state) = state_manager.load_state()? {
+        // No task ID provided, show the only active task or list them all
+        let active = state_manager.list_active_tasks()?;
+        if let [state] = active.as_slice() {
             if state.can_resume() {
                 println!("Current task found: {} ({})", state.short_id(), state.status);
                 println!();
@@ -102,30 +107,23 @@ impl ResumeArgs {
     }
 
     fn show_task_info(&self, state_manager: &StateManager, task_id: &str) -> Result<()> {
-        // Try current state first
-        let state = if let Some(s) = state_manager.load_state()? {
-            if s.task_id.starts_with(task_id) || s.short_id() == task_id {
-                Some(s)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        // Try history if not in current state
-        let state = match state {
-            Some(s) => s,
-            None => state_manager
-                .load_from_history(task_id)?
-                .context(format!("Task not found: {}", task_id))?,
-        };
+        let state = state_manager
+            .find_task(task_id)?
+            .context(format!("Task not found: {}", task_id))?;
 
         println!("Task Details");
         println!("============");
         println!();
         self.print_task_details(&state);
 
+        if let Some(project_dir) = state_manager.base_dir().parent() {
+            let journal = ProgressJournal::for_task(project_dir, &state.task_id);
+            if journal.exists() {
+                println!();
+                println!("Progress journal: {}", journal.path().display());
+            }
+        }
+
         Ok(())
     }
 
@@ -153,6 +151,20 @@ impl ResumeArgs {
             println!("Session ID: {}", session_id);
         }
 
+        if !state.iterations.is_empty() {
+            println!();
+            println!("Timeline:");
+            for record in &state.iterations {
+                println!("  {}", record.timeline_line());
+                if !record.tools.is_empty() {
+                    println!("       tools: {}", format_tool_stats(&record.tools));
+                }
+                for error in &record.errors {
+                    println!("       error: {}", truncate_str(error, 100));
+                }
+            }
+        }
+
         if let Some(ref error) = state.error {
             println!();
             println!("Error: {}", error);
@@ -171,24 +183,35 @@ impl ResumeArgs {
         }
     }
 
+    /// Restore the working tree to the checkpoint taken after `iteration`
+    fn restore_checkpoint(task_id: &str, iteration: u32) -> Result<()> {
+        let Ok(repo) = GitRepository::open(&std::env::current_dir()?) else {
+            return Ok(());
+        };
+        let manager = CheckpointManager::new(&repo);
+        match manager.find(task_id, iteration) {
+            Ok(checkpoint) => {
+                manager
+                    .restore(&checkpoint)
+                    .with_context(|| format!("Failed to restore checkpoint of iteration {}", iteration))?;
+                println!("  Restored checkpoint: {}", checkpoint.short_id());
+            }
+            Err(GitError::CheckpointNotFound(_)) => {
+                println!("  No checkpoint for iteration {}, working tree left as is", iteration);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
     async fn resume_task(&self, state_manager: &StateManager, task_id: &str) -> Result<()> {
         use console::Emoji;
         use indicatif::{ProgressBar, ProgressStyle};
 
         // Find the task
-        let mut state = if let Some(s) = state_manager.load_state()? {
-            if s.task_id.starts_with(task_id) || s.short_id() == task_id {
-                s
-            } else {
-                state_manager
-                    .load_from_history(task_id)?
-                    .context(format!("Task not found: {}", task_id))?
-            }
-        } else {
-            state_manager
-                .load_from_history(task_id)?
-                .context(format!("Task not found: {}", task_id))?
-        };
+        let mut state = state_manager
+            .find_task(task_id)?
+            .context(format!("Task not found: {}", task_id))?;
 
         if !state.can_resume() {
             anyhow::bail!(
@@ -197,21 +220,27 @@ impl ResumeArgs {
                 state.status
             );
         }
+        if let Some(pid) = state_manager.running_pid(&state.task_id)
+            && pid != std::process::id()
+        {
+            anyhow::bail!("Task {} is still running (pid {})", state.short_id(), pid);
+        }
 
         println!("{} Resuming task: {}", Emoji("🔄", ""), state.short_id());
         println!();
-        println!("  Prompt:     {}", if state.prompt.len() > 50 {
-            format!("{}...", &state.prompt[..47])
-        } else {
-            state.prompt.clone()
-        });
+        println!("  Prompt:     {}", truncate_str(&state.prompt, 50));
         println!("  Model:      {}", state.model);
         println!("  Iteration:  {}/{}", state.current_iteration, state.max_iterations);
         println!("  Cost so far: ${:.4}", state.total_cost_usd);
+        match state.session_id {
+            Some(ref session_id) => println!("  Session:    {}", session_id),
+            None => println!("  Session:    (none recorded, starting a new conversation)"),
+        }
 
         let start_iteration = if let Some(from_iter) = self.from_iteration {
             if from_iter < state.current_iteration {
                 println!("  Resetting to iteration: {}", from_iter);
+                Self::restore_checkpoint(&state.task_id, from_iter)?;
                 from_iter
             } else {
                 state.current_iteration
@@ -236,16 +265,9 @@ impl ResumeArgs {
             return Ok(());
         }
 
-        // Build continuation prompt
-        let resume_prompt = format!(
-            "Continue working on the task. You are resuming from iteration {}.\n\n\
-            Original task: {}\n\n\
-            When the task is complete, output: <promise>COMPLETE</promise>",
-            start_iteration,
-            state.prompt
-        );
-
-        // Build loop configuration
+        // Build loop configuration. The first iteration uses the resume prompt
+        // template; with a recorded session Claude also has the full conversation.
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
         let work_dir = std::env::current_dir().ok();
         let loop_config = LoopConfig {
             max_iterations: remaining_iterations,
@@ -259,6 +281,26 @@ impl ResumeArgs {
             enable_state: true,
             enable_cost_tracking: true,
             project_dir: work_dir,
+            resume_session: state.session_id.clone(),
+            resume_from_iteration: Some(start_iteration),
+            prompt_templates: doodoori_config.prompts.to_prompt_templates(std::path::Path::new(".")),
+            agent_command: doodoori_config.agent.clone(),
+            record_transcripts: doodoori_config.logging.transcripts,
+            stall: doodoori_config.stall.clone(),
+            model_policy: doodoori_config.model_policy.clone(),
+            record_progress: doodoori_config.logging.journal,
+            checkpoints: doodoori_config.git.checkpoints,
+            cost_ledger: doodoori_config.cost.ledger_path(),
+            spend_limits: doodoori_config.cost.limits.clone(),
+            price_file: doodoori_config.pricing_file(),
+            task_id: Some(state.task_id.clone()),
+            // Iterations after the one we resume from are discarded
+            iteration_history: state
+                .iterations
+                .iter()
+                .filter(|record| record.iteration <= start_iteration)
+                .cloned()
+                .collect(),
             ..Default::default()
         };
 
@@ -274,7 +316,8 @@ impl ResumeArgs {
         );
 
         // Execute with event handling
-        let (mut rx, handle) = engine.execute(&resume_prompt).await?;
+        let (mut rx, handle, control) = engine.execute(&state.prompt).await?;
+        let signal_handler = spawn_signal_handler(control);
 
         let mut total_cost = state.total_cost_usd;
 
@@ -299,6 +342,40 @@ impl ResumeArgs {
                 LoopEvent::HookExecuted { hook_type, success, duration_ms } => {
                     tracing::debug!("Hook {}: {} ({}ms)", hook_type, success, duration_ms);
                 }
+                LoopEvent::VerificationCompleted { iteration, result } => {
+                    if !result.success {
+                        progress.println(format!(
+                            "  ✗ Verification failed after iteration {}: {}",
+                            start_iteration + iteration + 1,
+                            result.command
+                        ));
+                    }
+                }
+                LoopEvent::ModelChanged { iteration, from, to } => {
+                    progress.println(format!(
+                        "  🔀 Iteration {}: switching from {} to {}",
+                        start_iteration + iteration + 1,
+                        from,
+                        to
+                    ));
+                }
+                LoopEvent::StallDetected { iteration, reason, action } => {
+                    progress.println(format!(
+                        "  🔁 No progress after iteration {} ({}), action: {}",
+                        start_iteration + iteration + 1,
+                        reason,
+                        action
+                    ));
+                }
+                LoopEvent::SpendWarning { message } => {
+                    progress.println(format!("  💰 {}", message));
+                }
+                LoopEvent::SpendCapReached { message } => {
+                    progress.println(format!("  💸 Spend cap reached: {}", message));
+                }
+                LoopEvent::GitWorkflowCompleted(result) => {
+                    tracing::debug!("Git workflow: {:?}", result);
+                }
                 LoopEvent::LoopFinished { status, total_iterations, total_usage } => {
                     progress.finish_and_clear();
                     println!();
@@ -309,6 +386,7 @@ impl ResumeArgs {
 
         // Wait for the loop to finish
         let result = handle.await??;
+        signal_handler.abort();
 
         // Print final status if not already printed
         if result.status != LoopStatus::Completed {
@@ -334,6 +412,7 @@ impl ResumeArgs {
             LoopStatus::MaxIterationsReached => (Emoji("⚠️", "[!]"), "Max iterations reached", console::Color::Yellow),
             LoopStatus::BudgetExceeded => (Emoji("💸", "[$]"), "Budget exceeded", console::Color::Yellow),
             LoopStatus::Stopped => (Emoji("🛑", "[X]"), "Stopped", console::Color::Red),
+            LoopStatus::Stalled => (Emoji("🔁", "[=]"), "Stalled", console::Color::Yellow),
             LoopStatus::Error(e) => {
                 println!("{} Error: {}", Emoji("❌", "[ERR]"), style(e).red());
                 return;
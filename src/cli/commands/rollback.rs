@@ -0,0 +1,84 @@
+//! Rollback command for restoring iteration checkpoints
+
+use anyhow::{Context, Result};
+use clap::Args;
+use console::Emoji;
+
+use crate::git::checkpoint::{CheckpointManager, BEFORE_ROLLBACK};
+use crate::git::repo::GitRepository;
+
+/// Arguments for the rollback command
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    /// Task ID (can be short prefix)
+    #[arg()]
+    pub task_id: String,
+
+    /// Iteration whose checkpoint to restore; lists checkpoints if omitted
+    #[arg(long)]
+    pub to_iteration: Option<u32>,
+}
+
+impl RollbackArgs {
+    pub async fn execute(self) -> Result<()> {
+        let work_dir = std::env::current_dir()?;
+        let repo = GitRepository::open(&work_dir).context("Checkpoints need a git repository")?;
+        let manager = CheckpointManager::new(&repo);
+
+        let Some(iteration) = self.to_iteration else {
+            return self.list_checkpoints(&manager);
+        };
+
+        let checkpoint = manager.find(&self.task_id, iteration)?;
+        manager.restore(&checkpoint)?;
+
+        println!(
+            "{} Restored iteration {} of task {} ({})",
+            Emoji("⏪", "<<"),
+            iteration,
+            checkpoint.task_id,
+            checkpoint.short_id()
+        );
+        println!(
+            "  The previous state is kept at refs/doodoori/{}/{}",
+            checkpoint.task_id, BEFORE_ROLLBACK
+        );
+        println!();
+        println!(
+            "Use 'doodoori resume {} --from-iteration {}' to continue from here",
+            self.task_id, iteration
+        );
+        Ok(())
+    }
+
+    fn list_checkpoints(&self, manager: &CheckpointManager) -> Result<()> {
+        let checkpoints = manager.list(&self.task_id)?;
+        if checkpoints.is_empty() {
+            println!("No checkpoints found for task {}.", self.task_id);
+            println!();
+            println!("Checkpoints are recorded after each iteration when [git] checkpoints = true.");
+            return Ok(());
+        }
+
+        println!("{:<38} {:<6} {:<9} {:<9} CREATED", "TASK", "ITER", "COMMIT", "HEAD");
+        println!("{}", "-".repeat(82));
+        for checkpoint in &checkpoints {
+            let head = checkpoint
+                .head
+                .map(|oid| oid.to_string()[..7].to_string())
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<38} {:<6} {:<9} {:<9} {}",
+                checkpoint.task_id,
+                checkpoint.iteration,
+                checkpoint.short_id(),
+                head,
+                checkpoint.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+
+        println!();
+        println!("Use 'doodoori rollback {} --to-iteration <N>' to restore one", self.task_id);
+        Ok(())
+    }
+}
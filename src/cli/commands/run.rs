@@ -2,20 +2,93 @@ use anyhow::Result;
 use clap::Args;
 use std::path::PathBuf;
 
-use crate::claude::{ClaudeEvent, ModelAlias};
-use crate::instructions::SpecParser;
-use crate::loop_engine::{LoopConfig, LoopEngine, LoopEvent, LoopStatus};
+use crate::claude::{AssistantMessage, ClaudeEvent, ContentBlock, ModelAlias, ToolActivity, ToolTracker};
+use crate::instructions::{SpecFile, SpecParser};
+use crate::loop_engine::{
+    spawn_signal_handler, CompletionStrategy, LoopConfig, LoopEngine, LoopEvent, LoopStatus,
+    ModelPolicy, VerificationConfig,
+};
 use crate::notifications::{NotificationManager, NotificationsConfig};
-use crate::output::{OutputFormat, OutputWriter, TaskOutput};
-
-/// Truncate a string to a maximum number of characters (Unicode-safe)
-fn truncate_str(s: &str, max_chars: usize) -> String {
-    let chars: Vec<char> = s.chars().collect();
-    if chars.len() > max_chars {
-        let truncated: String = chars[..max_chars.saturating_sub(3)].iter().collect();
-        format!("{}...", truncated)
-    } else {
-        s.to_string()
+use crate::output::{GitOutput, OutputFormat, OutputWriter, TaskOutput};
+use crate::state::StateManager;
+use crate::utils::truncate_str;
+
+/// Print a Claude event in verbose mode
+///
+/// `tools` is the tool activity the event produced (see [`ToolTracker`]).
+pub(crate) fn print_claude_event(event: &ClaudeEvent, tools: &[ToolActivity]) {
+    use console::{style, Emoji};
+
+    match event {
+        ClaudeEvent::System(sys) => {
+            if let Some(ref session_id) = sys.session_id {
+                println!("{} Session: {} ({})",
+                    Emoji("📋", "[SYS]"),
+                    style(session_id).cyan(),
+                    sys.subtype
+                );
+            }
+        }
+        ClaudeEvent::Assistant(asst) => {
+            if let Some(ref msg) = asst.message {
+                if let Some(thinking) = msg.thinking() {
+                    let display_thinking = truncate_str(&thinking, 200);
+                    println!("{} {}", Emoji("💭", "[THINK]"), style(display_thinking).dim().italic());
+                }
+                // Tool calls are printed from the tool activity below
+                let text = match msg {
+                    AssistantMessage::Text(text) => text.clone(),
+                    AssistantMessage::Object(_) => msg
+                        .blocks()
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text { text } => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                };
+                if !text.is_empty() {
+                    // Truncate long messages
+                    let display_msg = truncate_str(&text, 200);
+                    println!("{} {}", Emoji("🤖", "[AI]"), style(display_msg).dim());
+                }
+            }
+        }
+        ClaudeEvent::Result(res) => {
+            let duration = res.duration_ms.unwrap_or(0);
+            let (input_tok, output_tok) = res.usage.as_ref()
+                .map(|u| (u.input_tokens, u.output_tokens))
+                .unwrap_or((0, 0));
+            println!("{} Result: {} ({}ms, {}in/{}out tokens)",
+                Emoji("📊", "[RES]"),
+                if res.is_error { style("error").red() } else { style("success").green() },
+                duration,
+                input_tok,
+                output_tok
+            );
+        }
+        _ => {}
+    }
+
+    for activity in tools {
+        match activity {
+            ToolActivity::Started(call) => {
+                println!("{} {} {}",
+                    Emoji("🔧", "[TOOL]"),
+                    style(&call.name).yellow().bold(),
+                    style(call.summary().unwrap_or_default()).dim()
+                );
+            }
+            ToolActivity::Finished(result) => {
+                let status = if result.is_error {
+                    style("✗ error").red()
+                } else {
+                    style("✓ ok").green()
+                };
+                println!("   └─ {} ({}, {}ms)", status, result.name, result.duration_ms);
+            }
+        }
     }
 }
 
@@ -86,6 +159,10 @@ pub struct RunArgs {
     #[arg(long)]
     pub no_auto_merge: bool,
 
+    /// Command that must succeed before completion is accepted (e.g. "cargo test")
+    #[arg(long)]
+    pub verify: Option<String>,
+
     /// Show verbose output (Claude events, tool calls)
     #[arg(long)]
     pub verbose: bool,
@@ -129,6 +206,10 @@ pub struct RunArgs {
     #[arg(long, hide = true)]
     pub internal_detached: bool,
 
+    /// Internal flag: task ID chosen for a detached worker process
+    #[arg(long, hide = true)]
+    pub internal_task_id: Option<String>,
+
     /// Run with TUI dashboard for real-time monitoring
     #[cfg(feature = "dashboard")]
     #[arg(long)]
@@ -151,6 +232,12 @@ impl RunArgs {
     }
 
     pub async fn execute(self) -> Result<()> {
+        // Refuse to start once a daily or monthly spend cap is reached
+        if !self.dry_run {
+            let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+            doodoori_config.cost.ensure_spend_available(&std::env::current_dir()?)?;
+        }
+
         // Handle detached mode - spawn background worker and exit
         if self.detach && !self.internal_detached {
             return self.spawn_detached().await;
@@ -161,7 +248,7 @@ impl RunArgs {
         }
 
         // Load prompt from template, spec file, or use direct prompt
-        let (prompt, spec_model, spec_max_iterations) = if let Some(template_name) = &self.template {
+        let (prompt, spec_model, spec_max_iterations, spec) = if let Some(template_name) = &self.template {
             // Load template
             use crate::templates::storage::TemplateStorage;
 
@@ -182,16 +269,16 @@ impl RunArgs {
                 rendered = format!("{}\n\nAdditional instructions:\n{}", rendered, additional);
             }
 
-            (rendered, template.default_model, template.default_max_iterations)
+            (rendered, template.default_model, template.default_max_iterations, None)
         } else if let Some(spec_path) = &self.spec {
             tracing::info!("Loading spec file: {}", spec_path);
             let spec = SpecParser::parse_file(std::path::Path::new(spec_path))?;
             let prompt = spec.to_prompt();
             let model = spec.effective_model();
             let max_iter = spec.max_iterations;
-            (prompt, Some(model), max_iter)
+            (prompt, Some(model), max_iter, Some(spec))
         } else {
-            (self.prompt.clone().unwrap(), None, None)
+            (self.prompt.clone().unwrap(), None, None, None)
         };
 
         // Use spec/template values as defaults, CLI overrides take precedence
@@ -227,11 +314,11 @@ impl RunArgs {
         // Execute with Dashboard TUI if --dashboard flag is set
         #[cfg(feature = "dashboard")]
         if self.dashboard {
-            return self.execute_with_dashboard(&prompt, model, max_iterations).await;
+            return self.execute_with_dashboard(&prompt, model, max_iterations, spec.as_ref()).await;
         }
 
         // Execute with Loop Engine
-        self.execute_loop_engine(&prompt, model, max_iterations).await
+        self.execute_loop_engine(&prompt, model, max_iterations, spec.as_ref()).await
     }
 
     /// Execute task with the Loop Engine
@@ -240,6 +327,7 @@ impl RunArgs {
         prompt: &str,
         model: ModelAlias,
         max_iterations: u32,
+        spec: Option<&SpecFile>,
     ) -> Result<()> {
         use console::{style, Emoji};
         use indicatif::{ProgressBar, ProgressStyle};
@@ -358,6 +446,21 @@ impl RunArgs {
             disable_hooks: self.no_hooks,
             notifications: notifications_config,
             disable_notifications: self.no_notify,
+            git: self.git_config(&doodoori_config),
+            disable_git: self.no_git,
+            verification: self.verification_config(spec, &doodoori_config),
+            completion_strategy: Self::completion_strategy(spec),
+            prompt_templates: doodoori_config.prompts.to_prompt_templates(std::path::Path::new(".")),
+            agent_command: doodoori_config.agent.clone(),
+            record_transcripts: doodoori_config.logging.transcripts,
+            stall: doodoori_config.stall.clone(),
+            model_policy: Self::model_policy(spec, &doodoori_config),
+            record_progress: doodoori_config.logging.journal,
+            checkpoints: doodoori_config.git.checkpoints && !self.no_git,
+            cost_ledger: doodoori_config.cost.ledger_path(),
+            spend_limits: doodoori_config.cost.limits.clone(),
+            price_file: doodoori_config.pricing_file(),
+            task_id: self.internal_task_id.clone(),
             ..Default::default()
         };
 
@@ -375,10 +478,13 @@ impl RunArgs {
                 .progress_chars("█▓░"),
         );
 
-        // Execute with event handling
-        let (mut rx, handle) = engine.execute(prompt).await?;
+        // Execute with event handling; Ctrl+C/SIGTERM stop the loop gracefully
+        let (mut rx, handle, control) = engine.execute(prompt).await?;
+        let signal_handler = spawn_signal_handler(control);
 
         let mut total_cost = 0.0f64;
+        let mut git_result = None;
+        let mut tools = ToolTracker::new();
 
         while let Some(event) = rx.recv().await {
             match event {
@@ -387,8 +493,9 @@ impl RunArgs {
                     progress.set_message(format!("${:.4}", total_cost));
                 }
                 LoopEvent::ClaudeEvent(claude_event) => {
+                    let activity = tools.observe(&claude_event);
                     if self.verbose {
-                        self.print_claude_event(&claude_event);
+                        print_claude_event(&claude_event, &activity);
                     } else {
                         tracing::debug!("Claude event: {:?}", claude_event);
                     }
@@ -413,6 +520,46 @@ impl RunArgs {
                         );
                     }
                 }
+                LoopEvent::VerificationCompleted { iteration, result } => {
+                    if !result.success {
+                        progress.println(format!(
+                            "  ✗ Verification failed after iteration {} ({}): {}",
+                            iteration + 1,
+                            result.exit_code.map(|c| format!("exit {}", c)).unwrap_or_else(|| "no exit code".to_string()),
+                            result.command
+                        ));
+                    } else if self.verbose {
+                        progress.println(format!(
+                            "  ✓ Verification passed: {} ({}ms)",
+                            result.command, result.duration_ms
+                        ));
+                    }
+                }
+                LoopEvent::ModelChanged { iteration, from, to } => {
+                    progress.println(format!(
+                        "  🔀 Iteration {}: switching from {} to {}",
+                        iteration + 1,
+                        from,
+                        to
+                    ));
+                }
+                LoopEvent::StallDetected { iteration, reason, action } => {
+                    progress.println(format!(
+                        "  🔁 No progress after iteration {} ({}), action: {}",
+                        iteration + 1,
+                        reason,
+                        action
+                    ));
+                }
+                LoopEvent::SpendWarning { message } => {
+                    progress.println(format!("  💰 {}", message));
+                }
+                LoopEvent::SpendCapReached { message } => {
+                    progress.println(format!("  💸 Spend cap reached: {}", message));
+                }
+                LoopEvent::GitWorkflowCompleted(result) => {
+                    git_result = Some(result);
+                }
                 LoopEvent::LoopFinished { status, total_iterations, total_usage } => {
                     progress.finish_and_clear();
 
@@ -423,6 +570,9 @@ impl RunArgs {
                     if output_format == OutputFormat::Text {
                         println!();
                         self.print_result(&status, total_iterations, &total_usage);
+                        if let Some(ref git) = git_result {
+                            Self::print_git_result(git);
+                        }
                     } else {
                         // Build TaskOutput for structured formats
                         let task_output = TaskOutput::new(
@@ -434,7 +584,8 @@ impl RunArgs {
                         .with_iterations(total_iterations)
                         .with_cost(total_usage.total_cost_usd)
                         .with_duration(total_usage.duration_ms)
-                        .with_tokens(total_usage.input_tokens, total_usage.output_tokens);
+                        .with_tokens(total_usage.input_tokens, total_usage.output_tokens)
+                        .with_tools(tools.stats());
 
                         // Add error if present
                         let task_output = if let LoopStatus::Error(ref e) = status {
@@ -443,6 +594,12 @@ impl RunArgs {
                             task_output
                         };
 
+                        // Add git workflow result if present
+                        let task_output = match git_result {
+                            Some(ref git) => task_output.with_git(GitOutput::from(git)),
+                            None => task_output,
+                        };
+
                         // Write output
                         let writer = if let Some(ref path) = self.output {
                             OutputWriter::new(output_format).with_file(path)
@@ -460,6 +617,7 @@ impl RunArgs {
 
         // Wait for the loop to finish
         let result = handle.await??;
+        signal_handler.abort();
 
         // Print final status if not already printed (only for text format)
         let output_format: OutputFormat = self.format.parse().unwrap_or_default();
@@ -485,6 +643,7 @@ impl RunArgs {
             LoopStatus::MaxIterationsReached => (Emoji("⚠️", "[!]"), "Max iterations reached", console::Color::Yellow),
             LoopStatus::BudgetExceeded => (Emoji("💸", "[$]"), "Budget exceeded", console::Color::Yellow),
             LoopStatus::Stopped => (Emoji("🛑", "[X]"), "Stopped", console::Color::Red),
+            LoopStatus::Stalled => (Emoji("🔁", "[=]"), "Stalled", console::Color::Yellow),
             LoopStatus::Error(e) => {
                 println!("{} Error: {}", Emoji("❌", "[ERR]"), style(e).red());
                 return;
@@ -501,63 +660,60 @@ impl RunArgs {
         println!("  Duration:      {:.2}s", usage.duration_ms as f64 / 1000.0);
     }
 
-    /// Print a Claude event in verbose mode
-    fn print_claude_event(&self, event: &ClaudeEvent) {
-        use console::{style, Emoji};
+    /// Print the git workflow result
+    fn print_git_result(git: &crate::git::workflow::GitWorkflowResult) {
+        use console::style;
 
-        match event {
-            ClaudeEvent::System(sys) => {
-                if let Some(ref session_id) = sys.session_id {
-                    println!("{} Session: {} ({})",
-                        Emoji("📋", "[SYS]"),
-                        style(session_id).cyan(),
-                        sys.subtype
-                    );
-                }
-            }
-            ClaudeEvent::Assistant(asst) => {
-                if let Some(ref msg) = asst.message {
-                    // Truncate long messages
-                    let text = msg.as_text();
-                    let display_msg = if text.len() > 200 {
-                        format!("{}...", &text[..197])
-                    } else {
-                        text
-                    };
-                    println!("{} {}", Emoji("🤖", "[AI]"), style(display_msg).dim());
-                }
-            }
-            ClaudeEvent::ToolUse(tool) => {
-                println!("{} {}",
-                    Emoji("🔧", "[TOOL]"),
-                    style(&tool.tool_name).yellow().bold()
-                );
-            }
-            ClaudeEvent::ToolResult(result) => {
-                let status = if result.is_error {
-                    style("✗ error").red()
-                } else {
-                    style("✓ ok").green()
-                };
-                println!("   └─ {} ({})", status, result.tool_name);
-            }
-            ClaudeEvent::Result(res) => {
-                let duration = res.duration_ms.unwrap_or(0);
-                let (input_tok, output_tok) = res.usage.as_ref()
-                    .map(|u| (u.input_tokens, u.output_tokens))
-                    .unwrap_or((0, 0));
-                println!("{} Result: {} ({}ms, {}in/{}out tokens)",
-                    Emoji("📊", "[RES]"),
-                    if res.is_error { style("error").red() } else { style("success").green() },
-                    duration,
-                    input_tok,
-                    output_tok
-                );
-            }
-            ClaudeEvent::Unknown => {
-                // Ignore unknown events
-            }
+        if let Some(ref branch) = git.branch {
+            println!("  Git branch:    {}", style(branch).cyan());
+        }
+        if let Some(ref commit) = git.commit {
+            println!("  Git commit:    {}", commit);
+        }
+        if let Some(ref pr_url) = git.pr_url {
+            let auto_merge = if git.auto_merge { " (auto-merge)" } else { "" };
+            println!("  Pull request:  {}{}", pr_url, auto_merge);
+        }
+        for error in &git.errors {
+            println!("  {} {}", style("Git error:").red(), error);
+        }
+    }
+
+    /// Resolve the completion verification command (--verify > spec > doodoori.toml)
+    fn verification_config(
+        &self,
+        spec: Option<&SpecFile>,
+        doodoori_config: &crate::config::DoodooriConfig,
+    ) -> Option<VerificationConfig> {
+        let timeout_secs = doodoori_config.verification.timeout_secs;
+        self.verify
+            .as_deref()
+            .or(spec.and_then(|s| s.verify_command.as_deref()))
+            .map(str::trim)
+            .filter(|cmd| !cmd.is_empty())
+            .map(|cmd| VerificationConfig::new(cmd).with_timeout(timeout_secs))
+            .or_else(|| doodoori_config.verification.to_verification_config())
+    }
+
+    /// Model policy from the spec, falling back to doodoori.toml
+    fn model_policy(spec: Option<&SpecFile>, doodoori_config: &crate::config::DoodooriConfig) -> ModelPolicy {
+        spec.and_then(|s| s.model_policy.clone())
+            .unwrap_or_else(|| doodoori_config.model_policy.clone())
+    }
+
+    /// Completion strategy from the spec's completion promise (default otherwise)
+    fn completion_strategy(spec: Option<&SpecFile>) -> CompletionStrategy {
+        spec.map(|s| CompletionStrategy::from_promise(&s.effective_completion_promise()))
+            .unwrap_or_default()
+    }
+
+    /// Build the git workflow configuration from doodoori.toml and CLI flags
+    fn git_config(&self, doodoori_config: &crate::config::DoodooriConfig) -> crate::git::GitConfig {
+        let mut git_config = doodoori_config.git.to_git_config();
+        if self.no_auto_merge {
+            git_config.auto_merge = false;
         }
+        git_config
     }
 
     #[cfg(feature = "sandbox")]
@@ -673,6 +829,7 @@ impl RunArgs {
         prompt: &str,
         model: ModelAlias,
         max_iterations: u32,
+        spec: Option<&SpecFile>,
     ) -> Result<()> {
         use crossterm::{
             event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
@@ -776,6 +933,21 @@ impl RunArgs {
             disable_hooks: self.no_hooks,
             notifications: notifications_config,
             disable_notifications: self.no_notify,
+            git: self.git_config(&doodoori_config),
+            disable_git: self.no_git,
+            verification: self.verification_config(spec, &doodoori_config),
+            completion_strategy: Self::completion_strategy(spec),
+            prompt_templates: doodoori_config.prompts.to_prompt_templates(std::path::Path::new(".")),
+            agent_command: doodoori_config.agent.clone(),
+            record_transcripts: doodoori_config.logging.transcripts,
+            stall: doodoori_config.stall.clone(),
+            model_policy: Self::model_policy(spec, &doodoori_config),
+            record_progress: doodoori_config.logging.journal,
+            checkpoints: doodoori_config.git.checkpoints && !self.no_git,
+            cost_ledger: doodoori_config.cost.ledger_path(),
+            spend_limits: doodoori_config.cost.limits.clone(),
+            price_file: doodoori_config.pricing_file(),
+            task_id: self.internal_task_id.clone(),
             ..Default::default()
         };
 
@@ -783,7 +955,7 @@ impl RunArgs {
         let engine = LoopEngine::new(loop_config);
 
         // Execute with live monitoring - this returns (rx, handle)
-        let (event_rx, execution_handle) = engine.execute_live(prompt).await?;
+        let (event_rx, execution_handle, control) = engine.execute_live(prompt).await?;
 
         // Setup terminal
         enable_raw_mode()?;
@@ -795,6 +967,7 @@ impl RunArgs {
         // Create dashboard app with live monitoring
         let mut app = super::dashboard::tui::App::new(false);
         app.start_live_monitoring(event_rx, max_iterations);
+        app.set_live_control(control);
 
         // Main TUI loop
         let tick_rate = Duration::from_millis(100);
@@ -817,6 +990,9 @@ impl RunArgs {
                                 }
                                 // If still monitoring, Esc just waits for completion
                             }
+                            KeyCode::Char('k') => app.cancel_live_task(),
+                            KeyCode::Char('s') => app.stop_live_task(),
+                            KeyCode::Char('p') => app.toggle_live_pause(),
                             KeyCode::Up => app.scroll_live_up(),
                             KeyCode::Down => app.scroll_live_down(),
                             KeyCode::PageUp => app.scroll_live_page_up(),
@@ -888,7 +1064,7 @@ impl RunArgs {
         println!("=== Dry Run Preview ===\n");
 
         // Handle template, spec, or direct prompt
-        let (prompt, template_model, template_max_iter, is_template) = if let Some(template_name) = &self.template {
+        let (prompt, template_model, template_max_iter, spec, is_template) = if let Some(template_name) = &self.template {
             use crate::templates::storage::TemplateStorage;
 
             let storage = TemplateStorage::new()?;
@@ -913,17 +1089,17 @@ impl RunArgs {
             }
             println!();
 
-            (rendered, template.default_model, template.default_max_iterations, true)
+            (rendered, template.default_model, template.default_max_iterations, None, true)
         } else if let Some(spec) = &self.spec {
             println!("[Prompt Source]");
             println!("  Spec file: {}", spec);
             let spec = SpecParser::parse_file(std::path::Path::new(spec))?;
             let prompt = spec.to_prompt();
-            (prompt, Some(spec.effective_model()), spec.max_iterations, false)
+            (prompt, Some(spec.effective_model()), spec.max_iterations, Some(spec), false)
         } else if let Some(prompt) = &self.prompt {
             println!("[Prompt Source]");
             println!("  Direct prompt");
-            (prompt.clone(), None, None, false)
+            (prompt.clone(), None, None, None, false)
         } else {
             anyhow::bail!("Either --prompt, --spec, or --template is required");
         };
@@ -982,7 +1158,11 @@ impl RunArgs {
             self.max_iterations
         };
         println!("  Max iterations: {}", display_max_iter);
-        println!("  Completion promise: \"COMPLETE\"");
+        println!("  Completion: {}", Self::completion_strategy(spec.as_ref()).describe());
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        if let Some(verification) = self.verification_config(spec.as_ref(), &doodoori_config) {
+            println!("  Verification: {} (timeout {}s)", verification.command, verification.timeout_secs);
+        }
 
         if let Some(budget) = self.budget {
             println!("\n[Budget]");
@@ -990,11 +1170,15 @@ impl RunArgs {
         }
 
         println!("\n[Git Workflow]");
-        if self.no_git {
+        let git_config = self.git_config(&doodoori_config);
+        if self.no_git || !git_config.enabled {
             println!("  Disabled");
         } else {
             println!("  Enabled");
-            println!("  Auto-merge: {}", !self.no_auto_merge);
+            println!("  Auto-branch: {} (prefix: {})", git_config.auto_branch, git_config.branch_prefix_feature);
+            println!("  Auto-commit: {}", git_config.auto_commit);
+            println!("  Auto-PR: {}", git_config.auto_pr);
+            println!("  Auto-merge: {}", git_config.auto_merge);
         }
 
         println!("\n[Hooks]");
@@ -1076,7 +1260,7 @@ impl RunArgs {
         use std::process::{Command, Stdio};
         use uuid::Uuid;
 
-        let task_id = Uuid::new_v4().to_string()[..8].to_string();
+        let task_id = Uuid::new_v4().to_string();
 
         println!(
             "\n{} {}",
@@ -1151,6 +1335,10 @@ impl RunArgs {
         if self.no_auto_merge {
             args.push("--no-auto-merge".to_string());
         }
+        if let Some(ref verify) = self.verify {
+            args.push("--verify".to_string());
+            args.push(verify.clone());
+        }
         if self.verbose {
             args.push("--verbose".to_string());
         }
@@ -1167,6 +1355,8 @@ impl RunArgs {
 
         // Add internal flag to indicate this is a detached worker
         args.push("--internal-detached".to_string());
+        args.push("--internal-task-id".to_string());
+        args.push(task_id.clone());
 
         // Get current executable path
         let exe_path = std::env::current_exe()
@@ -1175,10 +1365,11 @@ impl RunArgs {
         // Get current working directory
         let cwd = std::env::current_dir()?;
 
-        // Create log file path
-        let log_dir = cwd.join(".doodoori/logs");
-        std::fs::create_dir_all(&log_dir)?;
-        let log_path = log_dir.join(format!("{}.log", task_id));
+        // Console output goes to the task's directory
+        let log_path = StateManager::new(&cwd)?.output_log_path(&task_id);
+        if let Some(log_dir) = log_path.parent() {
+            std::fs::create_dir_all(log_dir)?;
+        }
 
         // Open log file for stdout/stderr
         let log_file = std::fs::File::create(&log_path)?;
@@ -1209,7 +1400,7 @@ impl RunArgs {
 
             println!(
                 "  Task ID:  {}",
-                style(&task_id).green().bold()
+                style(&task_id[..8]).green().bold()
             );
             println!("  PID:      {}", pid);
             println!("  Log:      {}", log_path.display());
@@ -1238,7 +1429,7 @@ impl RunArgs {
 
             println!(
                 "  Task ID:  {}",
-                style(&task_id).green().bold()
+                style(&task_id[..8]).green().bold()
             );
             println!("  PID:      {}", pid);
             println!("  Log:      {}", log_path.display());
@@ -1279,6 +1470,7 @@ mod tests {
             no_instructions: false,
             no_git: false,
             no_auto_merge: false,
+            verify: None,
             verbose: false,
             no_hooks: false,
             notify: None,
@@ -1292,6 +1484,7 @@ mod tests {
             ],
             detach: false,
             internal_detached: false,
+            internal_task_id: None,
             #[cfg(feature = "dashboard")]
             dashboard: false,
         };
@@ -1320,6 +1513,7 @@ mod tests {
             no_instructions: false,
             no_git: false,
             no_auto_merge: false,
+            verify: None,
             verbose: false,
             no_hooks: false,
             notify: None,
@@ -1330,6 +1524,7 @@ mod tests {
             template_vars: vec!["url=https://example.com/path?foo=bar".to_string()],
             detach: false,
             internal_detached: false,
+            internal_task_id: None,
             #[cfg(feature = "dashboard")]
             dashboard: false,
         };
@@ -1360,6 +1555,7 @@ mod tests {
             no_instructions: false,
             no_git: false,
             no_auto_merge: false,
+            verify: None,
             verbose: false,
             no_hooks: false,
             notify: None,
@@ -1370,6 +1566,7 @@ mod tests {
             template_vars: vec!["invalid_format".to_string()],
             detach: false,
             internal_detached: false,
+            internal_task_id: None,
             #[cfg(feature = "dashboard")]
             dashboard: false,
         };
@@ -1401,6 +1598,7 @@ mod tests {
             no_instructions: false,
             no_git: false,
             no_auto_merge: false,
+            verify: None,
             verbose: false,
             no_hooks: false,
             notify: None,
@@ -1411,6 +1609,7 @@ mod tests {
             template_vars: vec![],
             detach: false,
             internal_detached: false,
+            internal_task_id: None,
             #[cfg(feature = "dashboard")]
             dashboard: false,
         };
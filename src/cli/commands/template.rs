@@ -311,6 +311,7 @@ impl TemplateUseArgs {
             no_instructions: false,
             no_git: false,
             no_auto_merge: false,
+            verify: None,
             verbose: false,
             no_hooks: false,
             notify: None,
@@ -321,6 +322,7 @@ impl TemplateUseArgs {
             template_vars: vec![],
             detach: false,
             internal_detached: false,
+            internal_task_id: None,
             #[cfg(feature = "dashboard")]
             dashboard: false,
         };
@@ -1,13 +1,28 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Args, Subcommand};
+use std::collections::VecDeque;
 use std::path::PathBuf;
-
-use crate::executor::{ParallelConfig, ParallelExecutor, TaskDefinition};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::claude::AgentBackend;
+use crate::executor::{ParallelConfig, ParallelExecutor, TaskDefinition, TaskResult, TaskStatus};
+use crate::git::integration::{IntegrationBranch, MergeOutcome};
+use crate::git::pr::{generate_pr_body, PrManager, PullRequest};
+use crate::git::workflow::build_commit;
+use crate::git::worktree::Worktree;
+use crate::hooks::{HookContext, HookExecutor, HookType, HooksConfig};
+use crate::loop_engine::CompletionStrategy;
+use crate::notifications::{NotificationEvent, NotificationManager, NotificationPayload};
 use crate::output::{OutputFormat, OutputWriter, StepOutput, WorkflowOutput};
 use crate::pricing::format_cost;
 use crate::workflow::{
-    DagScheduler, StepStatus, WorkflowDefinition, WorkflowState, WorkflowStateManager, WorkflowStatus,
+    capture_outputs, render_prompt, DagScheduler, DependencyFailure, PullRequestMode, StepDecision,
+    StepStatus, WorkflowDefinition, WorkflowGitState, WorkflowState, WorkflowStateManager,
+    WorkflowStatus, WorkflowStep,
 };
+use crate::utils::{format_duration, truncate_str};
 
 /// Workflow management commands
 #[derive(Args, Debug)]
@@ -54,6 +69,14 @@ pub struct WorkflowRunArgs {
     #[arg(long)]
     pub sandbox: bool,
 
+    /// Disable hooks execution
+    #[arg(long)]
+    pub no_hooks: bool,
+
+    /// Disable notifications
+    #[arg(long)]
+    pub no_notify: bool,
+
     /// Output format (text, json, json-pretty, yaml, markdown)
     #[arg(long, short = 'f', default_value = "text")]
     pub format: String,
@@ -85,6 +108,14 @@ pub struct WorkflowResumeArgs {
     /// Run in sandbox mode (Docker)
     #[arg(long)]
     pub sandbox: bool,
+
+    /// Disable hooks execution
+    #[arg(long)]
+    pub no_hooks: bool,
+
+    /// Disable notifications
+    #[arg(long)]
+    pub no_notify: bool,
 }
 
 /// Arguments for validating a workflow
@@ -126,15 +157,16 @@ impl WorkflowRunArgs {
             return self.execute_dry_run(&workflow).await;
         }
 
-        // Create scheduler
-        let scheduler = DagScheduler::new(workflow.clone());
-        let groups = scheduler.get_execution_groups();
+        // Refuse to start once a daily or monthly spend cap is reached
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        doodoori_config.cost.ensure_spend_available(&std::env::current_dir()?)?;
 
         // Determine workers
         let workers = self.workers.unwrap_or(workflow.global.max_parallel_workers);
 
         // Determine budget
         let budget = self.budget.or(workflow.global.budget_usd);
+        let cost_ledger = doodoori_config.cost.ledger_path();
 
         println!("=== Workflow: {} ===\n", workflow.name);
         println!("Workers: {}", workers);
@@ -160,115 +192,41 @@ impl WorkflowRunArgs {
         // Save initial state
         state_manager.save(&state)?;
         println!("Workflow ID: {}", state.short_id());
-
-        // Execute groups sequentially
-        for (group_idx, group) in groups.iter().enumerate() {
-            state.current_group = group_idx as u32;
-            println!("=== Group {} ({} steps) ===", group_idx, group.len());
-
-            if group.len() == 1 {
-                // Single step, run directly
-                let step = group[0];
-                println!("  Running: {} ({})", step.name, workflow.get_step_model(step));
-
-                state.update_step(&step.name, StepStatus::Running, 0.0, None);
-                state_manager.save(&state)?;
-
-                // Create task definition
-                let task = TaskDefinition::new(
-                    step.prompt.clone().unwrap_or_else(|| format!("Execute step: {}", step.name))
-                )
-                .with_model(workflow.get_step_model(step))
-                .with_max_iterations(step.max_iterations)
-                .with_yolo_mode(self.yolo);
-
-                // Execute using parallel executor (single task)
-                let executor = ParallelExecutor::with_workers(1);
-                let result = executor.execute_and_wait(vec![task]).await?;
-
-                if result.succeeded == 1 {
-                    state.update_step(&step.name, StepStatus::Completed, result.total_cost, None);
-                    state_manager.save(&state)?;
-                    println!("  ✓ Completed: {} ({})", step.name, format_cost(result.total_cost));
-                } else {
-                    let error = result.tasks.first()
-                        .and_then(|t| t.error.clone())
-                        .unwrap_or_else(|| "Unknown error".to_string());
-                    state.update_step(&step.name, StepStatus::Failed, result.total_cost, Some(error.clone()));
-                    state.status = WorkflowStatus::Failed;
-                    state_manager.save(&state)?;
-                    println!("  ✗ Failed: {} - {}", step.name, error);
-                    break;
-                }
-            } else {
-                // Multiple steps, run in parallel
-                let tasks: Vec<TaskDefinition> = group
-                    .iter()
-                    .map(|step| {
-                        state.update_step(&step.name, StepStatus::Running, 0.0, None);
-                        TaskDefinition::new(
-                            step.prompt.clone().unwrap_or_else(|| format!("Execute step: {}", step.name))
-                        )
-                        .with_model(workflow.get_step_model(step))
-                        .with_max_iterations(step.max_iterations)
-                        .with_yolo_mode(self.yolo)
-                    })
-                    .collect();
-
-                state_manager.save(&state)?;
-
-                println!("  Running {} tasks in parallel...", tasks.len());
-                for step in group.iter() {
-                    println!("    - {} ({})", step.name, workflow.get_step_model(step));
-                }
-
-                let config = ParallelConfig {
-                    workers,
-                    total_budget: budget,
-                    fail_fast: false,
-                    sandbox: self.sandbox,
-                    base_working_dir: std::env::current_dir().ok(),
-                    task_isolation: true,
-                    use_git_worktrees: false,
-                    git_branch_prefix: "task/".to_string(),
-                    git_auto_commit: false,
-                    git_auto_pr: false,
-                };
-
-                let executor = ParallelExecutor::new(config);
-                let result = executor.execute_and_wait(tasks).await?;
-
-                // Update states based on results
-                for (i, task_result) in result.tasks.iter().enumerate() {
-                    let step_name = &group[i].name;
-                    if task_result.status == crate::executor::TaskStatus::Completed {
-                        state.update_step(step_name, StepStatus::Completed, task_result.total_cost, None);
-                        println!("  ✓ Completed: {} ({})", step_name, format_cost(task_result.total_cost));
-                    } else {
-                        let error = task_result.error.clone().unwrap_or_else(|| "Unknown error".to_string());
-                        state.update_step(step_name, StepStatus::Failed, task_result.total_cost, Some(error.clone()));
-                        println!("  ✗ Failed: {} - {}", step_name, error);
-                    }
-                }
-
-                state_manager.save(&state)?;
-
-                if result.failed > 0 {
-                    state.status = WorkflowStatus::Failed;
-                    state_manager.save(&state)?;
-                    println!("\nWorkflow failed: {} steps failed", result.failed);
-                    break;
-                }
-            }
-
-            println!();
+        let integration = open_integration(&workflow, &mut state)?;
+        let hooks = (!self.no_hooks).then(|| doodoori_config.hooks.to_hooks_config());
+        let reporter =
+            WorkflowReporter::new(&workflow, &doodoori_config, hooks.as_ref(), self.no_notify)?;
+        reporter.started(&state).await;
+
+        // Start each step as soon as its dependencies have finished
+        let runner = StepRunner {
+            workflow: &workflow,
+            state_manager: &state_manager,
+            yolo: self.yolo,
+            sandbox: self.sandbox,
+            workers,
+            budget,
+            cost_ledger,
+            integration: integration.as_ref(),
+            hooks,
+            reporter: &reporter,
+            backend: None,
+        };
+        let outcome = runner.run(DagScheduler::new(workflow.clone()), &mut state).await?;
+        if !outcome.succeeded {
+            state.status = WorkflowStatus::Failed;
         }
+        println!();
 
         // Final status
         if state.status != WorkflowStatus::Failed {
             state.status = WorkflowStatus::Completed;
         }
+        if let Some(integration) = integration {
+            close_integration(&workflow, &mut state, integration).await;
+        }
         state_manager.save(&state)?;
+        reporter.finished(&state, outcome.wall_ms).await;
 
         // Parse output format
         let output_format: OutputFormat = self.format.parse().unwrap_or_default();
@@ -276,6 +234,8 @@ impl WorkflowRunArgs {
         if output_format == OutputFormat::Text {
             println!("=== Workflow {} ===", if state.status == WorkflowStatus::Completed { "Completed" } else { "Failed" });
             println!("Total cost: {}", format_cost(state.total_cost_usd));
+            outcome.print_timing();
+            print_git_summary(&state);
             println!("Workflow ID: {}", state.short_id());
 
             if state.status == WorkflowStatus::Failed {
@@ -284,12 +244,15 @@ impl WorkflowRunArgs {
         } else {
             // Build structured output
             let mut output = WorkflowOutput::new(workflow.name.clone(), workflow_id.clone())
-                .with_status(format!("{:?}", state.status));
+                .with_status(format!("{:?}", state.status))
+                .with_duration(outcome.wall_ms)
+                .with_step_duration(outcome.step_ms);
 
             for (name, step_state) in &state.steps {
                 let mut step_output = StepOutput::new(name.clone())
                     .with_status(format!("{:?}", step_state.status).to_lowercase())
-                    .with_cost(step_state.cost_usd);
+                    .with_cost(step_state.cost_usd)
+                    .with_duration(step_state.duration_ms);
 
                 if let Some(ref error) = step_state.error {
                     step_output = step_output.with_error(error);
@@ -326,8 +289,22 @@ impl WorkflowRunArgs {
             println!("  Total budget: ${:.2}", budget);
         }
         println!("  Completion promise: {}", workflow.global.completion_promise);
+        if workflow.uses_worktrees() {
+            println!("  Integration branch: {}", workflow.integration_branch());
+            match workflow.global.git.pull_requests {
+                PullRequestMode::None => {}
+                PullRequestMode::Workflow => println!("  Pull requests: one for the workflow"),
+                PullRequestMode::Step => println!("  Pull requests: one per step"),
+            }
+        }
+        let hooks = workflow.global.hooks.configured();
+        if !hooks.is_empty() {
+            let names: Vec<&str> = hooks.iter().map(HookType::as_str).collect();
+            println!("  Hooks: {}", names.join(", "));
+        }
 
         println!("\n[Execution Plan]");
+        println!("  Steps start as soon as their dependencies finish; groups only order steps that are ready together.");
 
         let mut total_budget = 0.0;
 
@@ -359,16 +336,34 @@ impl WorkflowRunArgs {
                 );
 
                 if let Some(ref prompt) = step.prompt {
-                    let short_prompt = if prompt.len() > 50 {
-                        format!("{}...", &prompt[..47])
-                    } else {
-                        prompt.clone()
-                    };
-                    println!("    └─ \"{}\"", short_prompt);
+                    println!("    └─ \"{}\"", truncate_str(prompt, 50));
                 }
                 if let Some(ref spec) = step.spec {
                     println!("    └─ spec: {}", spec);
                 }
+                print_step_branches(workflow, step);
+            }
+        }
+
+        let handlers: Vec<&WorkflowStep> = workflow.steps.iter()
+            .filter(|step| workflow.is_failure_handler(&step.name))
+            .collect();
+        if !handlers.is_empty() {
+            println!("\n[Failure Handlers]");
+            for handler in handlers {
+                let handles: Vec<&str> = workflow.steps.iter()
+                    .filter(|s| s.on_failure.as_deref() == Some(handler.name.as_str()))
+                    .map(|s| s.name.as_str())
+                    .collect();
+                println!("  {} │ {:?} │ runs when {} fails",
+                    handler.name,
+                    workflow.get_step_model(handler),
+                    handles.join(" or ")
+                );
+                if let Some(ref prompt) = handler.prompt {
+                    println!("    └─ \"{}\"", truncate_str(prompt, 50));
+                }
+                print_step_branches(workflow, handler);
             }
         }
 
@@ -401,6 +396,10 @@ impl WorkflowResumeArgs {
             return self.list_resumable(&state_manager).await;
         }
 
+        // Refuse to start once a daily or monthly spend cap is reached
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        doodoori_config.cost.ensure_spend_available(&std::env::current_dir()?)?;
+
         let workflow_id = self.workflow_id.as_ref()
             .ok_or_else(|| anyhow::anyhow!("Workflow ID is required"))?;
 
@@ -455,22 +454,18 @@ impl WorkflowResumeArgs {
             scheduler.mark_completed(step_name);
         }
 
-        // Get remaining groups to execute
-        let groups = scheduler.get_execution_groups();
-        let resumable_groups: Vec<_> = groups.iter()
-            .filter(|group| {
-                group.iter().any(|step| !completed_steps.contains(&step.name))
-            })
-            .collect();
+        let remaining = workflow.steps.iter()
+            .filter(|step| !workflow.is_failure_handler(&step.name) && !completed_steps.contains(&step.name))
+            .count();
 
-        if resumable_groups.is_empty() {
+        if remaining == 0 {
             println!("All steps are already completed!");
             state.status = WorkflowStatus::Completed;
             state_manager.save(&state)?;
             return Ok(());
         }
 
-        println!("Resuming from {} remaining groups...", resumable_groups.len());
+        println!("Resuming {} remaining steps...", remaining);
         println!();
 
         // Update state to running
@@ -479,121 +474,47 @@ impl WorkflowResumeArgs {
 
         // Determine workers and budget
         let workers = workflow.global.max_parallel_workers;
-
-        // Execute remaining groups
-        for group in resumable_groups {
-            // Filter to only non-completed steps
-            let pending_steps: Vec<_> = group.iter()
-                .filter(|step| !completed_steps.contains(&step.name))
-                .collect();
-
-            if pending_steps.is_empty() {
-                continue;
-            }
-
-            state.current_group += 1;
-            println!("=== Group {} ({} steps) ===", state.current_group, pending_steps.len());
-
-            if pending_steps.len() == 1 {
-                let step = pending_steps[0];
-                println!("  Running: {} ({})", step.name, workflow.get_step_model(step));
-
-                state.update_step(&step.name, StepStatus::Running, 0.0, None);
-                state_manager.save(&state)?;
-
-                let task = TaskDefinition::new(
-                    step.prompt.clone().unwrap_or_else(|| format!("Execute step: {}", step.name))
-                )
-                .with_model(workflow.get_step_model(step))
-                .with_max_iterations(step.max_iterations)
-                .with_yolo_mode(self.yolo);
-
-                let executor = ParallelExecutor::with_workers(1);
-                let result = executor.execute_and_wait(vec![task]).await?;
-
-                if result.succeeded == 1 {
-                    state.update_step(&step.name, StepStatus::Completed, result.total_cost, None);
-                    state_manager.save(&state)?;
-                    println!("  ✓ Completed: {} ({})", step.name, format_cost(result.total_cost));
-                } else {
-                    let error = result.tasks.first()
-                        .and_then(|t| t.error.clone())
-                        .unwrap_or_else(|| "Unknown error".to_string());
-                    state.update_step(&step.name, StepStatus::Failed, result.total_cost, Some(error.clone()));
-                    state.status = WorkflowStatus::Failed;
-                    state_manager.save(&state)?;
-                    println!("  ✗ Failed: {} - {}", step.name, error);
-                    break;
-                }
-            } else {
-                let tasks: Vec<TaskDefinition> = pending_steps.iter()
-                    .map(|step| {
-                        state.update_step(&step.name, StepStatus::Running, 0.0, None);
-                        TaskDefinition::new(
-                            step.prompt.clone().unwrap_or_else(|| format!("Execute step: {}", step.name))
-                        )
-                        .with_model(workflow.get_step_model(step))
-                        .with_max_iterations(step.max_iterations)
-                        .with_yolo_mode(self.yolo)
-                    })
-                    .collect();
-
-                state_manager.save(&state)?;
-
-                println!("  Running {} tasks in parallel...", tasks.len());
-                for step in pending_steps.iter() {
-                    println!("    - {} ({})", step.name, workflow.get_step_model(step));
-                }
-
-                let config = ParallelConfig {
-                    workers,
-                    total_budget: None,
-                    fail_fast: false,
-                    sandbox: self.sandbox,
-                    base_working_dir: std::env::current_dir().ok(),
-                    task_isolation: true,
-                    use_git_worktrees: false,
-                    git_branch_prefix: "task/".to_string(),
-                    git_auto_commit: false,
-                    git_auto_pr: false,
-                };
-
-                let executor = ParallelExecutor::new(config);
-                let result = executor.execute_and_wait(tasks).await?;
-
-                for (i, task_result) in result.tasks.iter().enumerate() {
-                    let step_name = &pending_steps[i].name;
-                    if task_result.status == crate::executor::TaskStatus::Completed {
-                        state.update_step(step_name, StepStatus::Completed, task_result.total_cost, None);
-                        println!("  ✓ Completed: {} ({})", step_name, format_cost(task_result.total_cost));
-                    } else {
-                        let error = task_result.error.clone().unwrap_or_else(|| "Unknown error".to_string());
-                        state.update_step(step_name, StepStatus::Failed, task_result.total_cost, Some(error.clone()));
-                        println!("  ✗ Failed: {} - {}", step_name, error);
-                    }
-                }
-
-                state_manager.save(&state)?;
-
-                if result.failed > 0 {
-                    state.status = WorkflowStatus::Failed;
-                    state_manager.save(&state)?;
-                    println!("\nWorkflow failed: {} steps failed", result.failed);
-                    break;
-                }
-            }
-
-            println!();
+        let cost_ledger = doodoori_config.cost.ledger_path();
+
+        let integration = open_integration(&workflow, &mut state)?;
+        let hooks = (!self.no_hooks).then(|| doodoori_config.hooks.to_hooks_config());
+        let reporter =
+            WorkflowReporter::new(&workflow, &doodoori_config, hooks.as_ref(), self.no_notify)?;
+        reporter.started(&state).await;
+
+        let runner = StepRunner {
+            workflow: &workflow,
+            state_manager: &state_manager,
+            yolo: self.yolo,
+            sandbox: self.sandbox,
+            workers,
+            budget: workflow.global.budget_usd,
+            cost_ledger,
+            integration: integration.as_ref(),
+            hooks,
+            reporter: &reporter,
+            backend: None,
+        };
+        let outcome = runner.run(scheduler, &mut state).await?;
+        if !outcome.succeeded {
+            state.status = WorkflowStatus::Failed;
         }
+        println!();
 
         // Final status
         if state.status != WorkflowStatus::Failed {
             state.status = WorkflowStatus::Completed;
         }
+        if let Some(integration) = integration {
+            close_integration(&workflow, &mut state, integration).await;
+        }
         state_manager.save(&state)?;
+        reporter.finished(&state, outcome.wall_ms).await;
 
         println!("=== Workflow {} ===", if state.status == WorkflowStatus::Completed { "Completed" } else { "Failed" });
         println!("Total cost: {}", format_cost(state.total_cost_usd));
+        outcome.print_timing();
+        print_git_summary(&state);
 
         if state.status == WorkflowStatus::Failed {
             println!("\nTo resume again: doodoori workflow resume {}", state.short_id());
@@ -705,3 +626,1060 @@ impl WorkflowInfoArgs {
         Ok(())
     }
 }
+
+/// Dry-run lines for a step's working tree, permissions, hooks, condition,
+/// retries and failure policy
+fn print_step_branches(workflow: &WorkflowDefinition, step: &WorkflowStep) {
+    if workflow.uses_worktree(step) {
+        println!("    └─ worktree: own branch, merged into {}", workflow.integration_branch());
+    }
+    if let Some(ref dir) = step.working_dir {
+        println!("    └─ working_dir: {}", dir.display());
+    }
+    match step.yolo {
+        Some(true) => println!("    └─ yolo"),
+        Some(false) => println!("    └─ asks for permissions (no yolo)"),
+        None => {}
+    }
+    if step.readonly {
+        println!("    └─ readonly");
+    }
+    if let Some(ref tools) = step.allowed_tools {
+        println!("    └─ allowed tools: {}", tools);
+    }
+    if let Some(ref path) = step.system_prompt {
+        println!("    └─ system prompt: {}", path.display());
+    }
+    if let Some(ref hooks) = step.hooks {
+        let names: Vec<&str> = hooks.configured().iter().map(HookType::as_str).collect();
+        match (hooks.enabled, names.is_empty()) {
+            (false, _) => println!("    └─ hooks: disabled"),
+            (true, false) => println!("    └─ hooks: {}", names.join(", ")),
+            (true, true) => {}
+        }
+    }
+    if let Some(ref when) = step.when {
+        println!("    └─ when: {} (otherwise skipped)", when);
+    }
+    if let Some(ref retry) = step.retry {
+        let models: Vec<String> = (1..=retry.attempts)
+            .map(|attempt| format!("{:?}", workflow.get_attempt_model(step, attempt)))
+            .collect();
+        println!("    └─ retry: {} attempts, backoff {}s doubling ({})",
+            retry.attempts,
+            retry.backoff_secs,
+            models.join(" → ")
+        );
+    }
+    if step.when.is_none() && !step.depends_on.is_empty() && step.on_dependency_failure == DependencyFailure::Fail {
+        println!("    └─ fails if a dependency fails or is skipped");
+    }
+    match (&step.on_failure, step.continue_on_error) {
+        (Some(handler), true) => println!("    └─ on failure: run {}, continue", handler),
+        (Some(handler), false) => println!("    └─ on failure: run {}, stop", handler),
+        (None, true) => println!("    └─ on failure: continue"),
+        (None, false) => {}
+    }
+}
+
+/// Build the task definition for a workflow step. `hooks` are the loop
+/// hooks doodoori.toml configures (None when hooks are disabled).
+fn step_task(
+    workflow: &WorkflowDefinition,
+    step: &WorkflowStep,
+    yolo: bool,
+    hooks: Option<&HooksConfig>,
+    attempt: u32,
+    state: &WorkflowState,
+) -> Result<TaskDefinition> {
+    // Fill in outputs of upstream steps
+    let prompt = match step.prompt {
+        Some(ref prompt) => render_prompt(prompt, &state.step_outputs())
+            .with_context(|| format!("Failed to render the prompt of step '{}'", step.name))?,
+        None => format!("Execute step: {}", step.name),
+    };
+
+    let mut task = TaskDefinition::new(prompt)
+        .with_model(workflow.get_attempt_model(step, attempt))
+        .with_max_iterations(step.max_iterations)
+        .with_yolo_mode(step.yolo.unwrap_or(yolo))
+        .with_readonly(step.readonly)
+        .with_completion_strategy(CompletionStrategy::from_promise(&workflow.global.completion_promise));
+
+    // Steps may run in a worktree or workspace, so files are resolved here
+    let cwd = std::env::current_dir()?;
+    if let Some(ref tools) = step.allowed_tools {
+        task = task.with_allowed_tools(tools);
+    }
+    if let Some(ref path) = step.system_prompt {
+        task = task.with_system_prompt(cwd.join(path));
+    }
+    if let Some(hooks) = hooks {
+        task = task.with_hooks(
+            workflow
+                .get_step_hooks(step, hooks.clone())
+                .relative_to(&cwd),
+        );
+    }
+
+    if let Some(verification) = workflow.get_step_verification(step) {
+        task = task.with_verification(verification);
+    }
+    if let Some(ref policy) = step.model_policy {
+        task = task.with_model_policy(policy.clone());
+    }
+
+    Ok(task)
+}
+
+/// Runs workflow steps as soon as their dependencies finish, up to
+/// `workers` at a time. Evaluates `when` conditions, retries failed steps
+/// and runs failure handlers.
+struct StepRunner<'a> {
+    workflow: &'a WorkflowDefinition,
+    state_manager: &'a WorkflowStateManager,
+    yolo: bool,
+    sandbox: bool,
+    workers: usize,
+    budget: Option<f64>,
+    cost_ledger: Option<PathBuf>,
+    /// Branch that steps running in worktrees are merged into
+    integration: Option<&'a IntegrationBranch>,
+    /// Loop hooks from doodoori.toml (None when hooks are disabled)
+    hooks: Option<HooksConfig>,
+    reporter: &'a WorkflowReporter<'a>,
+    /// Agent backend the steps run on (Claude Code when None)
+    backend: Option<Arc<dyn AgentBackend>>,
+}
+
+/// A finished attempt of a step
+struct StepJob {
+    step: String,
+    attempt: u32,
+    /// Worktree the attempt ran in
+    worktree: Option<Worktree>,
+    result: Result<TaskResult>,
+}
+
+/// A retry or failure handler waiting for a free worker
+struct QueuedAttempt {
+    step: String,
+    attempt: u32,
+    /// Wait before the attempt runs
+    delay: Duration,
+}
+
+/// Bookkeeping of a run in progress
+struct RunProgress {
+    scheduler: DagScheduler,
+    jobs: JoinSet<StepJob>,
+    /// Retries and failure handlers to start before new steps
+    queued: VecDeque<QueuedAttempt>,
+    /// Failed steps that stop the workflow
+    stopping: usize,
+    budget_exceeded: bool,
+    /// Time the steps ran for, summed
+    step_ms: u64,
+    /// Steps that completed or failed since they were last reported
+    finished: Vec<String>,
+}
+
+impl RunProgress {
+    /// Whether new steps may still be started
+    fn dispatching(&self) -> bool {
+        self.stopping == 0 && !self.budget_exceeded
+    }
+}
+
+/// Outcome of running a workflow's steps
+struct RunOutcome {
+    /// False if a step failure or the budget stopped the workflow
+    succeeded: bool,
+    /// Wall-clock time of the run
+    wall_ms: u64,
+    /// Time the steps ran for, summed
+    step_ms: u64,
+}
+
+impl RunOutcome {
+    fn print_timing(&self) {
+        if self.wall_ms > 0 && self.step_ms > 0 {
+            println!(
+                "Wall-clock time: {} (steps ran {} in total, {:.1}x speedup)",
+                format_duration(self.wall_ms),
+                format_duration(self.step_ms),
+                self.step_ms as f64 / self.wall_ms as f64
+            );
+        }
+    }
+}
+
+impl<'a> StepRunner<'a> {
+    /// Run every step the scheduler has not marked finished
+    async fn run(&self, scheduler: DagScheduler, state: &mut WorkflowState) -> Result<RunOutcome> {
+        let started = std::time::Instant::now();
+        let mut progress = RunProgress {
+            scheduler,
+            jobs: JoinSet::new(),
+            queued: VecDeque::new(),
+            stopping: 0,
+            budget_exceeded: false,
+            step_ms: 0,
+            finished: Vec::new(),
+        };
+
+        self.dispatch(&mut progress, state)?;
+        self.report(&mut progress, state).await;
+        while let Some(joined) = progress.jobs.join_next().await {
+            let job = joined.context("Workflow step task failed")?;
+            self.finish(job, &mut progress, state);
+            self.report(&mut progress, state).await;
+            self.dispatch(&mut progress, state)?;
+            self.report(&mut progress, state).await;
+        }
+
+        if progress.stopping > 0 {
+            println!("\nWorkflow failed: {} steps failed", progress.stopping);
+        }
+        Ok(RunOutcome {
+            succeeded: progress.stopping == 0 && !progress.budget_exceeded,
+            wall_ms: started.elapsed().as_millis() as u64,
+            step_ms: progress.step_ms,
+        })
+    }
+
+    /// Run hooks and send notifications for the steps that finished
+    async fn report(&self, progress: &mut RunProgress, state: &WorkflowState) {
+        for name in std::mem::take(&mut progress.finished) {
+            self.reporter.step_finished(&name, state).await;
+        }
+    }
+
+    /// Start queued retries and failure handlers, then ready steps, while
+    /// workers are free, settling the steps that are skipped or failed by
+    /// their condition or dependencies
+    fn dispatch(&self, progress: &mut RunProgress, state: &mut WorkflowState) -> Result<()> {
+        let mut settled = true;
+        while settled {
+            settled = false;
+            self.start_queued(progress, state);
+            if !progress.dispatching() {
+                break;
+            }
+            let ready: Vec<&'a WorkflowStep> = progress.scheduler.get_ready_steps()
+                .iter()
+                .filter_map(|step| self.workflow.get_step(&step.name))
+                .collect();
+
+            for step in ready {
+                if !progress.dispatching() {
+                    break;
+                }
+                match progress.scheduler.decide(step, state) {
+                    StepDecision::Run => {
+                        if !self.may_start(progress, state) {
+                            continue;
+                        }
+                        progress.scheduler.mark_started(&step.name);
+                        self.start(step, 1, Duration::ZERO, progress, state);
+                    }
+                    StepDecision::Skip(reason) => {
+                        state.update_step(&step.name, StepStatus::Skipped, 0.0, None);
+                        progress.scheduler.mark_skipped(&step.name);
+                        println!("  ○ Skipped: {} ({})", step.name, reason);
+                        settled = true;
+                    }
+                    StepDecision::Fail(reason) => {
+                        self.fail(step, reason, progress, state);
+                        settled = true;
+                    }
+                }
+            }
+        }
+        self.state_manager.save(state)
+    }
+
+    /// Start queued attempts while workers are free
+    fn start_queued(&self, progress: &mut RunProgress, state: &mut WorkflowState) {
+        while !progress.queued.is_empty() && self.may_start(progress, state) {
+            let Some(queued) = progress.queued.pop_front() else {
+                break;
+            };
+            if let Some(step) = self.workflow.get_step(&queued.step) {
+                progress.scheduler.mark_started(&step.name);
+                self.start(step, queued.attempt, queued.delay, progress, state);
+            }
+        }
+    }
+
+    /// Whether another attempt may start: a worker is free and the budget
+    /// is not used up. Every start goes through here.
+    fn may_start(&self, progress: &mut RunProgress, state: &WorkflowState) -> bool {
+        if progress.budget_exceeded || progress.jobs.len() >= self.workers.max(1) {
+            return false;
+        }
+        if let Some(budget) = self.budget.filter(|b| state.total_cost_usd >= *b) {
+            println!("  Budget of {} reached; not starting more steps", format_cost(budget));
+            progress.budget_exceeded = true;
+            return false;
+        }
+        true
+    }
+
+    /// Start an attempt of a step after `delay`
+    fn start(
+        &self,
+        step: &'a WorkflowStep,
+        attempt: u32,
+        delay: Duration,
+        progress: &mut RunProgress,
+        state: &mut WorkflowState,
+    ) {
+        let mut task = match step_task(
+            self.workflow,
+            step,
+            self.yolo,
+            self.hooks.as_ref(),
+            attempt,
+            state,
+        ) {
+            Ok(task) => task,
+            Err(e) => return self.fail(step, format!("{:#}", e), progress, state),
+        };
+
+        let worktree = match self.integration.filter(|_| self.workflow.uses_worktree(step)) {
+            Some(integration) => {
+                let name = format!("{} {}", self.workflow.name, step.name);
+                match integration.create_step_worktree(&task.task_id, &name, &self.workflow.global.git.branch_prefix) {
+                    Ok(worktree) => Some(worktree),
+                    Err(e) => return self.fail(step, format!("Failed to create a worktree: {}", e), progress, state),
+                }
+            }
+            None => None,
+        };
+        if let Some(dir) = step_working_dir(step, worktree.as_ref()) {
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                return self.fail(step, format!("Failed to create {}: {}", dir.display(), e), progress, state);
+            }
+            task = task.with_working_dir(dir);
+        }
+
+        if delay.is_zero() {
+            match worktree {
+                Some(ref worktree) => println!("  Running: {} ({}) on {}", step.name, task.model, worktree.branch),
+                None => println!("  Running: {} ({})", step.name, task.model),
+            }
+        }
+        state.update_step(&step.name, StepStatus::Running, 0.0, None);
+        if let Some(ref worktree) = worktree {
+            state.set_step_branch(&step.name, &worktree.branch);
+        }
+
+        let config = ParallelConfig {
+            workers: 1,
+            total_budget: self.budget.map(|budget| budget - state.total_cost_usd),
+            sandbox: self.sandbox,
+            base_working_dir: std::env::current_dir().ok(),
+            task_isolation: task.working_dir.is_none() && self.isolated(step),
+            cost_ledger: self.cost_ledger.clone(),
+            ..Default::default()
+        };
+        let mut executor = ParallelExecutor::new(config);
+        if let Some(ref backend) = self.backend {
+            executor = executor.with_backend(backend.clone());
+        }
+        let name = step.name.clone();
+        progress.jobs.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let result = executor
+                .execute_and_wait(vec![task])
+                .await
+                .and_then(|result| result.tasks.into_iter().next().context("Step finished without a result"));
+            StepJob { step: name, attempt, worktree, result }
+        });
+    }
+
+    /// Steps that may run alongside another step run in their own workspace
+    fn isolated(&self, step: &WorkflowStep) -> bool {
+        self.workers > 1 && self.workflow.steps.iter().any(|other| self.workflow.can_overlap(step, other))
+    }
+
+    /// Record a finished attempt, retrying the step if it failed and has
+    /// attempts left
+    fn finish(&self, job: StepJob, progress: &mut RunProgress, state: &mut WorkflowState) {
+        let Some(step) = self.workflow.get_step(&job.step) else {
+            return;
+        };
+        if let Ok(ref result) = job.result {
+            progress.step_ms += result.duration_ms;
+            state.add_step_duration(&step.name, result.duration_ms);
+        }
+
+        // A completed step in a worktree is merged once its outputs are captured
+        let worktree = job.worktree;
+        let completed = record_step_result(state, step, job.result, |task_id| match worktree {
+            Some(ref worktree) => self.merge_step(step, worktree, task_id),
+            None => Ok(()),
+        });
+        if let (Some(integration), Some(worktree)) = (self.integration, &worktree) {
+            integration.remove_step_worktree(worktree);
+        }
+        if completed {
+            progress.scheduler.mark_completed(&step.name);
+            progress.finished.push(step.name.clone());
+            return;
+        }
+
+        let retry = step.retry.as_ref().filter(|r| job.attempt < r.attempts && progress.dispatching());
+        match retry {
+            Some(retry) => {
+                let attempt = job.attempt + 1;
+                let delay = retry.delay(job.attempt);
+                println!(
+                    "  ↻ Retrying: {} (attempt {}/{}, {}) in {}s",
+                    step.name,
+                    attempt,
+                    retry.attempts,
+                    self.workflow.get_attempt_model(step, attempt),
+                    delay.as_secs()
+                );
+                progress.queued.push_back(QueuedAttempt { step: step.name.clone(), attempt, delay });
+            }
+            None => {
+                progress.scheduler.mark_failed(&step.name);
+                progress.finished.push(step.name.clone());
+                self.handle_failure(step, progress, state);
+            }
+        }
+    }
+
+    /// Commit a completed step's changes and merge its branch into the
+    /// integration branch. A conflict fails the step.
+    fn merge_step(&self, step: &WorkflowStep, worktree: &Worktree, task_id: &str) -> std::result::Result<(), String> {
+        let Some(integration) = self.integration else {
+            return Ok(());
+        };
+        let task = step.prompt.as_deref().unwrap_or(&step.name);
+        let committed = integration
+            .commit_step(worktree, |files| build_commit(task, task_id, files))
+            .map_err(|e| format!("Failed to commit the changes on {}: {}", worktree.branch, e))?;
+        if committed.is_none() {
+            return Ok(());
+        }
+
+        let message = format!("Merge step '{}' ({})", step.name, worktree.branch);
+        match integration.merge(&worktree.branch, &message) {
+            Ok(MergeOutcome::Merged(_)) => {
+                println!("  ⇢ Merged {} into {}", worktree.branch, integration.branch());
+                Ok(())
+            }
+            Ok(MergeOutcome::UpToDate) => Ok(()),
+            Ok(MergeOutcome::Conflict(files)) => Err(format!(
+                "Merging {} into {} conflicts in {}",
+                worktree.branch,
+                integration.branch(),
+                files.join(", ")
+            )),
+            Err(e) => Err(format!("Failed to merge {}: {}", worktree.branch, e)),
+        }
+    }
+
+    /// Fail a step without running it
+    fn fail(&self, step: &'a WorkflowStep, error: String, progress: &mut RunProgress, state: &mut WorkflowState) {
+        println!("  ✗ Failed: {} - {}", step.name, error);
+        state.update_step(&step.name, StepStatus::Failed, 0.0, Some(error));
+        progress.scheduler.mark_failed(&step.name);
+        progress.finished.push(step.name.clone());
+        self.handle_failure(step, progress, state);
+    }
+
+    /// Start the failure handler of a failed step. The failure stops the
+    /// workflow unless the step continues on error.
+    fn handle_failure(&self, step: &'a WorkflowStep, progress: &mut RunProgress, state: &mut WorkflowState) {
+        if let Some(handler) = step.on_failure.as_deref().and_then(|name| self.workflow.get_step(name)) {
+            println!("  ↳ Handling failure of {}: {}", step.name, handler.name);
+            match progress.scheduler.decide(handler, state) {
+                StepDecision::Run => {
+                    progress.queued.push_back(QueuedAttempt {
+                        step: handler.name.clone(),
+                        attempt: 1,
+                        delay: Duration::ZERO,
+                    });
+                }
+                StepDecision::Skip(reason) => {
+                    state.update_step(&handler.name, StepStatus::Skipped, 0.0, None);
+                    progress.scheduler.mark_skipped(&handler.name);
+                    println!("  ○ Skipped: {} ({})", handler.name, reason);
+                }
+                StepDecision::Fail(reason) => self.fail(handler, reason, progress, state),
+            }
+        }
+
+        if step.continue_on_error {
+            println!("  Continuing after failure of {}", step.name);
+        } else {
+            progress.stopping += 1;
+        }
+    }
+}
+
+/// Runs the workflow hooks and sends workflow notifications
+struct WorkflowReporter<'a> {
+    workflow: &'a WorkflowDefinition,
+    /// None when hooks are disabled
+    hooks: Option<HookExecutor>,
+    notifications: NotificationManager,
+}
+
+impl<'a> WorkflowReporter<'a> {
+    /// Reporter for doodoori.toml's `hooks` (None when disabled) with the
+    /// workflow's global hooks in their place, and its notifications
+    fn new(
+        workflow: &'a WorkflowDefinition,
+        config: &crate::config::DoodooriConfig,
+        hooks: Option<&HooksConfig>,
+        no_notify: bool,
+    ) -> Result<Self> {
+        let hooks = hooks.map(|hooks| hooks.clone().merged_with(&workflow.global.hooks));
+        let hooks = match hooks {
+            Some(hooks) => Some(HookExecutor::new(hooks, std::env::current_dir()?)),
+            None => None,
+        };
+        let notifications = if no_notify {
+            NotificationManager::default()
+        } else {
+            NotificationManager::new(config.notifications.to_notifications_config())
+        };
+        Ok(Self {
+            workflow,
+            hooks,
+            notifications,
+        })
+    }
+
+    async fn started(&self, state: &WorkflowState) {
+        self.notify(self.payload(NotificationEvent::WorkflowStarted, state))
+            .await;
+    }
+
+    /// Report a step that completed or failed
+    async fn step_finished(&self, name: &str, state: &WorkflowState) {
+        let (Some(step), Some(step_state)) = (self.workflow.get_step(name), state.steps.get(name))
+        else {
+            return;
+        };
+        let model = self.workflow.get_step_model(step).to_string();
+
+        if step_state.status == StepStatus::Completed {
+            let mut context = self
+                .context(state)
+                .with_step(name)
+                .with_model(&model)
+                .with_status(step_state.status.as_str())
+                .with_cost(step_state.cost_usd);
+            if let Some(ref branch) = step_state.branch {
+                context = context.with_custom("branch", branch);
+            }
+            for (output, value) in &step_state.outputs {
+                context = context.with_custom(format!("output_{}", output), value);
+            }
+            self.run_hook(HookType::OnStepComplete, &context).await;
+        }
+
+        let event = match step_state.status {
+            StepStatus::Completed => NotificationEvent::StepCompleted,
+            _ => NotificationEvent::StepFailed,
+        };
+        let mut payload = self
+            .payload(event, state)
+            .with_step(name)
+            .with_model(model)
+            .with_cost(step_state.cost_usd)
+            .with_duration(step_state.duration_ms);
+        if let Some(ref prompt) = step.prompt {
+            payload = payload.with_prompt(prompt);
+        }
+        if let Some(ref error) = step_state.error {
+            payload = payload.with_error(error);
+        }
+        self.notify(payload).await;
+    }
+
+    /// Report the end of a run that took `wall_ms`
+    async fn finished(&self, state: &WorkflowState, wall_ms: u64) {
+        let failed: Vec<&str> = self
+            .workflow
+            .steps
+            .iter()
+            .filter(|step| {
+                state
+                    .steps
+                    .get(&step.name)
+                    .is_some_and(|s| s.status == StepStatus::Failed)
+            })
+            .map(|step| step.name.as_str())
+            .collect();
+        let error = (!failed.is_empty()).then(|| format!("Failed steps: {}", failed.join(", ")));
+        let (hook_type, event) = match state.status {
+            WorkflowStatus::Completed => (
+                HookType::OnWorkflowComplete,
+                NotificationEvent::WorkflowCompleted,
+            ),
+            _ => (HookType::OnWorkflowError, NotificationEvent::WorkflowFailed),
+        };
+
+        let mut context = self
+            .context(state)
+            .with_status(format!("{:?}", state.status).to_lowercase());
+        let mut payload = self.payload(event, state).with_duration(wall_ms);
+        if let Some(ref error) = error {
+            context = context.with_error(error);
+            payload = payload.with_error(error);
+        }
+        self.run_hook(hook_type, &context).await;
+        self.notify(payload).await;
+    }
+
+    /// Hook context with the workflow and its cost so far
+    fn context(&self, state: &WorkflowState) -> HookContext {
+        HookContext::new()
+            .with_workflow(&self.workflow.name, &state.workflow_id)
+            .with_cost(state.total_cost_usd)
+    }
+
+    fn payload(&self, event: NotificationEvent, state: &WorkflowState) -> NotificationPayload {
+        NotificationPayload::new(event, &state.workflow_id)
+            .with_workflow(&self.workflow.name, &state.workflow_id)
+            .with_cost(state.total_cost_usd)
+    }
+
+    /// Run a workflow hook. A failing hook is reported but does not change
+    /// the outcome of the workflow.
+    async fn run_hook(&self, hook_type: HookType, context: &HookContext) {
+        let Some(ref hooks) = self.hooks else { return };
+        if hooks.config().get(hook_type).is_none() {
+            return;
+        }
+        match hooks.execute(hook_type, context).await {
+            Ok(result) if result.success => {
+                println!("  ⚙ Hook {} ({}ms)", hook_type, result.duration_ms)
+            }
+            Ok(result) => println!(
+                "  ⚠ Hook {} failed: {}",
+                hook_type,
+                result
+                    .error
+                    .unwrap_or_else(|| result.stderr.trim().to_string())
+            ),
+            Err(e) => println!("  ⚠ Hook {} failed: {}", hook_type, e),
+        }
+    }
+
+    async fn notify(&self, payload: NotificationPayload) {
+        if self.notifications.is_enabled() {
+            self.notifications.notify_silent(&payload).await;
+        }
+    }
+}
+
+/// Record a finished step attempt in the workflow state, capturing its
+/// outputs and calling `integrate` with its task ID if it completed.
+/// Returns whether it completed.
+fn record_step_result(
+    state: &mut WorkflowState,
+    step: &WorkflowStep,
+    result: Result<TaskResult>,
+    integrate: impl FnOnce(&str) -> std::result::Result<(), String>,
+) -> bool {
+    let cost = result.as_ref().map_or(0.0, |r| r.total_cost);
+
+    let outcome = match result {
+        Ok(r) if r.status == TaskStatus::Completed => {
+            let working_dir = r.working_dir.clone()
+                .or_else(|| std::env::current_dir().ok())
+                .unwrap_or_else(|| PathBuf::from("."));
+            capture_outputs(step, r.output.as_deref(), &working_dir)
+                .map_err(|e| format!("{:#}", e))
+                .and_then(|outputs| integrate(&r.task_id).map(|()| outputs))
+        }
+        Ok(r) => Err(r.error.unwrap_or_else(|| "Unknown error".to_string())),
+        Err(e) => Err(format!("{:#}", e)),
+    };
+
+    match outcome {
+        Ok(outputs) => {
+            state.set_step_outputs(&step.name, outputs);
+            state.update_step(&step.name, StepStatus::Completed, cost, None);
+            println!("  ✓ Completed: {} ({})", step.name, format_cost(cost));
+            true
+        }
+        Err(error) => {
+            state.update_step(&step.name, StepStatus::Failed, cost, Some(error.clone()));
+            println!("  ✗ Failed: {} - {}", step.name, error);
+            false
+        }
+    }
+}
+
+/// Directory a step runs in, if not the current directory: its
+/// `working_dir`, inside its worktree when it has one
+fn step_working_dir(step: &WorkflowStep, worktree: Option<&Worktree>) -> Option<PathBuf> {
+    match (worktree, &step.working_dir) {
+        (Some(worktree), Some(dir)) => Some(worktree.path.join(dir)),
+        (Some(worktree), None) => Some(worktree.path.clone()),
+        (None, Some(dir)) => Some(std::env::current_dir().unwrap_or_default().join(dir)),
+        (None, None) => None,
+    }
+}
+
+/// Check out the integration branch when steps run in worktrees, creating
+/// it on the first run and reusing it on resume
+fn open_integration(workflow: &WorkflowDefinition, state: &mut WorkflowState) -> Result<Option<IntegrationBranch>> {
+    if !workflow.uses_worktrees() {
+        return Ok(None);
+    }
+
+    let (branch, base) = match state.git {
+        Some(ref git) => (git.integration_branch.clone(), Some(git.base_branch.as_str())),
+        None => (workflow.integration_branch(), None),
+    };
+    let worktree_id = format!("{}-integration", state.short_id());
+    let integration = IntegrationBranch::open(&std::env::current_dir()?, &worktree_id, &branch, base)
+        .context("Failed to set up the integration branch (steps in worktrees need a git repository)")?;
+
+    println!("Integration branch: {} (from {})", integration.branch(), integration.base_branch());
+    let pull_requests = state.git.take().map(|git| git.pull_requests).unwrap_or_default();
+    state.git = Some(WorkflowGitState {
+        integration_branch: integration.branch().to_string(),
+        base_branch: integration.base_branch().to_string(),
+        pull_requests,
+    });
+    Ok(Some(integration))
+}
+
+/// Open the configured pull requests of a completed workflow and remove
+/// the integration worktree
+async fn close_integration(workflow: &WorkflowDefinition, state: &mut WorkflowState, integration: IntegrationBranch) {
+    if state.status == WorkflowStatus::Completed {
+        for url in open_pull_requests(workflow, state, &integration).await {
+            if let Some(ref mut git) = state.git {
+                git.pull_requests.push(url);
+            }
+        }
+    }
+    integration.close();
+}
+
+/// Push the integration branch or the merged step branches and open pull
+/// requests against the base branch, returning their URLs
+async fn open_pull_requests(
+    workflow: &WorkflowDefinition,
+    state: &WorkflowState,
+    integration: &IntegrationBranch,
+) -> Vec<String> {
+    let heads: Vec<(String, String)> = match workflow.global.git.pull_requests {
+        PullRequestMode::None => return Vec::new(),
+        PullRequestMode::Workflow => vec![(workflow.name.clone(), integration.branch().to_string())],
+        PullRequestMode::Step => DagScheduler::new(workflow.clone())
+            .topological_order()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|name| {
+                let step = state.steps.get(&name)?;
+                let branch = step.branch.clone().filter(|_| step.status == StepStatus::Completed)?;
+                Some((format!("{}: {}", workflow.name, name), branch))
+            })
+            .collect(),
+    };
+
+    let manager = PrManager::new(integration.path());
+    let mut urls = Vec::new();
+    for (title, branch) in heads {
+        let opened = async {
+            let files = integration.files_changed(&branch)?;
+            if files.is_empty() {
+                return Ok(None);
+            }
+            manager.push_branch(&branch)?;
+            let description = format!("Automated by doodoori (workflow {}).", state.short_id());
+            let pr = PullRequest::new(title.clone(), generate_pr_body(&title, Some(&description), &files))
+                .with_base(integration.base_branch())
+                .with_head(branch.clone());
+            manager.create(&pr).await.map(Some)
+        };
+        match opened.await {
+            Ok(Some(url)) => {
+                println!("  Pull request: {}", url);
+                urls.push(url);
+            }
+            Ok(None) => {}
+            Err(e) => println!("  Failed to open a pull request for {}: {}", branch, e),
+        }
+    }
+    urls
+}
+
+/// Integration branch and pull requests of a workflow, if any
+fn print_git_summary(state: &WorkflowState) {
+    if let Some(ref git) = state.git {
+        println!("Integration branch: {} (from {})", git.integration_branch, git.base_branch);
+        for url in &git.pull_requests {
+            println!("Pull request: {}", url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::{AgentExecution, AgentRequest, CancelSignal, ReplayBackend};
+    use crate::config::DoodooriConfig;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    const COMPLETE: &str = r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}"#;
+    const UNFINISHED: &str = r#"{"type":"assistant","message":"Still working"}"#;
+
+    /// Backend that runs each step for a while and plays its scripts in
+    /// turn, recording when runs start and end. Steps are told apart by a
+    /// word in their prompt.
+    #[derive(Default)]
+    struct StepBackend {
+        /// Word in the prompt, run time and scripts of each step
+        steps: Vec<(&'static str, Duration, Vec<&'static str>)>,
+        runs: Mutex<HashMap<&'static str, usize>>,
+        log: Mutex<Vec<String>>,
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    impl StepBackend {
+        fn new(steps: Vec<(&'static str, Duration, Vec<&'static str>)>) -> Self {
+            Self { steps, ..Default::default() }
+        }
+
+        fn position(&self, entry: &str) -> usize {
+            let log = self.log.lock().unwrap();
+            log.iter().position(|e| e == entry).unwrap_or_else(|| panic!("no '{}' in {:?}", entry, log))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AgentBackend for StepBackend {
+        async fn execute(&self, request: AgentRequest, cancel: CancelSignal) -> Result<AgentExecution> {
+            let (word, run_time, scripts) = self
+                .steps
+                .iter()
+                .find(|(word, _, _)| request.prompt.contains(word))
+                .context("Prompt of an unknown step")?;
+            let run = {
+                let mut runs = self.runs.lock().unwrap();
+                let run = runs.entry(word).or_default();
+                *run += 1;
+                *run
+            };
+
+            self.log.lock().unwrap().push(format!("start {}", word));
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(*run_time).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.log.lock().unwrap().push(format!("end {}", word));
+
+            let script = scripts[(run - 1).min(scripts.len() - 1)];
+            ReplayBackend::new(vec![script.to_string()]).execute(request, cancel).await
+        }
+
+        fn name(&self) -> &str {
+            "steps"
+        }
+    }
+
+    /// Run a workflow's steps in `dir` on `backend`
+    async fn run_offline(
+        yaml: &str,
+        dir: &std::path::Path,
+        backend: Arc<dyn AgentBackend>,
+        workers: usize,
+    ) -> (WorkflowState, RunOutcome) {
+        let mut workflow = WorkflowDefinition::parse(yaml).unwrap();
+        workflow.validate().unwrap();
+        for step in &mut workflow.steps {
+            step.working_dir = Some(dir.to_path_buf());
+        }
+
+        let state_manager = WorkflowStateManager::with_base_dir(dir.join("workflow_states"));
+        let mut state = WorkflowState::new(
+            "workflow-test".to_string(),
+            workflow.name.clone(),
+            dir.join("workflow.yaml"),
+            &workflow.steps,
+        );
+        let reporter = WorkflowReporter::new(&workflow, &DoodooriConfig::default(), None, true).unwrap();
+        let runner = StepRunner {
+            workflow: &workflow,
+            state_manager: &state_manager,
+            yolo: false,
+            sandbox: false,
+            workers,
+            budget: None,
+            cost_ledger: None,
+            integration: None,
+            hooks: None,
+            reporter: &reporter,
+            backend: Some(backend),
+        };
+        let outcome = runner.run(DagScheduler::new(workflow.clone()), &mut state).await.unwrap();
+        (state, outcome)
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"Picked version 2.1 <promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":0.1}"#
+                .to_string(),
+            r#"{"type":"assistant","message":"Built it <promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":0.2}"#
+                .to_string(),
+        ]));
+        let yaml = r#"
+name: "Offline"
+steps:
+  - name: "Plan"
+    prompt: "Plan the release"
+    outputs:
+      version: { regex: "version (\\S+)" }
+  - name: "Build"
+    prompt: "Build version {{ steps.plan.version }}"
+    depends_on: ["Plan"]
+"#;
+        let (state, outcome) = run_offline(yaml, dir.path(), backend.clone(), 2).await;
+
+        assert!(outcome.succeeded);
+        assert_eq!(state.steps["Plan"].status, StepStatus::Completed);
+        assert_eq!(state.steps["Plan"].outputs["version"], "2.1");
+        assert_eq!(state.steps["Build"].status, StepStatus::Completed);
+        assert!((state.total_cost_usd - 0.3).abs() < 1e-9);
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].prompt.contains("Plan the release"));
+        assert!(requests[1].prompt.contains("Build version 2.1"));
+    }
+
+    #[tokio::test]
+    async fn test_ready_step_starts_before_unrelated_slow_step() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(StepBackend::new(vec![
+            ("alpha", Duration::from_millis(300), vec![COMPLETE]),
+            ("beta", Duration::ZERO, vec![COMPLETE]),
+            ("gamma", Duration::ZERO, vec![COMPLETE]),
+        ]));
+        let yaml = r#"
+name: "Overlap"
+steps:
+  - name: "Slow"
+    prompt: "Run alpha"
+    parallel_group: 1
+  - name: "Quick"
+    prompt: "Run beta"
+    parallel_group: 1
+  - name: "Next"
+    prompt: "Run gamma"
+    depends_on: ["Quick"]
+    parallel_group: 2
+"#;
+        let (state, outcome) = run_offline(yaml, dir.path(), backend.clone(), 3).await;
+
+        assert!(outcome.succeeded);
+        assert!(state.steps.values().all(|s| s.status == StepStatus::Completed));
+        assert!(backend.position("start gamma") < backend.position("end alpha"));
+    }
+
+    #[tokio::test]
+    async fn test_steps_stay_within_workers() {
+        let dir = tempfile::tempdir().unwrap();
+        let run_time = Duration::from_millis(50);
+        let backend = Arc::new(StepBackend::new(vec![
+            ("alpha", run_time, vec![COMPLETE]),
+            ("beta", run_time, vec![COMPLETE]),
+            ("gamma", run_time, vec![COMPLETE]),
+            ("delta", run_time, vec![COMPLETE]),
+        ]));
+        let yaml = r#"
+name: "Capped"
+steps:
+  - name: "A"
+    prompt: "Run alpha"
+  - name: "B"
+    prompt: "Run beta"
+  - name: "C"
+    prompt: "Run gamma"
+  - name: "D"
+    prompt: "Run delta"
+"#;
+        let (state, outcome) = run_offline(yaml, dir.path(), backend.clone(), 2).await;
+
+        assert!(outcome.succeeded);
+        assert!(state.steps.values().all(|s| s.status == StepStatus::Completed));
+        assert_eq!(backend.max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_failure_handlers_are_dispatched() {
+        let dir = tempfile::tempdir().unwrap();
+        let run_time = Duration::from_millis(20);
+        let backend = Arc::new(StepBackend::new(vec![
+            ("alpha", Duration::from_millis(300), vec![COMPLETE]),
+            ("beta", run_time, vec![UNFINISHED, COMPLETE]),
+            ("gamma", run_time, vec![UNFINISHED]),
+            ("delta", run_time, vec![COMPLETE]),
+            ("epsilon", run_time, vec![COMPLETE]),
+            ("zeta", run_time, vec![COMPLETE]),
+        ]));
+        let yaml = r#"
+name: "Recovering"
+steps:
+  - name: "Long"
+    prompt: "Run alpha"
+  - name: "Flaky"
+    prompt: "Run beta"
+    max_iterations: 1
+    retry:
+      attempts: 2
+      backoff_secs: 0
+  - name: "Broken"
+    prompt: "Run gamma"
+    depends_on: ["Flaky"]
+    max_iterations: 1
+    on_failure: "Cleanup"
+    continue_on_error: true
+  - name: "Cleanup"
+    prompt: "Run delta"
+  - name: "Dependent"
+    prompt: "Run epsilon"
+    depends_on: ["Broken"]
+    on_dependency_failure: fail
+    on_failure: "Notify"
+    continue_on_error: true
+  - name: "Notify"
+    prompt: "Run zeta"
+"#;
+        let (state, outcome) = run_offline(yaml, dir.path(), backend.clone(), 2).await;
+
+        assert!(outcome.succeeded);
+        let status = |name: &str| state.steps[name].status.clone();
+        assert_eq!(status("Flaky"), StepStatus::Completed);
+        assert_eq!(backend.runs.lock().unwrap()["beta"], 2);
+        assert_eq!(status("Broken"), StepStatus::Failed);
+        assert_eq!(status("Cleanup"), StepStatus::Completed);
+        assert_eq!(status("Dependent"), StepStatus::Failed);
+        assert_eq!(status("Notify"), StepStatus::Completed);
+        assert!(!backend.runs.lock().unwrap().contains_key("epsilon"));
+        // Retries and handlers wait for a free worker too
+        assert_eq!(backend.max_running.load(Ordering::SeqCst), 2);
+    }
+}
@@ -6,11 +6,11 @@ use std::path::Path;
 
 use commands::{
     cost::CostArgs, dashboard::DashboardArgs, git::GitArgs, parallel::ParallelArgs,
-    resume::ResumeArgs, run::RunArgs, sandbox::SandboxArgs, secret::SecretArgs, spec::SpecArgs,
+    replay::ReplayArgs, resume::ResumeArgs, rollback::RollbackArgs, run::RunArgs, sandbox::SandboxArgs, secret::SecretArgs, spec::SpecArgs,
     template::TemplateCommand, watch::WatchArgs, workflow::WorkflowArgs,
 };
 use crate::config::DoodooriConfig;
-use crate::pricing::{format_cost, CostCalculator, PricingConfig};
+use crate::pricing::{diff_prices, format_cost, install_prices, CostCalculator, PriceSource, PricingConfig, DEFAULT_PRICE_SOURCE};
 use crate::claude::ModelAlias;
 
 /// Doodoori - Autonomous CLI tool powered by Claude Code
@@ -51,6 +51,12 @@ pub enum Commands {
     /// Resume an interrupted task
     Resume(ResumeArgs),
 
+    /// Replay a recorded Claude session
+    Replay(ReplayArgs),
+
+    /// Restore the working tree to an iteration checkpoint
+    Rollback(RollbackArgs),
+
     /// Manage secrets in the system keychain
     Secret(SecretArgs),
 
@@ -85,6 +91,10 @@ pub enum Commands {
         #[arg(long)]
         update: bool,
 
+        /// Where to fetch prices from with --update (URL or price.toml file)
+        #[arg(long, requires = "update")]
+        source: Option<String>,
+
         /// Show price for specific model
         #[arg(long)]
         model: Option<String>,
@@ -99,6 +109,8 @@ impl Cli {
             Commands::Spec(args) => args.execute().await,
             Commands::Sandbox(args) => args.execute().await,
             Commands::Resume(args) => args.execute().await,
+            Commands::Replay(args) => args.execute().await,
+            Commands::Rollback(args) => args.execute().await,
             Commands::Secret(args) => args.execute().await,
             Commands::Cost(args) => args.execute().await,
             Commands::Workflow(args) => args.execute().await,
@@ -117,8 +129,11 @@ impl Cli {
             Commands::Config => {
                 self.execute_config().await
             }
-            Commands::Price { update, ref model } => {
-                self.execute_price(update, model.clone()).await
+            Commands::Price { update: true, ref source, .. } => {
+                self.execute_price_update(source.clone()).await
+            }
+            Commands::Price { ref model, .. } => {
+                self.execute_price(model.clone()).await
             }
         }
     }
@@ -187,31 +202,21 @@ impl Cli {
     }
 
     /// Execute the price command - display model pricing
-    async fn execute_price(&self, update: bool, model: Option<String>) -> Result<()> {
+    async fn execute_price(&self, model: Option<String>) -> Result<()> {
         use console::{style, Emoji};
 
-        if update {
-            #[cfg(feature = "price-update")]
-            {
-                println!("Updating prices from Anthropic...");
-                // Price update logic would go here
-                println!("Price update not yet implemented");
-            }
-            #[cfg(not(feature = "price-update"))]
-            {
-                println!("{} Price update requires --features price-update", Emoji("⚠️", "[!]"));
-                println!("  cargo build --features price-update");
-            }
-            return Ok(());
-        }
-
-        let pricing = PricingConfig::default_pricing();
-        let calc = CostCalculator::with_default_pricing();
+        let config = DoodooriConfig::from_file(Path::new(&self.config)).unwrap_or_default();
+        let price_file = config.pricing_file();
+        let pricing = PricingConfig::load_or_default(price_file.as_deref());
+        let calc = CostCalculator::new(pricing.clone());
 
         println!("{} Model Pricing", Emoji("💰", ""));
         println!();
         println!("  Version:  {}", pricing.meta.version);
         println!("  Updated:  {}", pricing.meta.updated_at);
+        if let Some(ref file) = price_file {
+            println!("  File:     {}", file.display());
+        }
         println!();
 
         if let Some(model_name) = model {
@@ -287,6 +292,55 @@ impl Cli {
 
         Ok(())
     }
+
+    /// Execute `price --update` - fetch prices and install them as the user-level override
+    async fn execute_price_update(&self, source: Option<String>) -> Result<()> {
+        use console::{style, Emoji};
+
+        let config = DoodooriConfig::from_file(Path::new(&self.config))?;
+        let source = source
+            .or(config.price_source.clone())
+            .unwrap_or_else(|| DEFAULT_PRICE_SOURCE.to_string());
+        let source = PriceSource::parse(&source);
+        let target = PricingConfig::user_override_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the user config directory"))?;
+
+        println!("{} Fetching prices from {}", Emoji("💰", ""), source);
+        let (content, updated) = source.load().await?;
+
+        // Compare with the prices being replaced
+        let current = PricingConfig::load_or_default(Some(target.as_path()).filter(|path| path.exists()));
+        let changes = diff_prices(&current, &updated);
+        println!();
+        if changes.is_empty() {
+            println!("No rate changes.");
+        } else {
+            println!("{}", style("[Changes]").bold());
+            for change in &changes {
+                println!("  {}", change);
+            }
+        }
+
+        install_prices(&content, &target)?;
+        println!();
+        println!(
+            "{} Saved prices {} ({}) to {}",
+            Emoji("✅", ""),
+            updated.meta.version,
+            updated.meta.updated_at,
+            target.display()
+        );
+        if let Some(ref file) = config.price_file {
+            println!(
+                "{} price_file = \"{}\" in {} takes precedence over these prices",
+                Emoji("⚠️", "[!]"),
+                file.display(),
+                self.config
+            );
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -586,12 +640,50 @@ mod tests {
         matches!(cli.command, Commands::Config);
     }
 
+    #[test]
+    fn test_cli_replay() {
+        let cli = Cli::try_parse_from(["doodoori", "replay", "abc123", "--speed"]).unwrap();
+
+        match cli.command {
+            Commands::Replay(args) => {
+                assert_eq!(args.task_id, Some("abc123".to_string()));
+                assert_eq!(args.speed, Some(1.0));
+                assert!(args.iteration.is_none());
+            }
+            _ => panic!("Expected Replay command"),
+        }
+
+        let cli = Cli::try_parse_from(["doodoori", "replay", "abc123", "-i", "2", "--speed", "4"]).unwrap();
+        match cli.command {
+            Commands::Replay(args) => {
+                assert_eq!(args.iteration, Some(2));
+                assert_eq!(args.speed, Some(4.0));
+            }
+            _ => panic!("Expected Replay command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_rollback() {
+        let cli = Cli::try_parse_from(["doodoori", "rollback", "abc123", "--to-iteration", "3"]).unwrap();
+
+        match cli.command {
+            Commands::Rollback(args) => {
+                assert_eq!(args.task_id, "abc123");
+                assert_eq!(args.to_iteration, Some(3));
+            }
+            _ => panic!("Expected Rollback command"),
+        }
+
+        assert!(Cli::try_parse_from(["doodoori", "rollback"]).is_err());
+    }
+
     #[test]
     fn test_cli_price() {
         let cli = Cli::try_parse_from(["doodoori", "price"]).unwrap();
 
         match cli.command {
-            Commands::Price { update, model } => {
+            Commands::Price { update, model, .. } => {
                 assert!(!update);
                 assert!(model.is_none());
             }
@@ -609,6 +701,15 @@ mod tests {
             }
             _ => panic!("Expected Price command"),
         }
+
+        let cli = Cli::try_parse_from(["doodoori", "price", "--update", "--source", "./price.toml"]).unwrap();
+        match cli.command {
+            Commands::Price { source, .. } => assert_eq!(source.as_deref(), Some("./price.toml")),
+            _ => panic!("Expected Price command"),
+        }
+
+        // --source only applies to --update
+        assert!(Cli::try_parse_from(["doodoori", "price", "--source", "./price.toml"]).is_err());
     }
 
     #[test]
@@ -4,9 +4,11 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-use crate::claude::ModelAlias;
-use crate::hooks::HooksConfig;
+use crate::claude::{AgentCommand, ModelAlias};
+use crate::hooks::{HookDefinition, HooksConfig};
+use crate::loop_engine::{ModelPolicy, PromptTemplates, StallConfig, VerificationConfig};
 use crate::notifications::{NotificationEvent, NotificationsConfig};
+use crate::pricing::{PricingConfig, SpendGuard, SpendLimits};
 
 /// Main configuration for Doodoori
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -24,6 +26,8 @@ pub struct DoodooriConfig {
     pub sandbox_mode: bool,
     /// Custom price.toml path
     pub price_file: Option<PathBuf>,
+    /// Where `price --update` fetches prices from (URL or file)
+    pub price_source: Option<String>,
     /// Custom instructions file (like doodoori.md)
     pub instructions_file: Option<PathBuf>,
     /// Git workflow settings
@@ -32,10 +36,22 @@ pub struct DoodooriConfig {
     pub logging: LoggingConfig,
     /// Parallel execution settings
     pub parallel: ParallelConfig,
+    /// Cost tracking settings
+    pub cost: CostConfig,
     /// Hooks configuration
     pub hooks: HooksConfigFile,
     /// Notifications configuration
     pub notifications: NotificationsConfigFile,
+    /// Completion verification settings
+    pub verification: VerificationConfigFile,
+    /// Iteration prompt template overrides
+    pub prompts: PromptsConfigFile,
+    /// Claude Code binary and extra arguments
+    pub agent: AgentCommand,
+    /// Stuck-loop detection
+    pub stall: StallConfig,
+    /// Per-iteration model selection
+    pub model_policy: ModelPolicy,
 }
 
 impl Default for DoodooriConfig {
@@ -47,12 +63,19 @@ impl Default for DoodooriConfig {
             yolo_mode: false,
             sandbox_mode: false,
             price_file: None,
+            price_source: None,
             instructions_file: Some(PathBuf::from("doodoori.md")),
             git: GitConfig::default(),
             logging: LoggingConfig::default(),
             parallel: ParallelConfig::default(),
+            cost: CostConfig::default(),
             hooks: HooksConfigFile::default(),
             notifications: NotificationsConfigFile::default(),
+            verification: VerificationConfigFile::default(),
+            prompts: PromptsConfigFile::default(),
+            agent: AgentCommand::default(),
+            stall: StallConfig::default(),
+            model_policy: ModelPolicy::default(),
         }
     }
 }
@@ -73,6 +96,8 @@ pub struct GitConfig {
     pub auto_merge: bool,
     /// Branch prefix for feature branches
     pub branch_prefix: String,
+    /// Checkpoint the working tree after each iteration (refs/doodoori/<task>/<iter>)
+    pub checkpoints: bool,
 }
 
 impl Default for GitConfig {
@@ -84,6 +109,22 @@ impl Default for GitConfig {
             auto_pr: false,
             auto_merge: false,
             branch_prefix: "doodoori/".to_string(),
+            checkpoints: false,
+        }
+    }
+}
+
+impl GitConfig {
+    /// Convert to the git module's config for use with TaskGitWorkflow
+    pub fn to_git_config(&self) -> crate::git::GitConfig {
+        crate::git::GitConfig {
+            enabled: self.enabled,
+            auto_branch: self.auto_branch,
+            auto_commit: self.auto_commit,
+            auto_pr: self.auto_pr,
+            auto_merge: self.auto_merge,
+            branch_prefix_feature: self.branch_prefix.clone(),
+            ..Default::default()
         }
     }
 }
@@ -98,6 +139,10 @@ pub struct LoggingConfig {
     pub file: Option<PathBuf>,
     /// Show progress indicators
     pub progress: bool,
+    /// Record raw stream-json transcripts in .doodoori/transcripts
+    pub transcripts: bool,
+    /// Keep a per-task progress journal in .doodoori/progress
+    pub journal: bool,
 }
 
 impl Default for LoggingConfig {
@@ -106,6 +151,8 @@ impl Default for LoggingConfig {
             level: "info".to_string(),
             file: None,
             progress: true,
+            transcripts: true,
+            journal: true,
         }
     }
 }
@@ -129,6 +176,47 @@ impl Default for ParallelConfig {
     }
 }
 
+/// Cost tracking configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CostConfig {
+    /// Also record costs in the user-level ledger shared by all projects
+    ///
+    /// Off by default: each entry carries the project path, git remote and
+    /// prompt summary, outside the project.
+    pub global_ledger: bool,
+    /// Daily and monthly spend caps
+    #[serde(flatten)]
+    pub limits: SpendLimits,
+}
+
+impl CostConfig {
+    /// Path of the user-level ledger, if enabled
+    pub fn ledger_path(&self) -> Option<PathBuf> {
+        if self.global_ledger {
+            crate::pricing::GlobalCostLedger::default_path()
+        } else {
+            None
+        }
+    }
+
+    /// Guard enforcing the spend caps for a project
+    pub fn spend_guard(&self, project_dir: &Path) -> SpendGuard {
+        SpendGuard::new(self.limits.clone(), project_dir, self.ledger_path())
+    }
+
+    /// Refuse to start work once a daily or monthly spend cap is reached
+    ///
+    /// Caps close to their limit are reported but do not stop the command.
+    pub fn ensure_spend_available(&self, project_dir: &Path) -> Result<()> {
+        let check = self.spend_guard(project_dir).ensure_available()?;
+        for cap in check.warnings {
+            eprintln!("{} {}", console::Emoji("💰", "[$]"), cap);
+        }
+        Ok(())
+    }
+}
+
 /// Hooks configuration for TOML file (simple string paths)
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(default)]
@@ -145,6 +233,14 @@ pub struct HooksConfigFile {
     pub on_iteration: Option<String>,
     /// On-complete hook script path
     pub on_complete: Option<String>,
+    /// On-stall hook script path
+    pub on_stall: Option<String>,
+    /// Workflow step completed hook script path
+    pub on_step_complete: Option<String>,
+    /// Workflow completed hook script path
+    pub on_workflow_complete: Option<String>,
+    /// Workflow failed hook script path
+    pub on_workflow_error: Option<String>,
     /// Default timeout for hooks in seconds
     pub timeout_secs: u64,
 }
@@ -156,13 +252,19 @@ impl HooksConfigFile {
             return HooksConfig::default().disabled();
         }
 
-        HooksConfig::from_paths(
+        let mut hooks = HooksConfig::from_paths(
             self.pre_run.as_deref(),
             self.post_run.as_deref(),
             self.on_error.as_deref(),
             self.on_iteration.as_deref(),
             self.on_complete.as_deref(),
-        )
+        );
+        let hook = |path: &String| HookDefinition::new(path).continue_on_failure(true);
+        hooks.on_stall = self.on_stall.as_ref().map(hook);
+        hooks.on_step_complete = self.on_step_complete.as_ref().map(hook);
+        hooks.on_workflow_complete = self.on_workflow_complete.as_ref().map(hook);
+        hooks.on_workflow_error = self.on_workflow_error.as_ref().map(hook);
+        hooks
     }
 }
 
@@ -184,7 +286,14 @@ pub struct NotificationsConfigFile {
 }
 
 fn default_notification_events() -> Vec<String> {
-    vec!["completed".to_string(), "error".to_string()]
+    [
+        "completed",
+        "error",
+        "workflow_completed",
+        "workflow_failed",
+    ]
+    .map(String::from)
+    .to_vec()
 }
 
 impl NotificationsConfigFile {
@@ -204,6 +313,14 @@ impl NotificationsConfigFile {
                 "error" => Some(NotificationEvent::Error),
                 "budget_exceeded" => Some(NotificationEvent::BudgetExceeded),
                 "max_iterations" => Some(NotificationEvent::MaxIterations),
+                "interrupted" => Some(NotificationEvent::Interrupted),
+                "stalled" => Some(NotificationEvent::Stalled),
+                "spend_warning" => Some(NotificationEvent::SpendWarning),
+                "workflow_started" => Some(NotificationEvent::WorkflowStarted),
+                "step_completed" => Some(NotificationEvent::StepCompleted),
+                "step_failed" => Some(NotificationEvent::StepFailed),
+                "workflow_completed" => Some(NotificationEvent::WorkflowCompleted),
+                "workflow_failed" => Some(NotificationEvent::WorkflowFailed),
                 _ => None,
             })
             .collect();
@@ -246,6 +363,65 @@ impl NotificationsConfigFile {
     }
 }
 
+/// Completion verification configuration for TOML file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct VerificationConfigFile {
+    /// Command that must exit 0 before completion is accepted (e.g. "cargo test")
+    pub command: Option<String>,
+    /// Timeout for the command in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for VerificationConfigFile {
+    fn default() -> Self {
+        Self {
+            command: None,
+            timeout_secs: 600,
+        }
+    }
+}
+
+impl VerificationConfigFile {
+    /// Convert to VerificationConfig (None if no command is set)
+    pub fn to_verification_config(&self) -> Option<VerificationConfig> {
+        self.command
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| VerificationConfig::new(c).with_timeout(self.timeout_secs))
+    }
+}
+
+/// Prompt template overrides for TOML file
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PromptsConfigFile {
+    /// Template for the first iteration
+    pub first_iteration: Option<String>,
+    /// Template for subsequent iterations
+    pub continuation: Option<String>,
+    /// Template for the first iteration of a resumed task
+    pub resume: Option<String>,
+}
+
+impl PromptsConfigFile {
+    /// Resolve templates: doodoori.toml > .doodoori/prompts/*.md > built-in defaults
+    pub fn to_prompt_templates(&self, project_dir: &Path) -> PromptTemplates {
+        let mut templates = PromptTemplates::from_dir(&project_dir.join(".doodoori").join("prompts"));
+        if let Some(ref template) = self.first_iteration {
+            templates.first_iteration = template.clone();
+        }
+        if let Some(ref template) = self.continuation {
+            templates.continuation = template.clone();
+        }
+        if let Some(ref template) = self.resume {
+            templates.resume = template.clone();
+        }
+        templates
+    }
+}
+
 impl DoodooriConfig {
     /// Load configuration from a TOML file
     pub fn from_file(path: &Path) -> Result<Self> {
@@ -277,6 +453,14 @@ impl DoodooriConfig {
             .unwrap_or(ModelAlias::Sonnet)
     }
 
+    /// price.toml to price tokens with: `price_file`, else the user-level
+    /// override from `price --update` if there is one
+    pub fn pricing_file(&self) -> Option<PathBuf> {
+        self.price_file
+            .clone()
+            .or_else(|| PricingConfig::user_override_path().filter(|path| path.exists()))
+    }
+
     /// Get the instructions file path if it exists
     pub fn get_instructions_file(&self) -> Option<PathBuf> {
         self.instructions_file.as_ref().and_then(|p| {
@@ -317,9 +501,12 @@ yolo_mode = false
 # Enable sandbox mode by default (Docker isolation)
 sandbox_mode = false
 
-# Custom price.toml path (optional)
+# Custom price.toml path (optional; overrides `doodoori price --update`)
 # price_file = "custom-price.toml"
 
+# Where `doodoori price --update` fetches prices from (URL or file)
+# price_source = "https://raw.githubusercontent.com/devlikebear/doodoori/main/price.toml"
+
 # Instructions file (like CLAUDE.md for doodoori)
 instructions_file = "doodoori.md"
 
@@ -336,6 +523,8 @@ auto_pr = false
 auto_merge = false
 # Branch prefix for feature branches
 branch_prefix = "doodoori/"
+# Checkpoint the working tree after each iteration (see `doodoori rollback`)
+checkpoints = false
 
 [logging]
 # Log level: trace, debug, info, warn, error
@@ -344,6 +533,10 @@ level = "info"
 # file = ".doodoori/logs/doodoori.log"
 # Show progress indicators
 progress = true
+# Record raw Claude output per iteration (for `doodoori replay`)
+transcripts = true
+# Summarize each iteration in .doodoori/progress/<task-id>.md and carry it into later prompts
+journal = true
 
 [parallel]
 # Default number of parallel workers
@@ -351,6 +544,22 @@ workers = 3
 # Isolate workspaces per task
 isolate_workspaces = false
 
+[cost]
+# Also record costs in a ledger in your user data directory (see
+# `doodoori cost --all-projects`). Each entry holds the task ID, time, model,
+# tokens, cost, status and prompt summary, tagged with the project path and
+# git remote. Needed for the global spend caps.
+global_ledger = false
+# Spend caps in USD: tasks refuse to start, and running tasks stop between
+# iterations, once a cap is reached (days and months in UTC)
+# daily_budget_usd = 20.0
+# monthly_budget_usd = 300.0
+# Caps across all projects (read from the global ledger)
+# global_daily_budget_usd = 50.0
+# global_monthly_budget_usd = 1000.0
+# Send a spend_warning notification at this fraction of a cap
+warn_threshold = 0.8
+
 [hooks]
 # Enable hooks
 enabled = true
@@ -366,6 +575,12 @@ timeout_secs = 60
 # on_iteration = "scripts/on_iteration.sh"
 # On-complete hook (when task completes successfully)
 # on_complete = "scripts/on_complete.sh"
+# On-stall hook (when the loop stops making progress)
+# on_stall = "scripts/on_stall.sh"
+# Workflow hooks (when a step completes, the workflow completes or fails)
+# on_step_complete = "scripts/on_step_complete.sh"
+# on_workflow_complete = "scripts/on_workflow_complete.sh"
+# on_workflow_error = "scripts/on_workflow_error.sh"
 
 [notifications]
 # Enable notifications
@@ -376,8 +591,49 @@ enabled = false
 # discord_webhook = "https://discord.com/api/webhooks/..."
 # Generic webhook URL
 # webhook_url = "https://your-api.com/webhook"
-# Events to notify on: started, completed, error, budget_exceeded, max_iterations
-events = ["completed", "error"]
+# Events to notify on: started, completed, error, budget_exceeded, max_iterations, interrupted, stalled, spend_warning,
+# workflow_started, step_completed, step_failed, workflow_completed, workflow_failed
+events = ["completed", "error", "workflow_completed", "workflow_failed"]
+
+[verification]
+# Command that must succeed before a task is accepted as complete.
+# Its output is fed back to Claude when it fails.
+# command = "cargo test"
+# Verification timeout in seconds (default: 600)
+timeout_secs = 600
+
+[prompts]
+# Override iteration prompt templates (or put first_iteration.md,
+# continuation.md and resume.md in .doodoori/prompts/).
+# Placeholders: {prompt}, {completion_marker}, {iteration},
+# {previous_output}, {progress}, {verification}
+# first_iteration = "{prompt}\n\nWhen done, output {completion_marker}"
+# continuation = "Keep working on: {prompt}\n\nWhen done, output {completion_marker}"
+# resume = "Resume the interrupted task: {prompt}\n\nWhen done, output {completion_marker}"
+
+[agent]
+# Claude Code binary (name on PATH or full path)
+binary = "claude"
+# Extra arguments passed to every invocation
+# extra_args = ["--add-dir", "../shared"]
+
+[stall]
+# Detect iterations that make no progress (no file changes, repeated
+# output or the same error again)
+enabled = true
+# Consecutive iterations without progress before acting
+threshold = 3
+# Action: nudge (tell Claude to change approach), escalate (switch to a
+# larger model) or stop
+action = "nudge"
+# Prompt used by the nudge action ({reason} describes the stall)
+# prompt = "You are stuck ({reason}). Try a different approach."
+
+[model_policy]
+# Rules choosing the model for each iteration; the last matching rule wins
+# and default_model is used when none matches. Conditions: "from N",
+# "until N", "after N failures", "after N tool errors", "last N iterations"
+# rules = ["haiku", "sonnet after 2 failures", "opus last 2 iterations"]
 "#.to_string()
     }
 }
@@ -409,6 +665,10 @@ auto_pr = true
 
 [parallel]
 workers = 8
+
+[cost]
+global_ledger = false
+daily_budget_usd = 20.0
 "#;
         let config = DoodooriConfig::from_str(toml).unwrap();
 
@@ -419,6 +679,11 @@ workers = 8
         assert!(!config.git.enabled);
         assert!(config.git.auto_pr);
         assert_eq!(config.parallel.workers, 8);
+        assert!(config.cost.ledger_path().is_none());
+        assert_eq!(config.cost.limits.daily_budget_usd, Some(20.0));
+        assert_eq!(config.cost.limits.warn_threshold, 0.8);
+        assert!(!DoodooriConfig::default().cost.global_ledger);
+        assert!(DoodooriConfig::default().cost.ledger_path().is_none());
     }
 
     #[test]
@@ -432,6 +697,7 @@ default_model = "haiku"
         assert_eq!(config.default_model, "haiku");
         assert_eq!(config.max_iterations, 50);
         assert!(config.git.enabled);
+        assert!(!config.git.checkpoints);
     }
 
     #[test]
@@ -457,12 +723,104 @@ default_model = "haiku"
         assert_eq!(config.branch_prefix, "doodoori/");
     }
 
+    #[test]
+    fn test_git_config_conversion() {
+        let config = GitConfig {
+            auto_pr: true,
+            branch_prefix: "bot/".to_string(),
+            ..Default::default()
+        };
+        let git_config = config.to_git_config();
+        assert!(git_config.enabled);
+        assert!(git_config.auto_branch);
+        assert!(git_config.auto_commit);
+        assert!(git_config.auto_pr);
+        assert!(!git_config.auto_merge);
+        assert_eq!(git_config.branch_prefix_feature, "bot/");
+    }
+
+    #[test]
+    fn test_verification_config_conversion() {
+        let config = DoodooriConfig::default();
+        assert!(config.verification.to_verification_config().is_none());
+
+        let toml = r#"
+[verification]
+command = "cargo test --workspace"
+timeout_secs = 120
+"#;
+        let config = DoodooriConfig::from_str(toml).unwrap();
+        let verification = config.verification.to_verification_config().unwrap();
+        assert_eq!(verification.command, "cargo test --workspace");
+        assert_eq!(verification.timeout_secs, 120);
+    }
+
+    #[test]
+    fn test_prompts_config_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let prompts_dir = dir.path().join(".doodoori").join("prompts");
+        std::fs::create_dir_all(&prompts_dir).unwrap();
+        std::fs::write(prompts_dir.join("continuation.md"), "From file: {prompt}").unwrap();
+        std::fs::write(prompts_dir.join("resume.md"), "Resume from file: {prompt}").unwrap();
+
+        let toml = r#"
+[prompts]
+resume = "Resume from toml: {prompt}"
+"#;
+        let config = DoodooriConfig::from_str(toml).unwrap();
+        let templates = config.prompts.to_prompt_templates(dir.path());
+
+        assert_eq!(templates.first_iteration, PromptTemplates::default().first_iteration);
+        assert_eq!(templates.continuation, "From file: {prompt}");
+        assert_eq!(templates.resume, "Resume from toml: {prompt}");
+    }
+
+    #[test]
+    fn test_stall_and_on_stall_hook() {
+        let toml = r#"
+[hooks]
+enabled = true
+on_stall = "scripts/on_stall.sh"
+on_workflow_error = "scripts/on_workflow_error.sh"
+
+[stall]
+action = "stop"
+"#;
+        let config = DoodooriConfig::from_str(toml).unwrap();
+        assert_eq!(config.stall.action, crate::loop_engine::StallAction::Stop);
+        assert_eq!(config.stall.threshold, 3);
+        let hooks = config.hooks.to_hooks_config();
+        assert_eq!(hooks.on_stall.unwrap().command, "scripts/on_stall.sh");
+        assert!(hooks.on_workflow_error.unwrap().continue_on_failure);
+        assert!(hooks.on_step_complete.is_none());
+
+        let config = DoodooriConfig::from_str(&DoodooriConfig::default_config_string()).unwrap();
+        assert_eq!(config.stall, StallConfig::default());
+    }
+
+    #[test]
+    fn test_model_policy() {
+        let toml = r#"
+[model_policy]
+rules = ["opus until 1", { model = "sonnet", after_failures = 3 }]
+"#;
+        let config = DoodooriConfig::from_str(toml).unwrap();
+        assert_eq!(config.model_policy.rules.len(), 2);
+        assert_eq!(config.model_policy.rules[0].model, ModelAlias::Opus);
+        assert_eq!(config.model_policy.rules[1].after_failures, Some(3));
+
+        let config = DoodooriConfig::from_str(&DoodooriConfig::default_config_string()).unwrap();
+        assert!(config.model_policy.is_empty());
+    }
+
     #[test]
     fn test_logging_config_defaults() {
         let config = LoggingConfig::default();
         assert_eq!(config.level, "info");
         assert!(config.file.is_none());
         assert!(config.progress);
+        assert!(config.transcripts);
+        assert!(config.journal);
     }
 
     #[test]
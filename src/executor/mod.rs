@@ -12,8 +12,12 @@ use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex, Semaphore};
 use uuid::Uuid;
 
-use crate::claude::ModelAlias;
-use crate::loop_engine::{LoopConfig, LoopEngine, LoopStatus};
+use crate::claude::{AgentBackend, ModelAlias};
+use crate::hooks::HooksConfig;
+use crate::loop_engine::{
+    CompletionStrategy, LoopConfig, LoopEngine, LoopStatus, ModelPolicy, PromptTemplates, VerificationConfig,
+};
+use crate::utils::truncate_str;
 
 /// Definition of a task to be executed in parallel
 #[derive(Debug, Clone)]
@@ -26,6 +30,8 @@ pub struct TaskDefinition {
     pub name: Option<String>,
     /// Model to use for this task
     pub model: ModelAlias,
+    /// Per-iteration model rules (doodoori.toml's policy when None)
+    pub model_policy: Option<ModelPolicy>,
     /// Maximum iterations for this task
     pub max_iterations: u32,
     /// Budget limit for this task (USD)
@@ -34,8 +40,20 @@ pub struct TaskDefinition {
     pub working_dir: Option<PathBuf>,
     /// YOLO mode (skip permissions)
     pub yolo_mode: bool,
+    /// Read-only mode (no file writes)
+    pub readonly: bool,
+    /// Additional tools to allow (comma-separated)
+    pub allowed_tools: Option<String>,
+    /// System prompt file
+    pub system_prompt: Option<PathBuf>,
+    /// Hooks run by the task's loop (none when None)
+    pub hooks: Option<HooksConfig>,
     /// Git branch associated with this task (set when using worktrees)
     pub git_branch: Option<String>,
+    /// Command that must succeed before completion is accepted
+    pub verification: Option<VerificationConfig>,
+    /// How completion is detected (and described in prompts)
+    pub completion_strategy: CompletionStrategy,
 }
 
 impl TaskDefinition {
@@ -46,11 +64,18 @@ impl TaskDefinition {
             prompt: prompt.into(),
             name: None,
             model: ModelAlias::Sonnet,
+            model_policy: None,
             max_iterations: 50,
             budget_limit: None,
             working_dir: None,
             yolo_mode: false,
+            readonly: false,
+            allowed_tools: None,
+            system_prompt: None,
+            hooks: None,
             git_branch: None,
+            verification: None,
+            completion_strategy: CompletionStrategy::default(),
         }
     }
 
@@ -66,6 +91,12 @@ impl TaskDefinition {
         self
     }
 
+    /// Set the per-iteration model policy
+    pub fn with_model_policy(mut self, policy: ModelPolicy) -> Self {
+        self.model_policy = Some(policy);
+        self
+    }
+
     /// Set the maximum iterations
     pub fn with_max_iterations(mut self, max: u32) -> Self {
         self.max_iterations = max;
@@ -90,11 +121,47 @@ impl TaskDefinition {
         self
     }
 
+    /// Set read-only mode
+    pub fn with_readonly(mut self, enabled: bool) -> Self {
+        self.readonly = enabled;
+        self
+    }
+
+    /// Set additional tools to allow
+    pub fn with_allowed_tools(mut self, tools: impl Into<String>) -> Self {
+        self.allowed_tools = Some(tools.into());
+        self
+    }
+
+    /// Set the system prompt file
+    pub fn with_system_prompt(mut self, path: PathBuf) -> Self {
+        self.system_prompt = Some(path);
+        self
+    }
+
+    /// Set the hooks run by the task's loop
+    pub fn with_hooks(mut self, hooks: HooksConfig) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
     /// Set the git branch name for this task (used with git worktrees)
     pub fn with_git_branch(mut self, branch: impl Into<String>) -> Self {
         self.git_branch = Some(branch.into());
         self
     }
+
+    /// Set the completion verification command
+    pub fn with_verification(mut self, verification: VerificationConfig) -> Self {
+        self.verification = Some(verification);
+        self
+    }
+
+    /// Set the completion strategy
+    pub fn with_completion_strategy(mut self, strategy: CompletionStrategy) -> Self {
+        self.completion_strategy = strategy;
+        self
+    }
 }
 
 /// Result of a single task execution
@@ -116,6 +183,8 @@ pub struct TaskResult {
     pub output: Option<String>,
     /// Error message (if failed)
     pub error: Option<String>,
+    /// Working directory the task ran in (None for the current directory)
+    pub working_dir: Option<PathBuf>,
 }
 
 /// Status of a task
@@ -131,6 +200,8 @@ pub enum TaskStatus {
     Failed,
     /// Task was cancelled
     Cancelled,
+    /// Task stopped making progress
+    Stalled,
 }
 
 impl From<LoopStatus> for TaskStatus {
@@ -141,6 +212,7 @@ impl From<LoopStatus> for TaskStatus {
             LoopStatus::BudgetExceeded => TaskStatus::BudgetExceeded,
             LoopStatus::Error(_) => TaskStatus::Failed,
             LoopStatus::Stopped => TaskStatus::Cancelled,
+            LoopStatus::Stalled => TaskStatus::Stalled,
             LoopStatus::Running => TaskStatus::Failed, // Should not happen
         }
     }
@@ -216,6 +288,8 @@ pub struct ParallelConfig {
     pub git_auto_commit: bool,
     /// Auto-create PR on task completion
     pub git_auto_pr: bool,
+    /// User-level cost ledger that task costs are also recorded in (None = off)
+    pub cost_ledger: Option<PathBuf>,
 }
 
 impl Default for ParallelConfig {
@@ -231,6 +305,7 @@ impl Default for ParallelConfig {
             git_branch_prefix: "task/".to_string(),
             git_auto_commit: false,
             git_auto_pr: false,
+            cost_ledger: None,
         }
     }
 }
@@ -238,12 +313,22 @@ impl Default for ParallelConfig {
 /// Parallel task executor using a worker pool
 pub struct ParallelExecutor {
     config: ParallelConfig,
+    backend: Option<Arc<dyn AgentBackend>>,
 }
 
 impl ParallelExecutor {
     /// Create a new parallel executor with the given configuration
     pub fn new(config: ParallelConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            backend: None,
+        }
+    }
+
+    /// Use a different agent backend for all tasks
+    pub fn with_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.backend = Some(backend);
+        self
     }
 
     /// Create a new parallel executor with default configuration
@@ -260,6 +345,12 @@ impl ParallelExecutor {
         self
     }
 
+    /// Also record task costs in a user-level ledger
+    pub fn with_cost_ledger(mut self, ledger: Option<PathBuf>) -> Self {
+        self.config.cost_ledger = ledger;
+        self
+    }
+
     /// Set fail-fast mode
     pub fn with_fail_fast(mut self, enabled: bool) -> Self {
         self.config.fail_fast = enabled;
@@ -312,9 +403,10 @@ impl ParallelExecutor {
     ) -> Result<(mpsc::Receiver<ParallelEvent>, tokio::task::JoinHandle<Result<ParallelResult>>)> {
         let (tx, rx) = mpsc::channel(100);
         let config = self.config.clone();
+        let backend = self.backend.clone();
 
         let handle = tokio::spawn(async move {
-            Self::run_parallel(config, tasks, tx).await
+            Self::run_parallel(config, backend, tasks, tx).await
         });
 
         Ok((rx, handle))
@@ -333,6 +425,7 @@ impl ParallelExecutor {
     /// Internal parallel execution logic
     async fn run_parallel(
         config: ParallelConfig,
+        backend: Option<Arc<dyn AgentBackend>>,
         tasks: Vec<TaskDefinition>,
         tx: mpsc::Sender<ParallelEvent>,
     ) -> Result<ParallelResult> {
@@ -351,6 +444,19 @@ impl ParallelExecutor {
         // Flag for fail-fast cancellation
         let cancelled = Arc::new(Mutex::new(false));
 
+        // Prompt templates and agent command from doodoori.toml / .doodoori/prompts
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        let prompt_templates: PromptTemplates = doodoori_config
+            .prompts
+            .to_prompt_templates(config.base_working_dir.as_deref().unwrap_or(std::path::Path::new(".")));
+        let price_file = doodoori_config.pricing_file();
+        let agent_command = doodoori_config.agent;
+        let record_transcripts = doodoori_config.logging.transcripts;
+        let stall = doodoori_config.stall;
+        let model_policy = doodoori_config.model_policy;
+        let record_progress = doodoori_config.logging.journal;
+        let spend_limits = doodoori_config.cost.limits;
+
         // Channel to collect results
         let (result_tx, mut result_rx) = mpsc::channel(total_tasks);
 
@@ -362,6 +468,13 @@ impl ParallelExecutor {
             let spent = spent_budget.clone();
             let cancelled = cancelled.clone();
             let config = config.clone();
+            let prompt_templates = prompt_templates.clone();
+            let agent_command = agent_command.clone();
+            let stall = stall.clone();
+            let model_policy = task.model_policy.clone().unwrap_or_else(|| model_policy.clone());
+            let spend_limits = spend_limits.clone();
+            let price_file = price_file.clone();
+            let backend = backend.clone();
 
             tokio::spawn(async move {
                 // Check if cancelled
@@ -375,6 +488,7 @@ impl ParallelExecutor {
                         duration_ms: 0,
                         output: None,
                         error: Some("Cancelled due to fail-fast".to_string()),
+                        working_dir: None,
                     }).await;
                     drop(permit);
                     return;
@@ -393,6 +507,7 @@ impl ParallelExecutor {
                             duration_ms: 0,
                             output: None,
                             error: Some("Total budget exceeded".to_string()),
+                            working_dir: None,
                         }).await;
                         drop(permit);
                         return;
@@ -426,14 +541,32 @@ impl ParallelExecutor {
                     max_iterations: task.max_iterations,
                     budget_limit: task.budget_limit,
                     model: task.model.clone(),
-                    working_dir,
+                    working_dir: working_dir.clone(),
                     yolo_mode: task.yolo_mode,
+                    readonly: task.readonly,
+                    system_prompt: task.system_prompt.clone(),
+                    allowed_tools: task.allowed_tools.clone(),
+                    hooks: task.hooks.clone().unwrap_or_default(),
                     enable_state: true,
                     enable_cost_tracking: true,
+                    verification: task.verification.clone(),
+                    completion_strategy: task.completion_strategy.clone(),
+                    prompt_templates,
+                    agent_command,
+                    record_transcripts,
+                    stall,
+                    model_policy,
+                    record_progress,
+                    cost_ledger: config.cost_ledger.clone(),
+                    spend_limits,
+                    price_file,
                     ..Default::default()
                 };
 
-                let engine = LoopEngine::new(loop_config);
+                let mut engine = LoopEngine::new(loop_config);
+                if let Some(backend) = backend {
+                    engine = engine.with_backend(backend);
+                }
                 let task_start = std::time::Instant::now();
 
                 // Execute the task
@@ -469,6 +602,7 @@ impl ParallelExecutor {
                             duration_ms,
                             output: loop_result.final_output,
                             error: None,
+                            working_dir,
                         }
                     }
                     Err(e) => {
@@ -493,6 +627,7 @@ impl ParallelExecutor {
                             duration_ms,
                             output: None,
                             error: Some(e.to_string()),
+                            working_dir,
                         }
                     }
                 };
@@ -526,11 +661,7 @@ impl ParallelExecutor {
 
     /// Truncate prompt for display
     fn truncate_prompt(prompt: &str) -> String {
-        if prompt.len() > 50 {
-            format!("{}...", &prompt[..47])
-        } else {
-            prompt.to_string()
-        }
+        truncate_str(prompt, 50)
     }
 }
 
@@ -718,6 +849,7 @@ mod tests {
         assert_eq!(TaskStatus::from(LoopStatus::BudgetExceeded), TaskStatus::BudgetExceeded);
         assert_eq!(TaskStatus::from(LoopStatus::Error("test".to_string())), TaskStatus::Failed);
         assert_eq!(TaskStatus::from(LoopStatus::Stopped), TaskStatus::Cancelled);
+        assert_eq!(TaskStatus::from(LoopStatus::Stalled), TaskStatus::Stalled);
     }
 
     #[test]
@@ -732,6 +864,7 @@ mod tests {
                 duration_ms: 1000,
                 output: None,
                 error: None,
+                working_dir: None,
             },
             TaskResult {
                 task_id: "2".to_string(),
@@ -742,6 +875,7 @@ mod tests {
                 duration_ms: 500,
                 output: None,
                 error: Some("Error".to_string()),
+                working_dir: None,
             },
             TaskResult {
                 task_id: "3".to_string(),
@@ -752,6 +886,7 @@ mod tests {
                 duration_ms: 2000,
                 output: None,
                 error: None,
+                working_dir: None,
             },
         ];
 
@@ -803,4 +938,27 @@ mod tests {
 
         assert_eq!(task.name, Some("my-feature".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_execute_with_replay_backend() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":0.1}"#
+                .to_string(),
+        ]));
+        let tasks = vec![
+            TaskDefinition::new("Task A").with_working_dir(dir.path().to_path_buf()),
+            TaskDefinition::new("Task B").with_working_dir(dir.path().to_path_buf()),
+        ];
+
+        let executor = ParallelExecutor::with_workers(2).with_backend(backend.clone());
+        let result = executor.execute_and_wait(tasks).await.unwrap();
+
+        assert_eq!(result.succeeded, 2);
+        assert!((result.total_cost - 0.2).abs() < 1e-9);
+        assert_eq!(backend.requests().len(), 2);
+    }
 }
@@ -0,0 +1,386 @@
+//! Iteration checkpoints
+//!
+//! After each iteration the loop can record the whole working tree as a
+//! commit on a hidden ref, `refs/doodoori/<task-id>/<iteration>`; iteration 0
+//! is the state the task started from. The commit's parent is HEAD at that
+//! moment and its message names the branch checked out; HEAD, the index and
+//! the working tree itself are left untouched. Restoring a checkpoint moves
+//! that branch back to the parent and makes the working tree match the
+//! checkpoint again. Doodoori's own `.doodoori/` directory is never recorded
+//! or restored.
+#![allow(dead_code)]
+
+use super::repo::GitRepository;
+use super::{is_doodoori_path, GitError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::build::CheckoutBuilder;
+use git2::{Index, IndexAddOption, Oid, Repository, ResetType, Signature, StatusOptions};
+use std::path::Path;
+
+/// Namespace of checkpoint refs
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/doodoori";
+
+/// Ref name (instead of an iteration) holding the state before a rollback
+pub const BEFORE_ROLLBACK: &str = "before-rollback";
+
+/// A recorded working tree state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub task_id: String,
+    /// 1-based iteration the checkpoint was taken after (0 before the first)
+    pub iteration: u32,
+    /// Checkpoint commit
+    pub commit: Oid,
+    /// HEAD when the checkpoint was taken (None before the first commit)
+    pub head: Option<Oid>,
+    /// Branch checked out when the checkpoint was taken (None when detached)
+    pub branch: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    pub fn ref_name(&self) -> String {
+        checkpoint_ref(&self.task_id, &self.iteration.to_string())
+    }
+
+    pub fn short_id(&self) -> String {
+        self.commit.to_string()[..7].to_string()
+    }
+}
+
+fn checkpoint_ref(task_id: &str, name: &str) -> String {
+    format!("{}/{}/{}", CHECKPOINT_REF_PREFIX, task_id, name)
+}
+
+/// Line of a checkpoint commit message naming the branch it was taken on
+const BRANCH_TRAILER: &str = "Branch: ";
+
+/// Branch HEAD points to (None when detached)
+fn head_branch(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    target.strip_prefix("refs/heads/").map(str::to_string)
+}
+
+/// Creates, lists and restores iteration checkpoints
+pub struct CheckpointManager<'a> {
+    repo: &'a GitRepository,
+}
+
+impl<'a> CheckpointManager<'a> {
+    pub fn new(repo: &'a GitRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Record the working tree after an iteration
+    pub fn create(&self, task_id: &str, iteration: u32) -> Result<Checkpoint> {
+        let name = iteration.to_string();
+        let commit = self.record(task_id, &name, &format!("doodoori checkpoint: {} iteration {}", task_id, iteration))?;
+        self.load(task_id, iteration, commit)
+    }
+
+    /// Commit the working tree (tracked and untracked files) and point a
+    /// checkpoint ref at it
+    fn record(&self, task_id: &str, name: &str, message: &str) -> Result<Oid> {
+        // A separate handle, so the scratch index never replaces the real one
+        let repo = Repository::open(self.repo.path())?;
+        let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let tree = repo.find_tree(Self::working_tree(&repo)?)?;
+
+        let signature = repo
+            .signature()
+            .or_else(|_| Signature::now("doodoori", "doodoori@localhost"))?;
+        let parents: Vec<&git2::Commit> = head.iter().collect();
+        let full_message = match head_branch(&repo) {
+            Some(branch) => format!("{}\n\n{}{}", message, BRANCH_TRAILER, branch),
+            None => message.to_string(),
+        };
+        let commit = repo.commit(None, &signature, &signature, &full_message, &tree, &parents)?;
+
+        repo.reference(&checkpoint_ref(task_id, name), commit, true, message)?;
+        Ok(commit)
+    }
+
+    /// Write the working tree as a tree object
+    ///
+    /// Replaces the index of `repo`, so it must not be the caller's handle.
+    fn working_tree(repo: &Repository) -> Result<Oid> {
+        // An index file that is never written: the tree is built in memory
+        let mut index = Index::open(&repo.path().join("doodoori-checkpoint.index"))?;
+        repo.set_index(&mut index)?;
+        if let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) {
+            index.read_tree(&tree)?;
+        }
+        let mut skip_doodoori = |path: &Path, _: &[u8]| i32::from(is_doodoori_path(path));
+        index.add_all(["*"], IndexAddOption::DEFAULT, Some(&mut skip_doodoori))?;
+        index.update_all(["*"], Some(&mut skip_doodoori))?;
+        Ok(index.write_tree()?)
+    }
+
+    /// Whether HEAD and the working tree are exactly at a checkpoint
+    pub fn is_current(&self, checkpoint: &Checkpoint) -> Result<bool> {
+        let repo = Repository::open(self.repo.path())?;
+        let head = repo.head().ok().and_then(|head| head.target());
+        let target = repo.find_commit(checkpoint.commit)?;
+        Ok(head == checkpoint.head && Self::working_tree(&repo)? == target.tree_id())
+    }
+
+    fn load(&self, task_id: &str, iteration: u32, oid: Oid) -> Result<Checkpoint> {
+        let commit = self.repo.inner().find_commit(oid)?;
+        Ok(Checkpoint {
+            task_id: task_id.to_string(),
+            iteration,
+            commit: oid,
+            head: commit.parent_id(0).ok(),
+            branch: commit
+                .message()
+                .unwrap_or_default()
+                .lines()
+                .find_map(|line| line.strip_prefix(BRANCH_TRAILER))
+                .map(str::to_string),
+            created_at: Utc
+                .timestamp_opt(commit.time().seconds(), 0)
+                .single()
+                .unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Checkpoints of tasks whose ID starts with `task_prefix`, oldest first
+    pub fn list(&self, task_prefix: &str) -> Result<Vec<Checkpoint>> {
+        let repo = self.repo.inner();
+        let mut checkpoints = Vec::new();
+        for reference in repo.references_glob(&format!("{}/*", CHECKPOINT_REF_PREFIX))? {
+            let reference = reference?;
+            let Some(name) = reference.name() else {
+                continue;
+            };
+            let Some((task_id, iteration)) = name
+                .strip_prefix(CHECKPOINT_REF_PREFIX)
+                .and_then(|rest| rest.trim_start_matches('/').rsplit_once('/'))
+            else {
+                continue;
+            };
+            let (Ok(iteration), Some(oid)) = (iteration.parse::<u32>(), reference.target()) else {
+                continue;
+            };
+            if task_id.starts_with(task_prefix) {
+                checkpoints.push(self.load(task_id, iteration, oid)?);
+            }
+        }
+        checkpoints.sort_by(|a, b| a.task_id.cmp(&b.task_id).then(a.iteration.cmp(&b.iteration)));
+        Ok(checkpoints)
+    }
+
+    /// Checkpoint of one iteration of a task (ID or unique prefix)
+    pub fn find(&self, task_prefix: &str, iteration: u32) -> Result<Checkpoint> {
+        let mut matches: Vec<Checkpoint> = self
+            .list(task_prefix)?
+            .into_iter()
+            .filter(|c| c.iteration == iteration)
+            .collect();
+        match matches.len() {
+            0 => Err(GitError::CheckpointNotFound(format!("{} iteration {}", task_prefix, iteration))),
+            1 => Ok(matches.remove(0)),
+            _ => Err(GitError::OperationFailed(format!(
+                "Task ID prefix '{}' matches checkpoints of several tasks",
+                task_prefix
+            ))),
+        }
+    }
+
+    /// Restore HEAD and the working tree to a checkpoint
+    ///
+    /// Refuses when another branch is checked out than the one the
+    /// checkpoint was taken on, which would otherwise be reset. Unless the
+    /// working tree is already there, the current state is saved to
+    /// `refs/doodoori/<task-id>/before-rollback` first. HEAD is reset to the
+    /// commit the checkpoint was taken on, files created after the checkpoint
+    /// are removed and the checkpoint's files are written back as
+    /// uncommitted changes.
+    pub fn restore(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let current = head_branch(self.repo.inner());
+        if current != checkpoint.branch {
+            let describe = |branch: &Option<String>| match branch {
+                Some(branch) => format!("branch '{}'", branch),
+                None => "a detached HEAD".to_string(),
+            };
+            return Err(GitError::OperationFailed(format!(
+                "Checkpoint of iteration {} was taken on {}, but {} is checked out; check it out first",
+                checkpoint.iteration,
+                describe(&checkpoint.branch),
+                describe(&current)
+            )));
+        }
+        if self.is_current(checkpoint)? {
+            return Ok(());
+        }
+        self.record(
+            &checkpoint.task_id,
+            BEFORE_ROLLBACK,
+            &format!("doodoori: before rollback to iteration {}", checkpoint.iteration),
+        )?;
+
+        let repo = self.repo.inner();
+        let target = repo.find_commit(checkpoint.commit)?;
+        let target_tree = target.tree()?;
+
+        if let Ok(parent) = target.parent(0)
+            && repo.head().ok().and_then(|head| head.target()) != Some(parent.id())
+        {
+            repo.reset(parent.as_object(), ResetType::Mixed, None)?;
+        }
+
+        // Untracked files the checkpoint does not know about
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let untracked: Vec<String> = repo
+            .statuses(Some(&mut opts))?
+            .iter()
+            .filter(|entry| entry.status().is_wt_new())
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .filter(|path| !is_doodoori_path(Path::new(path)))
+            .filter(|path| target_tree.get_path(Path::new(path)).is_err())
+            .collect();
+        for path in untracked {
+            std::fs::remove_file(self.repo.path().join(path))?;
+        }
+
+        repo.checkout_tree(target_tree.as_object(), Some(CheckoutBuilder::new().force()))?;
+
+        // Keep the index at HEAD so the restored changes stay uncommitted
+        let mut index = repo.index()?;
+        match repo.head().and_then(|head| head.peel_to_tree()) {
+            Ok(tree) => index.read_tree(&tree)?,
+            Err(_) => index.clear()?,
+        }
+        index.write()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::branch::BranchManager;
+    use tempfile::TempDir;
+
+    fn setup_repo() -> (TempDir, GitRepository) {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = GitRepository::init(temp_dir.path()).unwrap();
+        BranchManager::new(&repo).ensure_initial_commit().unwrap();
+        (temp_dir, repo)
+    }
+
+    fn read(dir: &TempDir, path: &str) -> Option<String> {
+        std::fs::read_to_string(dir.path().join(path)).ok()
+    }
+
+    #[test]
+    fn test_checkpoint_does_not_touch_working_tree() {
+        let (dir, repo) = setup_repo();
+        std::fs::write(dir.path().join("a.txt"), "one").unwrap();
+        let before = repo.snapshot().unwrap();
+
+        let manager = CheckpointManager::new(&repo);
+        let checkpoint = manager.create("task-1", 1).unwrap();
+        assert_eq!(checkpoint.ref_name(), "refs/doodoori/task-1/1");
+        assert_eq!(checkpoint.head, before.head);
+        assert_eq!(repo.snapshot().unwrap(), before);
+
+        manager.create("task-1", 2).unwrap();
+        manager.create("task-2", 1).unwrap();
+        let iterations: Vec<u32> = manager.list("task-1").unwrap().iter().map(|c| c.iteration).collect();
+        assert_eq!(iterations, vec![1, 2]);
+        assert_eq!(manager.list("task").unwrap().len(), 3);
+        assert!(matches!(manager.find("task", 1), Err(GitError::OperationFailed(_))));
+        assert!(matches!(manager.find("task-1", 3), Err(GitError::CheckpointNotFound(_))));
+    }
+
+    #[test]
+    fn test_restore_checkpoint() {
+        let (dir, repo) = setup_repo();
+        let manager = CheckpointManager::new(&repo);
+
+        std::fs::write(dir.path().join("a.txt"), "one").unwrap();
+        manager.create("task-1", 1).unwrap();
+
+        // The next iteration edits a file, adds another and commits
+        std::fs::write(dir.path().join("a.txt"), "broken").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/b.txt"), "new").unwrap();
+        std::fs::create_dir_all(dir.path().join(".doodoori")).unwrap();
+        std::fs::write(dir.path().join(".doodoori/state.json"), "{}").unwrap();
+        let head_before = repo.snapshot().unwrap().head;
+        let git = repo.inner();
+        let mut index = git.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = git.head().unwrap().peel_to_commit().unwrap();
+        let sig = Signature::now("test", "test@localhost").unwrap();
+        git.commit(Some("HEAD"), &sig, &sig, "wip", &tree, &[&parent]).unwrap();
+        manager.create("task-1", 2).unwrap();
+
+        let checkpoint = manager.find("task-1", 1).unwrap();
+        manager.restore(&checkpoint).unwrap();
+
+        assert_eq!(read(&dir, "a.txt").as_deref(), Some("one"));
+        assert_eq!(read(&dir, "src/b.txt"), None);
+        assert_eq!(read(&dir, ".doodoori/state.json").as_deref(), Some("{}"));
+        assert_eq!(repo.snapshot().unwrap().head, head_before);
+        assert!(repo.modified_files().unwrap().contains(&"a.txt".to_string()));
+
+        // The state before the rollback can still be recovered
+        let saved = repo
+            .inner()
+            .find_reference("refs/doodoori/task-1/before-rollback")
+            .unwrap()
+            .peel_to_tree()
+            .unwrap();
+        assert!(saved.get_path(Path::new("src/b.txt")).is_ok());
+        assert!(saved.get_path(Path::new(".doodoori/state.json")).is_err());
+
+        // Restoring the same checkpoint again keeps the saved state
+        assert!(manager.is_current(&checkpoint).unwrap());
+        manager.restore(&checkpoint).unwrap();
+        let saved_again = repo
+            .inner()
+            .find_reference("refs/doodoori/task-1/before-rollback")
+            .unwrap()
+            .peel_to_tree()
+            .unwrap();
+        assert_eq!(saved_again.id(), saved.id());
+
+        // Rolling forward works too
+        manager.restore(&manager.find("task-1", 2).unwrap()).unwrap();
+        assert_eq!(read(&dir, "src/b.txt").as_deref(), Some("new"));
+        assert_eq!(read(&dir, "a.txt").as_deref(), Some("broken"));
+    }
+
+    #[test]
+    fn test_restore_needs_the_checkpoint_branch() {
+        let (dir, repo) = setup_repo();
+        let manager = CheckpointManager::new(&repo);
+        let branches = BranchManager::new(&repo);
+        let main = repo.current_branch().unwrap();
+
+        std::fs::write(dir.path().join("a.txt"), "one").unwrap();
+        let checkpoint = manager.create("task-1", 0).unwrap();
+        assert_eq!(checkpoint.iteration, 0);
+        assert_eq!(checkpoint.branch.as_deref(), Some(main.as_str()));
+        assert_eq!(manager.find("task-1", 0).unwrap(), checkpoint);
+
+        // Restoring on another branch would reset that branch instead
+        std::fs::remove_file(dir.path().join("a.txt")).unwrap();
+        branches.create_and_checkout("other").unwrap();
+        let other_head = repo.snapshot().unwrap().head;
+        let err = manager.restore(&checkpoint).unwrap_err();
+        assert!(err.to_string().contains(&format!("branch '{}'", main)));
+        assert_eq!(repo.snapshot().unwrap().head, other_head);
+        assert_eq!(read(&dir, "a.txt"), None);
+
+        branches.checkout(&main).unwrap();
+        manager.restore(&checkpoint).unwrap();
+        assert_eq!(read(&dir, "a.txt").as_deref(), Some("one"));
+    }
+}
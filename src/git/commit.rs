@@ -4,7 +4,7 @@
 //! https://www.conventionalcommits.org/
 #![allow(dead_code)]
 
-use super::{GitError, Result};
+use super::{GitError, Result, EXCLUDE_DOODOORI};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::path::Path;
@@ -194,10 +194,10 @@ impl CommitManager {
         }
     }
 
-    /// Stage all changes
+    /// Stage all changes, except doodoori's own `.doodoori/` files
     pub fn stage_all(&self) -> Result<()> {
         let output = Command::new("git")
-            .args(["add", "-A"])
+            .args(["add", "-A", "--", ".", EXCLUDE_DOODOORI])
             .current_dir(&self.work_dir)
             .output()?;
 
@@ -0,0 +1,275 @@
+//! Integration branches for workflows
+//!
+//! Workflow steps that run in their own worktree commit their changes on a
+//! step branch, which is merged into the workflow's integration branch when
+//! the step completes. The integration branch is checked out in a worktree
+//! of its own, so the main working tree is left alone, and new step branches
+//! start from it, so a step sees the changes of the steps it depends on.
+#![allow(dead_code)]
+
+use super::branch::BranchManager;
+use super::commit::ConventionalCommit;
+use super::repo::GitRepository;
+use super::worktree::{Worktree, WorktreeManager};
+use super::{GitError, Result, EXCLUDE_DOODOORI};
+use std::path::Path;
+use std::process::Command;
+
+/// Result of merging a step branch into the integration branch
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeOutcome {
+    /// Merged, creating the given merge commit
+    Merged(String),
+    /// The step branch has nothing the integration branch lacks
+    UpToDate,
+    /// The merge was aborted because these files conflict
+    Conflict(Vec<String>),
+}
+
+/// A workflow's integration branch, checked out in its own worktree
+pub struct IntegrationBranch {
+    worktrees: WorktreeManager,
+    worktree: Worktree,
+    /// Branch the integration branch was created from
+    base_branch: String,
+}
+
+impl IntegrationBranch {
+    /// Check out `branch` in the worktree `worktree_id`, creating the branch
+    /// from the current branch if it does not exist yet
+    pub fn open(repo_path: &Path, worktree_id: &str, branch: &str, base_branch: Option<&str>) -> Result<Self> {
+        let repo = GitRepository::open(repo_path)?;
+        let base_branch = match base_branch {
+            Some(base) => base.to_string(),
+            None => repo.current_branch()?,
+        };
+
+        let branches = BranchManager::new(&repo);
+        branches.ensure_initial_commit()?;
+        if !branches.exists(branch) {
+            branches.create_from(branch, None)?;
+        }
+
+        let worktrees = WorktreeManager::with_default_dir(repo.path())?;
+        // A worktree left behind by an interrupted run is replaced
+        if worktrees.exists(worktree_id) {
+            worktrees.remove(worktree_id)?;
+        }
+        worktrees.prune()?;
+        let worktree = worktrees.checkout_branch(worktree_id, branch)?;
+
+        Ok(Self {
+            worktrees,
+            worktree,
+            base_branch,
+        })
+    }
+
+    /// Name of the integration branch
+    pub fn branch(&self) -> &str {
+        &self.worktree.branch
+    }
+
+    /// Branch the integration branch was created from
+    pub fn base_branch(&self) -> &str {
+        &self.base_branch
+    }
+
+    /// Directory the integration branch is checked out in
+    pub fn path(&self) -> &Path {
+        &self.worktree.path
+    }
+
+    /// Create a worktree and step branch starting at the integration branch
+    pub fn create_step_worktree(&self, task_id: &str, step_name: &str, branch_prefix: &str) -> Result<Worktree> {
+        self.worktrees
+            .create_for_task_from(task_id, step_name, branch_prefix, self.branch())
+    }
+
+    /// Remove a step worktree, keeping its branch
+    pub fn remove_step_worktree(&self, worktree: &Worktree) {
+        if let Err(e) = self.worktrees.remove(&worktree.task_id) {
+            tracing::warn!("Failed to remove worktree {}: {}", worktree.path.display(), e);
+        }
+    }
+
+    /// Commit everything a step changed in its worktree, with the message
+    /// `message` builds from the changed files
+    ///
+    /// Returns the commit hash, or `None` when the step changed nothing.
+    pub fn commit_step(
+        &self,
+        worktree: &Worktree,
+        message: impl FnOnce(&[String]) -> ConventionalCommit,
+    ) -> Result<Option<String>> {
+        git(&worktree.path, &["add", "-A", "--", ".", EXCLUDE_DOODOORI])?;
+        let files: Vec<String> = git(&worktree.path, &["diff", "--cached", "--name-only"])?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if files.is_empty() {
+            return Ok(None);
+        }
+        git(&worktree.path, &["commit", "-m", &message(&files).format()])?;
+        Ok(Some(git(&worktree.path, &["rev-parse", "HEAD"])?.trim().to_string()))
+    }
+
+    /// Files that differ between the base branch and `branch`
+    pub fn files_changed(&self, branch: &str) -> Result<Vec<String>> {
+        let range = format!("{}...{}", self.base_branch, branch);
+        Ok(git(self.path(), &["diff", "--name-only", &range])?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Merge a step branch into the integration branch
+    ///
+    /// A conflicting merge is aborted, leaving the integration branch as it
+    /// was.
+    pub fn merge(&self, branch: &str, message: &str) -> Result<MergeOutcome> {
+        let output = Command::new("git")
+            .args(["merge", "--no-ff", "--no-edit", "-m", message, branch])
+            .current_dir(self.path())
+            .output()?;
+
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if stdout.contains("Already up to date") {
+                return Ok(MergeOutcome::UpToDate);
+            }
+            let head = git(self.path(), &["rev-parse", "HEAD"])?;
+            return Ok(MergeOutcome::Merged(head.trim().to_string()));
+        }
+
+        let conflicts: Vec<String> = git(self.path(), &["diff", "--name-only", "--diff-filter=U"])?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let _ = git(self.path(), &["merge", "--abort"]);
+
+        if conflicts.is_empty() {
+            return Err(GitError::OperationFailed(format!(
+                "Failed to merge {}: {}",
+                branch,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(MergeOutcome::Conflict(conflicts))
+    }
+
+    /// Remove the integration worktree, keeping the branch
+    pub fn close(self) {
+        if let Err(e) = self.worktrees.remove(&self.worktree.task_id) {
+            tracing::warn!("Failed to remove worktree {}: {}", self.worktree.path.display(), e);
+        }
+    }
+}
+
+/// Run git in `dir`, returning its standard output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output()?;
+    if !output.status.success() {
+        return Err(GitError::OperationFailed(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::commit::CommitType;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn setup_repo() -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_path_buf();
+
+        for args in [
+            vec!["init", "-b", "main"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            git(&repo_path, &args).unwrap();
+        }
+
+        std::fs::write(repo_path.join("README.md"), "# Test\n").unwrap();
+        git(&repo_path, &["add", "."]).unwrap();
+        git(&repo_path, &["commit", "-m", "Initial commit"]).unwrap();
+
+        (temp_dir, repo_path)
+    }
+
+    fn run_step(integration: &IntegrationBranch, id: &str, name: &str, file: &str, content: &str) -> Worktree {
+        let worktree = integration.create_step_worktree(id, name, "workflow/").unwrap();
+        std::fs::write(worktree.path.join(file), content).unwrap();
+        std::fs::create_dir_all(worktree.path.join(".doodoori")).unwrap();
+        std::fs::write(worktree.path.join(".doodoori/state.json"), "{}").unwrap();
+        let committed = integration
+            .commit_step(&worktree, |files| {
+                assert_eq!(files, [file]);
+                ConventionalCommit::new(CommitType::Feat, name)
+            })
+            .unwrap();
+        assert!(committed.is_some());
+        worktree
+    }
+
+    #[test]
+    fn test_merge_steps_into_integration_branch() {
+        let (_dir, repo_path) = setup_repo();
+        let integration = IntegrationBranch::open(&repo_path, "wf-integration", "workflow/demo", None).unwrap();
+        assert_eq!(integration.base_branch(), "main");
+
+        let backend = run_step(&integration, "wf-backend", "Demo Backend", "api.txt", "api\n");
+        assert_eq!(backend.branch, "workflow/demo-backend");
+        assert!(matches!(
+            integration.merge(&backend.branch, "Merge Backend").unwrap(),
+            MergeOutcome::Merged(_)
+        ));
+        integration.remove_step_worktree(&backend);
+
+        // A later step starts from the merged changes
+        let frontend = integration.create_step_worktree("wf-frontend", "Demo Frontend", "workflow/").unwrap();
+        assert!(frontend.path.join("api.txt").exists());
+        assert_eq!(
+            integration.merge(&frontend.branch, "Merge Frontend").unwrap(),
+            MergeOutcome::UpToDate
+        );
+        integration.remove_step_worktree(&frontend);
+
+        // doodoori's files are not committed, and the main tree is untouched
+        assert_eq!(integration.files_changed(integration.branch()).unwrap(), vec!["api.txt"]);
+        assert!(integration.path().join("api.txt").exists());
+        assert!(!integration.path().join(".doodoori").exists());
+        assert!(!repo_path.join("api.txt").exists());
+        integration.close();
+    }
+
+    #[test]
+    fn test_merge_conflict_is_aborted() {
+        let (_dir, repo_path) = setup_repo();
+        let integration = IntegrationBranch::open(&repo_path, "wf-integration", "workflow/demo", None).unwrap();
+
+        let first = run_step(&integration, "wf-a", "A", "README.md", "# From A\n");
+        let second = run_step(&integration, "wf-b", "B", "README.md", "# From B\n");
+
+        assert!(matches!(integration.merge(&first.branch, "Merge A").unwrap(), MergeOutcome::Merged(_)));
+        assert_eq!(
+            integration.merge(&second.branch, "Merge B").unwrap(),
+            MergeOutcome::Conflict(vec!["README.md".to_string()])
+        );
+        assert_eq!(std::fs::read_to_string(integration.path().join("README.md")).unwrap(), "# From A\n");
+        assert!(git(integration.path(), &["status", "--porcelain"]).unwrap().is_empty());
+
+        // Reopening picks the existing branch up again
+        integration.close();
+        let reopened = IntegrationBranch::open(&repo_path, "wf-integration", "workflow/demo", Some("main")).unwrap();
+        assert_eq!(std::fs::read_to_string(reopened.path().join("README.md")).unwrap(), "# From A\n");
+    }
+}
@@ -5,17 +5,33 @@
 //! - Repository management (init, open, status)
 //! - Branch management (create, checkout, delete)
 //! - Worktree management for parallel task isolation
+//! - Integration branches that workflow steps merge into
 //! - Conventional commit support
+//! - Per-iteration checkpoints on hidden refs
 //! - Pull request creation via gh CLI
+//! - Task-level branch/commit/PR automation for `doodoori run`
 
 pub mod branch;
+pub mod checkpoint;
 pub mod commit;
+pub mod integration;
 pub mod pr;
 pub mod repo;
+pub mod workflow;
 pub mod worktree;
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Pathspec that keeps doodoori's own files (`.doodoori/`) out of commits
+pub(crate) const EXCLUDE_DOODOORI: &str = ":(exclude,glob)**/.doodoori/**";
+
+/// Whether a repository-relative path is inside doodoori's own `.doodoori/`
+///
+/// For libgit2, which does not understand [`EXCLUDE_DOODOORI`]'s pathspec magic.
+pub(crate) fn is_doodoori_path(path: &Path) -> bool {
+    path.starts_with(".doodoori")
+}
 
 /// Git workflow configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +56,10 @@ pub struct GitConfig {
     #[serde(default)]
     pub commit_style: CommitStyle,
 
+    /// Auto-create a task branch before running
+    #[serde(default)]
+    pub auto_branch: bool,
+
     /// Auto-commit on task completion
     #[serde(default)]
     pub auto_commit: bool,
@@ -48,6 +68,10 @@ pub struct GitConfig {
     #[serde(default)]
     pub auto_pr: bool,
 
+    /// Enable auto-merge on the created PR (merges once CI passes)
+    #[serde(default)]
+    pub auto_merge: bool,
+
     /// Use git worktrees for task isolation
     #[serde(default)]
     pub use_worktrees: bool,
@@ -65,8 +89,10 @@ impl Default for GitConfig {
             branch_prefix_feature: "feature/".to_string(),
             branch_prefix_fix: "fix/".to_string(),
             commit_style: CommitStyle::default(),
+            auto_branch: false,
             auto_commit: false,
             auto_pr: false,
+            auto_merge: false,
             use_worktrees: false,
             worktrees_dir: PathBuf::from(".doodoori/worktrees"),
         }
@@ -125,6 +151,9 @@ pub enum GitError {
     #[error("Worktree already exists: {0}")]
     WorktreeExists(String),
 
+    #[error("Checkpoint not found: {0}")]
+    CheckpointNotFound(String),
+
     #[error("Working directory not clean")]
     NotClean,
 
@@ -195,8 +224,10 @@ mod tests {
         assert_eq!(config.branch_prefix_feature, "feature/");
         assert_eq!(config.branch_prefix_fix, "fix/");
         assert_eq!(config.commit_style, CommitStyle::Conventional);
+        assert!(!config.auto_branch);
         assert!(!config.auto_commit);
         assert!(!config.auto_pr);
+        assert!(!config.auto_merge);
         assert!(!config.use_worktrees);
     }
 }
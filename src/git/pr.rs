@@ -193,6 +193,31 @@ impl PrManager {
         Ok(())
     }
 
+    /// Enable auto-merge on a pull request (merged by GitHub once checks pass)
+    pub async fn enable_auto_merge(&self, pr_number: u32, squash: bool) -> Result<()> {
+        if !Self::is_gh_available() {
+            return Err(GitError::GhCliNotAvailable);
+        }
+
+        let output = Command::new("gh")
+            .args([
+                "pr",
+                "merge",
+                &pr_number.to_string(),
+                "--auto",
+                if squash { "--squash" } else { "--merge" },
+            ])
+            .current_dir(&self.work_dir)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::GhCliError(stderr.to_string()));
+        }
+
+        Ok(())
+    }
+
     /// List pull requests
     pub async fn list(&self, state: Option<&str>) -> Result<Vec<PullRequestInfo>> {
         if !Self::is_gh_available() {
@@ -327,6 +352,24 @@ impl PrManager {
         Ok(())
     }
 
+    /// Push a local branch to origin and track it
+    pub fn push_branch(&self, branch: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args(["push", "-u", "origin", branch])
+            .current_dir(&self.work_dir)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::OperationFailed(format!(
+                "Failed to push {}: {}",
+                branch, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Push current branch to remote
     pub fn push(&self, set_upstream: bool) -> Result<()> {
         let mut args = vec!["push".to_string()];
@@ -354,6 +397,14 @@ impl PrManager {
     }
 }
 
+/// Extract the PR number from a PR URL (e.g. `https://github.com/o/r/pull/42`)
+pub fn pr_number_from_url(url: &str) -> Option<u32> {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .and_then(|n| n.parse().ok())
+}
+
 /// Generate PR body from task information
 pub fn generate_pr_body(task_name: &str, description: Option<&str>, changes: &[String]) -> String {
     let mut body = String::new();
@@ -415,6 +466,19 @@ mod tests {
         assert!(body.contains("- login.rs"));
     }
 
+    #[test]
+    fn test_pr_number_from_url() {
+        assert_eq!(
+            pr_number_from_url("https://github.com/owner/repo/pull/42"),
+            Some(42)
+        );
+        assert_eq!(
+            pr_number_from_url("https://github.com/owner/repo/pull/7/"),
+            Some(7)
+        );
+        assert_eq!(pr_number_from_url("not a url"), None);
+    }
+
     #[test]
     fn test_gh_available() {
         // Just test that the function doesn't panic
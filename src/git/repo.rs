@@ -2,7 +2,8 @@
 #![allow(dead_code)]
 
 use super::{GitError, Result};
-use git2::{Repository, StatusOptions};
+use git2::{Oid, Repository, StatusOptions};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
 /// Git repository wrapper
@@ -103,6 +104,15 @@ impl GitRepository {
         Ok(remotes.get(0).map(|s| s.to_string()))
     }
 
+    /// Get the URL of the default remote
+    pub fn remote_url(&self) -> Result<Option<String>> {
+        let Some(name) = self.default_remote()? else {
+            return Ok(None);
+        };
+        let remote = self.repo.find_remote(&name)?;
+        Ok(remote.url().map(|url| url.to_string()))
+    }
+
     /// Get the default branch name (usually "main" or "master")
     pub fn default_branch(&self) -> Result<String> {
         // Try to find the default branch from remote
@@ -160,6 +170,95 @@ impl GitRepository {
 
         Ok(files)
     }
+
+    /// Snapshot of HEAD and all uncommitted changes
+    ///
+    /// Doodoori's own `.doodoori/` directory is ignored.
+    pub fn snapshot(&self) -> Result<WorkingTreeSnapshot> {
+        use std::hash::{Hash, Hasher};
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        let mut files = BTreeMap::new();
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            if path.starts_with(".doodoori/") {
+                continue;
+            }
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            entry.status().bits().hash(&mut hasher);
+            if let Ok(content) = std::fs::read(self.path.join(path)) {
+                content.hash(&mut hasher);
+            }
+            files.insert(path.to_string(), hasher.finish());
+        }
+
+        Ok(WorkingTreeSnapshot {
+            head: self.repo.head().ok().and_then(|head| head.target()),
+            files,
+        })
+    }
+
+    /// Hash of all uncommitted changes (paths, status and file contents)
+    ///
+    /// Doodoori's own `.doodoori/` directory is ignored. Two equal
+    /// fingerprints mean no file was changed in between.
+    pub fn working_tree_fingerprint(&self) -> Result<u64> {
+        Ok(self.snapshot()?.fingerprint())
+    }
+
+    /// Files changed between two snapshots, including files committed in between
+    pub fn changed_files(&self, before: &WorkingTreeSnapshot, after: &WorkingTreeSnapshot) -> Result<Vec<String>> {
+        let mut changed: BTreeSet<String> = before
+            .files
+            .iter()
+            .filter(|(path, hash)| after.files.get(*path) != Some(hash))
+            .chain(after.files.iter().filter(|(path, _)| !before.files.contains_key(*path)))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        if let (Some(old), Some(new)) = (before.head, after.head)
+            && old != new
+        {
+            let old_tree = self.repo.find_commit(old)?.tree()?;
+            let new_tree = self.repo.find_commit(new)?.tree()?;
+            let diff = self.repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) {
+                    changed.insert(path.to_string_lossy().to_string());
+                }
+            }
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+}
+
+/// HEAD and uncommitted changes at one point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkingTreeSnapshot {
+    /// HEAD commit (None before the first commit)
+    pub head: Option<Oid>,
+    /// Changed and untracked files with a hash of their status and content
+    pub files: BTreeMap<String, u64>,
+}
+
+impl WorkingTreeSnapshot {
+    /// Single hash of the snapshot
+    pub fn fingerprint(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        if let Some(head) = self.head {
+            head.as_bytes().hash(&mut hasher);
+        }
+        self.files.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 #[cfg(test)]
@@ -167,6 +266,41 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_working_tree_fingerprint() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = GitRepository::init(temp_dir.path()).unwrap();
+        let initial = repo.working_tree_fingerprint().unwrap();
+
+        std::fs::create_dir_all(temp_dir.path().join(".doodoori")).unwrap();
+        std::fs::write(temp_dir.path().join(".doodoori/state.json"), "{}").unwrap();
+        assert_eq!(repo.working_tree_fingerprint().unwrap(), initial);
+
+        std::fs::write(temp_dir.path().join("main.rs"), "fn main() {}").unwrap();
+        let changed = repo.working_tree_fingerprint().unwrap();
+        assert_ne!(changed, initial);
+        assert_eq!(repo.working_tree_fingerprint().unwrap(), changed);
+
+        std::fs::write(temp_dir.path().join("main.rs"), "fn main() { todo!() }").unwrap();
+        assert_ne!(repo.working_tree_fingerprint().unwrap(), changed);
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = GitRepository::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+        let before = repo.snapshot().unwrap();
+
+        std::fs::write(temp_dir.path().join("a.txt"), "a2").unwrap();
+        std::fs::write(temp_dir.path().join("c.txt"), "c").unwrap();
+        let after = repo.snapshot().unwrap();
+
+        assert_eq!(repo.changed_files(&before, &after).unwrap(), vec!["a.txt", "c.txt"]);
+        assert!(repo.changed_files(&after, &after).unwrap().is_empty());
+    }
+
     #[test]
     fn test_is_git_repo() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,451 @@
+//! Task-level git automation
+//!
+//! Applies the `[git]` settings around a single task run: a task branch is
+//! created before the loop starts, and once the task finishes the changes are
+//! committed, pushed and proposed as a pull request.
+#![allow(dead_code)]
+
+use super::branch::BranchManager;
+use super::commit::{CommitManager, CommitType, ConventionalCommit};
+use super::pr::{generate_pr_body, pr_number_from_url, PrManager, PullRequest};
+use super::repo::GitRepository;
+use super::{CommitStyle, GitConfig, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Maximum number of characters used for commit/PR titles
+const MAX_SUMMARY_CHARS: usize = 60;
+
+/// Maximum number of words used to derive a branch name from a task
+const MAX_BRANCH_WORDS: usize = 6;
+
+/// Maximum number of files listed in a generated commit body
+const MAX_LISTED_FILES: usize = 20;
+
+/// Outcome of the git automation for a single task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitWorkflowResult {
+    /// Branch that was checked out before the task started
+    pub base_branch: Option<String>,
+    /// Task branch created for this run
+    pub branch: Option<String>,
+    /// Hash of the commit created on completion
+    pub commit: Option<String>,
+    /// Files included in the commit
+    #[serde(default)]
+    pub files_changed: Vec<String>,
+    /// URL of the pull request opened on completion
+    pub pr_url: Option<String>,
+    /// Whether auto-merge was enabled on the pull request
+    pub auto_merge: bool,
+    /// Errors from individual git steps (the task itself is not failed)
+    pub errors: Vec<String>,
+}
+
+impl GitWorkflowResult {
+    /// Whether any git step failed
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+/// Runs the configured git steps around a task
+pub struct TaskGitWorkflow {
+    config: GitConfig,
+    work_dir: PathBuf,
+}
+
+impl TaskGitWorkflow {
+    /// Create a new task git workflow for the given working directory
+    pub fn new(config: GitConfig, work_dir: &Path) -> Self {
+        Self {
+            config,
+            work_dir: work_dir.to_path_buf(),
+        }
+    }
+
+    /// Whether any git step is enabled and the working directory is a repository
+    pub fn is_active(&self) -> bool {
+        self.config.enabled
+            && (self.config.auto_branch || self.config.auto_commit || self.config.auto_pr)
+            && GitRepository::is_git_repo(&self.work_dir)
+    }
+
+    /// Prepare the repository before the task runs
+    ///
+    /// Records the current branch and, if `auto_branch` is enabled, creates and
+    /// checks out a task branch. Uncommitted changes are carried over.
+    pub fn prepare(&self, task: &str) -> GitWorkflowResult {
+        let mut result = GitWorkflowResult::default();
+
+        let repo = match GitRepository::open(&self.work_dir) {
+            Ok(repo) => repo,
+            Err(e) => {
+                result.errors.push(format!("Failed to open repository: {}", e));
+                return result;
+            }
+        };
+
+        result.base_branch = repo.current_branch().ok();
+
+        if self.config.auto_branch {
+            let manager = BranchManager::new(&repo);
+            match manager
+                .create_and_checkout_task_branch(&branch_task_name(task), &self.config.branch_prefix_feature)
+            {
+                Ok(branch) => {
+                    tracing::info!("Created task branch: {}", branch);
+                    result.branch = Some(branch);
+                }
+                Err(e) => {
+                    result.errors.push(format!("Failed to create task branch: {}", e));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Commit, push and open a pull request after the task completed
+    pub async fn finish(&self, task: &str, task_id: &str, result: &mut GitWorkflowResult) {
+        if self.config.auto_commit {
+            match self.commit(task, task_id) {
+                Ok(Some((hash, files))) => {
+                    tracing::info!("Committed task changes: {}", hash);
+                    result.commit = Some(hash);
+                    result.files_changed = files;
+                }
+                Ok(None) => {
+                    tracing::info!("No changes to commit");
+                }
+                Err(e) => {
+                    result.errors.push(format!("Failed to commit changes: {}", e));
+                    return;
+                }
+            }
+        }
+
+        if self.config.auto_pr
+            && let Err(e) = self.open_pr(task, task_id, result).await
+        {
+            result.errors.push(format!("Failed to create pull request: {}", e));
+        }
+    }
+
+    /// Stage all changes and commit them, returning the commit hash and files
+    ///
+    /// Returns `Ok(None)` when there is nothing to commit.
+    fn commit(&self, task: &str, task_id: &str) -> Result<Option<(String, Vec<String>)>> {
+        let manager = CommitManager::new(&self.work_dir);
+        manager.stage_all()?;
+
+        if !manager.has_staged_changes()? {
+            return Ok(None);
+        }
+
+        let files = manager.get_staged_files()?;
+        let hash = match self.config.commit_style {
+            CommitStyle::Conventional => manager.commit(&build_commit(task, task_id, &files))?,
+            CommitStyle::Simple => manager.commit_simple(&summarize_task(task))?,
+        };
+
+        Ok(Some((hash, files)))
+    }
+
+    /// Push the current branch and open a pull request against the base branch
+    async fn open_pr(&self, task: &str, task_id: &str, result: &mut GitWorkflowResult) -> Result<()> {
+        let repo = GitRepository::open(&self.work_dir)?;
+        let head = repo.current_branch()?;
+
+        // A task branch targets the branch it was created from; otherwise
+        // the current branch targets the repository's default branch.
+        let base = match (&result.branch, &result.base_branch) {
+            (Some(_), Some(base)) => base.clone(),
+            _ => repo.default_branch()?,
+        };
+
+        if head == base {
+            return Err(super::GitError::OperationFailed(format!(
+                "Refusing to open a pull request from '{}' into itself",
+                head
+            )));
+        }
+
+        let pr_manager = PrManager::new(&self.work_dir);
+        pr_manager.push(true)?;
+
+        let description = format!("Automated by doodoori (task {}).", task_id);
+        let pr = PullRequest::new(
+            summarize_task(task),
+            generate_pr_body(&summarize_task(task), Some(&description), &result.files_changed),
+        )
+        .with_base(base)
+        .with_head(head);
+
+        let url = pr_manager.create(&pr).await?;
+        tracing::info!("Created pull request: {}", url);
+
+        if self.config.auto_merge {
+            match pr_number_from_url(&url) {
+                Some(number) => match pr_manager.enable_auto_merge(number, true).await {
+                    Ok(()) => result.auto_merge = true,
+                    Err(e) => result.errors.push(format!("Failed to enable auto-merge: {}", e)),
+                },
+                None => result
+                    .errors
+                    .push(format!("Failed to enable auto-merge: cannot parse PR number from {}", url)),
+            }
+        }
+
+        result.pr_url = Some(url);
+        Ok(())
+    }
+}
+
+/// First non-empty line of a task, without markdown heading markers
+fn first_line(task: &str) -> &str {
+    task.lines()
+        .map(|l| l.trim().trim_start_matches('#').trim())
+        .find(|l| !l.is_empty())
+        .unwrap_or("doodoori task")
+}
+
+/// Short, single-line summary of a task (Unicode-safe)
+pub fn summarize_task(task: &str) -> String {
+    let line = first_line(task);
+    if line.chars().count() > MAX_SUMMARY_CHARS {
+        let truncated: String = line.chars().take(MAX_SUMMARY_CHARS - 3).collect();
+        format!("{}...", truncated.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Name used to derive the task branch (first few words of the task)
+pub fn branch_task_name(task: &str) -> String {
+    first_line(task)
+        .split_whitespace()
+        .take(MAX_BRANCH_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Infer the conventional commit type and description from a task
+///
+/// A leading commit-type word ("Fix login bug") is used directly; otherwise
+/// mentions of bugs map to `fix` and everything else to `feat`.
+pub fn infer_commit_type(task: &str) -> (CommitType, String) {
+    let summary = summarize_task(task);
+    let mut words = summary.splitn(2, char::is_whitespace);
+    let first = words
+        .next()
+        .unwrap_or("")
+        .trim_end_matches(|c: char| !c.is_alphanumeric());
+
+    if let Ok(commit_type) = CommitType::from_str(first) {
+        let rest = words.next().unwrap_or("").trim();
+        if !rest.is_empty() {
+            return (commit_type, rest.to_string());
+        }
+    }
+
+    let lower = summary.to_lowercase();
+    let commit_type = if lower.contains("fix") || lower.contains("bug") {
+        CommitType::Fix
+    } else {
+        CommitType::Feat
+    };
+    (commit_type, summary)
+}
+
+/// Build the conventional commit for a finished task
+pub fn build_commit(task: &str, task_id: &str, files: &[String]) -> ConventionalCommit {
+    let (commit_type, description) = infer_commit_type(task);
+
+    let mut body = String::from("Files changed:\n");
+    for file in files.iter().take(MAX_LISTED_FILES) {
+        body.push_str(&format!("- {}\n", file));
+    }
+    if files.len() > MAX_LISTED_FILES {
+        body.push_str(&format!("- ... and {} more\n", files.len() - MAX_LISTED_FILES));
+    }
+
+    ConventionalCommit::new(commit_type, description)
+        .with_body(body.trim_end())
+        .with_footer(format!("Doodoori-Task: {}", task_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn setup_repo() -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_path_buf();
+
+        for args in [
+            vec!["init"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            Command::new("git")
+                .args(&args)
+                .current_dir(&repo_path)
+                .output()
+                .unwrap();
+        }
+
+        std::fs::write(repo_path.join("README.md"), "# Test").unwrap();
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(&repo_path)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["commit", "-m", "Initial commit"])
+            .current_dir(&repo_path)
+            .output()
+            .unwrap();
+
+        (temp_dir, repo_path)
+    }
+
+    fn config() -> GitConfig {
+        GitConfig {
+            auto_branch: true,
+            auto_commit: true,
+            branch_prefix_feature: "doodoori/".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summarize_task() {
+        assert_eq!(summarize_task("# Add login\n\nDetails"), "Add login");
+        let long = "a".repeat(100);
+        let summary = summarize_task(&long);
+        assert_eq!(summary.chars().count(), MAX_SUMMARY_CHARS);
+        assert!(summary.ends_with("..."));
+        // Multi-byte characters must not panic
+        let korean = "로그인 기능 추가 ".repeat(20);
+        assert!(summarize_task(&korean).ends_with("..."));
+    }
+
+    #[test]
+    fn test_infer_commit_type() {
+        let (t, d) = infer_commit_type("Fix login redirect");
+        assert_eq!(t, CommitType::Fix);
+        assert_eq!(d, "login redirect");
+
+        let (t, d) = infer_commit_type("Add a REST API for users");
+        assert_eq!(t, CommitType::Feat);
+        assert_eq!(d, "Add a REST API for users");
+
+        let (t, _) = infer_commit_type("Resolve the bug in parser");
+        assert_eq!(t, CommitType::Fix);
+
+        let (t, d) = infer_commit_type("docs: update README");
+        assert_eq!(t, CommitType::Docs);
+        assert_eq!(d, "update README");
+    }
+
+    #[test]
+    fn test_build_commit() {
+        let files: Vec<String> = (0..25).map(|i| format!("src/file{}.rs", i)).collect();
+        let commit = build_commit("Add user service", "task-123", &files);
+        let message = commit.format();
+
+        assert!(message.starts_with("feat: Add user service"));
+        assert!(message.contains("- src/file0.rs"));
+        assert!(message.contains("... and 5 more"));
+        assert!(message.ends_with("Doodoori-Task: task-123"));
+    }
+
+    #[test]
+    fn test_inactive_outside_repo() {
+        let temp_dir = TempDir::new().unwrap();
+        let workflow = TaskGitWorkflow::new(config(), temp_dir.path());
+        assert!(!workflow.is_active());
+    }
+
+    #[test]
+    fn test_inactive_when_disabled() {
+        let (_temp, repo_path) = setup_repo();
+        let workflow = TaskGitWorkflow::new(
+            GitConfig {
+                enabled: false,
+                ..config()
+            },
+            &repo_path,
+        );
+        assert!(!workflow.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_and_finish() {
+        let (_temp, repo_path) = setup_repo();
+        let workflow = TaskGitWorkflow::new(config(), &repo_path);
+        assert!(workflow.is_active());
+
+        let mut result = workflow.prepare("Add greeting module");
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert_eq!(result.branch.as_deref(), Some("doodoori/add-greeting-module"));
+        assert!(result.base_branch.is_some());
+
+        let repo = GitRepository::open(&repo_path).unwrap();
+        assert_eq!(repo.current_branch().unwrap(), "doodoori/add-greeting-module");
+
+        std::fs::write(repo_path.join("greeting.txt"), "hello").unwrap();
+        workflow.finish("Add greeting module", "task-1", &mut result).await;
+
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert!(result.commit.is_some());
+        assert_eq!(result.files_changed, vec!["greeting.txt".to_string()]);
+        assert!(repo.is_clean().unwrap());
+
+        let log = Command::new("git")
+            .args(["log", "-1", "--format=%B"])
+            .current_dir(&repo_path)
+            .output()
+            .unwrap();
+        let message = String::from_utf8_lossy(&log.stdout);
+        assert!(message.starts_with("feat: Add greeting module"));
+        assert!(message.contains("greeting.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_finish_without_changes() {
+        let (_temp, repo_path) = setup_repo();
+        let workflow = TaskGitWorkflow::new(config(), &repo_path);
+
+        let mut result = workflow.prepare("Nothing to do");
+        workflow.finish("Nothing to do", "task-2", &mut result).await;
+
+        assert!(!result.has_errors());
+        assert!(result.commit.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_commit_leaves_out_doodoori_files() {
+        let (_temp, repo_path) = setup_repo();
+        let workflow = TaskGitWorkflow::new(config(), &repo_path);
+
+        let mut result = workflow.prepare("Add greeting module");
+        std::fs::create_dir_all(repo_path.join(".doodoori")).unwrap();
+        std::fs::write(repo_path.join(".doodoori/state.json"), "{}").unwrap();
+        std::fs::write(repo_path.join("greeting.txt"), "hello").unwrap();
+        workflow.finish("Add greeting module", "task-3", &mut result).await;
+
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert_eq!(result.files_changed, vec!["greeting.txt".to_string()]);
+        let tracked = Command::new("git")
+            .args(["ls-files", ".doodoori"])
+            .current_dir(&repo_path)
+            .output()
+            .unwrap();
+        assert!(tracked.stdout.is_empty());
+    }
+}
@@ -62,16 +62,17 @@ impl WorktreeManager {
         task_name: &str,
         branch_prefix: &str,
     ) -> Result<Worktree> {
-        let worktree_path = self.worktrees_dir.join(task_id);
-
-        // Check if worktree already exists
-        if worktree_path.exists() {
-            return Err(GitError::WorktreeExists(task_id.to_string()));
-        }
-
-        // Ensure worktrees directory exists
-        std::fs::create_dir_all(&self.worktrees_dir)?;
+        self.create_for_task_from(task_id, task_name, branch_prefix, "HEAD")
+    }
 
+    /// Create a worktree for a task with a new branch starting at `start_point`
+    pub fn create_for_task_from(
+        &self,
+        task_id: &str,
+        task_name: &str,
+        branch_prefix: &str,
+        start_point: &str,
+    ) -> Result<Worktree> {
         // Create sanitized branch name
         let sanitized_name = sanitize_branch_name(task_name);
         let branch_name = format!("{}{}", branch_prefix, sanitized_name);
@@ -102,15 +103,32 @@ impl WorktreeManager {
             branch_name
         };
 
+        self.add(task_id, &["-b", &final_branch_name], start_point, &final_branch_name)
+    }
+
+    /// Create a worktree that checks out an existing branch
+    pub fn checkout_branch(&self, task_id: &str, branch: &str) -> Result<Worktree> {
+        self.add(task_id, &[], branch, branch)
+    }
+
+    /// Run `git worktree add` for `task_id` and describe the new worktree
+    fn add(&self, task_id: &str, options: &[&str], commit: &str, branch: &str) -> Result<Worktree> {
+        let worktree_path = self.worktrees_dir.join(task_id);
+
+        // Check if worktree already exists
+        if worktree_path.exists() {
+            return Err(GitError::WorktreeExists(task_id.to_string()));
+        }
+
+        // Ensure worktrees directory exists
+        std::fs::create_dir_all(&self.worktrees_dir)?;
+
         // Use git CLI for worktree operations (more reliable than libgit2)
         let output = Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                "-b",
-                &final_branch_name,
-                worktree_path.to_str().unwrap(),
-            ])
+            .args(["worktree", "add"])
+            .args(options)
+            .arg(&worktree_path)
+            .arg(commit)
             .current_dir(&self.main_repo_path)
             .output()?;
 
@@ -124,7 +142,7 @@ impl WorktreeManager {
 
         Ok(Worktree {
             path: worktree_path,
-            branch: final_branch_name,
+            branch: branch.to_string(),
             task_id: task_id.to_string(),
             created_at: Utc::now(),
         })
@@ -6,10 +6,15 @@
 //! - `on_error`: When an error occurs
 //! - `on_iteration`: After each loop iteration
 //! - `on_complete`: When task completes successfully
+//!
+//! Workflows add hooks of their own:
+//! - `on_step_complete`: When a workflow step completes successfully
+//! - `on_workflow_complete`: When a workflow completes successfully
+//! - `on_workflow_error`: When a workflow fails
 #![allow(dead_code)]
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
@@ -17,6 +22,8 @@ use std::time::Duration;
 use tokio::process::Command;
 use tokio::time::timeout;
 
+use crate::utils::truncate_str;
+
 /// Hook types that can be triggered during execution
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -31,9 +38,30 @@ pub enum HookType {
     OnIteration,
     /// When task completes successfully
     OnComplete,
+    /// When the loop stops making progress
+    OnStall,
+    /// When a workflow step completes successfully
+    OnStepComplete,
+    /// When a workflow completes successfully
+    OnWorkflowComplete,
+    /// When a workflow fails
+    OnWorkflowError,
 }
 
 impl HookType {
+    /// Every hook type, in the order they are listed in configs
+    pub const ALL: [HookType; 9] = [
+        HookType::PreRun,
+        HookType::PostRun,
+        HookType::OnError,
+        HookType::OnIteration,
+        HookType::OnComplete,
+        HookType::OnStall,
+        HookType::OnStepComplete,
+        HookType::OnWorkflowComplete,
+        HookType::OnWorkflowError,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             HookType::PreRun => "pre_run",
@@ -41,6 +69,10 @@ impl HookType {
             HookType::OnError => "on_error",
             HookType::OnIteration => "on_iteration",
             HookType::OnComplete => "on_complete",
+            HookType::OnStall => "on_stall",
+            HookType::OnStepComplete => "on_step_complete",
+            HookType::OnWorkflowComplete => "on_workflow_complete",
+            HookType::OnWorkflowError => "on_workflow_error",
         }
     }
 
@@ -51,6 +83,10 @@ impl HookType {
             HookType::OnError => "DOODOORI_ON_ERROR",
             HookType::OnIteration => "DOODOORI_ON_ITERATION",
             HookType::OnComplete => "DOODOORI_ON_COMPLETE",
+            HookType::OnStall => "DOODOORI_ON_STALL",
+            HookType::OnStepComplete => "DOODOORI_ON_STEP_COMPLETE",
+            HookType::OnWorkflowComplete => "DOODOORI_ON_WORKFLOW_COMPLETE",
+            HookType::OnWorkflowError => "DOODOORI_ON_WORKFLOW_ERROR",
         }
     }
 }
@@ -142,6 +178,17 @@ impl HookContext {
         self
     }
 
+    /// Set the workflow (`DOODOORI_WORKFLOW`, `DOODOORI_WORKFLOW_ID`)
+    pub fn with_workflow(self, name: impl Into<String>, id: impl Into<String>) -> Self {
+        self.with_custom("workflow", name)
+            .with_custom("workflow_id", id)
+    }
+
+    /// Set the workflow step (`DOODOORI_STEP`)
+    pub fn with_step(self, name: impl Into<String>) -> Self {
+        self.with_custom("step", name)
+    }
+
     /// Convert context to environment variables
     pub fn to_env_vars(&self) -> HashMap<String, String> {
         let mut vars = HashMap::new();
@@ -151,12 +198,7 @@ impl HookContext {
         }
         if let Some(ref prompt) = self.prompt {
             // Truncate long prompts for env var
-            let truncated = if prompt.len() > 1000 {
-                format!("{}...", &prompt[..997])
-            } else {
-                prompt.clone()
-            };
-            vars.insert("DOODOORI_PROMPT".to_string(), truncated);
+            vars.insert("DOODOORI_PROMPT".to_string(), truncate_str(prompt, 1000));
         }
         if let Some(ref model) = self.model {
             vars.insert("DOODOORI_MODEL".to_string(), model.clone());
@@ -253,18 +295,37 @@ impl HookDefinition {
 }
 
 /// Hooks configuration containing all hook definitions
+///
+/// Each hook is written either as a command or as a table.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HooksConfig {
     /// Pre-run hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
     pub pre_run: Option<HookDefinition>,
     /// Post-run hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
     pub post_run: Option<HookDefinition>,
     /// On-error hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
     pub on_error: Option<HookDefinition>,
     /// On-iteration hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
     pub on_iteration: Option<HookDefinition>,
     /// On-complete hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
     pub on_complete: Option<HookDefinition>,
+    /// On-stall hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
+    pub on_stall: Option<HookDefinition>,
+    /// Workflow step completed hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
+    pub on_step_complete: Option<HookDefinition>,
+    /// Workflow completed hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
+    pub on_workflow_complete: Option<HookDefinition>,
+    /// Workflow failed hook
+    #[serde(default, deserialize_with = "deserialize_hook")]
+    pub on_workflow_error: Option<HookDefinition>,
     /// Global timeout for all hooks (can be overridden per hook)
     #[serde(default = "default_timeout")]
     pub default_timeout_secs: u64,
@@ -273,6 +334,25 @@ pub struct HooksConfig {
     pub enabled: bool,
 }
 
+/// A hook given as a plain command runs it with the default settings
+fn deserialize_hook<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<HookDefinition>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawHook {
+        Command(String),
+        Definition(HookDefinition),
+    }
+
+    Ok(
+        Option::<RawHook>::deserialize(deserializer)?.map(|raw| match raw {
+            RawHook::Command(command) => HookDefinition::new(command),
+            RawHook::Definition(definition) => definition,
+        }),
+    )
+}
+
 fn default_enabled() -> bool {
     true
 }
@@ -285,6 +365,10 @@ impl Default for HooksConfig {
             on_error: None,
             on_iteration: None,
             on_complete: None,
+            on_stall: None,
+            on_step_complete: None,
+            on_workflow_complete: None,
+            on_workflow_error: None,
             default_timeout_secs: default_timeout(),
             enabled: true, // Enabled by default
         }
@@ -321,11 +405,89 @@ impl HooksConfig {
         self
     }
 
+    pub fn with_on_stall(mut self, hook: HookDefinition) -> Self {
+        self.on_stall = Some(hook);
+        self
+    }
+
+    pub fn with_on_step_complete(mut self, hook: HookDefinition) -> Self {
+        self.on_step_complete = Some(hook);
+        self
+    }
+
+    pub fn with_on_workflow_complete(mut self, hook: HookDefinition) -> Self {
+        self.on_workflow_complete = Some(hook);
+        self
+    }
+
+    pub fn with_on_workflow_error(mut self, hook: HookDefinition) -> Self {
+        self.on_workflow_error = Some(hook);
+        self
+    }
+
     pub fn disabled(mut self) -> Self {
         self.enabled = false;
         self
     }
 
+    /// These hooks with the ones configured in `overrides` in their place
+    ///
+    /// Disabled hooks contribute nothing, and disabling `overrides` disables
+    /// the result.
+    pub fn merged_with(self, overrides: &HooksConfig) -> Self {
+        let base = if self.enabled { self } else { Self::default() };
+        let pick = |hook: &Option<HookDefinition>, fallback: Option<HookDefinition>| {
+            hook.clone().or(fallback)
+        };
+        Self {
+            pre_run: pick(&overrides.pre_run, base.pre_run),
+            post_run: pick(&overrides.post_run, base.post_run),
+            on_error: pick(&overrides.on_error, base.on_error),
+            on_iteration: pick(&overrides.on_iteration, base.on_iteration),
+            on_complete: pick(&overrides.on_complete, base.on_complete),
+            on_stall: pick(&overrides.on_stall, base.on_stall),
+            on_step_complete: pick(&overrides.on_step_complete, base.on_step_complete),
+            on_workflow_complete: pick(&overrides.on_workflow_complete, base.on_workflow_complete),
+            on_workflow_error: pick(&overrides.on_workflow_error, base.on_workflow_error),
+            default_timeout_secs: base.default_timeout_secs,
+            enabled: overrides.enabled,
+        }
+    }
+
+    /// Resolve relative script paths against `base_dir`, so the hooks run
+    /// the same scripts from any working directory
+    pub fn relative_to(mut self, base_dir: &Path) -> Self {
+        for hook in [
+            &mut self.pre_run,
+            &mut self.post_run,
+            &mut self.on_error,
+            &mut self.on_iteration,
+            &mut self.on_complete,
+            &mut self.on_stall,
+            &mut self.on_step_complete,
+            &mut self.on_workflow_complete,
+            &mut self.on_workflow_error,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            // Commands without a path separator are looked up as system commands
+            let command = Path::new(&hook.command);
+            if command.is_relative() && hook.command.contains(['/', '\\']) {
+                hook.command = base_dir.join(command).display().to_string();
+            }
+        }
+        self
+    }
+
+    /// Types of the configured hooks
+    pub fn configured(&self) -> Vec<HookType> {
+        HookType::ALL
+            .into_iter()
+            .filter(|&hook_type| self.get(hook_type).is_some())
+            .collect()
+    }
+
     /// Get hook definition by type
     pub fn get(&self, hook_type: HookType) -> Option<&HookDefinition> {
         match hook_type {
@@ -334,16 +496,18 @@ impl HooksConfig {
             HookType::OnError => self.on_error.as_ref(),
             HookType::OnIteration => self.on_iteration.as_ref(),
             HookType::OnComplete => self.on_complete.as_ref(),
+            HookType::OnStall => self.on_stall.as_ref(),
+            HookType::OnStepComplete => self.on_step_complete.as_ref(),
+            HookType::OnWorkflowComplete => self.on_workflow_complete.as_ref(),
+            HookType::OnWorkflowError => self.on_workflow_error.as_ref(),
         }
     }
 
     /// Check if any hooks are configured
     pub fn has_hooks(&self) -> bool {
-        self.pre_run.is_some()
-            || self.post_run.is_some()
-            || self.on_error.is_some()
-            || self.on_iteration.is_some()
-            || self.on_complete.is_some()
+        HookType::ALL
+            .into_iter()
+            .any(|hook_type| self.get(hook_type).is_some())
     }
 
     /// Load hooks config from simple string paths (for doodoori.toml compatibility)
@@ -360,6 +524,10 @@ impl HooksConfig {
             on_error: on_error.map(|p| HookDefinition::new(p).continue_on_failure(true)),
             on_iteration: on_iteration.map(|p| HookDefinition::new(p).continue_on_failure(true)),
             on_complete: on_complete.map(|p| HookDefinition::new(p).continue_on_failure(true)),
+            on_stall: None,
+            on_step_complete: None,
+            on_workflow_complete: None,
+            on_workflow_error: None,
             default_timeout_secs: default_timeout(),
             enabled: true,
         }
@@ -701,6 +869,46 @@ mod tests {
         assert!(config.post_run.is_some());
     }
 
+    #[test]
+    fn test_hooks_config_merge() {
+        let config: HooksConfig = serde_yaml::from_str(
+            r#"
+pre_run: scripts/pre.sh
+on_step_complete:
+  command: scripts/step.sh
+  timeout_secs: 5
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.pre_run.as_ref().unwrap().command, "scripts/pre.sh");
+        assert_eq!(config.on_step_complete.as_ref().unwrap().timeout_secs, 5);
+        assert_eq!(
+            config.configured(),
+            vec![HookType::PreRun, HookType::OnStepComplete]
+        );
+
+        let base = HooksConfig::new()
+            .with_pre_run(HookDefinition::new("base-pre"))
+            .with_post_run(HookDefinition::new("base-post"));
+        let merged = base.clone().merged_with(&config);
+        assert_eq!(merged.pre_run.unwrap().command, "scripts/pre.sh");
+        assert_eq!(merged.post_run.unwrap().command, "base-post");
+        assert!(merged.enabled);
+
+        // Disabled hooks are dropped, and disabling the overrides disables all
+        assert!(
+            base.clone()
+                .disabled()
+                .merged_with(&config)
+                .post_run
+                .is_none()
+        );
+        assert!(!base.merged_with(&HooksConfig::new().disabled()).enabled);
+
+        let resolved = config.relative_to(Path::new("/project"));
+        assert_eq!(resolved.pre_run.unwrap().command, "/project/scripts/pre.sh");
+    }
+
     #[tokio::test]
     async fn test_hook_executor_no_hooks() {
         let dir = tempdir().unwrap();
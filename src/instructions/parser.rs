@@ -6,6 +6,7 @@ use std::path::Path;
 
 use super::spec::{GlobalSettings, Requirement, SpecFile, TaskSpec};
 use crate::claude::ModelAlias;
+use crate::loop_engine::ModelPolicy;
 
 /// Parser for spec files
 pub struct SpecParser;
@@ -46,7 +47,7 @@ impl SpecParser {
                         &text_buffer,
                         &list_items,
                         &mut current_task,
-                    );
+                    )?;
                     text_buffer.clear();
                     list_items.clear();
 
@@ -110,6 +111,12 @@ impl SpecParser {
                         text_buffer.push('\n');
                     }
                 }
+                Event::End(TagEnd::Paragraph)
+                    if !in_list_item && current_section.as_deref() == Some("completion criteria") =>
+                {
+                    // Keep a "Verify:" paragraph on its own line
+                    text_buffer.push('\n');
+                }
                 _ => {}
             }
         }
@@ -121,7 +128,7 @@ impl SpecParser {
             &text_buffer,
             &list_items,
             &mut current_task,
-        );
+        )?;
 
         // Save final task if exists
         if let Some(task) = current_task.take() {
@@ -137,9 +144,9 @@ impl SpecParser {
         text: &str,
         list_items: &[String],
         current_task: &mut Option<TaskSpec>,
-    ) {
+    ) -> Result<()> {
         let Some(section_name) = section else {
-            return;
+            return Ok(());
         };
 
         let text = text.trim();
@@ -161,6 +168,17 @@ impl SpecParser {
                     }
                 }
             }
+            "model policy" => {
+                let policy = ModelPolicy::parse_rules(list_items)
+                    .map_err(|e| anyhow::anyhow!("Invalid model policy: {}", e))?;
+                if !policy.is_empty() {
+                    if let Some(task) = current_task.as_mut() {
+                        task.model_policy = Some(policy);
+                    } else {
+                        spec.model_policy = Some(policy);
+                    }
+                }
+            }
             "requirements" => {
                 let reqs: Vec<Requirement> = list_items
                     .iter()
@@ -182,10 +200,13 @@ impl SpecParser {
                     .collect();
             }
             "completion criteria" => {
+                let verify_command = Self::parse_verify_command(text, list_items);
                 if let Some(task) = current_task.as_mut() {
                     task.completion_criteria = Some(text.to_string());
+                    task.verify_command = verify_command;
                 } else {
                     spec.completion_criteria = Some(text.to_string());
+                    spec.verify_command = verify_command;
                 }
             }
             "max iterations" => {
@@ -226,6 +247,7 @@ impl SpecParser {
                 }
             }
         }
+        Ok(())
     }
 
     fn parse_requirement(text: &str) -> Requirement {
@@ -277,6 +299,21 @@ impl SpecParser {
         settings
     }
 
+    /// Find a "Verify: <command>" line in completion criteria text or list items
+    fn parse_verify_command(text: &str, list_items: &[String]) -> Option<String> {
+        text.lines()
+            .chain(list_items.iter().map(String::as_str))
+            .find_map(|line| {
+                let line = line.trim();
+                let (key, value) = line.split_once(':')?;
+                if key.trim().eq_ignore_ascii_case("verify") {
+                    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+                } else {
+                    None
+                }
+            })
+    }
+
     fn parse_task_field(task: &mut TaskSpec, field: &str, text: &str, list_items: &[String]) {
         match field {
             "description" => {
@@ -340,6 +377,15 @@ impl SpecParser {
             md.push_str(&format!("## Model\n{}\n\n", model));
         }
 
+        // Model Policy
+        if let Some(ref policy) = spec.model_policy {
+            md.push_str("## Model Policy\n");
+            for rule in &policy.rules {
+                md.push_str(&format!("- {}\n", rule));
+            }
+            md.push('\n');
+        }
+
         // Requirements
         if !spec.requirements.is_empty() {
             md.push_str("## Requirements\n");
@@ -359,9 +405,18 @@ impl SpecParser {
             md.push('\n');
         }
 
-        // Completion Criteria
-        if let Some(ref criteria) = spec.completion_criteria {
-            md.push_str(&format!("## Completion Criteria\n{}\n\n", criteria));
+        // Completion Criteria (with the verification command, if not already listed)
+        match (&spec.completion_criteria, &spec.verify_command) {
+            (Some(criteria), Some(cmd)) if !criteria.contains(cmd.as_str()) => {
+                md.push_str(&format!("## Completion Criteria\n{}\n\nVerify: `{}`\n\n", criteria, cmd));
+            }
+            (Some(criteria), _) => {
+                md.push_str(&format!("## Completion Criteria\n{}\n\n", criteria));
+            }
+            (None, Some(cmd)) => {
+                md.push_str(&format!("## Completion Criteria\nVerify: `{}`\n\n", cmd));
+            }
+            (None, None) => {}
         }
 
         // Budget
@@ -463,6 +518,39 @@ Frontend implementation
         assert_eq!(spec.tasks[1].id, "frontend");
     }
 
+    #[test]
+    fn test_parse_verify_command() {
+        let content = r#"
+# Task: Fix parser
+
+## Objective
+Make the parser tests pass
+
+## Completion Criteria
+All tests pass and clippy is clean
+
+Verify: `cargo test && cargo clippy`
+"#;
+
+        let spec = SpecParser::parse(content).unwrap();
+        assert_eq!(spec.verify_command, Some("cargo test && cargo clippy".to_string()));
+        assert!(spec.completion_criteria.unwrap().starts_with("All tests pass and clippy is clean\n"));
+
+        let content = r#"
+# Task: Fix parser
+
+## Completion Criteria
+- All tests pass
+- verify: make check
+"#;
+
+        let spec = SpecParser::parse(content).unwrap();
+        assert_eq!(spec.verify_command, Some("make check".to_string()));
+
+        let roundtrip = SpecParser::parse(&SpecParser::to_markdown(&spec)).unwrap();
+        assert_eq!(roundtrip.verify_command, Some("make check".to_string()));
+    }
+
     #[test]
     fn test_parse_requirement() {
         let req1 = SpecParser::parse_requirement("[ ] Incomplete task");
@@ -550,6 +638,40 @@ Frontend implementation
         assert_eq!(parsed.max_iterations, original.max_iterations);
     }
 
+    #[test]
+    fn test_parse_model_policy() {
+        let content = r#"# Task: Policy
+
+## Objective
+Plan first, then implement
+
+## Model Policy
+- opus until 1
+- sonnet from 2
+- opus last 2 iterations
+
+### Task: cheap
+#### Model Policy
+- haiku
+- sonnet after 2 failures
+"#;
+
+        let spec = SpecParser::parse(content).unwrap();
+        let policy = spec.model_policy.as_ref().unwrap();
+        assert_eq!(policy.rules.len(), 3);
+        assert_eq!(policy.rules[0].until_iteration, Some(1));
+        assert_eq!(policy.rules[2].last_iterations, Some(2));
+
+        let task_policy = spec.tasks[0].model_policy.as_ref().unwrap();
+        assert_eq!(task_policy.rules[1].after_failures, Some(2));
+
+        let roundtrip = SpecParser::parse(&SpecParser::to_markdown(&spec)).unwrap();
+        assert_eq!(roundtrip.model_policy, spec.model_policy);
+
+        let err = SpecParser::parse(&content.replace("sonnet from 2", "sonnet form 2")).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid model policy"));
+    }
+
     #[test]
     fn test_roundtrip_with_budget() {
         let original = SpecFile {
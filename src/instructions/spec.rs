@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::claude::ModelAlias;
+use crate::loop_engine::ModelPolicy;
 
 /// A parsed spec file
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +17,10 @@ pub struct SpecFile {
     /// Model to use (optional, defaults to sonnet)
     pub model: Option<ModelAlias>,
 
+    /// Rules choosing the model per iteration (## Model Policy)
+    #[serde(default)]
+    pub model_policy: Option<ModelPolicy>,
+
     /// List of requirements
     pub requirements: Vec<Requirement>,
 
@@ -25,6 +30,11 @@ pub struct SpecFile {
     /// Completion criteria description
     pub completion_criteria: Option<String>,
 
+    /// Command that must succeed before completion is accepted
+    /// (from a "Verify: `cmd`" line in the completion criteria)
+    #[serde(default)]
+    pub verify_command: Option<String>,
+
     /// Maximum iterations for the loop engine
     pub max_iterations: Option<u32>,
 
@@ -50,9 +60,11 @@ impl Default for SpecFile {
             title: String::new(),
             objective: String::new(),
             model: None,
+            model_policy: None,
             requirements: Vec::new(),
             constraints: Vec::new(),
             completion_criteria: None,
+            verify_command: None,
             max_iterations: Some(50),
             completion_promise: Some("<promise>COMPLETE</promise>".to_string()),
             global_settings: None,
@@ -201,6 +213,10 @@ pub struct TaskSpec {
     /// Model to use for this task
     pub model: Option<ModelAlias>,
 
+    /// Model policy for this task
+    #[serde(default)]
+    pub model_policy: Option<ModelPolicy>,
+
     /// Priority (lower = higher priority)
     pub priority: u32,
 
@@ -216,6 +232,10 @@ pub struct TaskSpec {
     /// Completion criteria
     pub completion_criteria: Option<String>,
 
+    /// Verification command for this task
+    #[serde(default)]
+    pub verify_command: Option<String>,
+
     /// Maximum iterations for this task
     pub max_iterations: Option<u32>,
 }
@@ -225,11 +245,13 @@ impl Default for TaskSpec {
         Self {
             id: String::new(),
             model: None,
+            model_policy: None,
             priority: 1,
             depends_on: Vec::new(),
             description: String::new(),
             requirements: Vec::new(),
             completion_criteria: None,
+            verify_command: None,
             max_iterations: Some(30),
         }
     }
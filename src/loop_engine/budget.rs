@@ -0,0 +1,23 @@
+//! Mid-iteration budget enforcement
+//!
+//! The final cost of an iteration is only known from the `result` event at
+//! the end of the stream. The loop prices streamed usage with a
+//! [`CostMeter`](crate::pricing::CostMeter) as it arrives, so it can stop an
+//! iteration that would take the task over its budget.
+
+/// Whether the projected total cost has reached the budget limit
+pub fn exceeds_budget(limit: Option<f64>, spent: f64, iteration_estimate: f64) -> bool {
+    limit.is_some_and(|limit| spent + iteration_estimate >= limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeds_budget() {
+        assert!(!exceeds_budget(None, 100.0, 100.0));
+        assert!(!exceeds_budget(Some(5.0), 2.0, 2.0));
+        assert!(exceeds_budget(Some(5.0), 2.0, 3.0));
+    }
+}
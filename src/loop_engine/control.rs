@@ -0,0 +1,249 @@
+//! Cooperative control of a running loop
+//!
+//! A [`LoopControl`] handle is returned by `LoopEngine::execute*` and lets
+//! callers (signal handlers, the dashboard) stop, cancel, pause and resume
+//! the loop without killing the whole process, so state is still saved and
+//! post-run hooks and notifications still fire.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Requested state of a running loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlState {
+    /// Keep running
+    #[default]
+    Running,
+    /// Wait before starting the next iteration
+    Paused,
+    /// Stop after the current iteration finishes
+    Stopping,
+    /// Stop now, killing the running claude process
+    Cancelled,
+}
+
+impl ControlState {
+    /// Whether the loop has been asked to stop (gracefully or immediately)
+    pub fn is_stop_requested(&self) -> bool {
+        matches!(self, ControlState::Stopping | ControlState::Cancelled)
+    }
+}
+
+/// Cloneable handle for controlling a running loop
+#[derive(Debug, Clone)]
+pub struct LoopControl {
+    tx: Arc<watch::Sender<ControlState>>,
+}
+
+impl Default for LoopControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoopControl {
+    pub fn new() -> Self {
+        let (tx, _rx) = watch::channel(ControlState::Running);
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Current control state
+    pub fn state(&self) -> ControlState {
+        *self.tx.borrow()
+    }
+
+    /// Subscribe to control state changes
+    pub fn subscribe(&self) -> watch::Receiver<ControlState> {
+        self.tx.subscribe()
+    }
+
+    /// Stop after the current iteration
+    pub fn stop(&self) {
+        self.transition(|state| match state {
+            ControlState::Running | ControlState::Paused => Some(ControlState::Stopping),
+            _ => None,
+        });
+    }
+
+    /// Stop immediately, killing the running claude process
+    pub fn cancel(&self) {
+        self.transition(|state| match state {
+            ControlState::Cancelled => None,
+            _ => Some(ControlState::Cancelled),
+        });
+    }
+
+    /// Pause before the next iteration
+    pub fn pause(&self) {
+        self.transition(|state| match state {
+            ControlState::Running => Some(ControlState::Paused),
+            _ => None,
+        });
+    }
+
+    /// Resume a paused loop
+    pub fn resume(&self) {
+        self.transition(|state| match state {
+            ControlState::Paused => Some(ControlState::Running),
+            _ => None,
+        });
+    }
+
+    /// Toggle between paused and running
+    pub fn toggle_pause(&self) {
+        match self.state() {
+            ControlState::Paused => self.resume(),
+            _ => self.pause(),
+        }
+    }
+
+    /// Wait until the loop is no longer paused, returning the new state
+    pub async fn wait_while_paused(&self) -> ControlState {
+        let mut rx = self.subscribe();
+        match rx.wait_for(|state| *state != ControlState::Paused).await {
+            Ok(state) => *state,
+            Err(_) => ControlState::Cancelled,
+        }
+    }
+
+    /// Wait until an immediate cancel is requested
+    pub async fn cancelled(&self) {
+        let mut rx = self.subscribe();
+        let _ = rx.wait_for(|state| *state == ControlState::Cancelled).await;
+    }
+
+    fn transition(&self, next: impl FnOnce(ControlState) -> Option<ControlState>) {
+        self.tx.send_if_modified(|state| match next(*state) {
+            Some(new_state) => {
+                *state = new_state;
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+/// Route SIGINT/SIGTERM to a loop control handle
+///
+/// The first SIGINT stops after the current iteration, a second one cancels
+/// immediately and a third kills the running agents and exits the process.
+/// SIGTERM always cancels.
+pub fn spawn_signal_handler(control: LoopControl) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let mut sigterm =
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(signal) => Some(signal),
+                Err(e) => {
+                    tracing::warn!("Failed to install SIGTERM handler: {}", e);
+                    None
+                }
+            };
+
+        let mut interrupts = 0u32;
+        loop {
+            #[cfg(unix)]
+            let terminate = async {
+                match sigterm.as_mut() {
+                    Some(signal) => {
+                        signal.recv().await;
+                    }
+                    None => std::future::pending::<()>().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let terminate = std::future::pending::<()>();
+
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => {
+                    if result.is_err() {
+                        return;
+                    }
+                    interrupts += 1;
+                    match interrupts {
+                        1 => {
+                            eprintln!("\nStopping after the current iteration (press Ctrl+C again to cancel now)...");
+                            control.stop();
+                        }
+                        2 => {
+                            eprintln!("\nCancelling the current iteration...");
+                            control.cancel();
+                        }
+                        _ => {
+                            crate::claude::kill_running_agents();
+                            std::process::exit(130);
+                        }
+                    }
+                }
+                _ = terminate => {
+                    tracing::info!("Received SIGTERM, cancelling task");
+                    control.cancel();
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_transitions() {
+        let control = LoopControl::new();
+        assert_eq!(control.state(), ControlState::Running);
+
+        control.pause();
+        assert_eq!(control.state(), ControlState::Paused);
+        control.toggle_pause();
+        assert_eq!(control.state(), ControlState::Running);
+
+        control.stop();
+        assert_eq!(control.state(), ControlState::Stopping);
+        assert!(control.state().is_stop_requested());
+
+        // A stopping loop cannot be paused or resumed
+        control.pause();
+        control.resume();
+        assert_eq!(control.state(), ControlState::Stopping);
+
+        control.cancel();
+        assert_eq!(control.state(), ControlState::Cancelled);
+
+        // Cancel is final
+        control.stop();
+        assert_eq!(control.state(), ControlState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_wait_while_paused() {
+        let control = LoopControl::new();
+        control.pause();
+
+        let waiter = {
+            let control = control.clone();
+            tokio::spawn(async move { control.wait_while_paused().await })
+        };
+
+        tokio::task::yield_now().await;
+        control.resume();
+
+        assert_eq!(waiter.await.unwrap(), ControlState::Running);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        let control = LoopControl::new();
+        let waiter = {
+            let control = control.clone();
+            tokio::spawn(async move { control.cancelled().await })
+        };
+
+        control.stop();
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        control.cancel();
+        waiter.await.unwrap();
+    }
+}
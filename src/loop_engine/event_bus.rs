@@ -33,6 +33,11 @@ pub enum LiveEvent {
         is_complete: bool,
     },
 
+    /// Extended thinking from Claude
+    Thinking {
+        text: String,
+    },
+
     /// Tool execution started
     ToolStart {
         tool_name: String,
@@ -73,6 +78,8 @@ pub enum IterationPhase {
     ExecutingTools,
     /// Processing results
     Processing,
+    /// Running the completion verification command
+    Verifying,
     /// Iteration complete
     Complete,
 }
@@ -85,6 +92,7 @@ impl std::fmt::Display for IterationPhase {
             Self::Receiving => write!(f, "Receiving"),
             Self::ExecutingTools => write!(f, "Executing"),
             Self::Processing => write!(f, "Processing"),
+            Self::Verifying => write!(f, "Verifying"),
             Self::Complete => write!(f, "Complete"),
         }
     }
@@ -237,6 +245,13 @@ impl EventBus {
         }
     }
 
+    /// Send thinking event
+    pub fn send_thinking(&mut self, text: String) {
+        if self.config.emit_text_stream {
+            self.send(LiveEvent::Thinking { text });
+        }
+    }
+
     /// Send tool start event
     pub fn send_tool_start(&mut self, tool_name: String, tool_id: String) {
         self.send(LiveEvent::ToolStart { tool_name, tool_id });
@@ -351,6 +366,8 @@ pub struct ExecutionSnapshot {
     pub usage: ExecutionUsage,
     /// Recent text from Claude (last N characters)
     pub recent_text: String,
+    /// Latest thinking from Claude in the current response
+    pub thinking: Option<String>,
     /// Currently executing tools
     pub active_tools: Vec<ActiveTool>,
     /// Recent tool executions
@@ -422,6 +439,7 @@ impl ExecutionSnapshot {
             LiveEvent::TextStream { text, is_complete } => {
                 if *is_complete {
                     self.recent_text.clear();
+                    self.thinking = None;
                 } else {
                     self.recent_text.push_str(text);
                     // Keep only last 500 characters
@@ -431,6 +449,9 @@ impl ExecutionSnapshot {
                     }
                 }
             }
+            LiveEvent::Thinking { text } => {
+                self.thinking = Some(text.clone());
+            }
             LiveEvent::ToolStart { tool_name, tool_id } => {
                 self.active_tools.push(ActiveTool {
                     name: tool_name.clone(),
@@ -584,6 +605,32 @@ mod tests {
         assert_eq!(snapshot.recent_text.len(), 500);
     }
 
+    #[test]
+    fn test_execution_snapshot_tools_and_thinking() {
+        let mut snapshot = ExecutionSnapshot::new(50);
+
+        snapshot.update(&LiveEvent::Thinking { text: "Plan the fix".to_string() });
+        snapshot.update(&LiveEvent::ToolStart {
+            tool_name: "Bash".to_string(),
+            tool_id: "toolu_1".to_string(),
+        });
+        assert_eq!(snapshot.thinking.as_deref(), Some("Plan the fix"));
+        assert_eq!(snapshot.active_tools.len(), 1);
+
+        snapshot.update(&LiveEvent::ToolEnd {
+            tool_name: "Bash".to_string(),
+            tool_id: "toolu_1".to_string(),
+            success: false,
+            duration_ms: 1200,
+        });
+        assert!(snapshot.active_tools.is_empty());
+        assert!(!snapshot.tool_history[0].success);
+        assert_eq!(snapshot.tool_history[0].duration_ms, 1200);
+
+        snapshot.update(&LiveEvent::TextStream { text: String::new(), is_complete: true });
+        assert!(snapshot.thinking.is_none());
+    }
+
     #[test]
     fn test_iteration_phase_display() {
         assert_eq!(format!("{}", IterationPhase::Starting), "Starting");
@@ -0,0 +1,308 @@
+//! Iteration memory
+//!
+//! After every iteration the loop writes a short structured summary: the
+//! files it changed, the tools Claude used, the errors it ran into and the
+//! requirements that still look open. Summaries are appended to a progress
+//! journal in `.doodoori/progress/<task-id>.md`, and the most recent entries
+//! are included in the next iteration's prompt (and when the task is
+//! resumed) instead of a raw tail of the previous output.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::claude::{format_tool_stats, ToolStats};
+use crate::utils::{tail_str, truncate_str};
+
+/// Directory under .doodoori holding progress journals
+pub const PROGRESS_DIR: &str = "progress";
+
+/// Maximum number of entries listed per section
+const MAX_LIST_ITEMS: usize = 15;
+
+/// Characters of Claude's output kept in a summary
+const OUTPUT_TAIL_CHARS: usize = 800;
+
+/// What one iteration did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IterationSummary {
+    /// 1-based iteration number
+    pub iteration: u32,
+    /// Files changed during the iteration (from git)
+    pub files_changed: Vec<String>,
+    /// Tool calls made during the iteration
+    pub tools: BTreeMap<String, ToolStats>,
+    /// Errors seen (first line of each)
+    pub errors: Vec<String>,
+    /// Requirements that are not done yet
+    pub remaining: Vec<String>,
+    /// End of Claude's output
+    pub output_tail: String,
+    /// Whether the iteration completed the task
+    pub completed: bool,
+}
+
+impl IterationSummary {
+    pub fn new(iteration: u32) -> Self {
+        Self {
+            iteration,
+            ..Default::default()
+        }
+    }
+
+    /// Record Claude's output and the requirements it leaves open
+    pub fn with_output(mut self, prompt: &str, output: &str) -> Self {
+        self.output_tail = tail_str(output.trim(), OUTPUT_TAIL_CHARS).to_string();
+        self.remaining = remaining_requirements(prompt, output);
+        self
+    }
+
+    pub fn with_files_changed(mut self, files: Vec<String>) -> Self {
+        self.files_changed = files;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: &BTreeMap<String, ToolStats>) -> Self {
+        self.tools = tools.clone();
+        self
+    }
+
+    /// Record an error, keeping its first non-empty line
+    pub fn add_error(&mut self, error: &str) {
+        if let Some(line) = error.lines().map(str::trim).find(|l| !l.is_empty()) {
+            let line = truncate_str(line, 200);
+            if !self.errors.contains(&line) {
+                self.errors.push(line);
+            }
+        }
+    }
+
+    pub fn with_completed(mut self, completed: bool) -> Self {
+        self.completed = completed;
+        self
+    }
+
+    /// Markdown journal entry for this iteration
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("## Iteration {}\n\n", self.iteration);
+        if self.completed {
+            md.push_str("Claimed the task complete.\n\n");
+        }
+
+        push_list(&mut md, "Files changed", &self.files_changed);
+
+        if !self.tools.is_empty() {
+            md.push_str(&format!("Tools used: {}\n\n", format_tool_stats(&self.tools)));
+        }
+
+        push_list(&mut md, "Errors", &self.errors);
+        push_list(&mut md, "Remaining", &self.remaining);
+
+        if !self.output_tail.is_empty() {
+            md.push_str("Last output:\n\n");
+            for line in self.output_tail.lines() {
+                md.push_str(&format!("> {}\n", line));
+            }
+            md.push('\n');
+        }
+        md
+    }
+}
+
+fn push_list(md: &mut String, title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    md.push_str(&format!("{}:\n", title));
+    for item in items.iter().take(MAX_LIST_ITEMS) {
+        md.push_str(&format!("- {}\n", item));
+    }
+    if items.len() > MAX_LIST_ITEMS {
+        md.push_str(&format!("- ... and {} more\n", items.len() - MAX_LIST_ITEMS));
+    }
+    md.push('\n');
+}
+
+/// Requirements that still look open
+///
+/// Unchecked Markdown checklist items (`- [ ] ...`) in Claude's output are
+/// taken as the current to-do list. Without one, the prompt's checklist items
+/// that the output does not tick off are returned.
+pub fn remaining_requirements(prompt: &str, output: &str) -> Vec<String> {
+    let unchecked = checklist(output, false);
+    if !unchecked.is_empty() {
+        return unchecked;
+    }
+
+    let done = checklist(output, true);
+    let mut remaining = checklist(prompt, false);
+    remaining.extend(checklist(prompt, true));
+    remaining.retain(|item| !done.contains(item));
+    remaining
+}
+
+/// Checklist items of a Markdown text that are (or are not) checked
+fn checklist(text: &str, checked: bool) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let item = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))?
+                .trim_start();
+            let rest = if checked {
+                item.strip_prefix("[x]").or_else(|| item.strip_prefix("[X]"))?
+            } else {
+                item.strip_prefix("[ ]")?
+            };
+            let rest = rest.trim();
+            (!rest.is_empty()).then(|| rest.to_string())
+        })
+        .collect()
+}
+
+/// Running progress journal of a task (`.doodoori/progress/<task-id>.md`)
+#[derive(Debug, Clone)]
+pub struct ProgressJournal {
+    path: PathBuf,
+}
+
+impl ProgressJournal {
+    pub fn for_task(project_dir: &Path, task_id: &str) -> Self {
+        Self {
+            path: project_dir
+                .join(".doodoori")
+                .join(PROGRESS_DIR)
+                .join(format!("{}.md", task_id)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Append an iteration summary, creating the journal if needed
+    pub fn append(&self, prompt: &str, summary: &IterationSummary) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open progress journal: {}", self.path.display()))?;
+        if file.metadata().map(|m| m.len() == 0).unwrap_or(true) {
+            let task = prompt.lines().find(|l| !l.trim().is_empty()).unwrap_or_default();
+            write!(file, "# Progress\n\nTask: {}\n\n", truncate_str(task.trim(), 200))?;
+        }
+        file.write_all(summary.to_markdown().as_bytes())?;
+        Ok(())
+    }
+
+    /// Full journal contents
+    pub fn read(&self) -> Option<String> {
+        std::fs::read_to_string(&self.path).ok()
+    }
+
+    /// The most recent entries that fit in `max_chars`, for a prompt
+    pub fn recent(&self, max_chars: usize) -> Option<String> {
+        let journal = self.read()?;
+        let entries: Vec<&str> = journal
+            .split("\n## Iteration ")
+            .skip(1)
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+
+        let mut recent = Vec::new();
+        let mut len = 0;
+        for entry in entries.iter().rev() {
+            let entry = format!("## Iteration {}", entry.trim_end());
+            len += entry.chars().count() + 2;
+            if len > max_chars && !recent.is_empty() {
+                break;
+            }
+            recent.push(entry);
+        }
+        recent.reverse();
+
+        let omitted = entries.len() - recent.len();
+        let mut text = recent.join("\n\n");
+        if text.chars().count() > max_chars {
+            text = tail_str(&text, max_chars).to_string();
+        }
+        if omitted > 0 {
+            text = format!("({} earlier iteration(s) omitted)\n\n{}", omitted, text);
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_markdown() {
+        let mut tools = BTreeMap::new();
+        tools.insert("Bash".to_string(), ToolStats { calls: 3, failures: 1, duration_ms: 900 });
+        tools.insert("Edit".to_string(), ToolStats { calls: 2, failures: 0, duration_ms: 40 });
+
+        let mut summary = IterationSummary::new(2)
+            .with_files_changed(vec!["src/lib.rs".to_string()])
+            .with_tools(&tools)
+            .with_output("Fix it\n- [ ] parser\n- [ ] lexer", "Fixed the parser.\n- [x] parser\n- [ ] lexer");
+        summary.add_error("Bash: error[E0308]: mismatched types\n --> src/lib.rs:3");
+        summary.add_error("Bash: error[E0308]: mismatched types");
+
+        let md = summary.to_markdown();
+        assert!(md.starts_with("## Iteration 2\n"));
+        assert!(md.contains("Files changed:\n- src/lib.rs\n"));
+        assert!(md.contains("Tools used: Bash (3, 1 failed), Edit (2)"));
+        assert!(md.contains("Errors:\n- Bash: error[E0308]: mismatched types\n\n"));
+        assert!(md.contains("Remaining:\n- lexer\n"));
+        assert!(md.contains("> - [ ] lexer"));
+    }
+
+    #[test]
+    fn test_remaining_requirements() {
+        let prompt = "Build it\n- [ ] API\n- [ ] CLI\n* [ ] Docs";
+        // Nothing ticked off yet
+        assert_eq!(remaining_requirements(prompt, "Started"), vec!["API", "CLI", "Docs"]);
+        // Items the output checks off are done
+        assert_eq!(remaining_requirements(prompt, "- [x] API\n- [X] Docs"), vec!["CLI"]);
+        // Claude's own to-do list wins
+        assert_eq!(remaining_requirements(prompt, "- [ ] Tests"), vec!["Tests"]);
+        assert!(remaining_requirements("No checklist", "Done").is_empty());
+    }
+
+    #[test]
+    fn test_journal_keeps_recent_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = ProgressJournal::for_task(dir.path(), "task-1");
+        assert!(journal.recent(1000).is_none());
+
+        for i in 1..=3 {
+            let summary = IterationSummary::new(i).with_output("전체 작업: parse", &"출력 ".repeat(50));
+            journal.append("전체 작업: parse\nmore", &summary).unwrap();
+        }
+
+        let content = journal.read().unwrap();
+        assert!(content.starts_with("# Progress\n\nTask: 전체 작업: parse\n\n## Iteration 1"));
+        assert_eq!(content.matches("# Progress").count(), 1);
+
+        let all = journal.recent(10_000).unwrap();
+        assert!(all.starts_with("## Iteration 1"));
+        let recent = journal.recent(250).unwrap();
+        assert!(recent.starts_with("(2 earlier iteration(s) omitted)"));
+        assert!(recent.contains("## Iteration 3"));
+    }
+}
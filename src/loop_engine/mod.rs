@@ -3,18 +3,43 @@
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
 
-use crate::claude::{ClaudeConfig, ClaudeEvent, ClaudeRunner, ExecutionUsage, ModelAlias};
+use crate::claude::{
+    AgentBackend, AgentCommand, AgentRequest, ClaudeConfig, ClaudeEvent, ClaudeRunner, ExecutionUsage, ModelAlias,
+    ToolActivity, ToolTracker, TranscriptStore,
+};
+use crate::git::workflow::{GitWorkflowResult, TaskGitWorkflow};
+use crate::git::checkpoint::CheckpointManager;
+use crate::git::repo::{GitRepository, WorkingTreeSnapshot};
+use crate::git::GitConfig;
 use crate::hooks::{HookContext, HookExecutor, HookType, HooksConfig};
 use crate::notifications::{NotificationEvent, NotificationManager, NotificationPayload, NotificationsConfig};
-use crate::pricing::CostHistoryManager;
-use crate::state::{StateManager, TaskState};
-
+use crate::pricing::{CostEntry, CostHistoryManager, CostMeter, GlobalCostLedger, IterationCost, SpendCheck, SpendGuard, SpendLimits, SpendPeriod};
+use crate::state::{IterationRecord, StateManager, TaskState};
+use crate::utils::{tail_str, truncate_str};
+
+pub mod budget;
+pub use budget::exceeds_budget;
+pub mod control;
+pub use control::{spawn_signal_handler, ControlState, LoopControl};
 pub mod event_bus;
 pub use event_bus::{
     EventBus, ExecutionSnapshot, IterationPhase, LiveEvent, LiveStatus,
 };
+pub mod memory;
+pub use memory::{IterationSummary, ProgressJournal};
+pub mod model_policy;
+pub use model_policy::{ModelPolicy, ModelSelector};
+pub mod prompt;
+pub use prompt::{PromptTemplates, PromptVars};
+pub mod stall;
+pub use stall::{IterationFingerprint, Stall, StallAction, StallConfig, StallDetector};
+pub mod verification;
+pub use verification::{VerificationConfig, VerificationResult};
+
+/// Maximum characters of the progress journal included in a prompt
+const PROGRESS_PROMPT_CHARS: usize = 4000;
 
 /// Completion detection strategies
 #[derive(Debug, Clone)]
@@ -33,6 +58,49 @@ impl Default for CompletionStrategy {
     }
 }
 
+impl CompletionStrategy {
+    /// Build a promise strategy from a configured promise string.
+    /// A bare word such as `COMPLETE` or `DONE` is wrapped in `<promise>` tags.
+    pub fn from_promise(promise: &str) -> Self {
+        let promise = promise.trim();
+        if promise.is_empty() {
+            CompletionStrategy::default()
+        } else if promise.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            CompletionStrategy::Promise(format!("<promise>{}</promise>", promise))
+        } else {
+            CompletionStrategy::Promise(promise.to_string())
+        }
+    }
+
+    /// Text to output to signal completion, for use in prompts
+    ///
+    /// The promise, or the first of several markers. A regex has no literal
+    /// marker, so it is described instead.
+    pub fn marker(&self) -> String {
+        match self {
+            CompletionStrategy::Promise(promise) => promise.clone(),
+            CompletionStrategy::AnyOf(patterns) if !patterns.is_empty() => patterns[0].clone(),
+            _ => self.describe(),
+        }
+    }
+
+    /// Describe how to signal completion
+    pub fn describe(&self) -> String {
+        match self {
+            CompletionStrategy::Promise(promise) => format!("the completion marker: {}", promise),
+            CompletionStrategy::AnyOf(patterns) if patterns.len() == 1 => {
+                format!("the completion marker: {}", patterns[0])
+            }
+            CompletionStrategy::AnyOf(patterns) => {
+                format!("one of the completion markers: {}", patterns.join(", "))
+            }
+            CompletionStrategy::Regex(pattern) => {
+                format!("text matching the regular expression: {}", pattern)
+            }
+        }
+    }
+}
+
 /// Configuration for the Loop Engine
 #[derive(Debug, Clone)]
 pub struct LoopConfig {
@@ -42,8 +110,10 @@ pub struct LoopConfig {
     pub budget_limit: Option<f64>,
     /// How to detect completion
     pub completion_strategy: CompletionStrategy,
-    /// Model to use
+    /// Model to use (unless the model policy picks another one)
     pub model: ModelAlias,
+    /// Rules choosing the model for each iteration
+    pub model_policy: ModelPolicy,
     /// Working directory
     pub working_dir: Option<PathBuf>,
     /// YOLO mode (skip permissions)
@@ -60,6 +130,12 @@ pub struct LoopConfig {
     pub enable_cost_tracking: bool,
     /// Project directory for state/cost files
     pub project_dir: Option<PathBuf>,
+    /// User-level cost ledger that costs are also recorded in (None = off)
+    pub cost_ledger: Option<PathBuf>,
+    /// Daily and monthly spend caps, checked before each iteration
+    pub spend_limits: SpendLimits,
+    /// price.toml used to calculate costs from tokens (embedded prices if None)
+    pub price_file: Option<PathBuf>,
     /// Hooks configuration
     pub hooks: HooksConfig,
     /// Disable hooks execution
@@ -68,6 +144,32 @@ pub struct LoopConfig {
     pub notifications: NotificationsConfig,
     /// Disable notifications
     pub disable_notifications: bool,
+    /// Git workflow configuration (task branch, commit, PR)
+    pub git: GitConfig,
+    /// Disable git workflow
+    pub disable_git: bool,
+    /// Claude session ID to continue in the first iteration (used by resume)
+    pub resume_session: Option<String>,
+    /// Command that must succeed before completion is accepted
+    pub verification: Option<VerificationConfig>,
+    /// Templates for iteration prompts
+    pub prompt_templates: PromptTemplates,
+    /// Iteration a resumed task continues from (selects the resume template)
+    pub resume_from_iteration: Option<u32>,
+    /// Claude Code binary and extra arguments
+    pub agent_command: AgentCommand,
+    /// Record raw stream-json output to .doodoori/transcripts
+    pub record_transcripts: bool,
+    /// Stuck-loop detection
+    pub stall: StallConfig,
+    /// Keep a progress journal in .doodoori/progress
+    pub record_progress: bool,
+    /// Task ID to continue instead of generating a new one (used by resume)
+    pub task_id: Option<String>,
+    /// Iterations recorded before a resume
+    pub iteration_history: Vec<IterationRecord>,
+    /// Checkpoint the working tree after each iteration (refs/doodoori/<task>/<iter>)
+    pub checkpoints: bool,
 }
 
 impl Default for LoopConfig {
@@ -77,6 +179,7 @@ impl Default for LoopConfig {
             budget_limit: None,
             completion_strategy: CompletionStrategy::default(),
             model: ModelAlias::Sonnet,
+            model_policy: ModelPolicy::default(),
             working_dir: None,
             yolo_mode: false,
             readonly: false,
@@ -85,10 +188,26 @@ impl Default for LoopConfig {
             enable_state: true,
             enable_cost_tracking: true,
             project_dir: None,
+            cost_ledger: None,
+            spend_limits: SpendLimits::default(),
+            price_file: None,
             hooks: HooksConfig::default(),
             disable_hooks: false,
             notifications: NotificationsConfig::default(),
             disable_notifications: false,
+            git: GitConfig::default(),
+            disable_git: false,
+            resume_session: None,
+            verification: None,
+            prompt_templates: PromptTemplates::default(),
+            resume_from_iteration: None,
+            agent_command: AgentCommand::default(),
+            record_transcripts: true,
+            stall: StallConfig::default(),
+            record_progress: true,
+            task_id: None,
+            iteration_history: Vec::new(),
+            checkpoints: false,
         }
     }
 }
@@ -108,6 +227,8 @@ pub enum LoopStatus {
     Error(String),
     /// Stopped by user
     Stopped,
+    /// Stopped because iterations stopped making progress
+    Stalled,
 }
 
 /// Events emitted by the Loop Engine
@@ -129,6 +250,29 @@ pub enum LoopEvent {
         success: bool,
         duration_ms: u64,
     },
+    /// Completion verification command finished
+    VerificationCompleted {
+        iteration: u32,
+        result: VerificationResult,
+    },
+    /// The model policy (or a stall escalation) switched models
+    ModelChanged {
+        iteration: u32,
+        from: ModelAlias,
+        to: ModelAlias,
+    },
+    /// No progress was detected and the stall action was taken
+    StallDetected {
+        iteration: u32,
+        reason: String,
+        action: StallAction,
+    },
+    /// Spend is nearing a daily or monthly cap
+    SpendWarning { message: String },
+    /// A daily or monthly spend cap was reached; the loop stops
+    SpendCapReached { message: String },
+    /// Git workflow (branch/commit/PR) finished
+    GitWorkflowCompleted(GitWorkflowResult),
     /// Loop finished
     LoopFinished {
         status: LoopStatus,
@@ -144,6 +288,10 @@ pub struct LoopResult {
     pub iterations: u32,
     pub total_usage: ExecutionUsage,
     pub final_output: Option<String>,
+    /// Git workflow outcome (None if git workflow was not active)
+    pub git: Option<GitWorkflowResult>,
+    /// Last Claude session ID seen during the loop
+    pub session_id: Option<String>,
 }
 
 /// Persistence managers for state and cost tracking
@@ -156,13 +304,43 @@ struct PersistenceManagers {
 pub struct LoopEngine {
     config: LoopConfig,
     persistence: Option<PersistenceManagers>,
+    control: LoopControl,
+    backend: Arc<dyn AgentBackend>,
 }
 
 impl LoopEngine {
     pub fn new(config: LoopConfig) -> Self {
         // Initialize persistence managers if enabled
         let persistence = Self::init_persistence(&config);
-        Self { config, persistence }
+        Self {
+            config,
+            persistence,
+            control: LoopControl::new(),
+            backend: Arc::new(ClaudeRunner::new(ClaudeConfig::default())),
+        }
+    }
+
+    /// Handle for stopping, cancelling or pausing this engine's loop
+    pub fn control(&self) -> LoopControl {
+        self.control.clone()
+    }
+
+    fn with_control(mut self, control: LoopControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// Use a different agent backend (defaults to the local Claude Code CLI)
+    pub fn with_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Directory holding .doodoori (project dir, else working dir, else cwd)
+    fn resolve_project_dir(config: &LoopConfig) -> Option<PathBuf> {
+        config.project_dir.clone()
+            .or_else(|| config.working_dir.clone())
+            .or_else(|| std::env::current_dir().ok())
     }
 
     fn init_persistence(config: &LoopConfig) -> Option<PersistenceManagers> {
@@ -170,9 +348,7 @@ impl LoopEngine {
             return None;
         }
 
-        let project_dir = config.project_dir.clone()
-            .or_else(|| config.working_dir.clone())
-            .or_else(|| std::env::current_dir().ok())?;
+        let project_dir = Self::resolve_project_dir(config)?;
 
         let state_manager = if config.enable_state {
             StateManager::new(&project_dir).ok()
@@ -222,6 +398,21 @@ impl LoopEngine {
         self
     }
 
+    pub fn with_resume_session(mut self, session_id: String) -> Self {
+        self.config.resume_session = Some(session_id);
+        self
+    }
+
+    pub fn with_verification(mut self, verification: VerificationConfig) -> Self {
+        self.config.verification = Some(verification);
+        self
+    }
+
+    pub fn with_prompt_templates(mut self, templates: PromptTemplates) -> Self {
+        self.config.prompt_templates = templates;
+        self
+    }
+
     /// Check if the output indicates task completion
     fn is_complete(&self, output: &str) -> bool {
         match &self.config.completion_strategy {
@@ -236,77 +427,396 @@ impl LoopEngine {
     }
 
     /// Build the prompt for a specific iteration
-    fn build_prompt(&self, initial_prompt: &str, iteration: u32, previous_output: Option<&str>) -> String {
-        if iteration == 0 {
-            // First iteration: include completion instructions
-            format!(
-                "{}\n\n---\n\nWhen you have completed the task, output the completion marker: <promise>COMPLETE</promise>\n\nIf you cannot complete the task, explain why and still output: <promise>COMPLETE</promise>",
-                initial_prompt
-            )
-        } else {
-            // Subsequent iterations: continue from where we left off
-            if let Some(prev) = previous_output {
-                format!(
-                    "Continue from the previous attempt. The task is not yet complete.\n\nOriginal task: {}\n\nPrevious output summary:\n{}\n\nPlease continue working on the task. When complete, output: <promise>COMPLETE</promise>",
-                    initial_prompt,
-                    Self::truncate_output(prev, 2000)
-                )
+    fn build_prompt(
+        &self,
+        initial_prompt: &str,
+        iteration: u32,
+        previous_output: Option<&str>,
+        progress: Option<&str>,
+        verification_failure: Option<&VerificationResult>,
+        nudge: Option<&str>,
+    ) -> String {
+        let templates = &self.config.prompt_templates;
+        let completion_marker = self.config.completion_strategy.marker();
+
+        let (template, verification) = if iteration == 0 {
+            // First iteration: a resumed task gets the resume template
+            let template = if self.config.resume_from_iteration.is_some() {
+                &templates.resume
             } else {
-                format!(
-                    "Continue working on the task. Original task: {}\n\nWhen complete, output: <promise>COMPLETE</promise>",
-                    initial_prompt
-                )
+                &templates.first_iteration
+            };
+            let note = self
+                .config
+                .verification
+                .as_ref()
+                .map(|v| format!("Completion is verified by running `{}`; it must succeed before the task is accepted.\n\n", v.command))
+                .unwrap_or_default();
+            (template, note)
+        } else {
+            // Completion was claimed but the verification command failed
+            let failure = verification_failure
+                .map(|f| format!("{}\n\n", f.to_prompt_section()))
+                .unwrap_or_default();
+            (&templates.continuation, failure)
+        };
+
+        let progress = progress
+            .map(|p| format!("Progress so far:\n\n{}\n\n", p.trim_end()))
+            .unwrap_or_default();
+
+        let rendered = prompt::render(
+            template,
+            &PromptVars {
+                prompt: initial_prompt,
+                completion_marker: &completion_marker,
+                iteration: self.iteration_number(iteration),
+                previous_output: previous_output.map(|prev| tail_str(prev, 2000)).unwrap_or(""),
+                progress: &progress,
+                verification: &verification,
+            },
+        );
+
+        // The loop stalled: tell Claude before anything else
+        match nudge {
+            Some(nudge) => format!("{}\n\n{}", nudge, rendered),
+            None => rendered,
+        }
+    }
+
+    /// Task state to record this run in, if state management is enabled.
+    /// A resumed task continues its saved state, keeping its creation time,
+    /// session and totals so far.
+    fn initial_task_state(&self, initial_prompt: &str) -> Option<TaskState> {
+        if !self.config.enable_state {
+            return None;
+        }
+        let saved = match (&self.config.task_id, self.config.resume_from_iteration) {
+            (Some(task_id), Some(_)) => self
+                .persistence
+                .as_ref()
+                .and_then(|p| p.state_manager.as_ref())
+                .and_then(|manager| manager.find_task(task_id).ok().flatten()),
+            _ => None,
+        };
+        let mut state = saved.unwrap_or_else(|| {
+            TaskState::new(initial_prompt.to_string(), self.config.model.to_string(), self.config.max_iterations)
+        });
+        if let Some(ref task_id) = self.config.task_id {
+            state.task_id = task_id.clone();
+        }
+        // A resumed task keeps its earlier iterations and overall limit
+        if let Some(done) = self.config.resume_from_iteration {
+            state.current_iteration = done;
+            state.max_iterations = done + self.config.max_iterations;
+            state.iterations = self.config.iteration_history.clone();
+            state.error = None;
+            state.final_output = None;
+        }
+        Some(state)
+    }
+
+    /// 1-based iteration number, counting iterations before a resume
+    fn iteration_number(&self, iteration: u32) -> u32 {
+        self.config.resume_from_iteration.unwrap_or(0) + iteration + 1
+    }
+
+    /// Transcript file for an iteration, if transcripts are recorded
+    fn transcript_path(&self, task_id: &str, iteration: u32) -> Option<PathBuf> {
+        if !self.config.record_transcripts {
+            return None;
+        }
+        let project_dir = Self::resolve_project_dir(&self.config)?;
+        Some(TranscriptStore::for_project(&project_dir).iteration_path(task_id, self.iteration_number(iteration)))
+    }
+
+    /// Progress journal for a task, if journals are recorded
+    fn progress_journal(&self, task_id: &str) -> Option<ProgressJournal> {
+        if !self.config.record_progress {
+            return None;
+        }
+        let project_dir = Self::resolve_project_dir(&self.config)?;
+        Some(ProgressJournal::for_task(&project_dir, task_id))
+    }
+
+    /// Append an iteration summary to the journal; returns the progress
+    /// section for the next prompt
+    fn record_progress(&self, journal: Option<&ProgressJournal>, prompt: &str, summary: &IterationSummary) -> Option<String> {
+        if let Some(journal) = journal {
+            match journal.append(prompt, summary) {
+                Ok(()) => return journal.recent(PROGRESS_PROMPT_CHARS),
+                Err(e) => tracing::warn!("Failed to update progress journal: {}", e),
             }
         }
+        Some(summary.to_markdown())
     }
 
-    /// Truncate output to a maximum length
-    fn truncate_output(output: &str, max_len: usize) -> &str {
-        if output.len() <= max_len {
-            output
+    /// Current state of the working tree (None outside a git repository)
+    fn working_tree_snapshot(working_dir: &std::path::Path) -> Option<WorkingTreeSnapshot> {
+        GitRepository::open(working_dir)
+            .and_then(|repo| repo.snapshot())
+            .ok()
+    }
+
+    /// Record a checkpoint of the working tree after an iteration (0 for
+    /// the state before the first one)
+    ///
+    /// Returns the checkpoint commit, or None when checkpoints are disabled
+    /// or the working directory is not a git repository.
+    fn checkpoint(&self, working_dir: &std::path::Path, task_id: &str, iteration: u32) -> Option<String> {
+        if !self.config.checkpoints || self.config.readonly {
+            return None;
+        }
+        let repo = GitRepository::open(working_dir).ok()?;
+        match CheckpointManager::new(&repo).create(task_id, iteration) {
+            Ok(checkpoint) => Some(checkpoint.commit.to_string()),
+            Err(e) => {
+                tracing::warn!("Failed to checkpoint iteration {}: {}", iteration, e);
+                None
+            }
+        }
+    }
+
+    /// Files changed between two working tree snapshots
+    fn changed_files(
+        working_dir: &std::path::Path,
+        before: Option<&WorkingTreeSnapshot>,
+        after: Option<&WorkingTreeSnapshot>,
+    ) -> Vec<String> {
+        let (Some(before), Some(after)) = (before, after) else {
+            return Vec::new();
+        };
+        GitRepository::open(working_dir)
+            .and_then(|repo| repo.changed_files(before, after))
+            .unwrap_or_default()
+    }
+
+    /// Cancellation future for one iteration: fires when the loop is
+    /// cancelled or when `budget_kill` is notified
+    fn iteration_cancel(&self, budget_kill: Arc<Notify>) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let control = self.control();
+        async move {
+            tokio::select! {
+                _ = control.cancelled() => {}
+                _ = budget_kill.notified() => {}
+            }
+        }
+    }
+
+    /// Whether the streamed estimate for the current iteration takes the task over budget
+    fn budget_reached(&self, spent: f64, meter: &CostMeter) -> bool {
+        let reached = exceeds_budget(self.config.budget_limit, spent, meter.estimated_cost());
+        if reached {
+            tracing::warn!(
+                "Budget limit reached mid-iteration (${:.4} spent, ${:.4} estimated for this iteration); stopping Claude",
+                spent,
+                meter.estimated_cost()
+            );
+        }
+        reached
+    }
+
+    /// Run the verification command, if one is configured
+    async fn run_verification(&self, working_dir: &std::path::Path) -> Option<VerificationResult> {
+        let verification = self.config.verification.as_ref()?;
+        let result = verification.run(working_dir).await;
+        if result.success {
+            tracing::info!("Verification passed: {}", verification.command);
         } else {
-            &output[output.len() - max_len..]
+            tracing::warn!(
+                "Verification failed ({:?}): {}",
+                result.exit_code,
+                verification.command
+            );
         }
+        Some(result)
+    }
+
+    /// Stall detector for this run, if detection is enabled
+    fn stall_detector(&self) -> Option<StallDetector> {
+        self.config
+            .stall
+            .enabled
+            .then(|| StallDetector::new(self.config.stall.threshold))
+    }
+
+    /// Fingerprint of a finished iteration for stall detection
+    fn iteration_fingerprint(
+        &self,
+        snapshot: Option<&WorkingTreeSnapshot>,
+        output: &str,
+        errors: &[String],
+        verification_failure: Option<&VerificationResult>,
+    ) -> IterationFingerprint {
+        // Read-only runs cannot change files, so the working tree is no signal
+        let working_tree = if self.config.readonly {
+            None
+        } else {
+            snapshot.map(WorkingTreeSnapshot::fingerprint)
+        };
+
+        let mut fingerprint = IterationFingerprint::new(working_tree, output);
+        for error in errors {
+            fingerprint.add_error(error);
+        }
+        if let Some(failure) = verification_failure {
+            fingerprint.add_error(&failure.output);
+        }
+        fingerprint
+    }
+
+    /// Action to take for a stall; escalation moves `model` to the next
+    /// larger model for the rest of the run and stops when there is none
+    fn stall_action(&self, stall: &Stall, models: &mut ModelSelector, model: &mut ModelAlias) -> StallAction {
+        let mut action = self.config.stall.action;
+        if action == StallAction::Escalate {
+            match models.escalate(model) {
+                Some(next) => {
+                    tracing::warn!("Escalating from {} to {}", model, next);
+                    *model = next;
+                }
+                None => action = StallAction::Stop,
+            }
+        }
+        tracing::warn!("Loop stalled ({}), action: {}", stall, action);
+        action
+    }
+
+    /// Add the task's cost to the project cost history
+    fn record_task_cost(
+        &self,
+        task_id: &str,
+        initial_prompt: &str,
+        status: &LoopStatus,
+        total_usage: &ExecutionUsage,
+        iterations: Vec<IterationCost>,
+    ) {
+        let Some(cost_manager) = self.persistence.as_ref().and_then(|p| p.cost_manager.as_ref()) else {
+            return;
+        };
+
+        // Tasks that switched models are listed as e.g. "haiku+sonnet"
+        let mut models: Vec<&str> = Vec::new();
+        for iteration in &iterations {
+            if !models.contains(&iteration.model.as_str()) {
+                models.push(&iteration.model);
+            }
+        }
+        let model = if models.is_empty() {
+            self.config.model.to_string()
+        } else {
+            models.join("+")
+        };
+
+        let entry = CostEntry {
+            task_id: task_id.to_string(),
+            timestamp: chrono::Utc::now(),
+            model,
+            input_tokens: total_usage.input_tokens,
+            output_tokens: total_usage.output_tokens,
+            cache_read_tokens: total_usage.cache_read_tokens,
+            cache_creation_tokens: total_usage.cache_creation_tokens,
+            cost_usd: total_usage.total_cost_usd,
+            status: format!("{:?}", status),
+            description: Some(truncate_str(initial_prompt, 50)),
+            iterations,
+        };
+        if let Err(e) = cost_manager.append_entry(&entry) {
+            tracing::warn!("Failed to record task cost: {:#}", e);
+        }
+        if let (Some(ledger), Some(project_dir)) = (&self.config.cost_ledger, Self::resolve_project_dir(&self.config))
+            && let Err(e) = GlobalCostLedger::new(ledger).record(&project_dir, &entry)
+        {
+            tracing::warn!("Failed to record task cost in the global ledger: {:#}", e);
+        }
+    }
+
+    /// Check the spend caps before an iteration
+    ///
+    /// `pending_usd` is the task's cost so far, which is not recorded yet.
+    /// Each warning is reported (and notified) once per task; the returned
+    /// check holds only new warnings.
+    async fn check_spend_caps(
+        &self,
+        task_id: &str,
+        pending_usd: f64,
+        warned: &mut Vec<(bool, SpendPeriod)>,
+        notification_manager: Option<&NotificationManager>,
+    ) -> Option<SpendCheck> {
+        if !self.config.spend_limits.is_enabled() {
+            return None;
+        }
+        let project_dir = Self::resolve_project_dir(&self.config)?;
+        let guard = SpendGuard::new(self.config.spend_limits.clone(), &project_dir, self.config.cost_ledger.clone());
+        let mut check = match guard.check(pending_usd) {
+            Ok(check) => check,
+            Err(e) => {
+                tracing::warn!("Failed to check spend caps: {:#}", e);
+                return None;
+            }
+        };
+
+        check.warnings.retain(|cap| !warned.contains(&cap.key()));
+        for cap in &check.warnings {
+            warned.push(cap.key());
+            tracing::warn!("{}", cap);
+            if let Some(manager) = notification_manager {
+                let payload = NotificationPayload::new(NotificationEvent::SpendWarning, task_id)
+                    .with_model(self.config.model.to_string())
+                    .with_cost(pending_usd)
+                    .with_error(cap.to_string())
+                    .with_metadata("spent_usd", format!("{:.2}", cap.spent_usd))
+                    .with_metadata("limit_usd", format!("{:.2}", cap.limit_usd));
+                manager.notify_silent(&payload).await;
+            }
+        }
+        Some(check)
     }
 
     /// Execute the loop, returning events through a channel
+    ///
+    /// The returned [`LoopControl`] can stop, cancel or pause the loop.
     pub async fn execute(
         &self,
         prompt: &str,
-    ) -> Result<(mpsc::Receiver<LoopEvent>, tokio::task::JoinHandle<Result<LoopResult>>)> {
+    ) -> Result<(mpsc::Receiver<LoopEvent>, tokio::task::JoinHandle<Result<LoopResult>>, LoopControl)> {
         let (tx, rx) = mpsc::channel(100);
         let config = self.config.clone();
         let prompt = prompt.to_string();
+        let control = self.control();
+        let backend = Arc::clone(&self.backend);
 
         let handle = tokio::spawn(async move {
-            let engine = LoopEngine::new(config);
+            let engine = LoopEngine::new(config).with_control(control).with_backend(backend);
             engine.run_loop(&prompt, tx).await
         });
 
-        Ok((rx, handle))
+        Ok((rx, handle, self.control()))
     }
 
     /// Execute the loop with live event broadcasting for real-time TUI
     ///
-    /// Returns a broadcast receiver for LiveEvents and a handle to the task.
-    /// Multiple subscribers can receive events simultaneously.
+    /// Returns a broadcast receiver for LiveEvents, a handle to the task and
+    /// a [`LoopControl`]. Multiple subscribers can receive events simultaneously.
     pub async fn execute_live(
         &self,
         prompt: &str,
-    ) -> Result<(broadcast::Receiver<LiveEvent>, tokio::task::JoinHandle<Result<LoopResult>>)> {
+    ) -> Result<(broadcast::Receiver<LiveEvent>, tokio::task::JoinHandle<Result<LoopResult>>, LoopControl)> {
         let event_bus = Arc::new(Mutex::new(EventBus::new()));
         let rx = event_bus.lock().await.subscribe();
 
         let config = self.config.clone();
         let prompt = prompt.to_string();
         let event_bus_clone = Arc::clone(&event_bus);
+        let control = self.control();
+        let backend = Arc::clone(&self.backend);
 
         let handle = tokio::spawn(async move {
-            let engine = LoopEngine::new(config);
+            let engine = LoopEngine::new(config).with_control(control).with_backend(backend);
             engine.run_loop_live(&prompt, event_bus_clone).await
         });
 
-        Ok((rx, handle))
+        Ok((rx, handle, self.control()))
     }
 
     /// Execute the loop with a custom event bus (for shared subscription)
@@ -314,16 +824,60 @@ impl LoopEngine {
         &self,
         prompt: &str,
         event_bus: Arc<Mutex<EventBus>>,
-    ) -> Result<tokio::task::JoinHandle<Result<LoopResult>>> {
+    ) -> Result<(tokio::task::JoinHandle<Result<LoopResult>>, LoopControl)> {
         let config = self.config.clone();
         let prompt = prompt.to_string();
+        let control = self.control();
+        let backend = Arc::clone(&self.backend);
 
         let handle = tokio::spawn(async move {
-            let engine = LoopEngine::new(config);
+            let engine = LoopEngine::new(config).with_control(control).with_backend(backend);
             engine.run_loop_live(&prompt, event_bus).await
         });
 
-        Ok(handle)
+        Ok((handle, self.control()))
+    }
+
+    /// Record the Claude session ID announced by a `system/init` event
+    fn record_session_id(
+        &self,
+        event: &ClaudeEvent,
+        task_state: &mut Option<TaskState>,
+        session_id: &mut Option<String>,
+    ) {
+        let ClaudeEvent::System(sys) = event else {
+            return;
+        };
+        let (Some(id), "init") = (&sys.session_id, sys.subtype.as_str()) else {
+            return;
+        };
+
+        *session_id = Some(id.clone());
+
+        // Save immediately so an interrupted iteration can still be resumed
+        if let Some(state) = task_state {
+            state.set_session_id(id.clone());
+            if let Some(state_manager) = self.persistence.as_ref().and_then(|p| p.state_manager.as_ref()) {
+                let _ = state_manager.save_state(state);
+            }
+        }
+    }
+
+    /// Create the git workflow for this run if it is enabled and applicable
+    fn create_git_workflow(&self) -> Option<TaskGitWorkflow> {
+        if self.config.disable_git {
+            return None;
+        }
+
+        let working_dir = self.config.working_dir.clone()
+            .or_else(|| std::env::current_dir().ok())?;
+        let workflow = TaskGitWorkflow::new(self.config.git.clone(), &working_dir);
+
+        if workflow.is_active() {
+            Some(workflow)
+        } else {
+            None
+        }
     }
 
     /// Create hook context for the current execution state
@@ -391,6 +945,7 @@ impl LoopEngine {
         let mut total_usage = ExecutionUsage::default();
         let mut iteration = 0u32;
         let mut previous_output: Option<String> = None;
+        let mut verification_failure: Option<VerificationResult> = None;
         let mut final_output: Option<String> = None;
         let mut status = LoopStatus::Running;
 
@@ -411,20 +966,13 @@ impl LoopEngine {
         let start_time = std::time::Instant::now();
 
         // Initialize task state if state management is enabled
-        let mut task_state = if self.config.enable_state {
-            let state = TaskState::new(
-                initial_prompt.to_string(),
-                self.config.model.to_string(),
-                self.config.max_iterations,
-            );
-            Some(state)
-        } else {
-            None
-        };
+        let mut task_state = self.initial_task_state(initial_prompt);
+        let prior_usage = task_state.as_ref().map(TaskState::execution_usage).unwrap_or_default();
 
         // Get task ID for cost tracking
         let task_id = task_state.as_ref()
             .map(|s| s.task_id.clone())
+            .or_else(|| self.config.task_id.clone())
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
         // Mark task as started and save initial state
@@ -492,10 +1040,45 @@ impl LoopEngine {
                 iterations: 0,
                 total_usage,
                 final_output: None,
+                git: None,
+                session_id: None,
             });
         }
 
+        // Create the task branch before the first iteration
+        let git_workflow = self.create_git_workflow();
+        let mut git_result = git_workflow.as_ref().map(|w| w.prepare(initial_prompt));
+        let mut session_id: Option<String> = None;
+
+        // Checkpoint 0 keeps the working tree a new task starts from
+        if self.config.resume_from_iteration.is_none() {
+            self.checkpoint(&working_dir, &task_id, 0);
+        }
+
+        // The model policy picks each iteration's model; stall detection may
+        // nudge Claude or switch to a larger model
+        let mut models = ModelSelector::new(self.config.model.clone(), self.config.model_policy.clone());
+        let mut model = self.config.model.clone();
+        let mut iteration_costs: Vec<IterationCost> = Vec::new();
+        let mut stall_detector = self.stall_detector();
+        let mut stall_nudge: Option<String> = None;
+        let mut spend_warned: Vec<(bool, SpendPeriod)> = Vec::new();
+        let mut spend_cap: Option<String> = None;
+
+        // Progress so far, carried from one iteration to the next (and across resumes)
+        let journal = self.progress_journal(&task_id);
+        let mut progress = journal.as_ref().and_then(|j| j.recent(PROGRESS_PROMPT_CHARS));
+
         while iteration < self.config.max_iterations {
+            // Honor pause/stop requests before starting the next iteration
+            if self.control.state() == ControlState::Paused {
+                tracing::info!("Loop paused before iteration {}", iteration + 1);
+            }
+            if self.control.wait_while_paused().await.is_stop_requested() {
+                status = LoopStatus::Stopped;
+                break;
+            }
+
             // Check budget before starting
             if let Some(limit) = self.config.budget_limit {
                 if total_usage.total_cost_usd >= limit {
@@ -504,33 +1087,98 @@ impl LoopEngine {
                 }
             }
 
+            // Check the daily and monthly spend caps
+            let spend = self
+                .check_spend_caps(&task_id, total_usage.total_cost_usd, &mut spend_warned, notification_manager.as_ref())
+                .await;
+            if let Some(check) = spend {
+                for cap in &check.warnings {
+                    let _ = tx.send(LoopEvent::SpendWarning { message: cap.to_string() }).await;
+                }
+                if let Some(cap) = check.exceeded.first() {
+                    let _ = tx.send(LoopEvent::SpendCapReached { message: cap.to_string() }).await;
+                    spend_cap = Some(cap.to_string());
+                    status = LoopStatus::BudgetExceeded;
+                    break;
+                }
+            }
+
             // Send iteration started event
             let _ = tx.send(LoopEvent::IterationStarted { iteration }).await;
 
+            // Pick the model for this iteration
+            let selected = models.select(self.iteration_number(iteration), self.config.max_iterations - iteration);
+            if selected != model {
+                tracing::info!("Switching from {} to {} for iteration {}", model, selected, iteration + 1);
+                let _ = tx
+                    .send(LoopEvent::ModelChanged {
+                        iteration,
+                        from: model.clone(),
+                        to: selected.clone(),
+                    })
+                    .await;
+                model = selected;
+            }
+
             // Build the prompt for this iteration
-            let prompt = self.build_prompt(initial_prompt, iteration, previous_output.as_deref());
+            let prompt = self.build_prompt(
+                initial_prompt,
+                iteration,
+                previous_output.as_deref(),
+                progress.as_deref(),
+                verification_failure.as_ref(),
+                stall_nudge.take().as_deref(),
+            );
 
             // Create Claude runner
             let claude_config = ClaudeConfig {
-                model: self.config.model.clone(),
+                model: model.clone(),
                 working_dir: self.config.working_dir.clone(),
                 allowed_tools: self.config.allowed_tools.clone(),
                 yolo_mode: self.config.yolo_mode,
                 readonly: self.config.readonly,
                 system_prompt: self.config.system_prompt.clone(),
+                // Only the first iteration continues a resumed conversation
+                resume_session: if iteration == 0 {
+                    self.config.resume_session.clone()
+                } else {
+                    None
+                },
+                command: self.config.agent_command.clone(),
+                price_file: self.config.price_file.clone(),
                 ..Default::default()
             };
+            let mut cost_meter = claude_config.cost_meter();
+            let mut request = AgentRequest::new(prompt, claude_config).with_task_id(task_id.clone());
+            if let Some(path) = self.transcript_path(&task_id, iteration) {
+                request = request.with_transcript(path);
+            }
 
-            let runner = ClaudeRunner::new(claude_config)
-                .with_task_id(task_id.clone());
-
-            // Execute Claude
-            match runner.execute(&prompt).await {
+            // Execute Claude (killed if the loop is cancelled or the budget runs out)
+            let started_at = chrono::Utc::now();
+            let iteration_start = Self::working_tree_snapshot(&working_dir);
+            let budget_kill = Arc::new(Notify::new());
+            let cancel = Box::pin(self.iteration_cancel(budget_kill.clone()));
+            tracing::debug!("Running iteration {} with the {} backend", iteration + 1, self.backend.name());
+            match self.backend.execute(request, cancel).await {
                 Ok((mut event_rx, usage_handle)) => {
                     let mut output_buffer = String::new();
+                    let mut budget_hit = false;
+                    let mut tools = ToolTracker::new();
+                    let mut tool_errors = Vec::new();
 
                     // Forward events and collect output
                     while let Some(event) = event_rx.recv().await {
+                        self.record_session_id(&event, &mut task_state, &mut session_id);
+
+                        if cost_meter.record_event(&event)
+                            && !budget_hit
+                            && self.budget_reached(total_usage.total_cost_usd, &cost_meter)
+                        {
+                            budget_hit = true;
+                            budget_kill.notify_one();
+                        }
+
                         // Extract text from assistant events
                         if let ClaudeEvent::Assistant(ref asst) = event {
                             if let Some(ref msg) = asst.message {
@@ -538,15 +1186,39 @@ impl LoopEngine {
                             }
                         }
 
+                        // Failed tool calls feed stall detection
+                        for activity in tools.observe(&event) {
+                            if let ToolActivity::Finished(result) = activity
+                                && result.is_error
+                            {
+                                tool_errors.push(format!("{}: {}", result.name, result.output.unwrap_or_default()));
+                            }
+                        }
+
                         // Forward the event
                         let _ = tx.send(LoopEvent::ClaudeEvent(event)).await;
                     }
 
-                    // Get usage stats
+                    // Get usage stats (the backend prices tokens if no cost was reported)
                     let iter_usage = usage_handle.await.context("Task panicked")??;
 
-                    // Check for completion
-                    let completed = self.is_complete(&output_buffer);
+                    // Check for completion, then verify it if configured
+                    let mut completed = !budget_hit && self.is_complete(&output_buffer);
+                    verification_failure = None;
+                    if completed
+                        && let Some(result) = self.run_verification(&working_dir).await
+                    {
+                        let _ = tx
+                            .send(LoopEvent::VerificationCompleted {
+                                iteration,
+                                result: result.clone(),
+                            })
+                            .await;
+                        if !result.success {
+                            completed = false;
+                            verification_failure = Some(result);
+                        }
+                    }
 
                     // Send iteration completed event
                     let _ = tx
@@ -558,22 +1230,50 @@ impl LoopEngine {
                         .await;
 
                     // Update totals
-                    total_usage.input_tokens += iter_usage.input_tokens;
-                    total_usage.output_tokens += iter_usage.output_tokens;
-                    total_usage.cache_creation_tokens += iter_usage.cache_creation_tokens;
-                    total_usage.cache_read_tokens += iter_usage.cache_read_tokens;
-                    total_usage.total_cost_usd += iter_usage.total_cost_usd;
-                    total_usage.duration_ms += iter_usage.duration_ms;
+                    total_usage.accumulate(&iter_usage);
+
+                    // Summarize the iteration for the next prompt and the progress journal
+                    let snapshot = Self::working_tree_snapshot(&working_dir);
+                    let checkpoint = self.checkpoint(&working_dir, &task_id, self.iteration_number(iteration));
+                    let mut summary = IterationSummary::new(self.iteration_number(iteration))
+                        .with_files_changed(Self::changed_files(&working_dir, iteration_start.as_ref(), snapshot.as_ref()))
+                        .with_tools(tools.stats())
+                        .with_output(initial_prompt, &output_buffer)
+                        .with_completed(completed);
+                    for error in &tool_errors {
+                        summary.add_error(error);
+                    }
+                    if let Some(ref failure) = verification_failure {
+                        summary.add_error(&format!("Verification command `{}` failed", failure.command));
+                    }
+
+                    // Record the iteration's model and cost
+                    models.record(completed, tool_errors.len());
+                    iteration_costs.push(IterationCost::new(self.iteration_number(iteration), model.to_string(), &iter_usage));
 
                     // Update task state and save
                     if let (Some(state), Some(persistence)) = (&mut task_state, &self.persistence) {
-                        state.update_iteration(iteration);
-                        state.update_usage(&total_usage);
+                        state.update_iteration(self.iteration_number(iteration));
+                        let mut task_usage = prior_usage.clone();
+                        task_usage.accumulate(&total_usage);
+                        state.update_usage(&task_usage);
+                        state.record_iteration(
+                            IterationRecord::new(self.iteration_number(iteration), model.to_string(), started_at)
+                                .with_usage(&iter_usage)
+                                .with_completed(completed)
+                                .with_tools(summary.tools.clone())
+                                .with_errors(summary.errors.clone())
+                                .with_git_head(snapshot.as_ref().and_then(|s| s.head).map(|oid| oid.to_string()))
+                                .with_checkpoint(checkpoint)
+                                .with_session_id(session_id.clone()),
+                        );
                         if let Some(state_manager) = &persistence.state_manager {
                             let _ = state_manager.save_state(state);
                         }
                     }
 
+                    progress = self.record_progress(journal.as_ref(), initial_prompt, &summary);
+
                     previous_output = Some(output_buffer.clone());
                     final_output = Some(output_buffer);
 
@@ -586,13 +1286,76 @@ impl LoopEngine {
                         total_usage.total_cost_usd,
                         if completed { "completed" } else { "running" },
                         None,
-                    );
+                    )
+                    .with_model(model.to_string());
                     let _ = self.execute_hook(&hook_executor, HookType::OnIteration, &iter_context, &tx).await;
 
+                    if budget_hit {
+                        status = LoopStatus::BudgetExceeded;
+                        break;
+                    }
                     if completed {
                         status = LoopStatus::Completed;
                         break;
                     }
+
+                    // Act when iterations stop making progress
+                    if let Some(detector) = stall_detector.as_mut() {
+                        let fingerprint = self.iteration_fingerprint(
+                            snapshot.as_ref(),
+                            previous_output.as_deref().unwrap_or_default(),
+                            &tool_errors,
+                            verification_failure.as_ref(),
+                        );
+                        if let Some(stall) = detector.observe(fingerprint) {
+                            detector.reset();
+                            let action = self.stall_action(&stall, &mut models, &mut model);
+                            let reason = stall.to_string();
+                            let _ = tx
+                                .send(LoopEvent::StallDetected {
+                                    iteration,
+                                    reason: reason.clone(),
+                                    action,
+                                })
+                                .await;
+
+                            let stall_context = self
+                                .create_hook_context(
+                                    &task_id,
+                                    initial_prompt,
+                                    Some(iteration),
+                                    Some(self.config.max_iterations),
+                                    total_usage.total_cost_usd,
+                                    "stalled",
+                                    Some(&reason),
+                                )
+                                .with_model(model.to_string())
+                                .with_custom("stall_action", action.as_str());
+                            let _ = self.execute_hook(&hook_executor, HookType::OnStall, &stall_context, &tx).await;
+
+                            match action {
+                                StallAction::Stop => {
+                                    status = LoopStatus::Stalled;
+                                    break;
+                                }
+                                StallAction::Nudge => stall_nudge = Some(self.config.stall.render_prompt(&stall)),
+                                StallAction::Escalate => {}
+                            }
+
+                            if let Some(ref manager) = notification_manager {
+                                let payload = NotificationPayload::new(NotificationEvent::Stalled, task_id.clone())
+                                    .with_prompt(initial_prompt)
+                                    .with_model(model.to_string())
+                                    .with_iterations(iteration + 1)
+                                    .with_cost(total_usage.total_cost_usd)
+                                    .with_duration(start_time.elapsed().as_millis() as u64)
+                                    .with_error(reason.clone())
+                                    .with_metadata("action", action.as_str())
+                                    .with_metadata("reason", reason);
+                                manager.notify_silent(&payload).await;
+                            }
+                        }
+                    }
                 }
                 Err(e) => {
                     // Execute on_error hook
@@ -609,6 +1372,11 @@ impl LoopEngine {
 
                     // Mark task as failed
                     if let (Some(state), Some(persistence)) = (&mut task_state, &self.persistence) {
+                        state.record_iteration(
+                            IterationRecord::new(self.iteration_number(iteration), model.to_string(), started_at)
+                                .with_errors(vec![e.to_string()])
+                                .with_session_id(session_id.clone()),
+                        );
                         state.fail(e.to_string());
                         if let Some(state_manager) = &persistence.state_manager {
                             let _ = state_manager.save_state(state);
@@ -619,6 +1387,12 @@ impl LoopEngine {
                 }
             }
 
+            // Stop requested during this iteration
+            if self.control.state().is_stop_requested() {
+                status = LoopStatus::Stopped;
+                break;
+            }
+
             iteration += 1;
         }
 
@@ -633,8 +1407,14 @@ impl LoopEngine {
                 LoopStatus::Completed => {
                     state.complete(final_output.clone());
                 }
-                LoopStatus::MaxIterationsReached | LoopStatus::BudgetExceeded | LoopStatus::Stopped => {
+                LoopStatus::MaxIterationsReached
+                | LoopStatus::BudgetExceeded
+                | LoopStatus::Stopped
+                | LoopStatus::Stalled => {
                     state.interrupt();
+                    if spend_cap.is_some() {
+                        state.error = spend_cap.clone();
+                    }
                 }
                 LoopStatus::Error(err) => {
                     state.fail(err.clone());
@@ -658,34 +1438,18 @@ impl LoopEngine {
             }
         }
 
-        // Record total cost for the task
-        // Note: We re-initialize cost manager here to get a mutable reference
-        if self.config.enable_cost_tracking {
-            let project_dir = self.config.project_dir.clone()
-                .or_else(|| self.config.working_dir.clone())
-                .or_else(|| std::env::current_dir().ok());
-
-            if let Some(ref project_dir) = project_dir {
-                if let Ok(mut cost_manager) = CostHistoryManager::for_project(project_dir) {
-                    let status_str = format!("{:?}", status);
-                    let prompt_summary = if initial_prompt.len() > 50 {
-                        format!("{}...", &initial_prompt[..47])
-                    } else {
-                        initial_prompt.to_string()
-                    };
-                    let _ = cost_manager.record_cost(
-                        &task_id,
-                        &self.config.model.to_string(),
-                        total_usage.input_tokens,
-                        total_usage.output_tokens,
-                        total_usage.cache_read_tokens,
-                        total_usage.cache_creation_tokens,
-                        total_usage.total_cost_usd,
-                        &status_str,
-                        Some(prompt_summary),
-                    );
-                }
+        // Record total cost for the task, split by the models used
+        self.record_task_cost(&task_id, initial_prompt, &status, &total_usage, iteration_costs);
+
+        // Commit, push and open a PR for completed tasks
+        if let (Some(workflow), Some(result)) = (&git_workflow, &mut git_result) {
+            if status == LoopStatus::Completed {
+                workflow.finish(initial_prompt, &task_id, result).await;
+            }
+            for error in &result.errors {
+                tracing::warn!("Git workflow: {}", error);
             }
+            let _ = tx.send(LoopEvent::GitWorkflowCompleted(result.clone())).await;
         }
 
         // Execute final hooks based on status
@@ -695,6 +1459,7 @@ impl LoopEngine {
             LoopStatus::BudgetExceeded => "budget_exceeded",
             LoopStatus::Error(_) => "error",
             LoopStatus::Stopped => "stopped",
+            LoopStatus::Stalled => "stalled",
             LoopStatus::Running => "running",
         };
 
@@ -734,19 +1499,22 @@ impl LoopEngine {
                 LoopStatus::MaxIterationsReached => NotificationEvent::MaxIterations,
                 LoopStatus::BudgetExceeded => NotificationEvent::BudgetExceeded,
                 LoopStatus::Error(_) => NotificationEvent::Error,
-                _ => NotificationEvent::Completed, // Stopped/Running treated as completed
+                LoopStatus::Stopped => NotificationEvent::Interrupted,
+                LoopStatus::Stalled => NotificationEvent::Stalled,
+                LoopStatus::Running => NotificationEvent::Completed,
             };
 
             let payload = NotificationPayload {
                 event: notification_event,
                 task_id: task_id.clone(),
                 prompt: initial_prompt.to_string(),
-                model: self.config.model.to_string(),
+                model: model.to_string(),
                 iterations: iteration + 1,
                 cost_usd: total_usage.total_cost_usd,
                 duration_ms: start_time.elapsed().as_millis() as u64,
                 error: match &status {
                     LoopStatus::Error(e) => Some(e.clone()),
+                    LoopStatus::BudgetExceeded => spend_cap.clone(),
                     _ => None,
                 },
                 timestamp: chrono::Utc::now().to_rfc3339(),
@@ -770,12 +1538,14 @@ impl LoopEngine {
             iterations: iteration + 1,
             total_usage,
             final_output,
+            git: git_result,
+            session_id,
         })
     }
 
     /// Execute the loop and wait for completion (blocking)
     pub async fn execute_and_wait(&self, prompt: &str) -> Result<LoopResult> {
-        let (mut rx, handle) = self.execute(prompt).await?;
+        let (mut rx, handle, _control) = self.execute(prompt).await?;
 
         // Drain the channel (we don't need the events here)
         while rx.recv().await.is_some() {}
@@ -796,6 +1566,7 @@ impl LoopEngine {
         let mut total_usage = ExecutionUsage::default();
         let mut iteration = 0u32;
         let mut previous_output: Option<String> = None;
+        let mut verification_failure: Option<VerificationResult> = None;
         let mut final_output: Option<String> = None;
         let mut status = LoopStatus::Running;
 
@@ -822,20 +1593,13 @@ impl LoopEngine {
         let start_time = std::time::Instant::now();
 
         // Initialize task state if state management is enabled
-        let mut task_state = if self.config.enable_state {
-            let state = TaskState::new(
-                initial_prompt.to_string(),
-                self.config.model.to_string(),
-                self.config.max_iterations,
-            );
-            Some(state)
-        } else {
-            None
-        };
+        let mut task_state = self.initial_task_state(initial_prompt);
+        let prior_usage = task_state.as_ref().map(TaskState::execution_usage).unwrap_or_default();
 
         // Get task ID for cost tracking
         let task_id = task_state.as_ref()
             .map(|s| s.task_id.clone())
+            .or_else(|| self.config.task_id.clone())
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
         // Mark task as started and save initial state
@@ -910,9 +1674,35 @@ impl LoopEngine {
                 iterations: 0,
                 total_usage,
                 final_output: None,
+                git: None,
+                session_id: None,
             });
         }
 
+        // Create the task branch before the first iteration
+        let git_workflow = self.create_git_workflow();
+        let mut git_result = git_workflow.as_ref().map(|w| w.prepare(initial_prompt));
+        let mut session_id: Option<String> = None;
+
+        // Checkpoint 0 keeps the working tree a new task starts from
+        if self.config.resume_from_iteration.is_none() {
+            self.checkpoint(&working_dir, &task_id, 0);
+        }
+
+        // The model policy picks each iteration's model; stall detection may
+        // nudge Claude or switch to a larger model
+        let mut models = ModelSelector::new(self.config.model.clone(), self.config.model_policy.clone());
+        let mut model = self.config.model.clone();
+        let mut iteration_costs: Vec<IterationCost> = Vec::new();
+        let mut stall_detector = self.stall_detector();
+        let mut stall_nudge: Option<String> = None;
+        let mut spend_warned: Vec<(bool, SpendPeriod)> = Vec::new();
+        let mut spend_cap: Option<String> = None;
+
+        // Progress so far, carried from one iteration to the next (and across resumes)
+        let journal = self.progress_journal(&task_id);
+        let mut progress = journal.as_ref().and_then(|j| j.recent(PROGRESS_PROMPT_CHARS));
+
         // Send running status
         {
             let mut bus = event_bus.lock().await;
@@ -920,6 +1710,23 @@ impl LoopEngine {
         }
 
         while iteration < self.config.max_iterations {
+            // Honor pause/stop requests before starting the next iteration
+            let paused = self.control.state() == ControlState::Paused;
+            if paused {
+                let mut bus = event_bus.lock().await;
+                bus.send_status_change(LiveStatus::Paused, Some(format!("Paused before iteration {}", iteration + 1)));
+            }
+            if self.control.wait_while_paused().await.is_stop_requested() {
+                status = LoopStatus::Stopped;
+                let mut bus = event_bus.lock().await;
+                bus.send_status_change(LiveStatus::Finished(LoopStatus::Stopped), Some("Stopped by user".to_string()));
+                break;
+            }
+            if paused {
+                let mut bus = event_bus.lock().await;
+                bus.send_status_change(LiveStatus::Running, None);
+            }
+
             // Check budget before starting
             if let Some(limit) = self.config.budget_limit {
                 if total_usage.total_cost_usd >= limit {
@@ -930,6 +1737,24 @@ impl LoopEngine {
                 }
             }
 
+            // Check the daily and monthly spend caps
+            let spend = self
+                .check_spend_caps(&task_id, total_usage.total_cost_usd, &mut spend_warned, notification_manager.as_ref())
+                .await;
+            if let Some(check) = spend {
+                let mut bus = event_bus.lock().await;
+                for cap in &check.warnings {
+                    bus.send_loop_event(LoopEvent::SpendWarning { message: cap.to_string() });
+                }
+                if let Some(cap) = check.exceeded.first() {
+                    bus.send_loop_event(LoopEvent::SpendCapReached { message: cap.to_string() });
+                    bus.send_status_change(LiveStatus::Finished(LoopStatus::BudgetExceeded), Some(cap.to_string()));
+                    spend_cap = Some(cap.to_string());
+                    status = LoopStatus::BudgetExceeded;
+                    break;
+                }
+            }
+
             // Send iteration started event and phase
             {
                 let mut bus = event_bus.lock().await;
@@ -937,8 +1762,28 @@ impl LoopEngine {
                 bus.send_iteration_progress(iteration, IterationPhase::Starting);
             }
 
+            // Pick the model for this iteration
+            let selected = models.select(self.iteration_number(iteration), self.config.max_iterations - iteration);
+            if selected != model {
+                tracing::info!("Switching from {} to {} for iteration {}", model, selected, iteration + 1);
+                let mut bus = event_bus.lock().await;
+                bus.send_loop_event(LoopEvent::ModelChanged {
+                    iteration,
+                    from: model.clone(),
+                    to: selected.clone(),
+                });
+                model = selected;
+            }
+
             // Build the prompt for this iteration
-            let prompt = self.build_prompt(initial_prompt, iteration, previous_output.as_deref());
+            let prompt = self.build_prompt(
+                initial_prompt,
+                iteration,
+                previous_output.as_deref(),
+                progress.as_deref(),
+                verification_failure.as_ref(),
+                stall_nudge.take().as_deref(),
+            );
 
             // Send sending phase
             {
@@ -948,23 +1793,41 @@ impl LoopEngine {
 
             // Create Claude runner
             let claude_config = ClaudeConfig {
-                model: self.config.model.clone(),
+                model: model.clone(),
                 working_dir: self.config.working_dir.clone(),
                 allowed_tools: self.config.allowed_tools.clone(),
                 yolo_mode: self.config.yolo_mode,
                 readonly: self.config.readonly,
                 system_prompt: self.config.system_prompt.clone(),
+                // Only the first iteration continues a resumed conversation
+                resume_session: if iteration == 0 {
+                    self.config.resume_session.clone()
+                } else {
+                    None
+                },
+                command: self.config.agent_command.clone(),
+                price_file: self.config.price_file.clone(),
                 ..Default::default()
             };
+            let mut cost_meter = claude_config.cost_meter();
+            let mut request = AgentRequest::new(prompt, claude_config).with_task_id(task_id.clone());
+            if let Some(path) = self.transcript_path(&task_id, iteration) {
+                request = request.with_transcript(path);
+            }
 
-            let runner = ClaudeRunner::new(claude_config)
-                .with_task_id(task_id.clone());
-
-            // Execute Claude
-            match runner.execute(&prompt).await {
+            // Execute Claude (killed if the loop is cancelled or the budget runs out)
+            let started_at = chrono::Utc::now();
+            let iteration_start = Self::working_tree_snapshot(&working_dir);
+            let budget_kill = Arc::new(Notify::new());
+            let cancel = Box::pin(self.iteration_cancel(budget_kill.clone()));
+            tracing::debug!("Running iteration {} with the {} backend", iteration + 1, self.backend.name());
+            match self.backend.execute(request, cancel).await {
                 Ok((mut event_rx, usage_handle)) => {
                     let mut output_buffer = String::new();
+                    let mut budget_hit = false;
                     let mut prev_usage = ExecutionUsage::default();
+                    let mut tools = ToolTracker::new();
+                    let mut tool_errors = Vec::new();
 
                     // Send receiving phase
                     {
@@ -974,6 +1837,20 @@ impl LoopEngine {
 
                     // Forward events and collect output
                     while let Some(event) = event_rx.recv().await {
+                        self.record_session_id(&event, &mut task_state, &mut session_id);
+
+                        // Report the running cost estimate and stop once the budget is reached
+                        let previous_estimate = cost_meter.estimated_cost();
+                        if cost_meter.record_event(&event) {
+                            let estimate = cost_meter.estimated_cost();
+                            let mut bus = event_bus.lock().await;
+                            bus.send_cost_update(total_usage.total_cost_usd + estimate, estimate - previous_estimate);
+                            if !budget_hit && self.budget_reached(total_usage.total_cost_usd, &cost_meter) {
+                                budget_hit = true;
+                                budget_kill.notify_one();
+                            }
+                        }
+
                         // Process different event types
                         match &event {
                             ClaudeEvent::Assistant(asst) => {
@@ -981,28 +1858,14 @@ impl LoopEngine {
                                     let text = msg.as_text();
                                     output_buffer.push_str(&text);
 
-                                    // Send text stream event
                                     let mut bus = event_bus.lock().await;
+                                    if let Some(thinking) = msg.thinking() {
+                                        bus.send_thinking(thinking);
+                                    }
+                                    // Send text stream event
                                     bus.send_text_stream(text, false);
                                 }
                             }
-                            ClaudeEvent::ToolUse(tool) => {
-                                let mut bus = event_bus.lock().await;
-                                bus.send_iteration_progress(iteration, IterationPhase::ExecutingTools);
-                                // Use tool_name as identifier since tool_id is not available
-                                let tool_id = format!("{}-{}", tool.tool_name, uuid::Uuid::new_v4().to_string()[..8].to_string());
-                                bus.send_tool_start(tool.tool_name.clone(), tool_id);
-                            }
-                            ClaudeEvent::ToolResult(result) => {
-                                let mut bus = event_bus.lock().await;
-                                // Use tool_name as identifier since tool_id is not available
-                                bus.send_tool_end(
-                                    result.tool_name.clone(),
-                                    result.tool_name.clone(), // Use name as fallback ID
-                                    !result.is_error,
-                                    0, // Duration not available from result
-                                );
-                            }
                             ClaudeEvent::Result(res) => {
                                 // Send token delta if usage changed
                                 if let Some(ref usage) = res.usage {
@@ -1024,11 +1887,32 @@ impl LoopEngine {
                             _ => {}
                         }
 
+                        // Tool calls, paired with their results by tool use ID
+                        for activity in tools.observe(&event) {
+                            let mut bus = event_bus.lock().await;
+                            match activity {
+                                ToolActivity::Started(call) => {
+                                    bus.send_iteration_progress(iteration, IterationPhase::ExecutingTools);
+                                    bus.send_tool_start(call.name, call.id);
+                                }
+                                ToolActivity::Finished(result) => {
+                                    if result.is_error {
+                                        tool_errors.push(format!("{}: {}", result.name, result.output.unwrap_or_default()));
+                                    }
+                                    bus.send_tool_end(result.name, result.id, !result.is_error, result.duration_ms);
+                                }
+                            }
+                        }
+
                         // Forward the event as a loop event
                         let mut bus = event_bus.lock().await;
                         bus.send_loop_event(LoopEvent::ClaudeEvent(event));
                     }
 
+                    if tools.pending() > 0 {
+                        tracing::debug!("{} tool call(s) ended without a result", tools.pending());
+                    }
+
                     // Send text stream complete
                     {
                         let mut bus = event_bus.lock().await;
@@ -1036,14 +1920,32 @@ impl LoopEngine {
                         bus.send_iteration_progress(iteration, IterationPhase::Processing);
                     }
 
-                    // Get usage stats
+                    // Get usage stats (the backend prices tokens if no cost was reported)
                     let iter_usage = usage_handle.await.context("Task panicked")??;
 
-                    // Check for completion
-                    let completed = self.is_complete(&output_buffer);
+                    // Check for completion, then verify it if configured
+                    let mut completed = !budget_hit && self.is_complete(&output_buffer);
+                    verification_failure = None;
+                    if completed && self.config.verification.is_some() {
+                        {
+                            let mut bus = event_bus.lock().await;
+                            bus.send_iteration_progress(iteration, IterationPhase::Verifying);
+                        }
+                        if let Some(result) = self.run_verification(&working_dir).await {
+                            let mut bus = event_bus.lock().await;
+                            bus.send_loop_event(LoopEvent::VerificationCompleted {
+                                iteration,
+                                result: result.clone(),
+                            });
+                            if !result.success {
+                                completed = false;
+                                verification_failure = Some(result);
+                            }
+                        }
+                    }
 
-                    // Calculate cost delta
-                    let cost_delta = iter_usage.total_cost_usd;
+                    // Calculate cost delta relative to the streamed estimate already reported
+                    let cost_delta = iter_usage.total_cost_usd - cost_meter.estimated_cost();
 
                     // Send iteration completed event and cost update
                     {
@@ -1053,27 +1955,55 @@ impl LoopEngine {
                             usage: iter_usage.clone(),
                             completed,
                         });
-                        bus.send_cost_update(total_usage.total_cost_usd + cost_delta, cost_delta);
+                        bus.send_cost_update(total_usage.total_cost_usd + iter_usage.total_cost_usd, cost_delta);
                         bus.send_iteration_progress(iteration, IterationPhase::Complete);
                     }
 
                     // Update totals
-                    total_usage.input_tokens += iter_usage.input_tokens;
-                    total_usage.output_tokens += iter_usage.output_tokens;
-                    total_usage.cache_creation_tokens += iter_usage.cache_creation_tokens;
-                    total_usage.cache_read_tokens += iter_usage.cache_read_tokens;
-                    total_usage.total_cost_usd += iter_usage.total_cost_usd;
-                    total_usage.duration_ms += iter_usage.duration_ms;
+                    total_usage.accumulate(&iter_usage);
+
+                    // Summarize the iteration for the next prompt and the progress journal
+                    let snapshot = Self::working_tree_snapshot(&working_dir);
+                    let checkpoint = self.checkpoint(&working_dir, &task_id, self.iteration_number(iteration));
+                    let mut summary = IterationSummary::new(self.iteration_number(iteration))
+                        .with_files_changed(Self::changed_files(&working_dir, iteration_start.as_ref(), snapshot.as_ref()))
+                        .with_tools(tools.stats())
+                        .with_output(initial_prompt, &output_buffer)
+                        .with_completed(completed);
+                    for error in &tool_errors {
+                        summary.add_error(error);
+                    }
+                    if let Some(ref failure) = verification_failure {
+                        summary.add_error(&format!("Verification command `{}` failed", failure.command));
+                    }
+
+                    // Record the iteration's model and cost
+                    models.record(completed, tool_errors.len());
+                    iteration_costs.push(IterationCost::new(self.iteration_number(iteration), model.to_string(), &iter_usage));
 
                     // Update task state and save
                     if let (Some(state), Some(persistence)) = (&mut task_state, &self.persistence) {
-                        state.update_iteration(iteration);
-                        state.update_usage(&total_usage);
+                        state.update_iteration(self.iteration_number(iteration));
+                        let mut task_usage = prior_usage.clone();
+                        task_usage.accumulate(&total_usage);
+                        state.update_usage(&task_usage);
+                        state.record_iteration(
+                            IterationRecord::new(self.iteration_number(iteration), model.to_string(), started_at)
+                                .with_usage(&iter_usage)
+                                .with_completed(completed)
+                                .with_tools(summary.tools.clone())
+                                .with_errors(summary.errors.clone())
+                                .with_git_head(snapshot.as_ref().and_then(|s| s.head).map(|oid| oid.to_string()))
+                                .with_checkpoint(checkpoint)
+                                .with_session_id(session_id.clone()),
+                        );
                         if let Some(state_manager) = &persistence.state_manager {
                             let _ = state_manager.save_state(state);
                         }
                     }
 
+                    progress = self.record_progress(journal.as_ref(), initial_prompt, &summary);
+
                     previous_output = Some(output_buffer.clone());
                     final_output = Some(output_buffer);
 
@@ -1086,15 +2016,86 @@ impl LoopEngine {
                         total_usage.total_cost_usd,
                         if completed { "completed" } else { "running" },
                         None,
-                    );
+                    )
+                    .with_model(model.to_string());
                     let _ = self.execute_hook_live(&hook_executor, HookType::OnIteration, &iter_context, &event_bus).await;
 
+                    if budget_hit {
+                        status = LoopStatus::BudgetExceeded;
+                        let mut bus = event_bus.lock().await;
+                        bus.send_status_change(LiveStatus::Finished(LoopStatus::BudgetExceeded), Some("Budget limit reached".to_string()));
+                        break;
+                    }
                     if completed {
                         status = LoopStatus::Completed;
                         let mut bus = event_bus.lock().await;
                         bus.send_status_change(LiveStatus::Completing, Some("Task completed".to_string()));
                         break;
                     }
+
+                    // Act when iterations stop making progress
+                    if let Some(detector) = stall_detector.as_mut() {
+                        let fingerprint = self.iteration_fingerprint(
+                            snapshot.as_ref(),
+                            previous_output.as_deref().unwrap_or_default(),
+                            &tool_errors,
+                            verification_failure.as_ref(),
+                        );
+                        if let Some(stall) = detector.observe(fingerprint) {
+                            detector.reset();
+                            let action = self.stall_action(&stall, &mut models, &mut model);
+                            let reason = stall.to_string();
+                            {
+                                let mut bus = event_bus.lock().await;
+                                bus.send_loop_event(LoopEvent::StallDetected {
+                                    iteration,
+                                    reason: reason.clone(),
+                                    action,
+                                });
+                                if action != StallAction::Stop {
+                                    bus.send_status_change(LiveStatus::Running, Some(format!("Stalled ({}): {}", action, reason)));
+                                }
+                            }
+
+                            let stall_context = self
+                                .create_hook_context(
+                                    &task_id,
+                                    initial_prompt,
+                                    Some(iteration),
+                                    Some(self.config.max_iterations),
+                                    total_usage.total_cost_usd,
+                                    "stalled",
+                                    Some(&reason),
+                                )
+                                .with_model(model.to_string())
+                                .with_custom("stall_action", action.as_str());
+                            let _ = self.execute_hook_live(&hook_executor, HookType::OnStall, &stall_context, &event_bus).await;
+
+                            match action {
+                                StallAction::Stop => {
+                                    status = LoopStatus::Stalled;
+                                    let mut bus = event_bus.lock().await;
+                                    bus.send_status_change(LiveStatus::Finished(LoopStatus::Stalled), Some(reason));
+                                    break;
+                                }
+                                StallAction::Nudge => stall_nudge = Some(self.config.stall.render_prompt(&stall)),
+                                StallAction::Escalate => {}
+                            }
+
+                            if let Some(ref manager) = notification_manager {
+                                let payload = NotificationPayload::new(NotificationEvent::Stalled, task_id.clone())
+                                    .with_prompt(initial_prompt)
+                                    .with_model(model.to_string())
+                                    .with_iterations(iteration + 1)
+                                    .with_cost(total_usage.total_cost_usd)
+                                    .with_duration(start_time.elapsed().as_millis() as u64)
+                                    .with_error(reason.clone())
+                                    .with_metadata("action", action.as_str())
+                                    .with_metadata("reason", reason);
+                                manager.notify_silent(&payload).await;
+                            }
+                        }
+                    }
                 }
                 Err(e) => {
                     // Send error status
@@ -1117,6 +2118,11 @@ impl LoopEngine {
 
                     // Mark task as failed
                     if let (Some(state), Some(persistence)) = (&mut task_state, &self.persistence) {
+                        state.record_iteration(
+                            IterationRecord::new(self.iteration_number(iteration), model.to_string(), started_at)
+                                .with_errors(vec![e.to_string()])
+                                .with_session_id(session_id.clone()),
+                        );
                         state.fail(e.to_string());
                         if let Some(state_manager) = &persistence.state_manager {
                             let _ = state_manager.save_state(state);
@@ -1127,6 +2133,14 @@ impl LoopEngine {
                 }
             }
 
+            // Stop requested during this iteration
+            if self.control.state().is_stop_requested() {
+                status = LoopStatus::Stopped;
+                let mut bus = event_bus.lock().await;
+                bus.send_status_change(LiveStatus::Finished(LoopStatus::Stopped), Some("Stopped by user".to_string()));
+                break;
+            }
+
             iteration += 1;
         }
 
@@ -1141,8 +2155,14 @@ impl LoopEngine {
                 LoopStatus::Completed => {
                     state.complete(final_output.clone());
                 }
-                LoopStatus::MaxIterationsReached | LoopStatus::BudgetExceeded | LoopStatus::Stopped => {
+                LoopStatus::MaxIterationsReached
+                | LoopStatus::BudgetExceeded
+                | LoopStatus::Stopped
+                | LoopStatus::Stalled => {
                     state.interrupt();
+                    if spend_cap.is_some() {
+                        state.error = spend_cap.clone();
+                    }
                 }
                 LoopStatus::Error(err) => {
                     state.fail(err.clone());
@@ -1165,33 +2185,19 @@ impl LoopEngine {
             }
         }
 
-        // Record total cost for the task
-        if self.config.enable_cost_tracking {
-            let project_dir = self.config.project_dir.clone()
-                .or_else(|| self.config.working_dir.clone())
-                .or_else(|| std::env::current_dir().ok());
-
-            if let Some(ref project_dir) = project_dir {
-                if let Ok(mut cost_manager) = CostHistoryManager::for_project(project_dir) {
-                    let status_str = format!("{:?}", status);
-                    let prompt_summary = if initial_prompt.len() > 50 {
-                        format!("{}...", &initial_prompt[..47])
-                    } else {
-                        initial_prompt.to_string()
-                    };
-                    let _ = cost_manager.record_cost(
-                        &task_id,
-                        &self.config.model.to_string(),
-                        total_usage.input_tokens,
-                        total_usage.output_tokens,
-                        total_usage.cache_read_tokens,
-                        total_usage.cache_creation_tokens,
-                        total_usage.total_cost_usd,
-                        &status_str,
-                        Some(prompt_summary),
-                    );
-                }
+        // Record total cost for the task, split by the models used
+        self.record_task_cost(&task_id, initial_prompt, &status, &total_usage, iteration_costs);
+
+        // Commit, push and open a PR for completed tasks
+        if let (Some(workflow), Some(result)) = (&git_workflow, &mut git_result) {
+            if status == LoopStatus::Completed {
+                workflow.finish(initial_prompt, &task_id, result).await;
             }
+            for error in &result.errors {
+                tracing::warn!("Git workflow: {}", error);
+            }
+            let mut bus = event_bus.lock().await;
+            bus.send_loop_event(LoopEvent::GitWorkflowCompleted(result.clone()));
         }
 
         // Execute final hooks based on status
@@ -1201,6 +2207,7 @@ impl LoopEngine {
             LoopStatus::BudgetExceeded => "budget_exceeded",
             LoopStatus::Error(_) => "error",
             LoopStatus::Stopped => "stopped",
+            LoopStatus::Stalled => "stalled",
             LoopStatus::Running => "running",
         };
 
@@ -1240,19 +2247,22 @@ impl LoopEngine {
                 LoopStatus::MaxIterationsReached => NotificationEvent::MaxIterations,
                 LoopStatus::BudgetExceeded => NotificationEvent::BudgetExceeded,
                 LoopStatus::Error(_) => NotificationEvent::Error,
-                _ => NotificationEvent::Completed,
+                LoopStatus::Stopped => NotificationEvent::Interrupted,
+                LoopStatus::Stalled => NotificationEvent::Stalled,
+                LoopStatus::Running => NotificationEvent::Completed,
             };
 
             let payload = NotificationPayload {
                 event: notification_event,
                 task_id: task_id.clone(),
                 prompt: initial_prompt.to_string(),
-                model: self.config.model.to_string(),
+                model: model.to_string(),
                 iterations: iteration + 1,
                 cost_usd: total_usage.total_cost_usd,
                 duration_ms: start_time.elapsed().as_millis() as u64,
                 error: match &status {
                     LoopStatus::Error(e) => Some(e.clone()),
+                    LoopStatus::BudgetExceeded => spend_cap.clone(),
                     _ => None,
                 },
                 timestamp: chrono::Utc::now().to_rfc3339(),
@@ -1278,6 +2288,8 @@ impl LoopEngine {
             iterations: iteration + 1,
             total_usage,
             final_output,
+            git: git_result,
+            session_id,
         })
     }
 
@@ -1313,6 +2325,15 @@ impl LoopEngine {
 mod tests {
     use super::*;
 
+    /// Config for tests that must not write state or costs into the repository
+    fn test_config() -> LoopConfig {
+        LoopConfig {
+            enable_state: false,
+            enable_cost_tracking: false,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_default_config() {
         let config = LoopConfig::default();
@@ -1324,7 +2345,7 @@ mod tests {
 
     #[test]
     fn test_completion_strategy_promise() {
-        let engine = LoopEngine::new(LoopConfig::default());
+        let engine = LoopEngine::new(test_config());
         assert!(engine.is_complete("Task done! <promise>COMPLETE</promise>"));
         assert!(!engine.is_complete("Task still in progress"));
     }
@@ -1337,7 +2358,7 @@ mod tests {
                 "FINISHED".to_string(),
                 "COMPLETE".to_string(),
             ]),
-            ..Default::default()
+            ..test_config()
         };
         let engine = LoopEngine::new(config);
 
@@ -1351,7 +2372,7 @@ mod tests {
     fn test_completion_strategy_regex() {
         let config = LoopConfig {
             completion_strategy: CompletionStrategy::Regex(r"(?i)task\s+completed?".to_string()),
-            ..Default::default()
+            ..test_config()
         };
         let engine = LoopEngine::new(config);
 
@@ -1362,8 +2383,8 @@ mod tests {
 
     #[test]
     fn test_build_prompt_first_iteration() {
-        let engine = LoopEngine::new(LoopConfig::default());
-        let prompt = engine.build_prompt("Write hello world", 0, None);
+        let engine = LoopEngine::new(test_config());
+        let prompt = engine.build_prompt("Write hello world", 0, None, None, None, None);
 
         assert!(prompt.contains("Write hello world"));
         assert!(prompt.contains("<promise>COMPLETE</promise>"));
@@ -1371,27 +2392,123 @@ mod tests {
 
     #[test]
     fn test_build_prompt_subsequent_iteration() {
-        let engine = LoopEngine::new(LoopConfig::default());
-        let prompt = engine.build_prompt("Write hello world", 1, Some("Started writing..."));
+        let engine = LoopEngine::new(test_config());
+        let progress = "## Iteration 1\n\nFiles changed:\n- hello.rs";
+        let prompt = engine.build_prompt("Write hello world", 1, Some("Started writing..."), Some(progress), None, None);
 
         assert!(prompt.contains("Continue"));
         assert!(prompt.contains("Write hello world"));
-        assert!(prompt.contains("Started writing..."));
+        assert!(prompt.contains("Progress so far:\n\n## Iteration 1\n\nFiles changed:\n- hello.rs\n\n"));
+
+        // The previous output is still available to custom templates, cut on a character boundary
+        let config = LoopConfig {
+            prompt_templates: PromptTemplates {
+                continuation: "{previous_output}".to_string(),
+                ..Default::default()
+            },
+            ..test_config()
+        };
+        let engine = LoopEngine::new(config);
+        let output = format!("{}끝", "가".repeat(2500));
+        let prompt = engine.build_prompt("Write hello world", 1, Some(&output), None, None, None);
+        assert_eq!(prompt.chars().count(), 2000);
+        assert!(prompt.ends_with("가끝"));
     }
 
     #[test]
-    fn test_truncate_output() {
-        let short = "hello";
-        assert_eq!(LoopEngine::truncate_output(short, 10), "hello");
+    fn test_build_prompt_uses_completion_strategy() {
+        let engine = LoopEngine::new(test_config())
+            .with_completion_strategy(CompletionStrategy::from_promise("DONE"));
+
+        let first = engine.build_prompt("Write hello world", 0, None, None, None, None);
+        assert!(first.contains("<promise>DONE</promise>"));
+        assert!(!first.contains("COMPLETE"));
+
+        let next = engine.build_prompt("Write hello world", 1, Some("Started"), None, None, None);
+        assert!(next.contains("<promise>DONE</promise>"));
+        assert!(engine.is_complete(&next));
+
+        let engine = LoopEngine::new(test_config())
+            .with_completion_strategy(CompletionStrategy::AnyOf(vec!["SHIPPED".to_string(), "ABANDONED".to_string()]));
+        let first = engine.build_prompt("Ship it", 0, None, None, None, None);
+        assert!(first.contains("output SHIPPED"));
+
+        let engine = LoopEngine::new(test_config())
+            .with_completion_strategy(CompletionStrategy::Regex(r"(?i)all done".to_string()));
+        let first = engine.build_prompt("Ship it", 0, None, None, None, None);
+        assert!(first.contains("text matching the regular expression: (?i)all done"));
+    }
 
-        let long = "hello world this is a long string";
-        let truncated = LoopEngine::truncate_output(long, 10);
-        assert_eq!(truncated.len(), 10);
+    #[test]
+    fn test_completion_strategy_from_promise() {
+        assert!(matches!(
+            CompletionStrategy::from_promise("COMPLETE"),
+            CompletionStrategy::Promise(p) if p == "<promise>COMPLETE</promise>"
+        ));
+        assert!(matches!(
+            CompletionStrategy::from_promise("<promise>DONE</promise>"),
+            CompletionStrategy::Promise(p) if p == "<promise>DONE</promise>"
+        ));
+        assert!(matches!(
+            CompletionStrategy::from_promise("ALL TESTS PASS"),
+            CompletionStrategy::Promise(p) if p == "ALL TESTS PASS"
+        ));
+    }
+
+    #[test]
+    fn test_build_prompt_custom_templates() {
+        let config = LoopConfig {
+            prompt_templates: PromptTemplates {
+                first_iteration: "TASK: {prompt}\nEND WITH {completion_marker}".to_string(),
+                continuation: "AGAIN #{iteration}: {prompt}".to_string(),
+                resume: "RESUMED #{iteration}: {prompt}".to_string(),
+            },
+            ..test_config()
+        };
+        let engine = LoopEngine::new(config);
+
+        assert_eq!(
+            engine.build_prompt("Fix it", 0, None, None, None, None),
+            "TASK: Fix it\nEND WITH <promise>COMPLETE</promise>"
+        );
+        assert_eq!(engine.build_prompt("Fix it", 2, Some("..."), None, None, None), "AGAIN #3: Fix it");
+
+        let config = LoopConfig {
+            resume_from_iteration: Some(4),
+            ..engine.config.clone()
+        };
+        let engine = LoopEngine::new(config);
+        assert_eq!(engine.build_prompt("Fix it", 0, None, None, None, None), "RESUMED #5: Fix it");
+        assert_eq!(engine.build_prompt("Fix it", 1, None, None, None, None), "AGAIN #6: Fix it");
+    }
+
+    #[test]
+    fn test_build_prompt_with_verification() {
+        let config = LoopConfig {
+            verification: Some(VerificationConfig::new("cargo test")),
+            ..test_config()
+        };
+        let engine = LoopEngine::new(config);
+
+        let prompt = engine.build_prompt("Fix the tests", 0, None, None, None, None);
+        assert!(prompt.contains("`cargo test`"));
+
+        let failure = VerificationResult {
+            command: "cargo test".to_string(),
+            success: false,
+            exit_code: Some(101),
+            output: "test parser::tests::test_empty ... FAILED".to_string(),
+            duration_ms: 1200,
+        };
+        let prompt = engine.build_prompt("Fix the tests", 1, Some("All done!"), None, Some(&failure), None);
+        assert!(prompt.contains("Verification Failed"));
+        assert!(prompt.contains("test_empty ... FAILED"));
+        assert!(prompt.contains("Fix the tests"));
     }
 
     #[test]
     fn test_builder_pattern() {
-        let engine = LoopEngine::new(LoopConfig::default())
+        let engine = LoopEngine::new(test_config())
             .with_max_iterations(100)
             .with_budget(10.0)
             .with_model(ModelAlias::Opus)
@@ -1403,6 +2520,484 @@ mod tests {
         assert!(engine.config.yolo_mode);
     }
 
+    #[test]
+    fn test_record_session_id() {
+        use crate::claude::SystemEvent;
+
+        let engine = LoopEngine::new(test_config());
+        let mut task_state = Some(TaskState::new("Test".to_string(), "sonnet".to_string(), 5));
+        let mut session_id = None;
+
+        // Non-init system events are ignored
+        let event = ClaudeEvent::System(SystemEvent {
+            subtype: "status".to_string(),
+            session_id: Some("ignored".to_string()),
+            tools: None,
+        });
+        engine.record_session_id(&event, &mut task_state, &mut session_id);
+        assert!(session_id.is_none());
+
+        let event = ClaudeEvent::System(SystemEvent {
+            subtype: "init".to_string(),
+            session_id: Some("abc123".to_string()),
+            tools: None,
+        });
+        engine.record_session_id(&event, &mut task_state, &mut session_id);
+        assert_eq!(session_id.as_deref(), Some("abc123"));
+        assert_eq!(task_state.unwrap().session_id.as_deref(), Some("abc123"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_before_first_iteration() {
+        let engine = LoopEngine::new(test_config());
+        engine.control().stop();
+
+        let result = engine.execute_and_wait("Test").await.unwrap();
+        assert_eq!(result.status, LoopStatus::Stopped);
+        assert_eq!(result.total_usage.total_cost_usd, 0.0);
+    }
+
+    fn offline_config(dir: &std::path::Path) -> LoopConfig {
+        LoopConfig {
+            working_dir: Some(dir.to_path_buf()),
+            enable_state: false,
+            enable_cost_tracking: false,
+            disable_hooks: true,
+            disable_notifications: true,
+            disable_git: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_backend_runs_until_complete() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"system","subtype":"init","session_id":"sess-1"}
+{"type":"assistant","message":"Still working"}
+{"type":"result","subtype":"success","total_cost_usd":0.1}"#
+                .to_string(),
+            r#"{"type":"assistant","message":"Done <promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":0.2}"#
+                .to_string(),
+        ]));
+        let engine = LoopEngine::new(offline_config(dir.path())).with_backend(backend.clone());
+
+        let result = engine.execute_and_wait("Build it").await.unwrap();
+        assert_eq!(result.status, LoopStatus::Completed);
+        assert_eq!(result.iterations, 2);
+        assert!((result.total_usage.total_cost_usd - 0.3).abs() < 1e-9);
+        assert_eq!(result.session_id.as_deref(), Some("sess-1"));
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].prompt.contains("Build it"));
+        assert_eq!(requests[0].config.working_dir.as_deref(), Some(dir.path()));
+        let transcript = requests[1].transcript.as_ref().unwrap();
+        assert!(transcript.starts_with(dir.path().join(".doodoori/tasks")));
+        assert!(transcript.ends_with("iteration-002.jsonl.gz"));
+    }
+
+    #[tokio::test]
+    async fn test_budget_exceeded_mid_iteration() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        // 1M Sonnet input tokens is an estimated $6 (long context), over the $1 budget
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":{"id":"msg_1","usage":{"input_tokens":1000000,"output_tokens":0}}}
+{"type":"assistant","message":"<promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":3.5}"#
+                .to_string(),
+        ]));
+        let engine = LoopEngine::new(offline_config(dir.path()))
+            .with_budget(1.0)
+            .with_backend(backend);
+
+        let (rx, handle, _control) = engine.execute_live("Expensive task").await.unwrap();
+        drop(rx);
+        let result = handle.await.unwrap().unwrap();
+
+        assert_eq!(result.status, LoopStatus::BudgetExceeded);
+        assert_eq!(result.iterations, 1);
+        assert!(result.total_usage.total_cost_usd >= 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_spend_cap_stops_loop() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let iteration = r#"{"type":"assistant","message":"Still working"}
+{"type":"result","subtype":"success","total_cost_usd":0.3}"#
+            .to_string();
+        let backend = Arc::new(ReplayBackend::new(vec![iteration; 5]));
+        let config = LoopConfig {
+            max_iterations: 5,
+            spend_limits: SpendLimits {
+                daily_budget_usd: Some(0.5),
+                ..Default::default()
+            },
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+
+        let (mut rx, handle, _control) = engine.execute("Keep going").await.unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        let result = handle.await.unwrap().unwrap();
+
+        // $0.30 of $0.50 passes, $0.60 reaches the cap before the third iteration
+        assert_eq!(result.status, LoopStatus::BudgetExceeded);
+        assert_eq!(backend.requests().len(), 2);
+        assert!(events.iter().any(|e| matches!(
+            e,
+            LoopEvent::SpendCapReached { message } if message.starts_with("Daily spend of this project")
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_progress_journal_carries_into_next_iteration() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Parser done.\n- [x] parser\n- [ ] 렉서"},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"cargo test"}}]}}
+{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"error: could not compile","is_error":true}]}}
+{"type":"result","subtype":"success","total_cost_usd":0.1}"#
+                .to_string(),
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}"#.to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some("task-progress".to_string()),
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+
+        let result = engine.execute_and_wait("Build it\n- [ ] parser\n- [ ] 렉서").await.unwrap();
+        assert_eq!(result.status, LoopStatus::Completed);
+
+        let requests = backend.requests();
+        assert!(!requests[0].prompt.contains("Progress so far"));
+        let second = &requests[1].prompt;
+        assert!(second.contains("Progress so far:\n\n## Iteration 1"));
+        assert!(second.contains("Tools used: Bash (1, 1 failed)"));
+        assert!(second.contains("- Bash: error: could not compile"));
+        assert!(second.contains("Remaining:\n- 렉서"));
+
+        let journal = ProgressJournal::for_task(dir.path(), "task-progress").read().unwrap();
+        assert!(journal.starts_with("# Progress\n\nTask: Build it"));
+        assert!(journal.contains("## Iteration 2\n\nClaimed the task complete."));
+
+        // A resumed run of the same task starts from the journal
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}"#.to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some("task-progress".to_string()),
+            resume_from_iteration: Some(2),
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+        engine.execute_and_wait("Build it").await.unwrap();
+        let prompt = &backend.requests()[0].prompt;
+        assert!(prompt.contains("resuming at iteration 3"));
+        assert!(prompt.contains("## Iteration 2"));
+    }
+
+    #[tokio::test]
+    async fn test_stall_nudges_then_stops() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = r#"{"type":"assistant","message":"Still looking into it"}
+{"type":"result","subtype":"success","total_cost_usd":0.01}"#;
+        let stall = |action| StallConfig {
+            threshold: 2,
+            action,
+            ..Default::default()
+        };
+
+        // Nudge: the iteration after the stall starts with the stuck prompt
+        let backend = Arc::new(ReplayBackend::new(vec![script.to_string()]));
+        let config = LoopConfig {
+            max_iterations: 3,
+            stall: stall(StallAction::Nudge),
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+        let result = engine.execute_and_wait("Fix it").await.unwrap();
+        assert_eq!(result.status, LoopStatus::MaxIterationsReached);
+        let requests = backend.requests();
+        assert!(!requests[1].prompt.contains("You appear to be stuck"));
+        assert!(requests[2].prompt.starts_with("You appear to be stuck"));
+        assert!(requests[2].prompt.contains("the same output 2 times in a row"));
+
+        // Stop: the loop ends as soon as it stalls
+        let backend = Arc::new(ReplayBackend::new(vec![script.to_string()]));
+        let config = LoopConfig {
+            stall: stall(StallAction::Stop),
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        let result = engine.execute_and_wait("Fix it").await.unwrap();
+        assert_eq!(result.status, LoopStatus::Stalled);
+        assert_eq!(result.iterations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stall_escalates_model() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"Same answer"}"#.to_string(),
+        ]));
+        let config = LoopConfig {
+            model: ModelAlias::Haiku,
+            stall: StallConfig {
+                threshold: 2,
+                action: StallAction::Escalate,
+                ..Default::default()
+            },
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+
+        let (rx, handle, _control) = engine.execute_live("Fix it").await.unwrap();
+        drop(rx);
+        let result = handle.await.unwrap().unwrap();
+
+        // haiku -> sonnet -> opus, then stop once there is nothing larger
+        assert_eq!(result.status, LoopStatus::Stalled);
+        let models: Vec<ModelAlias> = backend.requests().iter().map(|r| r.config.model.clone()).collect();
+        assert_eq!(
+            models,
+            vec![
+                ModelAlias::Haiku,
+                ModelAlias::Haiku,
+                ModelAlias::Sonnet,
+                ModelAlias::Sonnet,
+                ModelAlias::Opus,
+                ModelAlias::Opus,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_model_policy_records_model_per_iteration() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let iteration = |text: &str, cost: f64| {
+            format!(
+                "{{\"type\":\"assistant\",\"message\":\"{}\"}}\n{{\"type\":\"result\",\"subtype\":\"success\",\"total_cost_usd\":{}}}",
+                text, cost
+            )
+        };
+        let backend = Arc::new(ReplayBackend::new(vec![
+            iteration("Step 1", 0.01),
+            iteration("Step 2", 0.01),
+            iteration("Step 3", 0.1),
+            iteration("Step 4", 0.1),
+            iteration("Done <promise>COMPLETE</promise>", 0.5),
+        ]));
+        let config = LoopConfig {
+            model: ModelAlias::Sonnet,
+            model_policy: ModelPolicy::parse_rules(&["haiku", "sonnet after 2 failures", "opus last 1"]).unwrap(),
+            max_iterations: 5,
+            enable_state: true,
+            enable_cost_tracking: true,
+            stall: StallConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend.clone());
+
+        let (mut rx, handle, _control) = engine.execute("Fix it").await.unwrap();
+        let mut switches = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let LoopEvent::ModelChanged { iteration, to, .. } = event {
+                switches.push((iteration, to));
+            }
+        }
+        let result = handle.await.unwrap().unwrap();
+        assert_eq!(result.status, LoopStatus::Completed);
+
+        let models: Vec<ModelAlias> = backend.requests().iter().map(|r| r.config.model.clone()).collect();
+        use ModelAlias::*;
+        assert_eq!(models, vec![Haiku, Haiku, Sonnet, Sonnet, Opus]);
+        assert_eq!(switches, vec![(0, Haiku), (2, Sonnet), (4, Opus)]);
+
+        // The task state keeps each iteration's model
+        let state = StateManager::new(dir.path()).unwrap().list_history(10).unwrap().remove(0);
+        let recorded: Vec<&str> = state.iterations.iter().map(|r| r.model.as_str()).collect();
+        assert_eq!(recorded, vec!["haiku", "haiku", "sonnet", "sonnet", "opus"]);
+        assert!(state.iterations[4].completed);
+
+        // The cost entry is split by model
+        let costs = CostHistoryManager::for_project(dir.path()).unwrap();
+        let entry = &costs.history().entries[0];
+        assert_eq!(entry.model, "haiku+sonnet+opus");
+        let by_model: Vec<(String, f64)> = entry
+            .cost_by_model()
+            .into_iter()
+            .map(|(model, cost)| (model, (cost * 100.0).round() / 100.0))
+            .collect();
+        assert_eq!(
+            by_model,
+            vec![("haiku".to_string(), 0.02), ("sonnet".to_string(), 0.2), ("opus".to_string(), 0.5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_state_records_iteration_timeline() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"system","subtype":"init","session_id":"sess-1"}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Testing"},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"cargo test"}}]}}
+{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"error: could not compile","is_error":true}]}}
+{"type":"result","subtype":"success","total_cost_usd":0.1,"usage":{"input_tokens":1200,"output_tokens":300}}"#
+                .to_string(),
+            r#"{"type":"system","subtype":"init","session_id":"sess-2"}
+{"type":"assistant","message":"Still working"}"#
+                .to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some("task-timeline".to_string()),
+            max_iterations: 2,
+            enable_state: true,
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        let (rx, handle, _control) = engine.execute_live("Fix it").await.unwrap();
+        drop(rx);
+        assert_eq!(handle.await.unwrap().unwrap().status, LoopStatus::MaxIterationsReached);
+
+        let state_manager = StateManager::new(dir.path()).unwrap();
+        let state = state_manager.load_state().unwrap().unwrap();
+        assert_eq!(state.current_iteration, 2);
+        assert_eq!(state.iterations.len(), 2);
+        let first = &state.iterations[0];
+        assert_eq!((first.iteration, first.model.as_str(), first.completed), (1, "sonnet", false));
+        assert_eq!(first.usage.input_tokens, 1200);
+        assert!(first.finished_at >= first.started_at);
+        assert_eq!(first.tools["Bash"].failures, 1);
+        assert_eq!(first.errors, vec!["Bash: error: could not compile"]);
+        assert!(first.git_head.is_none());
+        assert_eq!(first.session_id.as_deref(), Some("sess-1"));
+        assert!(state.iterations[1].tools.is_empty());
+        assert_eq!(state.iterations[1].session_id.as_deref(), Some("sess-2"));
+
+        // Resuming from iteration 1 drops iteration 2 and continues the numbering
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}"#.to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some(state.task_id.clone()),
+            max_iterations: 1,
+            resume_from_iteration: Some(1),
+            iteration_history: state.iterations[..1].to_vec(),
+            enable_state: true,
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        assert_eq!(engine.execute_and_wait("Fix it").await.unwrap().status, LoopStatus::Completed);
+
+        let state = state_manager.load_from_history("task-timeline").unwrap().unwrap();
+        let iterations: Vec<(u32, bool)> = state.iterations.iter().map(|r| (r.iteration, r.completed)).collect();
+        assert_eq!(iterations, vec![(1, false), (2, true)]);
+        assert_eq!(state.current_iteration, 2);
+        assert_eq!(state.max_iterations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_task_totals() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let iteration = r#"{"type":"system","subtype":"init","session_id":"sess-1"}
+{"type":"assistant","message":"Still working"}
+{"type":"result","subtype":"success","total_cost_usd":0.1,"usage":{"input_tokens":1000,"output_tokens":100}}"#;
+        let backend = Arc::new(ReplayBackend::new(vec![iteration.to_string(); 2]));
+        let config = LoopConfig {
+            task_id: Some("task-totals".to_string()),
+            max_iterations: 2,
+            enable_state: true,
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        assert_eq!(engine.execute_and_wait("Fix it").await.unwrap().status, LoopStatus::MaxIterationsReached);
+
+        let state_manager = StateManager::new(dir.path()).unwrap();
+        let before = state_manager.find_task("task-totals").unwrap().unwrap();
+        assert!((before.total_cost_usd - 0.2).abs() < 1e-9);
+
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}
+{"type":"result","subtype":"success","total_cost_usd":0.25,"usage":{"input_tokens":500,"output_tokens":50}}"#
+                .to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some("task-totals".to_string()),
+            max_iterations: 1,
+            resume_from_iteration: Some(2),
+            iteration_history: before.iterations.clone(),
+            enable_state: true,
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        assert_eq!(engine.execute_and_wait("Fix it").await.unwrap().status, LoopStatus::Completed);
+
+        let after = state_manager.find_task("task-totals").unwrap().unwrap();
+        assert!((after.total_cost_usd - 0.45).abs() < 1e-9);
+        assert_eq!(after.usage.input_tokens, 2500);
+        assert_eq!(after.usage.output_tokens, 250);
+        assert_eq!(after.created_at, before.created_at);
+        assert_eq!(after.session_id.as_deref(), Some("sess-1"));
+        assert_eq!(after.iterations.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_each_iteration() {
+        use crate::claude::ReplayBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = GitRepository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let backend = Arc::new(ReplayBackend::new(vec![
+            r#"{"type":"assistant","message":"Still working"}"#.to_string(),
+            r#"{"type":"assistant","message":"<promise>COMPLETE</promise>"}"#.to_string(),
+        ]));
+        let config = LoopConfig {
+            task_id: Some("task-checkpoint".to_string()),
+            checkpoints: true,
+            enable_state: true,
+            ..offline_config(dir.path())
+        };
+        let engine = LoopEngine::new(config).with_backend(backend);
+        assert_eq!(engine.execute_and_wait("Fix it").await.unwrap().status, LoopStatus::Completed);
+
+        let checkpoints = CheckpointManager::new(&repo).list("task-checkpoint").unwrap();
+        let iterations: Vec<u32> = checkpoints.iter().map(|c| c.iteration).collect();
+        assert_eq!(iterations, vec![0, 1, 2]);
+        let tree = repo.inner().find_commit(checkpoints[1].commit).unwrap().tree().unwrap();
+        assert!(tree.get_path(std::path::Path::new("main.rs")).is_ok());
+        assert!(tree.get_path(std::path::Path::new(".doodoori")).is_err());
+
+        let state = StateManager::new(dir.path()).unwrap().load_from_history("task-checkpoint").unwrap().unwrap();
+        assert_eq!(state.iterations[1].checkpoint, Some(checkpoints[2].commit.to_string()));
+    }
+
     #[test]
     fn test_loop_status_equality() {
         assert_eq!(LoopStatus::Running, LoopStatus::Running);
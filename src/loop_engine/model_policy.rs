@@ -0,0 +1,339 @@
+//! Per-iteration model selection
+//!
+//! A [`ModelPolicy`] is an ordered list of rules. Before every iteration the
+//! rules are checked against the loop's progress and the model of the last
+//! matching rule is used; without a match the task's model is kept. This
+//! allows starting cheap and moving up after failures, or planning with a
+//! large model and implementing with a smaller one:
+//!
+//! ```toml
+//! [model_policy]
+//! rules = [
+//!     "haiku",
+//!     "sonnet after 2 failures",
+//!     "sonnet after 1 tool error",
+//!     "opus last 2 iterations",
+//! ]
+//! ```
+//!
+//! Rules can also be written as tables
+//! (`{ model = "opus", until_iteration = 1 }`).
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::claude::ModelAlias;
+
+/// Ordered model selection rules (`[model_policy]` in doodoori.toml)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPolicy {
+    /// Rules checked in order; the last matching rule wins
+    #[serde(deserialize_with = "deserialize_rules")]
+    pub rules: Vec<ModelRule>,
+}
+
+/// A model and the conditions under which it is used
+///
+/// All conditions that are set must hold; a rule without conditions always
+/// matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRule {
+    pub model: ModelAlias,
+    /// First iteration (1-based) the rule applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_iteration: Option<u32>,
+    /// Last iteration (1-based) the rule applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until_iteration: Option<u32>,
+    /// Applies once this many iterations ended without completing the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_failures: Option<u32>,
+    /// Applies once this many tool calls failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_tool_errors: Option<u32>,
+    /// Applies to the last N iterations of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_iterations: Option<u32>,
+}
+
+impl ModelRule {
+    pub fn new(model: ModelAlias) -> Self {
+        Self {
+            model,
+            from_iteration: None,
+            until_iteration: None,
+            after_failures: None,
+            after_tool_errors: None,
+            last_iterations: None,
+        }
+    }
+
+    /// Whether the rule applies to the given loop progress
+    pub fn matches(&self, progress: &PolicyProgress) -> bool {
+        self.from_iteration.is_none_or(|n| progress.iteration >= n)
+            && self.until_iteration.is_none_or(|n| progress.iteration <= n)
+            && self.after_failures.is_none_or(|n| progress.failures >= n)
+            && self.after_tool_errors.is_none_or(|n| progress.tool_errors >= n)
+            && self.last_iterations.is_none_or(|n| progress.remaining <= n)
+    }
+}
+
+impl fmt::Display for ModelRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.model)?;
+        if let Some(n) = self.from_iteration {
+            write!(f, " from {}", n)?;
+        }
+        if let Some(n) = self.until_iteration {
+            write!(f, " until {}", n)?;
+        }
+        if let Some(n) = self.after_failures {
+            write!(f, " after {} failures", n)?;
+        }
+        if let Some(n) = self.after_tool_errors {
+            write!(f, " after {} tool errors", n)?;
+        }
+        if let Some(n) = self.last_iterations {
+            write!(f, " last {} iterations", n)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ModelRule {
+    type Err = String;
+
+    /// Parse a rule such as `sonnet after 2 failures`, `opus until 1`,
+    /// `opus last 2 iterations` or `sonnet from 3, after 1 tool error`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowered = s.to_lowercase().replace(',', " ");
+        let mut words = lowered.split_whitespace().filter(|w| *w != "and").peekable();
+
+        let model = words.next().ok_or_else(|| "Empty model rule".to_string())?;
+        let mut rule = ModelRule::new(model.parse()?);
+
+        let number = |word: Option<&str>| -> Result<u32, String> {
+            word.and_then(|w| w.parse().ok())
+                .ok_or_else(|| format!("Expected a number in model rule: {}", s))
+        };
+
+        while let Some(word) = words.next() {
+            match word {
+                "from" | "until" => {
+                    words.next_if(|w| w.starts_with("iteration"));
+                    let n = number(words.next())?;
+                    if word == "from" {
+                        rule.from_iteration = Some(n);
+                    } else {
+                        rule.until_iteration = Some(n);
+                    }
+                }
+                "after" => {
+                    let n = number(words.next())?;
+                    match words.next() {
+                        Some(w) if w.starts_with("failure") => rule.after_failures = Some(n),
+                        Some("tool") if words.next_if(|w| w.starts_with("error")).is_some() => {
+                            rule.after_tool_errors = Some(n)
+                        }
+                        _ => return Err(format!("Expected 'failures' or 'tool errors' in model rule: {}", s)),
+                    }
+                }
+                "last" => {
+                    rule.last_iterations = Some(number(words.next())?);
+                    words.next_if(|w| w.starts_with("iteration"));
+                }
+                other => return Err(format!("Unknown condition '{}' in model rule: {}", other, s)),
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+/// Rules are written either as strings or as tables
+fn deserialize_rules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ModelRule>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawRule {
+        Text(String),
+        Rule(ModelRule),
+    }
+
+    Vec::<RawRule>::deserialize(deserializer)?
+        .into_iter()
+        .map(|raw| match raw {
+            RawRule::Text(text) => text.parse().map_err(serde::de::Error::custom),
+            RawRule::Rule(rule) => Ok(rule),
+        })
+        .collect()
+}
+
+impl ModelPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Parse a policy from rule strings (e.g. a spec's `## Model Policy` list)
+    pub fn parse_rules<S: AsRef<str>>(rules: &[S]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| rule.as_ref().trim())
+            .filter(|rule| !rule.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Model of the last matching rule
+    pub fn select(&self, progress: &PolicyProgress) -> Option<ModelAlias> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(progress))
+            .map(|rule| rule.model.clone())
+    }
+}
+
+/// Loop progress the rules are checked against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PolicyProgress {
+    /// Iteration about to run (1-based, continues across resumes)
+    pub iteration: u32,
+    /// Iterations left in this run, including the one about to run
+    pub remaining: u32,
+    /// Iterations that ended without completing the task
+    pub failures: u32,
+    /// Failed tool calls so far
+    pub tool_errors: u32,
+}
+
+/// Chooses the model for each iteration of a run
+///
+/// A stall escalation raises the floor for the rest of the run: the policy
+/// can still pick a larger model, but not a smaller one.
+#[derive(Debug, Clone)]
+pub struct ModelSelector {
+    base: ModelAlias,
+    policy: ModelPolicy,
+    progress: PolicyProgress,
+    floor: Option<ModelAlias>,
+}
+
+impl ModelSelector {
+    pub fn new(base: ModelAlias, policy: ModelPolicy) -> Self {
+        Self {
+            base,
+            policy,
+            progress: PolicyProgress::default(),
+            floor: None,
+        }
+    }
+
+    /// Model for the next iteration
+    pub fn select(&mut self, iteration: u32, remaining: u32) -> ModelAlias {
+        self.progress.iteration = iteration;
+        self.progress.remaining = remaining;
+        let model = self.policy.select(&self.progress).unwrap_or_else(|| self.base.clone());
+        match self.floor {
+            Some(ref floor) if model.size() < floor.size() => floor.clone(),
+            _ => model,
+        }
+    }
+
+    /// Record the outcome of an iteration
+    pub fn record(&mut self, completed: bool, tool_errors: usize) {
+        if !completed {
+            self.progress.failures += 1;
+        }
+        self.progress.tool_errors += tool_errors as u32;
+    }
+
+    /// Move up from `current` for the rest of the run; None when already on
+    /// the largest model
+    pub fn escalate(&mut self, current: &ModelAlias) -> Option<ModelAlias> {
+        let next = current.escalate()?;
+        self.floor = Some(next.clone());
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(iteration: u32, remaining: u32, failures: u32, tool_errors: u32) -> PolicyProgress {
+        PolicyProgress { iteration, remaining, failures, tool_errors }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rule: ModelRule = "Sonnet after 2 failures".parse().unwrap();
+        assert_eq!(rule.model, ModelAlias::Sonnet);
+        assert_eq!(rule.after_failures, Some(2));
+
+        let rule: ModelRule = "opus from iteration 3, after 1 tool error and last 2 iterations".parse().unwrap();
+        assert_eq!(rule.from_iteration, Some(3));
+        assert_eq!(rule.after_tool_errors, Some(1));
+        assert_eq!(rule.last_iterations, Some(2));
+        assert_eq!(rule.to_string(), "opus from 3 after 1 tool errors last 2 iterations");
+        assert_eq!(rule.to_string().parse::<ModelRule>().unwrap(), rule);
+
+        assert!("gpt after 2 failures".parse::<ModelRule>().is_err());
+        assert!("sonnet after two failures".parse::<ModelRule>().is_err());
+        assert!("sonnet sometimes".parse::<ModelRule>().is_err());
+    }
+
+    #[test]
+    fn test_policy_from_toml() {
+        let policy: ModelPolicy = toml::from_str(
+            r#"rules = ["haiku", { model = "opus", until_iteration = 1 }, "sonnet after 2 failures"]"#,
+        )
+        .unwrap();
+        assert_eq!(policy.rules.len(), 3);
+        assert_eq!(policy.rules[1].until_iteration, Some(1));
+
+        assert!(toml::from_str::<ModelPolicy>(r#"rules = ["sonnet whenever"]"#).is_err());
+        assert!(toml::from_str::<ModelPolicy>("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_escalating_policy() {
+        let policy = ModelPolicy::parse_rules(&[
+            "haiku",
+            "sonnet after 2 failures",
+            "sonnet after 1 tool error",
+            "opus last 2",
+        ])
+        .unwrap();
+
+        assert_eq!(policy.select(&progress(1, 10, 0, 0)), Some(ModelAlias::Haiku));
+        assert_eq!(policy.select(&progress(2, 9, 1, 0)), Some(ModelAlias::Haiku));
+        assert_eq!(policy.select(&progress(3, 8, 2, 0)), Some(ModelAlias::Sonnet));
+        assert_eq!(policy.select(&progress(2, 9, 1, 1)), Some(ModelAlias::Sonnet));
+        assert_eq!(policy.select(&progress(9, 2, 8, 0)), Some(ModelAlias::Opus));
+    }
+
+    #[test]
+    fn test_selector_plans_with_opus_then_implements() {
+        let policy = ModelPolicy::parse_rules(&["opus until 1"]).unwrap();
+        let mut selector = ModelSelector::new(ModelAlias::Sonnet, policy);
+
+        assert_eq!(selector.select(1, 5), ModelAlias::Opus);
+        selector.record(false, 0);
+        assert_eq!(selector.select(2, 4), ModelAlias::Sonnet);
+    }
+
+    #[test]
+    fn test_selector_escalation_is_a_floor() {
+        let policy = ModelPolicy::parse_rules(&["haiku", "opus last 1"]).unwrap();
+        let mut selector = ModelSelector::new(ModelAlias::Sonnet, policy);
+
+        assert_eq!(selector.select(1, 3), ModelAlias::Haiku);
+        assert_eq!(selector.escalate(&ModelAlias::Haiku), Some(ModelAlias::Sonnet));
+        assert_eq!(selector.select(2, 2), ModelAlias::Sonnet);
+        assert_eq!(selector.select(3, 1), ModelAlias::Opus);
+        assert_eq!(selector.escalate(&ModelAlias::Opus), None);
+    }
+}
@@ -0,0 +1,181 @@
+//! Prompt templates for loop iterations
+//!
+//! The loop builds each iteration's prompt from one of three templates
+//! (first iteration, continuation, resume). Templates use `{name}`
+//! placeholders, like task templates, and can be overridden in
+//! `doodoori.toml` (`[prompts]`) or with files in `.doodoori/prompts/`.
+//!
+//! Available placeholders:
+//! - `{prompt}`: the original task prompt
+//! - `{completion_marker}`: the text that signals completion, from the
+//!   active `CompletionStrategy` (e.g. `<promise>COMPLETE</promise>`)
+//! - `{iteration}`: 1-based iteration number
+//! - `{previous_output}`: tail of the previous iteration's output
+//! - `{progress}`: recent entries of the task's progress journal (files
+//!   changed, tools used, errors, remaining requirements), followed by a
+//!   blank line (empty on the first iteration of a new task)
+//! - `{verification}`: verification instructions or failure output,
+//!   followed by a blank line (empty when not applicable)
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Default template for the first iteration
+pub const DEFAULT_FIRST_ITERATION: &str = "{prompt}\n\n---\n\n{verification}When you have completed the task, output {completion_marker}\n\nIf you cannot complete the task, explain why and still output {completion_marker}";
+
+/// Default template for subsequent iterations
+pub const DEFAULT_CONTINUATION: &str = "Continue from the previous attempt. The task is not yet complete.\n\nOriginal task: {prompt}\n\n{progress}{verification}Please continue working on the task. When complete, output {completion_marker}";
+
+/// Default template for the first iteration of a resumed task
+pub const DEFAULT_RESUME: &str = "The previous run was interrupted. Continue working on the task from where you left off. You are resuming at iteration {iteration}.\n\nOriginal task: {prompt}\n\n{progress}{verification}When the task is complete, output {completion_marker}";
+
+/// File names looked up in a prompts directory
+pub const FIRST_ITERATION_FILE: &str = "first_iteration.md";
+pub const CONTINUATION_FILE: &str = "continuation.md";
+pub const RESUME_FILE: &str = "resume.md";
+
+/// Templates used to build iteration prompts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplates {
+    /// First iteration of a new task
+    pub first_iteration: String,
+    /// Iterations after the first
+    pub continuation: String,
+    /// First iteration of a resumed task
+    pub resume: String,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self {
+            first_iteration: DEFAULT_FIRST_ITERATION.to_string(),
+            continuation: DEFAULT_CONTINUATION.to_string(),
+            resume: DEFAULT_RESUME.to_string(),
+        }
+    }
+}
+
+impl PromptTemplates {
+    /// Load templates from a directory, falling back to defaults for missing files
+    pub fn from_dir(dir: &Path) -> Self {
+        let read = |name: &str, default: &str| {
+            std::fs::read_to_string(dir.join(name))
+                .ok()
+                .filter(|content| !content.trim().is_empty())
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        Self {
+            first_iteration: read(FIRST_ITERATION_FILE, DEFAULT_FIRST_ITERATION),
+            continuation: read(CONTINUATION_FILE, DEFAULT_CONTINUATION),
+            resume: read(RESUME_FILE, DEFAULT_RESUME),
+        }
+    }
+}
+
+/// Values substituted into a prompt template
+#[derive(Debug, Clone, Default)]
+pub struct PromptVars<'a> {
+    pub prompt: &'a str,
+    pub completion_marker: &'a str,
+    pub iteration: u32,
+    pub previous_output: &'a str,
+    pub progress: &'a str,
+    pub verification: &'a str,
+}
+
+impl PromptVars<'_> {
+    /// Value of a placeholder, or None if the name is not known
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        let value = match name {
+            "prompt" => self.prompt,
+            "completion_marker" => self.completion_marker,
+            "iteration" => return Some(Cow::Owned(self.iteration.to_string())),
+            "previous_output" => self.previous_output,
+            "progress" => self.progress,
+            "verification" => self.verification,
+            _ => return None,
+        };
+        Some(Cow::Borrowed(value))
+    }
+}
+
+/// Render a template by replacing known `{name}` placeholders
+///
+/// Placeholders are replaced in a single pass over the template, so braces
+/// in the substituted values (the task prompt, model or command output) are
+/// left as they are. Unknown placeholders are kept.
+pub fn render(template: &str, vars: &PromptVars<'_>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = rest
+            .find('}')
+            .and_then(|end| vars.get(&rest[1..end]).map(|value| (value, end)));
+        match placeholder {
+            Some((value, end)) => {
+                rendered.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let vars = PromptVars {
+            prompt: "Print {name}",
+            completion_marker: "<promise>DONE</promise>",
+            iteration: 2,
+            previous_output: "partial",
+            progress: "Progress so far\n\n",
+            verification: "",
+        };
+        let rendered = render("{prompt} #{iteration} ({previous_output}) {verification}output {completion_marker}", &vars);
+        assert_eq!(rendered, "Print {name} #2 (partial) output <promise>DONE</promise>");
+
+        let rendered = render("{progress}Next: {prompt} {unknown} {", &vars);
+        assert_eq!(rendered, "Progress so far\n\nNext: Print {name} {unknown} {");
+    }
+
+    #[test]
+    fn test_render_leaves_placeholders_in_values() {
+        let vars = PromptVars {
+            prompt: "Fix {verification}",
+            completion_marker: "<promise>COMPLETE</promise>",
+            iteration: 3,
+            previous_output: "I printed {prompt} and {completion_marker}",
+            progress: "",
+            verification: "error: expected `{iteration}`\n\n",
+        };
+        let rendered = render("{previous_output}\n{verification}{prompt}", &vars);
+        assert_eq!(
+            rendered,
+            "I printed {prompt} and {completion_marker}\nerror: expected `{iteration}`\n\nFix {verification}"
+        );
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONTINUATION_FILE), "Keep going: {prompt}").unwrap();
+
+        let templates = PromptTemplates::from_dir(dir.path());
+        assert_eq!(templates.first_iteration, DEFAULT_FIRST_ITERATION);
+        assert_eq!(templates.continuation, "Keep going: {prompt}");
+        assert_eq!(templates.resume, DEFAULT_RESUME);
+    }
+}
@@ -0,0 +1,275 @@
+//! Stuck-loop detection
+//!
+//! After every iteration the loop records a fingerprint of what happened: a
+//! hash of the working tree, a hash of Claude's output and the errors seen
+//! (failed tool calls and verification failures). When the working tree is
+//! left unchanged, the output repeats or the same error comes back for
+//! `threshold` iterations in a row, the loop is considered stalled and the
+//! configured [`StallAction`] is taken.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Default prompt injected by [`StallAction::Nudge`]
+pub const DEFAULT_STALL_PROMPT: &str = "You appear to be stuck: the last iterations made no progress ({reason}). Stop repeating the same approach. Step back, reconsider the problem and try a different approach.";
+
+/// What to do when the loop stops making progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StallAction {
+    /// Tell Claude it is stuck in the next prompt
+    #[default]
+    Nudge,
+    /// Switch to the next larger model (haiku -> sonnet -> opus); stops when
+    /// already on the largest model
+    Escalate,
+    /// Stop the loop with `LoopStatus::Stalled`
+    Stop,
+}
+
+impl StallAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StallAction::Nudge => "nudge",
+            StallAction::Escalate => "escalate",
+            StallAction::Stop => "stop",
+        }
+    }
+}
+
+impl std::fmt::Display for StallAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Stuck-loop detection settings (`[stall]` in doodoori.toml)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StallConfig {
+    /// Enable stall detection
+    pub enabled: bool,
+    /// Consecutive iterations without progress before acting
+    pub threshold: u32,
+    /// Action taken when the loop stalls
+    pub action: StallAction,
+    /// Prompt injected by the nudge action (`{reason}` is replaced)
+    pub prompt: String,
+}
+
+impl Default for StallConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 3,
+            action: StallAction::default(),
+            prompt: DEFAULT_STALL_PROMPT.to_string(),
+        }
+    }
+}
+
+impl StallConfig {
+    /// Nudge prompt for a detected stall
+    pub fn render_prompt(&self, stall: &Stall) -> String {
+        self.prompt.replace("{reason}", &stall.to_string())
+    }
+}
+
+/// Why the loop is considered stalled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stall {
+    /// No file changed for this many iterations
+    UnchangedWorkingTree(u32),
+    /// Claude produced the same output this many times
+    RepeatedOutput(u32),
+    /// The same error occurred this many times in a row
+    RepeatedError(u32, String),
+}
+
+impl std::fmt::Display for Stall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stall::UnchangedWorkingTree(n) => write!(f, "no files changed in {} iterations", n),
+            Stall::RepeatedOutput(n) => write!(f, "the same output {} times in a row", n),
+            Stall::RepeatedError(n, error) => {
+                write!(f, "the same error {} times in a row: {}", n, error)
+            }
+        }
+    }
+}
+
+/// What an iteration left behind
+#[derive(Debug, Clone, Default)]
+pub struct IterationFingerprint {
+    /// Hash of the working tree (None outside a git repository)
+    pub working_tree: Option<u64>,
+    /// Hash of Claude's output, ignoring whitespace
+    pub output: u64,
+    /// Errors seen during the iteration (first line of each)
+    pub errors: Vec<String>,
+}
+
+impl IterationFingerprint {
+    pub fn new(working_tree: Option<u64>, output: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        for word in output.split_whitespace() {
+            word.hash(&mut hasher);
+        }
+        Self {
+            working_tree,
+            output: hasher.finish(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Record an error, keeping its first non-empty line
+    pub fn add_error(&mut self, error: &str) {
+        if let Some(line) = error.lines().map(str::trim).find(|l| !l.is_empty()) {
+            let line: String = line.chars().take(200).collect();
+            if !self.errors.contains(&line) {
+                self.errors.push(line);
+            }
+        }
+    }
+}
+
+/// Tracks iteration fingerprints and reports when progress stops
+#[derive(Debug, Clone)]
+pub struct StallDetector {
+    threshold: u32,
+    previous: Option<IterationFingerprint>,
+    unchanged_tree: u32,
+    repeated_output: u32,
+    repeated_errors: HashMap<String, u32>,
+}
+
+impl StallDetector {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(2),
+            previous: None,
+            unchanged_tree: 1,
+            repeated_output: 1,
+            repeated_errors: HashMap::new(),
+        }
+    }
+
+    /// Record an iteration; returns the stall if the threshold was reached
+    pub fn observe(&mut self, fingerprint: IterationFingerprint) -> Option<Stall> {
+        if let Some(ref previous) = self.previous {
+            self.unchanged_tree = match (previous.working_tree, fingerprint.working_tree) {
+                (Some(a), Some(b)) if a == b => self.unchanged_tree + 1,
+                _ => 1,
+            };
+            self.repeated_output = if previous.output == fingerprint.output {
+                self.repeated_output + 1
+            } else {
+                1
+            };
+        }
+
+        self.repeated_errors = fingerprint
+            .errors
+            .iter()
+            .map(|e| (e.clone(), self.repeated_errors.get(e).copied().unwrap_or(0) + 1))
+            .collect();
+        self.previous = Some(fingerprint);
+
+        let repeated_error = self
+            .repeated_errors
+            .iter()
+            .filter(|(_, count)| **count >= self.threshold)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(error, count)| Stall::RepeatedError(*count, error.clone()));
+
+        if let Some(stall) = repeated_error {
+            Some(stall)
+        } else if self.repeated_output >= self.threshold {
+            Some(Stall::RepeatedOutput(self.repeated_output))
+        } else if self.unchanged_tree >= self.threshold {
+            Some(Stall::UnchangedWorkingTree(self.unchanged_tree))
+        } else {
+            None
+        }
+    }
+
+    /// Start counting again, e.g. after acting on a stall
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.unchanged_tree = 1;
+        self.repeated_output = 1;
+        self.repeated_errors.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_unchanged_working_tree() {
+        let mut detector = StallDetector::new(3);
+        assert_eq!(detector.observe(IterationFingerprint::new(Some(1), "a")), None);
+        assert_eq!(detector.observe(IterationFingerprint::new(Some(1), "b")), None);
+        assert_eq!(
+            detector.observe(IterationFingerprint::new(Some(1), "c")),
+            Some(Stall::UnchangedWorkingTree(3))
+        );
+
+        // Outside a git repository the working tree is not a signal
+        let mut detector = StallDetector::new(2);
+        detector.observe(IterationFingerprint::new(None, "a"));
+        assert_eq!(detector.observe(IterationFingerprint::new(None, "b")), None);
+    }
+
+    #[test]
+    fn test_detects_repeated_output() {
+        let mut detector = StallDetector::new(2);
+        detector.observe(IterationFingerprint::new(Some(1), "I could not  fix it"));
+        assert_eq!(
+            detector.observe(IterationFingerprint::new(Some(2), "I could not fix it\n")),
+            Some(Stall::RepeatedOutput(2))
+        );
+
+        detector.reset();
+        assert_eq!(detector.observe(IterationFingerprint::new(Some(3), "Fixed")), None);
+    }
+
+    #[test]
+    fn test_detects_repeated_error() {
+        let mut detector = StallDetector::new(3);
+        let iteration = |tree: u64, errors: &[&str]| {
+            let mut fingerprint = IterationFingerprint::new(Some(tree), &tree.to_string());
+            for error in errors {
+                fingerprint.add_error(error);
+            }
+            fingerprint
+        };
+
+        detector.observe(iteration(1, &["error[E0308]: mismatched types\n  --> src/lib.rs"]));
+        detector.observe(iteration(2, &["error[E0308]: mismatched types", "other"]));
+        // An iteration without the error resets its count
+        detector.observe(iteration(3, &[]));
+        detector.observe(iteration(4, &["error[E0308]: mismatched types"]));
+        detector.observe(iteration(5, &["error[E0308]: mismatched types"]));
+        let stall = detector.observe(iteration(6, &["error[E0308]: mismatched types"]));
+
+        assert_eq!(
+            stall,
+            Some(Stall::RepeatedError(3, "error[E0308]: mismatched types".to_string()))
+        );
+        let prompt = StallConfig::default().render_prompt(&stall.unwrap());
+        assert!(prompt.contains("the same error 3 times in a row: error[E0308]"));
+    }
+
+    #[test]
+    fn test_stall_config_from_toml() {
+        let config: StallConfig = toml::from_str("action = \"escalate\"\nthreshold = 4").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.action, StallAction::Escalate);
+        assert_eq!(config.threshold, 4);
+        assert_eq!(config.prompt, DEFAULT_STALL_PROMPT);
+    }
+}
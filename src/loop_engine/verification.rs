@@ -0,0 +1,199 @@
+//! Completion verification
+//!
+//! A verification command (e.g. `cargo test`) must exit 0 before the loop
+//! accepts a completion marker. When it fails, its output is fed back into
+//! the next iteration's prompt.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// Shell command that must succeed before completion is accepted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationConfig {
+    /// Command to run through the shell
+    pub command: String,
+    /// Timeout in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Maximum characters of output kept for the next prompt
+    #[serde(default = "default_max_output_chars")]
+    pub max_output_chars: usize,
+}
+
+fn default_timeout_secs() -> u64 {
+    600
+}
+
+fn default_max_output_chars() -> usize {
+    4000
+}
+
+impl VerificationConfig {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            timeout_secs: default_timeout_secs(),
+            max_output_chars: default_max_output_chars(),
+        }
+    }
+
+    pub fn with_timeout(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    /// Run the verification command in the given directory
+    pub async fn run(&self, working_dir: &Path) -> VerificationResult {
+        let start = std::time::Instant::now();
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", &self.command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &self.command]);
+            cmd
+        };
+        cmd.current_dir(working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let (success, exit_code, output) =
+            match timeout(Duration::from_secs(self.timeout_secs), cmd.output()).await {
+                Ok(Ok(output)) => {
+                    let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    if !stderr.trim().is_empty() {
+                        if !combined.is_empty() && !combined.ends_with('\n') {
+                            combined.push('\n');
+                        }
+                        combined.push_str(&stderr);
+                    }
+                    (output.status.success(), output.status.code(), combined)
+                }
+                Ok(Err(e)) => (false, None, format!("Failed to run verification command: {}", e)),
+                Err(_) => (
+                    false,
+                    None,
+                    format!("Verification command timed out after {} seconds", self.timeout_secs),
+                ),
+            };
+
+        VerificationResult {
+            command: self.command.clone(),
+            success,
+            exit_code,
+            output: tail_chars(&output, self.max_output_chars),
+            duration_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Outcome of a verification run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResult {
+    /// Command that was run
+    pub command: String,
+    /// Whether the command exited with status 0
+    pub success: bool,
+    /// Exit code (None if it could not run or timed out)
+    pub exit_code: Option<i32>,
+    /// Combined stdout/stderr (tail, truncated)
+    pub output: String,
+    /// Duration in milliseconds
+    pub duration_ms: u64,
+}
+
+impl VerificationResult {
+    /// Prompt section describing this failure for the next iteration
+    pub fn to_prompt_section(&self) -> String {
+        let exit = self
+            .exit_code
+            .map(|c| format!("exit code {}", c))
+            .unwrap_or_else(|| "no exit code".to_string());
+        format!(
+            "## Verification Failed\n\nYou reported the task as complete, but the verification command `{}` failed ({}). \
+            Fix the problems below before outputting the completion marker again.\n\n```\n{}\n```",
+            self.command,
+            exit,
+            self.output.trim_end()
+        )
+    }
+}
+
+/// Keep the last `max_chars` characters (failures are usually at the end)
+fn tail_chars(s: &str, max_chars: usize) -> String {
+    let count = s.chars().count();
+    if count <= max_chars {
+        s.to_string()
+    } else {
+        let tail: String = s.chars().skip(count - max_chars).collect();
+        format!("...{}", tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_chars() {
+        assert_eq!(tail_chars("hello", 10), "hello");
+        assert_eq!(tail_chars("hello world", 5), "...world");
+        assert_eq!(tail_chars("한글 테스트", 3), "...테스트");
+    }
+
+    #[test]
+    fn test_prompt_section() {
+        let result = VerificationResult {
+            command: "cargo test".to_string(),
+            success: false,
+            exit_code: Some(101),
+            output: "test foo ... FAILED\n".to_string(),
+            duration_ms: 10,
+        };
+        let section = result.to_prompt_section();
+
+        assert!(section.contains("`cargo test`"));
+        assert!(section.contains("exit code 101"));
+        assert!(section.contains("test foo ... FAILED"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_success_and_failure() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = VerificationConfig::new("echo ok").run(dir.path()).await;
+        assert!(result.success);
+        assert_eq!(result.exit_code, Some(0));
+        assert!(result.output.contains("ok"));
+
+        let result = VerificationConfig::new("echo broken >&2; exit 3")
+            .run(dir.path())
+            .await;
+        assert!(!result.success);
+        assert_eq!(result.exit_code, Some(3));
+        assert!(result.output.contains("broken"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = VerificationConfig::new("sleep 5")
+            .with_timeout(1)
+            .run(dir.path())
+            .await;
+
+        assert!(!result.success);
+        assert!(result.output.contains("timed out"));
+    }
+}
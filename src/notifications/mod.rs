@@ -11,6 +11,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
 
+use crate::utils::truncate_str;
+
 /// Notification event types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -25,6 +27,22 @@ pub enum NotificationEvent {
     BudgetExceeded,
     /// Max iterations reached
     MaxIterations,
+    /// Stopped or cancelled by the user
+    Interrupted,
+    /// Loop stopped making progress
+    Stalled,
+    /// Spend is nearing a daily or monthly cap
+    SpendWarning,
+    /// Workflow started
+    WorkflowStarted,
+    /// Workflow step completed
+    StepCompleted,
+    /// Workflow step failed
+    StepFailed,
+    /// Workflow completed successfully
+    WorkflowCompleted,
+    /// Workflow failed
+    WorkflowFailed,
 }
 
 impl NotificationEvent {
@@ -35,6 +53,14 @@ impl NotificationEvent {
             NotificationEvent::Error => "error",
             NotificationEvent::BudgetExceeded => "budget_exceeded",
             NotificationEvent::MaxIterations => "max_iterations",
+            NotificationEvent::Interrupted => "interrupted",
+            NotificationEvent::Stalled => "stalled",
+            NotificationEvent::SpendWarning => "spend_warning",
+            NotificationEvent::WorkflowStarted => "workflow_started",
+            NotificationEvent::StepCompleted => "step_completed",
+            NotificationEvent::StepFailed => "step_failed",
+            NotificationEvent::WorkflowCompleted => "workflow_completed",
+            NotificationEvent::WorkflowFailed => "workflow_failed",
         }
     }
 
@@ -45,6 +71,14 @@ impl NotificationEvent {
             NotificationEvent::Error => "❌",
             NotificationEvent::BudgetExceeded => "💸",
             NotificationEvent::MaxIterations => "⚠️",
+            NotificationEvent::Interrupted => "🛑",
+            NotificationEvent::Stalled => "🔁",
+            NotificationEvent::SpendWarning => "💰",
+            NotificationEvent::WorkflowStarted => "🚀",
+            NotificationEvent::StepCompleted => "☑️",
+            NotificationEvent::StepFailed => "✖️",
+            NotificationEvent::WorkflowCompleted => "🏁",
+            NotificationEvent::WorkflowFailed => "❌",
         }
     }
 
@@ -55,6 +89,14 @@ impl NotificationEvent {
             NotificationEvent::Error => "#e74c3c",      // Red
             NotificationEvent::BudgetExceeded => "#f39c12", // Orange
             NotificationEvent::MaxIterations => "#f1c40f",  // Yellow
+            NotificationEvent::Interrupted => "#95a5a6",    // Gray
+            NotificationEvent::Stalled => "#e67e22",        // Dark orange
+            NotificationEvent::SpendWarning => "#f39c12",   // Orange
+            NotificationEvent::WorkflowStarted => "#3498db",   // Blue
+            NotificationEvent::StepCompleted => "#27ae60",     // Dark green
+            NotificationEvent::StepFailed => "#c0392b",        // Dark red
+            NotificationEvent::WorkflowCompleted => "#2ecc71", // Green
+            NotificationEvent::WorkflowFailed => "#e74c3c",    // Red
         }
     }
 
@@ -66,6 +108,36 @@ impl NotificationEvent {
             NotificationEvent::Error => 0xe74c3c,
             NotificationEvent::BudgetExceeded => 0xf39c12,
             NotificationEvent::MaxIterations => 0xf1c40f,
+            NotificationEvent::Interrupted => 0x95a5a6,
+            NotificationEvent::Stalled => 0xe67e22,
+            NotificationEvent::SpendWarning => 0xf39c12,
+            NotificationEvent::WorkflowStarted => 0x3498db,
+            NotificationEvent::StepCompleted => 0x27ae60,
+            NotificationEvent::StepFailed => 0xc0392b,
+            NotificationEvent::WorkflowCompleted => 0x2ecc71,
+            NotificationEvent::WorkflowFailed => 0xe74c3c,
+        }
+    }
+
+    /// Whether the event reports workflow progress rather than a task
+    pub fn is_workflow_event(&self) -> bool {
+        matches!(
+            self,
+            NotificationEvent::WorkflowStarted
+                | NotificationEvent::StepCompleted
+                | NotificationEvent::StepFailed
+                | NotificationEvent::WorkflowCompleted
+                | NotificationEvent::WorkflowFailed
+        )
+    }
+
+    /// Title of the event, e.g. "Task COMPLETED" or "WORKFLOW COMPLETED"
+    fn title(&self) -> String {
+        let event = self.as_str().replace('_', " ").to_uppercase();
+        if self.is_workflow_event() {
+            event
+        } else {
+            format!("Task {}", event)
         }
     }
 }
@@ -119,12 +191,7 @@ impl NotificationPayload {
     }
 
     pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
-        let prompt = prompt.into();
-        self.prompt = if prompt.len() > 100 {
-            format!("{}...", &prompt[..97])
-        } else {
-            prompt
-        };
+        self.prompt = truncate_str(&prompt.into(), 100);
         self
     }
 
@@ -158,6 +225,17 @@ impl NotificationPayload {
         self
     }
 
+    /// Set the workflow (`workflow` and `workflow_id` metadata)
+    pub fn with_workflow(self, name: impl Into<String>, id: impl Into<String>) -> Self {
+        self.with_metadata("workflow", name)
+            .with_metadata("workflow_id", id)
+    }
+
+    /// Set the workflow step (`step` metadata)
+    pub fn with_step(self, name: impl Into<String>) -> Self {
+        self.with_metadata("step", name)
+    }
+
     /// Format duration as human-readable string
     pub fn duration_string(&self) -> String {
         let secs = self.duration_ms / 1000;
@@ -226,7 +304,12 @@ pub struct WebhookConfig {
 }
 
 fn default_events() -> Vec<NotificationEvent> {
-    vec![NotificationEvent::Completed, NotificationEvent::Error]
+    vec![
+        NotificationEvent::Completed,
+        NotificationEvent::Error,
+        NotificationEvent::WorkflowCompleted,
+        NotificationEvent::WorkflowFailed,
+    ]
 }
 
 fn default_method() -> String {
@@ -311,14 +394,14 @@ impl SlackNotifier {
 
     fn build_message(&self, payload: &NotificationPayload) -> serde_json::Value {
         let title = format!(
-            "{} Doodoori Task {}",
+            "{} Doodoori {}",
             payload.event.emoji(),
-            payload.event.as_str().replace('_', " ").to_uppercase()
+            payload.event.title()
         );
 
         let mut fields = vec![
             serde_json::json!({
-                "title": "Task ID",
+                "title": if payload.event.is_workflow_event() { "Workflow ID" } else { "Task ID" },
                 "value": &payload.task_id[..8.min(payload.task_id.len())],
                 "short": true
             }),
@@ -352,6 +435,19 @@ impl SlackNotifier {
             }));
         }
 
+        for (key, title) in [("step", "Step"), ("workflow", "Workflow")] {
+            if let Some(value) = payload.metadata.get(key) {
+                fields.insert(
+                    0,
+                    serde_json::json!({
+                        "title": title,
+                        "value": value,
+                        "short": true
+                    }),
+                );
+            }
+        }
+
         if let Some(ref error) = payload.error {
             fields.push(serde_json::json!({
                 "title": "Error",
@@ -433,15 +529,11 @@ impl DiscordNotifier {
     }
 
     fn build_message(&self, payload: &NotificationPayload) -> serde_json::Value {
-        let title = format!(
-            "{} Task {}",
-            payload.event.emoji(),
-            payload.event.as_str().replace('_', " ").to_uppercase()
-        );
+        let title = format!("{} {}", payload.event.emoji(), payload.event.title());
 
         let mut fields = vec![
             serde_json::json!({
-                "name": "Task ID",
+                "name": if payload.event.is_workflow_event() { "Workflow ID" } else { "Task ID" },
                 "value": &payload.task_id[..8.min(payload.task_id.len())],
                 "inline": true
             }),
@@ -475,6 +567,19 @@ impl DiscordNotifier {
             }));
         }
 
+        for (key, name) in [("step", "Step"), ("workflow", "Workflow")] {
+            if let Some(value) = payload.metadata.get(key) {
+                fields.insert(
+                    0,
+                    serde_json::json!({
+                        "name": name,
+                        "value": value,
+                        "inline": true
+                    }),
+                );
+            }
+        }
+
         if let Some(ref error) = payload.error {
             fields.push(serde_json::json!({
                 "name": "Error",
@@ -808,6 +913,33 @@ mod tests {
         assert_eq!(message["username"], "Doodoori Bot");
     }
 
+    #[test]
+    fn test_workflow_event_message() {
+        let notifier = SlackNotifier::new(SlackConfig {
+            webhook_url: "https://hooks.slack.com/test".to_string(),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+            events: default_events(),
+        });
+        assert!(notifier.should_notify(NotificationEvent::WorkflowFailed));
+        assert!(!notifier.should_notify(NotificationEvent::StepCompleted));
+
+        let payload = NotificationPayload::new(NotificationEvent::StepFailed, "1234abcd-workflow")
+            .with_workflow("release", "1234abcd-workflow")
+            .with_step("backend")
+            .with_error("Tests failed");
+        assert_eq!(payload.metadata["workflow"], "release");
+        assert_eq!(payload.metadata["step"], "backend");
+
+        let message = notifier.build_message(&payload);
+        let attachment = &message["attachments"][0];
+        assert_eq!(attachment["title"], "✖️ Doodoori STEP FAILED");
+        assert_eq!(attachment["fields"][0]["title"], "Workflow");
+        assert_eq!(attachment["fields"][1]["value"], "backend");
+        assert_eq!(attachment["fields"][2]["title"], "Workflow ID");
+    }
+
     #[test]
     fn test_discord_message_builder() {
         let config = DiscordConfig {
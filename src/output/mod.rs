@@ -80,12 +80,65 @@ pub struct TaskOutput {
     pub output: Option<String>,
     /// Timestamp (ISO 8601)
     pub timestamp: String,
+    /// Git workflow result (branch, commit, PR)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub git: Option<GitOutput>,
+    /// Tool calls made, per tool
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub tools: Vec<ToolOutput>,
     /// Additional metadata
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     #[serde(default)]
     pub metadata: HashMap<String, String>,
 }
 
+/// Git workflow result for output formatting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitOutput {
+    /// Task branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Commit hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Pull request URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_url: Option<String>,
+    /// Whether auto-merge was enabled on the PR
+    pub auto_merge: bool,
+    /// Git errors encountered
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+/// Tool call totals for output formatting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutput {
+    /// Tool name
+    pub name: String,
+    /// Number of calls
+    pub calls: u32,
+    /// Number of failed calls
+    pub failures: u32,
+    /// Total time spent in the tool in milliseconds
+    pub duration_ms: u64,
+}
+
+impl From<&crate::git::workflow::GitWorkflowResult> for GitOutput {
+    fn from(result: &crate::git::workflow::GitWorkflowResult) -> Self {
+        Self {
+            branch: result.branch.clone(),
+            commit: result.commit.clone(),
+            pr_url: result.pr_url.clone(),
+            auto_merge: result.auto_merge,
+            errors: result.errors.clone(),
+        }
+    }
+}
+
 impl TaskOutput {
     pub fn new(task_id: impl Into<String>, prompt: impl Into<String>) -> Self {
         Self {
@@ -101,6 +154,8 @@ impl TaskOutput {
             error: None,
             output: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
+            git: None,
+            tools: Vec::new(),
             metadata: HashMap::new(),
         }
     }
@@ -146,6 +201,24 @@ impl TaskOutput {
         self
     }
 
+    pub fn with_git(mut self, git: GitOutput) -> Self {
+        self.git = Some(git);
+        self
+    }
+
+    pub fn with_tools(mut self, stats: &std::collections::BTreeMap<String, crate::claude::ToolStats>) -> Self {
+        self.tools = stats
+            .iter()
+            .map(|(name, stats)| ToolOutput {
+                name: name.clone(),
+                calls: stats.calls,
+                failures: stats.failures,
+                duration_ms: stats.duration_ms,
+            })
+            .collect();
+        self
+    }
+
     pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.metadata.insert(key.into(), value.into());
         self
@@ -228,6 +301,9 @@ pub struct WorkflowOutput {
     pub total_cost_usd: f64,
     /// Total duration in milliseconds
     pub total_duration_ms: u64,
+    /// Time the steps ran for, summed (their duration one after another)
+    #[serde(default)]
+    pub step_duration_ms: u64,
     /// Step results
     pub steps: Vec<StepOutput>,
     /// Timestamp
@@ -246,6 +322,7 @@ impl WorkflowOutput {
             skipped_steps: 0,
             total_cost_usd: 0.0,
             total_duration_ms: 0,
+            step_duration_ms: 0,
             steps: Vec::new(),
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
@@ -272,6 +349,17 @@ impl WorkflowOutput {
         self.total_duration_ms = duration_ms;
         self
     }
+
+    pub fn with_step_duration(mut self, step_duration_ms: u64) -> Self {
+        self.step_duration_ms = step_duration_ms;
+        self
+    }
+
+    /// How much faster the workflow ran than its steps one after another
+    pub fn speedup(&self) -> Option<f64> {
+        (self.total_duration_ms > 0 && self.step_duration_ms > 0)
+            .then(|| self.step_duration_ms as f64 / self.total_duration_ms as f64)
+    }
 }
 
 /// Workflow step result
@@ -341,6 +429,24 @@ pub struct CostOutput {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub daily: Vec<DailyCostOutput>,
+    /// What `groups` are grouped by (project, model, day or status)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub group_by: Option<String>,
+    /// Cost breakdown by `group_by`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub groups: Vec<CostGroupOutput>,
+}
+
+/// Cost of one group in a breakdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostGroupOutput {
+    pub key: String,
+    pub cost_usd: f64,
+    pub tasks: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
 }
 
 /// Daily cost breakdown
@@ -360,6 +466,8 @@ impl CostOutput {
             total_tasks: 0,
             by_model: HashMap::new(),
             daily: Vec::new(),
+            group_by: None,
+            groups: Vec::new(),
         }
     }
 
@@ -383,6 +491,12 @@ impl CostOutput {
         self
     }
 
+    pub fn with_groups(mut self, group_by: impl Into<String>, groups: Vec<CostGroupOutput>) -> Self {
+        self.group_by = Some(group_by.into());
+        self.groups = groups;
+        self
+    }
+
     pub fn with_tokens(self, _input: u64, _output: u64) -> Self {
         // Note: CostOutput doesn't currently track tokens directly
         // This method is a placeholder for future extension
@@ -545,6 +659,40 @@ impl Formatter for MarkdownFormatter {
             md.push_str("\n```\n");
         }
 
+        if let Some(ref git) = output.git {
+            md.push_str("\n## Git\n\n");
+            if let Some(ref branch) = git.branch {
+                md.push_str(&format!("- **Branch:** `{}`\n", branch));
+            }
+            if let Some(ref commit) = git.commit {
+                md.push_str(&format!("- **Commit:** `{}`\n", commit));
+            }
+            if let Some(ref pr_url) = git.pr_url {
+                md.push_str(&format!("- **Pull Request:** {}\n", pr_url));
+                if git.auto_merge {
+                    md.push_str("- **Auto-merge:** enabled\n");
+                }
+            }
+            for error in &git.errors {
+                md.push_str(&format!("- ❌ {}\n", error));
+            }
+        }
+
+        if !output.tools.is_empty() {
+            md.push_str("\n## Tools\n\n");
+            md.push_str("| Tool | Calls | Failed | Duration |\n");
+            md.push_str("|------|-------|--------|----------|\n");
+            for tool in &output.tools {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    tool.name,
+                    tool.calls,
+                    tool.failures,
+                    Self::format_duration(tool.duration_ms)
+                ));
+            }
+        }
+
         if let Some(ref out) = output.output {
             md.push_str("\n## Output\n\n");
             md.push_str(out);
@@ -619,6 +767,13 @@ impl Formatter for MarkdownFormatter {
             "| Total Duration | {} |\n",
             Self::format_duration(output.total_duration_ms)
         ));
+        if let Some(speedup) = output.speedup() {
+            md.push_str(&format!(
+                "| Step Time | {} ({:.1}x speedup) |\n",
+                Self::format_duration(output.step_duration_ms),
+                speedup
+            ));
+        }
 
         md.push_str("\n## Steps\n\n");
         md.push_str("| Step | Status | Cost | Duration |\n");
@@ -668,6 +823,22 @@ impl Formatter for MarkdownFormatter {
             }
         }
 
+        if let Some(ref group_by) = output.group_by {
+            md.push_str(&format!("\n## Cost by {}\n\n", group_by));
+            md.push_str("| Group | Cost | Tasks | Input | Output |\n");
+            md.push_str("|-------|------|-------|-------|--------|\n");
+            for group in &output.groups {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    group.key,
+                    Self::format_cost(group.cost_usd),
+                    group.tasks,
+                    group.input_tokens,
+                    group.output_tokens
+                ));
+            }
+        }
+
         if !output.daily.is_empty() {
             md.push_str("\n## Daily Breakdown\n\n");
             md.push_str("| Date | Cost | Tasks |\n");
@@ -708,6 +879,28 @@ impl Formatter for TextFormatter {
             text.push_str(&format!("Error: {}\n", error));
         }
 
+        if let Some(ref git) = output.git {
+            if let Some(ref branch) = git.branch {
+                text.push_str(&format!("Git Branch: {}\n", branch));
+            }
+            if let Some(ref commit) = git.commit {
+                text.push_str(&format!("Git Commit: {}\n", commit));
+            }
+            if let Some(ref pr_url) = git.pr_url {
+                text.push_str(&format!("Pull Request: {}\n", pr_url));
+            }
+            for error in &git.errors {
+                text.push_str(&format!("Git Error: {}\n", error));
+            }
+        }
+
+        for tool in &output.tools {
+            text.push_str(&format!(
+                "Tool: {} ({} calls, {} failed, {}ms)\n",
+                tool.name, tool.calls, tool.failures, tool.duration_ms
+            ));
+        }
+
         Ok(text)
     }
 
@@ -747,6 +940,12 @@ impl Formatter for TextFormatter {
         text.push_str(&format!("This Month: ${:.4}\n", output.month_usd));
         text.push_str(&format!("All Time: ${:.4}\n", output.total_usd));
         text.push_str(&format!("Total Tasks: {}\n", output.total_tasks));
+        if let Some(ref group_by) = output.group_by {
+            text.push_str(&format!("By {}:\n", group_by));
+            for group in &output.groups {
+                text.push_str(&format!("  {}: ${:.4} ({} tasks)\n", group.key, group.cost_usd, group.tasks));
+            }
+        }
 
         Ok(text)
     }
@@ -856,6 +1055,55 @@ mod tests {
         assert_eq!(output.cost_usd, 0.05);
     }
 
+    #[test]
+    fn test_task_output_with_git() {
+        let git = GitOutput {
+            branch: Some("doodoori/add-login".to_string()),
+            commit: Some("abc1234".to_string()),
+            pr_url: None,
+            auto_merge: false,
+            errors: vec!["Failed to push: no remote".to_string()],
+        };
+        let output = TaskOutput::new("task-123", "Add login").with_git(git);
+
+        let md = MarkdownFormatter.format_task(&output).unwrap();
+        assert!(md.contains("## Git"));
+        assert!(md.contains("`doodoori/add-login`"));
+        assert!(md.contains("Failed to push"));
+
+        let text = TextFormatter.format_task(&output).unwrap();
+        assert!(text.contains("Git Commit: abc1234"));
+        assert!(text.contains("Git Error: Failed to push: no remote"));
+
+        let json = JsonFormatter::new(false).format_task(&output).unwrap();
+        assert!(json.contains("\"branch\":\"doodoori/add-login\""));
+        assert!(!json.contains("pr_url"));
+    }
+
+    #[test]
+    fn test_task_output_with_tools() {
+        let mut stats = std::collections::BTreeMap::new();
+        stats.insert(
+            "Bash".to_string(),
+            crate::claude::ToolStats { calls: 3, failures: 1, duration_ms: 4200 },
+        );
+        let output = TaskOutput::new("task-123", "Fix tests").with_tools(&stats);
+
+        let md = MarkdownFormatter.format_task(&output).unwrap();
+        assert!(md.contains("## Tools"));
+        assert!(md.contains("| Bash | 3 | 1 | 4.2s |"));
+
+        let text = TextFormatter.format_task(&output).unwrap();
+        assert!(text.contains("Tool: Bash (3 calls, 1 failed, 4200ms)"));
+
+        let json = JsonFormatter::new(false).format_task(&output).unwrap();
+        assert!(json.contains("\"tools\":[{\"name\":\"Bash\",\"calls\":3"));
+        let json = JsonFormatter::new(false)
+            .format_task(&TaskOutput::new("task-456", "No tools"))
+            .unwrap();
+        assert!(!json.contains("\"tools\""));
+    }
+
     #[test]
     fn test_parallel_output() {
         let mut parallel = ParallelOutput::new();
@@ -878,6 +1126,18 @@ mod tests {
         assert_eq!(parallel.total_cost_usd, 0.03);
     }
 
+    #[test]
+    fn test_workflow_output_speedup() {
+        let output = WorkflowOutput::new("DAG", "wf-1").with_status("completed");
+        assert_eq!(output.speedup(), None);
+
+        let output = output.with_duration(60_000).with_step_duration(150_000);
+        assert_eq!(output.speedup(), Some(2.5));
+
+        let md = MarkdownFormatter.format_workflow(&output).unwrap();
+        assert!(md.contains("| Step Time | 2m 30s (2.5x speedup) |"));
+    }
+
     #[test]
     fn test_json_formatter() {
         let output = TaskOutput::new("task-123", "Test")
@@ -918,6 +1178,26 @@ mod tests {
         assert!(md.contains("| Iterations | 5 |"));
     }
 
+    #[test]
+    fn test_cost_output_groups() {
+        let group = CostGroupOutput {
+            key: "git@github.com:team/api.git".to_string(),
+            cost_usd: 1.5,
+            tasks: 3,
+            input_tokens: 1000,
+            output_tokens: 200,
+        };
+        let output = CostOutput::new().with_total(1.5).with_groups("project", vec![group]);
+
+        let md = MarkdownFormatter.format_cost(&output).unwrap();
+        assert!(md.contains("## Cost by project"));
+        assert!(md.contains("| git@github.com:team/api.git | $1.50 | 3 |"));
+
+        let json = JsonFormatter::new(false).format_cost(&output).unwrap();
+        assert!(json.contains("\"group_by\":\"project\""));
+        assert!(!JsonFormatter::new(false).format_cost(&CostOutput::new()).unwrap().contains("groups"));
+    }
+
     #[test]
     fn test_markdown_duration_format() {
         assert_eq!(MarkdownFormatter::format_duration(500), "500ms");
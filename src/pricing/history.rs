@@ -7,6 +7,9 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::claude::ExecutionUsage;
+use crate::utils::fs::{append_line, atomic_write, FileLock};
+
 /// A single cost entry for a task execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostEntry {
@@ -30,6 +33,84 @@ pub struct CostEntry {
     pub status: String,
     /// Optional description or prompt summary
     pub description: Option<String>,
+    /// Per-iteration model and cost, when the model changed during the task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<IterationCost>,
+}
+
+/// Model and cost of one iteration of a task
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IterationCost {
+    /// 1-based iteration number
+    pub iteration: u32,
+    /// Model used for the iteration
+    pub model: String,
+    /// Input tokens
+    pub input_tokens: u64,
+    /// Output tokens
+    pub output_tokens: u64,
+    /// Cost in USD
+    pub cost_usd: f64,
+    /// Cost reported by the CLI (absent if it reported none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_cost_usd: Option<f64>,
+    /// Cost calculated from token usage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calculated_cost_usd: Option<f64>,
+}
+
+impl IterationCost {
+    pub fn new(iteration: u32, model: impl Into<String>, usage: &ExecutionUsage) -> Self {
+        Self {
+            iteration,
+            model: model.into(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cost_usd: usage.total_cost_usd,
+            reported_cost_usd: usage.reported_cost_usd,
+            calculated_cost_usd: Some(usage.calculated_cost_usd),
+        }
+    }
+}
+
+impl CostEntry {
+    /// Cost split by the models used, in order of first use
+    pub fn cost_by_model(&self) -> Vec<(String, f64)> {
+        if self.iterations.is_empty() {
+            return vec![(self.model.clone(), self.cost_usd)];
+        }
+
+        let mut by_model: Vec<(String, f64)> = Vec::new();
+        for iteration in &self.iterations {
+            match by_model.iter_mut().find(|(model, _)| *model == iteration.model) {
+                Some((_, cost)) => *cost += iteration.cost_usd,
+                None => by_model.push((iteration.model.clone(), iteration.cost_usd)),
+            }
+        }
+        by_model
+    }
+
+    /// Reported and calculated cost over the iterations that recorded both
+    ///
+    /// A gap between the two shows price.toml drifting from what the CLI bills.
+    pub fn cost_drift(&self) -> Option<(f64, f64)> {
+        let compared: Vec<_> = self
+            .iterations
+            .iter()
+            .filter_map(|i| Some((i.reported_cost_usd?, i.calculated_cost_usd?)))
+            .collect();
+        if compared.is_empty() {
+            return None;
+        }
+        Some(compared.iter().fold((0.0, 0.0), |(r, c), (reported, calculated)| (r + reported, c + calculated)))
+    }
+
+    /// Whether some of the cost was calculated because the CLI reported none
+    pub fn has_calculated_cost(&self) -> bool {
+        self.iterations
+            .iter()
+            .any(|i| i.reported_cost_usd.is_none() && i.calculated_cost_usd.is_some())
+    }
 }
 
 /// Daily cost summary
@@ -83,7 +164,9 @@ impl CostHistory {
         summary.total_input_tokens += entry.input_tokens;
         summary.total_output_tokens += entry.output_tokens;
         summary.task_count += 1;
-        *summary.by_model.entry(entry.model.clone()).or_insert(0.0) += entry.cost_usd;
+        for (model, cost) in entry.cost_by_model() {
+            *summary.by_model.entry(model).or_insert(0.0) += cost;
+        }
 
         self.entries.push(entry);
         self.updated_at = Some(Utc::now());
@@ -172,10 +255,26 @@ impl CostHistory {
     }
 }
 
+/// Cost history file (compacted entries and daily summaries)
+const HISTORY_FILE: &str = "cost_history.json";
+
+/// Append-only ledger of entries recorded since the last compaction
+const LEDGER_FILE: &str = "cost_ledger.jsonl";
+
+/// Ledger entries after which the ledger is folded into the history file
+const COMPACT_THRESHOLD: usize = 50;
+
 /// Cost history manager for persistence
+///
+/// New entries are appended as single lines to `cost_ledger.jsonl` under an
+/// advisory lock, so concurrent tasks never overwrite each other's costs.
+/// Once the ledger grows past a threshold it is compacted into
+/// `cost_history.json`, which is replaced atomically.
 pub struct CostHistoryManager {
     /// Path to the cost history file
     file_path: PathBuf,
+    /// Path to the cost ledger
+    ledger_path: PathBuf,
     /// Cached history
     history: CostHistory,
 }
@@ -183,17 +282,13 @@ pub struct CostHistoryManager {
 impl CostHistoryManager {
     /// Create a new cost history manager
     pub fn new(base_dir: &Path) -> Result<Self> {
-        let file_path = base_dir.join("cost_history.json");
-        let history = if file_path.exists() {
-            let content = fs::read_to_string(&file_path)
-                .context("Failed to read cost history file")?;
-            serde_json::from_str(&content)
-                .context("Failed to parse cost history file")?
-        } else {
-            CostHistory::new()
+        let mut manager = Self {
+            file_path: base_dir.join(HISTORY_FILE),
+            ledger_path: base_dir.join(LEDGER_FILE),
+            history: CostHistory::new(),
         };
-
-        Ok(Self { file_path, history })
+        manager.reload()?;
+        Ok(manager)
     }
 
     /// Create a manager for the .doodoori directory in the given project
@@ -228,26 +323,90 @@ impl CostHistoryManager {
             cost_usd,
             status: status.to_string(),
             description,
+            iterations: Vec::new(),
         };
 
+        self.record_entry(entry)
+    }
+
+    /// Add a prepared cost entry and save
+    pub fn record_entry(&mut self, entry: CostEntry) -> Result<()> {
+        self.append_entry(&entry)?;
         self.history.add_entry(entry);
-        self.save()
+        Ok(())
     }
 
-    /// Save history to file
-    pub fn save(&self) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.history)?;
-        fs::write(&self.file_path, json)
-            .context("Failed to write cost history file")?;
+    /// Append an entry to the ledger without touching the cached history
+    ///
+    /// Compacts the ledger once it holds enough entries.
+    pub fn append_entry(&self, entry: &CostEntry) -> Result<()> {
+        let _lock = FileLock::exclusive(&self.file_path).context("Failed to lock cost history")?;
+
+        let line = serde_json::to_string(entry)?;
+        append_line(&self.ledger_path, &line).context("Failed to write cost ledger")?;
+
+        if Self::read_ledger(&self.ledger_path)?.len() >= COMPACT_THRESHOLD {
+            self.compact_locked()?;
+        }
+        Ok(())
+    }
+
+    /// Fold the ledger into the history file
+    pub fn compact(&mut self) -> Result<()> {
+        let _lock = FileLock::exclusive(&self.file_path).context("Failed to lock cost history")?;
+        self.history = self.compact_locked()?;
         Ok(())
     }
 
+    fn compact_locked(&self) -> Result<CostHistory> {
+        let history = self.read_history()?;
+        let json = serde_json::to_string_pretty(&history)?;
+        atomic_write(&self.file_path, json).context("Failed to write cost history file")?;
+        if self.ledger_path.exists() {
+            fs::remove_file(&self.ledger_path).context("Failed to remove cost ledger")?;
+        }
+        tracing::debug!("Compacted cost ledger into {:?}", self.file_path);
+        Ok(history)
+    }
+
+    /// History file plus the entries in the ledger
+    fn read_history(&self) -> Result<CostHistory> {
+        let mut history = if self.file_path.exists() {
+            let content = fs::read_to_string(&self.file_path)
+                .context("Failed to read cost history file")?;
+            serde_json::from_str(&content)
+                .context("Failed to parse cost history file")?
+        } else {
+            CostHistory::new()
+        };
+        for entry in Self::read_ledger(&self.ledger_path)? {
+            history.add_entry(entry);
+        }
+        Ok(history)
+    }
+
+    fn read_ledger(path: &Path) -> Result<Vec<CostEntry>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(path).context("Failed to read cost ledger")?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping damaged cost ledger line: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
     /// Reload history from file
     pub fn reload(&mut self) -> Result<()> {
-        if self.file_path.exists() {
-            let content = fs::read_to_string(&self.file_path)?;
-            self.history = serde_json::from_str(&content)?;
-        }
+        let _lock = FileLock::shared(&self.file_path).context("Failed to lock cost history")?;
+        self.history = self.read_history()?;
         Ok(())
     }
 
@@ -263,8 +422,14 @@ impl CostHistoryManager {
 
     /// Reset (clear) the history
     pub fn reset(&mut self) -> Result<()> {
+        let _lock = FileLock::exclusive(&self.file_path).context("Failed to lock cost history")?;
         self.history.clear();
-        self.save()
+        let json = serde_json::to_string_pretty(&self.history)?;
+        atomic_write(&self.file_path, json).context("Failed to write cost history file")?;
+        if self.ledger_path.exists() {
+            fs::remove_file(&self.ledger_path).context("Failed to remove cost ledger")?;
+        }
+        Ok(())
     }
 }
 
@@ -285,6 +450,7 @@ mod tests {
             cost_usd: cost,
             status: "completed".to_string(),
             description: Some("Test task".to_string()),
+            iterations: Vec::new(),
         }
     }
 
@@ -325,6 +491,76 @@ mod tests {
         assert_eq!(summary.task_count, 2);
     }
 
+    #[test]
+    fn test_cost_by_model_uses_iterations() {
+        let mut history = CostHistory::new();
+        let iteration = |n: u32, model: &str, cost: f64| IterationCost {
+            iteration: n,
+            model: model.to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd: cost,
+            ..Default::default()
+        };
+
+        let mut entry = create_test_entry("task-1", 0.06);
+        entry.model = "haiku+sonnet".to_string();
+        entry.iterations = vec![
+            iteration(1, "haiku", 0.01),
+            iteration(2, "haiku", 0.01),
+            iteration(3, "sonnet", 0.04),
+        ];
+        assert_eq!(
+            entry.cost_by_model(),
+            vec![("haiku".to_string(), 0.02), ("sonnet".to_string(), 0.04)]
+        );
+
+        history.add_entry(entry);
+        history.add_entry(create_test_entry("task-2", 0.10));
+
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let summary = history.get_daily_summary(&today).unwrap();
+        assert_eq!(summary.by_model.len(), 2);
+        assert!((summary.by_model["haiku"] - 0.02).abs() < 0.001);
+        assert!((summary.by_model["sonnet"] - 0.14).abs() < 0.001);
+
+        // Entries without iterations keep the old format
+        let json = serde_json::to_string(&create_test_entry("task-3", 0.01)).unwrap();
+        assert!(!json.contains("iterations"));
+    }
+
+    #[test]
+    fn test_cost_drift() {
+        let reported = ExecutionUsage {
+            total_cost_usd: 0.10,
+            reported_cost_usd: Some(0.10),
+            calculated_cost_usd: 0.12,
+            ..Default::default()
+        };
+        let unreported = ExecutionUsage {
+            total_cost_usd: 0.05,
+            calculated_cost_usd: 0.05,
+            ..Default::default()
+        };
+
+        let mut entry = create_test_entry("task-1", 0.15);
+        assert_eq!(entry.cost_drift(), None);
+        entry.iterations = vec![
+            IterationCost::new(1, "sonnet", &reported),
+            IterationCost::new(2, "sonnet", &unreported),
+        ];
+
+        // Only iterations with both costs are compared
+        let (r, c) = entry.cost_drift().unwrap();
+        assert!((r - 0.10).abs() < 1e-9 && (c - 0.12).abs() < 1e-9);
+        assert!(entry.has_calculated_cost());
+
+        // Iterations recorded before calculated costs still load
+        let old = r#"{"iteration":1,"model":"haiku","input_tokens":1,"output_tokens":2,"cost_usd":0.1}"#;
+        let iteration: IterationCost = serde_json::from_str(old).unwrap();
+        assert_eq!(iteration.calculated_cost_usd, None);
+    }
+
     #[test]
     fn test_cost_history_manager_save_load() {
         let dir = tempdir().unwrap();
@@ -370,6 +606,59 @@ mod tests {
 
         assert!(manager.history().entries.is_empty());
         assert!((manager.history().get_total_cost()).abs() < 0.001);
+        assert!(CostHistoryManager::new(&base_dir).unwrap().history().entries.is_empty());
+    }
+
+    #[test]
+    fn test_cost_history_manager_concurrent_writers() {
+        let dir = tempdir().unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|worker| {
+                let project_dir = dir.path().to_path_buf();
+                std::thread::spawn(move || {
+                    // Each worker opens its own manager, like parallel tasks do
+                    let manager = CostHistoryManager::for_project(&project_dir).unwrap();
+                    for i in 0..40 {
+                        let entry = create_test_entry(&format!("task-{}-{}", worker, i), 0.01);
+                        manager.append_entry(&entry).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // 320 entries crossed the compaction threshold several times
+        let manager = CostHistoryManager::for_project(dir.path()).unwrap();
+        assert_eq!(manager.history().entries.len(), 320);
+        assert_eq!(manager.history().get_today_summary().unwrap().task_count, 320);
+        assert!((manager.history().get_total_cost() - 3.2).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_cost_history_manager_ledger_compaction() {
+        let dir = tempdir().unwrap();
+        let base_dir = dir.path().join(".doodoori");
+        fs::create_dir_all(&base_dir).unwrap();
+
+        let mut manager = CostHistoryManager::new(&base_dir).unwrap();
+        manager.record_entry(create_test_entry("task-1", 0.05)).unwrap();
+        assert!(base_dir.join(LEDGER_FILE).exists());
+        assert!(!base_dir.join(HISTORY_FILE).exists());
+
+        // A line cut short by a crash is skipped, later entries are kept
+        use std::io::Write;
+        let mut ledger = fs::OpenOptions::new().append(true).open(base_dir.join(LEDGER_FILE)).unwrap();
+        ledger.write_all(b"{\"task_id\":\"tru").unwrap();
+        manager.record_entry(create_test_entry("task-2", 0.10)).unwrap();
+        assert_eq!(CostHistoryManager::new(&base_dir).unwrap().history().entries.len(), 2);
+
+        manager.compact().unwrap();
+        assert!(!base_dir.join(LEDGER_FILE).exists());
+        let reloaded = CostHistoryManager::new(&base_dir).unwrap();
+        assert_eq!(reloaded.history().entries.len(), 2);
+        assert!((reloaded.history().get_today_summary().unwrap().total_cost_usd - 0.15).abs() < 0.001);
     }
 
     #[test]
@@ -0,0 +1,356 @@
+//! User-level cost ledger shared by all projects
+//!
+//! Every cost entry recorded in a project is also appended to
+//! `cost_ledger.jsonl` in the user's data directory, tagged with the project
+//! path and git remote, so `doodoori cost --all-projects` can report spend
+//! across repositories.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::history::{CostEntry, CostHistory};
+use crate::git::repo::GitRepository;
+use crate::utils::fs::{append_line, FileLock};
+
+/// Ledger file name inside the user data directory
+const LEDGER_FILE: &str = "cost_ledger.jsonl";
+
+/// A cost entry together with the project it was recorded in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalCostEntry {
+    #[serde(flatten)]
+    pub entry: CostEntry,
+    /// Project directory
+    pub project: PathBuf,
+    /// URL of the project's default git remote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_remote: Option<String>,
+}
+
+impl GlobalCostEntry {
+    /// Display name of the project (the remote if known, else the path)
+    pub fn project_name(&self) -> String {
+        self.git_remote
+            .clone()
+            .unwrap_or_else(|| self.project.display().to_string())
+    }
+}
+
+/// Append-only cost ledger in the user's data directory
+#[derive(Debug, Clone)]
+pub struct GlobalCostLedger {
+    path: PathBuf,
+}
+
+impl GlobalCostLedger {
+    /// Ledger stored at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default ledger location (e.g. `~/.local/share/doodoori/cost_ledger.jsonl`)
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "doodoori").map(|dirs| dirs.data_dir().join(LEDGER_FILE))
+    }
+
+    /// Ledger at the default location
+    pub fn user() -> Option<Self> {
+        Self::default_path().map(Self::new)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry recorded in `project_dir`
+    pub fn record(&self, project_dir: &Path, entry: &CostEntry) -> Result<()> {
+        let project = project_dir
+            .canonicalize()
+            .unwrap_or_else(|_| project_dir.to_path_buf());
+        let git_remote = GitRepository::open(&project)
+            .ok()
+            .and_then(|repo| repo.remote_url().ok().flatten());
+        let global = GlobalCostEntry {
+            entry: entry.clone(),
+            project,
+            git_remote,
+        };
+
+        let _lock = FileLock::exclusive(&self.path).context("Failed to lock global cost ledger")?;
+        append_line(&self.path, &serde_json::to_string(&global)?)
+            .with_context(|| format!("Failed to write global cost ledger: {}", self.path.display()))
+    }
+
+    /// All entries in the ledger, oldest first
+    pub fn entries(&self) -> Result<Vec<GlobalCostEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let _lock = FileLock::shared(&self.path).context("Failed to lock global cost ledger")?;
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read global cost ledger: {}", self.path.display()))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping damaged global cost ledger line: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// How to group cost entries in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostGroupBy {
+    Project,
+    Model,
+    Day,
+    Status,
+}
+
+impl std::str::FromStr for CostGroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "project" => Ok(CostGroupBy::Project),
+            "model" => Ok(CostGroupBy::Model),
+            "day" | "date" => Ok(CostGroupBy::Day),
+            "status" => Ok(CostGroupBy::Status),
+            _ => Err(format!("Unknown grouping '{}'. Use: project, model, day, status", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for CostGroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CostGroupBy::Project => write!(f, "project"),
+            CostGroupBy::Model => write!(f, "model"),
+            CostGroupBy::Day => write!(f, "day"),
+            CostGroupBy::Status => write!(f, "status"),
+        }
+    }
+}
+
+/// Which entries a cost report covers
+#[derive(Debug, Clone, Default)]
+pub struct CostFilter {
+    /// Project path, or part of a project path or git remote
+    pub project: Option<String>,
+    /// First day included (UTC)
+    pub since: Option<NaiveDate>,
+    /// Last day included (UTC)
+    pub until: Option<NaiveDate>,
+}
+
+impl CostFilter {
+    pub fn matches(&self, entry: &GlobalCostEntry) -> bool {
+        let day = entry.entry.timestamp.date_naive();
+        if self.since.is_some_and(|since| day < since) || self.until.is_some_and(|until| day > until) {
+            return false;
+        }
+        let Some(ref project) = self.project else {
+            return true;
+        };
+
+        // An existing directory must match exactly, anything else matches by substring
+        match Path::new(project).canonicalize() {
+            Ok(path) => entry.project == path,
+            Err(_) => {
+                entry.project.to_string_lossy().contains(project.as_str())
+                    || entry.git_remote.as_ref().is_some_and(|remote| remote.contains(project.as_str()))
+            }
+        }
+    }
+}
+
+/// Cost of one group in a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostGroup {
+    pub key: String,
+    pub cost_usd: f64,
+    pub task_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Group entries, days newest first and everything else by cost
+///
+/// Grouping by model splits tasks that switched models by iteration.
+pub fn group_entries(entries: &[GlobalCostEntry], by: CostGroupBy) -> Vec<CostGroup> {
+    let mut groups: Vec<CostGroup> = Vec::new();
+    let mut add = |key: String, cost_usd: f64, input_tokens: u64, output_tokens: u64| {
+        let index = match groups.iter().position(|g| g.key == key) {
+            Some(index) => index,
+            None => {
+                groups.push(CostGroup {
+                    key,
+                    cost_usd: 0.0,
+                    task_count: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        group.cost_usd += cost_usd;
+        group.task_count += 1;
+        group.input_tokens += input_tokens;
+        group.output_tokens += output_tokens;
+    };
+
+    for global in entries {
+        let entry = &global.entry;
+        match by {
+            CostGroupBy::Project => add(global.project_name(), entry.cost_usd, entry.input_tokens, entry.output_tokens),
+            CostGroupBy::Day => add(
+                entry.timestamp.format("%Y-%m-%d").to_string(),
+                entry.cost_usd,
+                entry.input_tokens,
+                entry.output_tokens,
+            ),
+            CostGroupBy::Status => add(entry.status.clone(), entry.cost_usd, entry.input_tokens, entry.output_tokens),
+            CostGroupBy::Model if entry.iterations.is_empty() => {
+                add(entry.model.clone(), entry.cost_usd, entry.input_tokens, entry.output_tokens)
+            }
+            CostGroupBy::Model => {
+                for (model, cost) in entry.cost_by_model() {
+                    let iterations = entry.iterations.iter().filter(|i| i.model == model);
+                    let (input, output) = iterations.fold((0, 0), |(i, o), it| (i + it.input_tokens, o + it.output_tokens));
+                    add(model, cost, input, output);
+                }
+            }
+        }
+    }
+
+    if by == CostGroupBy::Day {
+        groups.sort_by(|a, b| b.key.cmp(&a.key));
+    } else {
+        groups.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+    }
+    groups
+}
+
+/// Cost history built from ledger entries (for today/month/total figures)
+pub fn history_of(entries: &[GlobalCostEntry]) -> CostHistory {
+    let mut history = CostHistory::new();
+    for global in entries {
+        history.add_entry(global.entry.clone());
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::IterationCost;
+    use chrono::{TimeZone, Utc};
+    use tempfile::tempdir;
+
+    fn entry(task_id: &str, day: u32, model: &str, cost: f64, status: &str) -> CostEntry {
+        CostEntry {
+            task_id: task_id.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap(),
+            model: model.to_string(),
+            input_tokens: 1000,
+            output_tokens: 500,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: cost,
+            status: status.to_string(),
+            description: None,
+            iterations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_global_ledger_tags_entries_with_project() {
+        let dir = tempdir().unwrap();
+        let ledger = GlobalCostLedger::new(dir.path().join("data/cost_ledger.jsonl"));
+        let project_a = dir.path().join("a");
+        let project_b = dir.path().join("b");
+        fs::create_dir_all(&project_a).unwrap();
+        fs::create_dir_all(&project_b).unwrap();
+        let repo = git2::Repository::init(&project_b).unwrap();
+        repo.remote("origin", "git@github.com:team/b.git").unwrap();
+
+        ledger.record(&project_a, &entry("t1", 1, "sonnet", 0.5, "Completed")).unwrap();
+        ledger.record(&project_b, &entry("t2", 2, "opus", 2.0, "Completed")).unwrap();
+
+        let entries = ledger.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].project, project_a.canonicalize().unwrap());
+        assert_eq!(entries[0].git_remote, None);
+        assert_eq!(entries[1].git_remote.as_deref(), Some("git@github.com:team/b.git"));
+        assert_eq!(entries[1].entry.task_id, "t2");
+
+        let filter = CostFilter {
+            project: Some(project_a.display().to_string()),
+            ..Default::default()
+        };
+        let matched: Vec<_> = entries.iter().filter(|e| filter.matches(e)).collect();
+        assert_eq!(matched.len(), 1);
+        let filter = CostFilter {
+            project: Some("team/b".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&entries[1]) && !filter.matches(&entries[0]));
+    }
+
+    #[test]
+    fn test_cost_filter_dates_and_grouping() {
+        let tag = |entry: CostEntry, project: &str| GlobalCostEntry {
+            entry,
+            project: PathBuf::from(project),
+            git_remote: None,
+        };
+        let mut switched = entry("t3", 3, "haiku+sonnet", 0.3, "Completed");
+        switched.iterations = vec![
+            IterationCost { iteration: 1, model: "haiku".to_string(), input_tokens: 10, output_tokens: 5, cost_usd: 0.1, ..Default::default() },
+            IterationCost { iteration: 2, model: "sonnet".to_string(), input_tokens: 20, output_tokens: 5, cost_usd: 0.2, ..Default::default() },
+        ];
+        let entries = vec![
+            tag(entry("t1", 1, "sonnet", 1.0, "Completed"), "/work/a"),
+            tag(entry("t2", 2, "opus", 2.0, "Failed"), "/work/b"),
+            tag(switched, "/work/a"),
+        ];
+
+        let filter = CostFilter {
+            since: NaiveDate::from_ymd_opt(2026, 3, 2),
+            until: NaiveDate::from_ymd_opt(2026, 3, 3),
+            ..Default::default()
+        };
+        let selected: Vec<GlobalCostEntry> = entries.iter().filter(|e| filter.matches(e)).cloned().collect();
+        assert_eq!(selected.len(), 2);
+
+        let days = group_entries(&entries, CostGroupBy::Day);
+        let keys: Vec<&str> = days.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["2026-03-03", "2026-03-02", "2026-03-01"]);
+
+        let projects = group_entries(&entries, CostGroupBy::Project);
+        assert_eq!(projects[0].key, "/work/b");
+        assert_eq!(projects[1].task_count, 2);
+        assert!((projects[1].cost_usd - 1.3).abs() < 1e-9);
+
+        let models = group_entries(&entries, CostGroupBy::Model);
+        let sonnet = models.iter().find(|g| g.key == "sonnet").unwrap();
+        assert!((sonnet.cost_usd - 1.2).abs() < 1e-9);
+        assert_eq!(sonnet.input_tokens, 1020);
+        assert!(models.iter().any(|g| g.key == "haiku"));
+
+        let statuses = group_entries(&entries, CostGroupBy::Status);
+        assert_eq!(statuses[0].key, "Failed");
+        assert_eq!("date".parse::<CostGroupBy>().unwrap(), CostGroupBy::Day);
+        assert!("week".parse::<CostGroupBy>().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Cost of a run from streamed token usage
+//!
+//! The `result` event at the end of the stream reports a run's cost, but
+//! the CLI leaves it out in some auth modes and a killed run never sends
+//! it. [`CostMeter`] prices the per-message usage carried by assistant
+//! events as they arrive, which gives both a running estimate (for budget
+//! checks) and a calculated cost to record next to the reported one.
+
+use std::collections::HashMap;
+
+use super::CostCalculator;
+use crate::claude::{AssistantMessage, ClaudeEvent, ExecutionUsage, MessageUsage, ModelAlias};
+
+/// Running cost of one run, built from streamed usage
+#[derive(Debug)]
+pub struct CostMeter {
+    calculator: CostCalculator,
+    model: ModelAlias,
+    /// Latest usage and model per message ID (the CLI repeats usage on every content block)
+    messages: HashMap<String, (Option<String>, MessageUsage)>,
+    /// Turns seen so far; messages without an ID are keyed by their turn
+    turn: usize,
+}
+
+impl CostMeter {
+    pub fn new(model: ModelAlias) -> Self {
+        Self::with_calculator(model, CostCalculator::with_default_pricing())
+    }
+
+    pub fn with_calculator(model: ModelAlias, calculator: CostCalculator) -> Self {
+        Self {
+            calculator,
+            model,
+            messages: HashMap::new(),
+            turn: 0,
+        }
+    }
+
+    /// Record usage from a streamed event. Returns true if the estimate changed.
+    pub fn record_event(&mut self, event: &ClaudeEvent) -> bool {
+        let ClaudeEvent::Assistant(asst) = event else {
+            // Tool results and the like end the assistant's turn
+            self.turn += 1;
+            return false;
+        };
+        let Some(AssistantMessage::Object(message)) = asst.message.as_ref() else {
+            return false;
+        };
+        let Some(ref usage) = message.usage else {
+            return false;
+        };
+
+        let key = match message.id {
+            Some(ref id) => id.clone(),
+            None => format!("turn-{}", self.turn),
+        };
+        let changed = self.messages.get(&key).map(|(_, seen)| seen) != Some(usage);
+        self.messages.insert(key, (message.model.clone(), usage.clone()));
+        changed
+    }
+
+    /// Token usage seen so far, with its cost
+    ///
+    /// Each message is priced on its own, so long-context requests are
+    /// billed at long-context rates.
+    pub fn usage(&self) -> ExecutionUsage {
+        let mut usage = ExecutionUsage::default();
+        let mut cost = 0.0;
+        for (model_id, message) in self.messages.values() {
+            usage.input_tokens += message.input_tokens;
+            usage.output_tokens += message.output_tokens;
+            usage.cache_creation_tokens += message.cache_creation_input_tokens;
+            usage.cache_creation_1h_tokens += message.cache_creation_1h_tokens();
+            usage.cache_read_tokens += message.cache_read_input_tokens;
+            cost += self
+                .calculator
+                .calculate_request_cost(&self.model, model_id.as_deref(), message)
+                .total_cost;
+        }
+        usage.set_calculated_cost(cost);
+        usage
+    }
+
+    /// Estimated cost of the run so far
+    pub fn estimated_cost(&self) -> f64 {
+        self.usage().calculated_cost_usd
+    }
+
+    /// Final usage of a run: the usage from the `result` event with the
+    /// calculated cost added, which also stands in for the cost if the CLI
+    /// reported none. Runs stopped before the `result` event get the
+    /// streamed token counts.
+    pub fn settle(&self, mut reported: ExecutionUsage) -> ExecutionUsage {
+        let streamed = self.usage();
+        if reported.reported_cost_usd.is_none() && reported.input_tokens == 0 && reported.output_tokens == 0 {
+            return ExecutionUsage {
+                duration_ms: reported.duration_ms,
+                ..streamed
+            };
+        }
+
+        let calculated = if self.messages.is_empty() {
+            self.calculator.calculate_cost(&self.model, &reported).total_cost
+        } else {
+            streamed.calculated_cost_usd
+        };
+        reported.set_calculated_cost(calculated);
+        reported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assistant_event(id: &str, input: u64, output: u64) -> ClaudeEvent {
+        let line = format!(
+            r#"{{"type":"assistant","message":{{"id":"{}","usage":{{"input_tokens":{},"output_tokens":{}}}}}}}"#,
+            id, input, output
+        );
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn test_meter_deduplicates_messages() {
+        let mut meter = CostMeter::new(ModelAlias::Sonnet);
+
+        assert!(meter.record_event(&assistant_event("msg_1", 100_000, 0)));
+        // Same message repeated for another content block
+        assert!(!meter.record_event(&assistant_event("msg_1", 100_000, 0)));
+        assert!(meter.record_event(&assistant_event("msg_2", 0, 100_000)));
+
+        let usage = meter.usage();
+        assert_eq!(usage.input_tokens, 100_000);
+        assert_eq!(usage.output_tokens, 100_000);
+        // Sonnet: $3/MTok input, $15/MTok output
+        assert!((meter.estimated_cost() - 1.8).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_meter_keys_messages_without_id_by_turn() {
+        let event = |input: u64, output: u64| -> ClaudeEvent {
+            let line = format!(
+                r#"{{"type":"assistant","message":{{"usage":{{"input_tokens":{},"output_tokens":{}}}}}}}"#,
+                input, output
+            );
+            serde_json::from_str(&line).unwrap()
+        };
+        let tool_result: ClaudeEvent = serde_json::from_str(r#"{"type":"user","message":{"content":[]}}"#).unwrap();
+        let mut meter = CostMeter::new(ModelAlias::Sonnet);
+
+        // Usage repeated on each content block of one message counts once
+        assert!(meter.record_event(&event(100_000, 10)));
+        assert!(meter.record_event(&event(100_000, 20)));
+        assert!(!meter.record_event(&event(100_000, 20)));
+        assert_eq!(meter.usage().input_tokens, 100_000);
+        assert_eq!(meter.usage().output_tokens, 20);
+
+        // The next turn is a new message
+        meter.record_event(&tool_result);
+        assert!(meter.record_event(&event(50_000, 30)));
+        assert_eq!(meter.usage().input_tokens, 150_000);
+        assert_eq!(meter.usage().output_tokens, 50);
+    }
+
+    #[test]
+    fn test_meter_prices_long_context_messages() {
+        let mut meter = CostMeter::new(ModelAlias::Sonnet);
+        meter.record_event(&assistant_event("msg_1", 1_000_000, 0));
+
+        // A 1M-token prompt is billed at the $6/MTok long-context rate
+        assert!((meter.estimated_cost() - 6.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_settle_prefers_reported_cost() {
+        let mut meter = CostMeter::new(ModelAlias::Sonnet);
+        meter.record_event(&assistant_event("msg_1", 100_000, 0));
+
+        // Killed before the result event: fall back to the streamed usage
+        let settled = meter.settle(ExecutionUsage::default());
+        assert_eq!(settled.input_tokens, 100_000);
+        assert_eq!(settled.reported_cost_usd, None);
+        assert!((settled.total_cost_usd - 0.3).abs() < 0.01);
+
+        let reported = ExecutionUsage {
+            input_tokens: 100_000,
+            total_cost_usd: 0.25,
+            reported_cost_usd: Some(0.25),
+            ..Default::default()
+        };
+        let settled = meter.settle(reported);
+        assert_eq!(settled.total_cost_usd, 0.25);
+        assert!((settled.calculated_cost_usd - 0.3).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_settle_prices_result_usage_without_cost() {
+        // The result event carried tokens but no cost, and no assistant usage was streamed
+        let meter = CostMeter::new(ModelAlias::Sonnet);
+        let reported = ExecutionUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            ..Default::default()
+        };
+        let settled = meter.settle(reported);
+        assert_eq!(settled.reported_cost_usd, None);
+        assert!((settled.total_cost_usd - 4.5).abs() < 0.01);
+        assert_eq!(settled.total_cost_usd, settled.calculated_cost_usd);
+    }
+}
@@ -1,15 +1,24 @@
 #![allow(dead_code)]
 
 mod history;
+mod ledger;
+mod meter;
+mod spend;
+mod update;
 
-pub use history::CostHistoryManager;
+pub use history::{CostEntry, CostHistoryManager, IterationCost};
+pub use ledger::{group_entries, history_of, CostFilter, CostGroupBy, GlobalCostEntry, GlobalCostLedger};
+pub use meter::CostMeter;
+pub use spend::{SpendCheck, SpendGuard, SpendLimits, SpendPeriod};
+pub use update::{diff_prices, install_prices, PriceSource, DEFAULT_PRICE_SOURCE};
 
 use anyhow::{Context, Result};
+use directories::ProjectDirs;
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::claude::{ExecutionUsage, ModelAlias};
+use crate::claude::{ExecutionUsage, MessageUsage, ModelAlias};
 
 /// Pricing information for a model
 #[derive(Debug, Clone, Deserialize)]
@@ -38,6 +47,21 @@ pub struct ModelPricing {
     pub release_date: Option<String>,
 }
 
+impl ModelPricing {
+    /// Rates per million tokens, by name
+    pub fn rates(&self) -> [(&'static str, Option<f64>); 7] {
+        [
+            ("input", Some(self.input_per_mtok)),
+            ("output", Some(self.output_per_mtok)),
+            ("cache write (5m)", self.cache_write_5m_per_mtok),
+            ("cache write (1h)", self.cache_write_1h_per_mtok),
+            ("cache read", self.cache_read_per_mtok),
+            ("long-context input", self.long_context_input_per_mtok),
+            ("long-context output", self.long_context_output_per_mtok),
+        ]
+    }
+}
+
 /// Metadata about the price file
 #[derive(Debug, Clone, Deserialize)]
 pub struct PricingMeta {
@@ -97,6 +121,56 @@ impl PricingConfig {
         Self::from_str(content).expect("Default price.toml should be valid")
     }
 
+    /// Load pricing from `path`, or the embedded pricing if there is none
+    ///
+    /// A price file that fails to load is reported and the embedded pricing
+    /// is used instead.
+    pub fn load_or_default(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::default_pricing();
+        };
+        match Self::from_file(path).and_then(|config| config.validate().map(|_| config)) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Using built-in prices: {:#}", e);
+                Self::default_pricing()
+            }
+        }
+    }
+
+    /// User-level override written by `doodoori price --update`
+    /// (e.g. `~/.config/doodoori/price.toml` on Linux)
+    pub fn user_override_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "doodoori").map(|dirs| dirs.config_dir().join("price.toml"))
+    }
+
+    /// Check that aliases resolve and rates are sensible
+    pub fn validate(&self) -> Result<()> {
+        if self.models.is_empty() {
+            anyhow::bail!("Price file defines no models");
+        }
+        for alias in ["haiku", "sonnet", "opus"] {
+            if !self.aliases.contains_key(alias) {
+                anyhow::bail!("Price file has no '{}' alias", alias);
+            }
+        }
+        for (alias, model_id) in &self.aliases {
+            if !self.models.contains_key(model_id) {
+                anyhow::bail!("Alias '{}' points to unknown model '{}'", alias, model_id);
+            }
+        }
+        for (model_id, pricing) in &self.models {
+            for (rate, value) in pricing.rates() {
+                if let Some(value) = value
+                    && !(value.is_finite() && value >= 0.0)
+                {
+                    anyhow::bail!("Model '{}' has an invalid {} rate: {}", model_id, rate, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Get the model ID for an alias
     pub fn resolve_alias(&self, alias: &str) -> Option<&str> {
         self.aliases.get(alias).map(|s| s.as_str())
@@ -143,35 +217,61 @@ impl CostCalculator {
         Self::new(PricingConfig::default_pricing())
     }
 
-    /// Calculate the cost for a given usage
-    pub fn calculate_cost(&self, model: &ModelAlias, usage: &ExecutionUsage) -> CostBreakdown {
-        let pricing = self.config.get_model_by_alias(model);
+    /// Calculator using `price_file`, or the embedded prices if there is none
+    pub fn from_price_file(price_file: Option<&Path>) -> Self {
+        Self::new(PricingConfig::load_or_default(price_file))
+    }
 
-        let (input_rate, output_rate, cache_read_rate) = pricing
-            .map(|p| {
-                (
-                    p.input_per_mtok,
-                    p.output_per_mtok,
-                    p.cache_read_per_mtok.unwrap_or(0.0),
-                )
-            })
-            .unwrap_or((0.0, 0.0, 0.0));
+    /// Pricing for a model ID reported by the CLI, or else for the alias
+    fn pricing_for(&self, model: &ModelAlias, model_id: Option<&str>) -> Option<&ModelPricing> {
+        model_id
+            .and_then(|id| self.config.get_model(id))
+            .or_else(|| self.config.get_model_by_alias(model))
+    }
 
-        let input_cost = (usage.input_tokens as f64 / 1_000_000.0) * input_rate;
-        let output_cost = (usage.output_tokens as f64 / 1_000_000.0) * output_rate;
-        let cache_read_cost = (usage.cache_read_tokens as f64 / 1_000_000.0) * cache_read_rate;
+    /// Calculate the cost for a given usage
+    ///
+    /// Usage summed over several requests cannot tell which of them were
+    /// long-context requests, so base rates apply; see
+    /// [`calculate_request_cost`](Self::calculate_request_cost).
+    pub fn calculate_cost(&self, model: &ModelAlias, usage: &ExecutionUsage) -> CostBreakdown {
+        let rates = self
+            .config
+            .get_model_by_alias(model)
+            .map(|p| Rates::of(p, false))
+            .unwrap_or_default();
+        rates.breakdown(
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_read_tokens,
+            usage.cache_creation_tokens,
+            usage.cache_creation_1h_tokens,
+        )
+    }
 
-        CostBreakdown {
-            input_cost,
-            output_cost,
-            cache_read_cost,
-            cache_write_cost: 0.0, // Not tracked in ExecutionUsage
-            total_cost: input_cost + output_cost + cache_read_cost,
-            input_tokens: usage.input_tokens,
-            output_tokens: usage.output_tokens,
-            cache_read_tokens: usage.cache_read_tokens,
-            cache_write_tokens: usage.cache_creation_tokens,
-        }
+    /// Calculate the cost of a single API request
+    ///
+    /// `model_id` is the model named in the response, if any. Requests whose
+    /// prompt exceeds [`LONG_CONTEXT_THRESHOLD`] tokens are billed at the
+    /// model's long-context rates.
+    pub fn calculate_request_cost(
+        &self,
+        model: &ModelAlias,
+        model_id: Option<&str>,
+        usage: &MessageUsage,
+    ) -> CostBreakdown {
+        let long_context = usage.prompt_tokens() > LONG_CONTEXT_THRESHOLD;
+        let rates = self
+            .pricing_for(model, model_id)
+            .map(|p| Rates::of(p, long_context))
+            .unwrap_or_default();
+        rates.breakdown(
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_read_input_tokens,
+            usage.cache_creation_input_tokens,
+            usage.cache_creation_1h_tokens(),
+        )
     }
 
     /// Estimate cost for a given number of tokens
@@ -212,6 +312,77 @@ impl CostCalculator {
     }
 }
 
+/// Prompt size above which requests are billed at long-context rates
+pub const LONG_CONTEXT_THRESHOLD: u64 = 200_000;
+
+/// Prices per million tokens applied to one request
+#[derive(Debug, Clone, Copy, Default)]
+struct Rates {
+    input: f64,
+    output: f64,
+    cache_read: f64,
+    cache_write_5m: f64,
+    cache_write_1h: f64,
+}
+
+impl Rates {
+    fn of(pricing: &ModelPricing, long_context: bool) -> Self {
+        let base = Self {
+            input: pricing.input_per_mtok,
+            output: pricing.output_per_mtok,
+            cache_read: pricing.cache_read_per_mtok.unwrap_or(0.0),
+            cache_write_5m: pricing.cache_write_5m_per_mtok.unwrap_or(pricing.input_per_mtok),
+            cache_write_1h: pricing
+                .cache_write_1h_per_mtok
+                .or(pricing.cache_write_5m_per_mtok)
+                .unwrap_or(pricing.input_per_mtok),
+        };
+        let Some(long_input) = pricing.long_context_input_per_mtok.filter(|_| long_context) else {
+            return base;
+        };
+
+        // Cache prices are multiples of the input price, so they scale with it
+        let scale = if base.input > 0.0 { long_input / base.input } else { 1.0 };
+        Self {
+            input: long_input,
+            output: pricing.long_context_output_per_mtok.unwrap_or(base.output),
+            cache_read: base.cache_read * scale,
+            cache_write_5m: base.cache_write_5m * scale,
+            cache_write_1h: base.cache_write_1h * scale,
+        }
+    }
+
+    fn breakdown(
+        &self,
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_read_tokens: u64,
+        cache_write_tokens: u64,
+        cache_write_1h_tokens: u64,
+    ) -> CostBreakdown {
+        let per_token = |tokens: u64, rate: f64| (tokens as f64 / 1_000_000.0) * rate;
+        let cache_write_1h_tokens = cache_write_1h_tokens.min(cache_write_tokens);
+
+        let input_cost = per_token(input_tokens, self.input);
+        let output_cost = per_token(output_tokens, self.output);
+        let cache_read_cost = per_token(cache_read_tokens, self.cache_read);
+        let cache_write_cost = per_token(cache_write_tokens - cache_write_1h_tokens, self.cache_write_5m)
+            + per_token(cache_write_1h_tokens, self.cache_write_1h);
+
+        CostBreakdown {
+            input_cost,
+            output_cost,
+            cache_read_cost,
+            cache_write_cost,
+            total_cost: input_cost + output_cost + cache_read_cost + cache_write_cost,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens,
+            cache_write_tokens,
+        }
+    }
+}
+
 /// Detailed cost breakdown
 #[derive(Debug, Clone, Default)]
 pub struct CostBreakdown {
@@ -308,10 +479,7 @@ mod tests {
         let usage = ExecutionUsage {
             input_tokens: 1_000_000, // 1M tokens
             output_tokens: 100_000,  // 100K tokens
-            cache_creation_tokens: 0,
-            cache_read_tokens: 0,
-            total_cost_usd: 0.0,
-            duration_ms: 0,
+            ..Default::default()
         };
 
         let breakdown = calc.calculate_cost(&ModelAlias::Sonnet, &usage);
@@ -322,6 +490,59 @@ mod tests {
         assert!((breakdown.total_cost - 4.5).abs() < 0.01);
     }
 
+    #[test]
+    fn test_calculate_cost_with_cache() {
+        let calc = CostCalculator::with_default_pricing();
+        let usage = ExecutionUsage {
+            cache_creation_tokens: 1_000_000,
+            cache_read_tokens: 1_000_000,
+            ..Default::default()
+        };
+
+        let breakdown = calc.calculate_cost(&ModelAlias::Sonnet, &usage);
+
+        // Sonnet 4.5: $3.75/MTok cache write (5m), $0.30/MTok cache read
+        assert!((breakdown.cache_write_cost - 3.75).abs() < 0.01);
+        assert!((breakdown.cache_read_cost - 0.30).abs() < 0.01);
+        assert!((breakdown.total_cost - 4.05).abs() < 0.01);
+
+        // Writes to the 1-hour cache cost $6/MTok
+        let usage = ExecutionUsage {
+            cache_creation_tokens: 1_000_000,
+            cache_creation_1h_tokens: 500_000,
+            ..Default::default()
+        };
+        let breakdown = calc.calculate_cost(&ModelAlias::Sonnet, &usage);
+        assert!((breakdown.cache_write_cost - 4.875).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_calculate_request_cost_long_context() {
+        let calc = CostCalculator::with_default_pricing();
+        let usage = MessageUsage {
+            input_tokens: 100_000,
+            output_tokens: 100_000,
+            ..Default::default()
+        };
+        let normal = calc.calculate_request_cost(&ModelAlias::Sonnet, None, &usage);
+        // Sonnet 4.5: $3/MTok input, $15/MTok output
+        assert!((normal.total_cost - 1.8).abs() < 0.01);
+
+        // Over 200K prompt tokens: $6/MTok input, $22.50/MTok output, cache reads doubled
+        let usage = MessageUsage {
+            cache_read_input_tokens: 150_000,
+            ..usage
+        };
+        let long = calc.calculate_request_cost(&ModelAlias::Sonnet, None, &usage);
+        assert!((long.input_cost - 0.6).abs() < 0.01);
+        assert!((long.output_cost - 2.25).abs() < 0.01);
+        assert!((long.cache_read_cost - 0.09).abs() < 0.01);
+
+        // The model named in the response takes precedence over the alias
+        let opus = calc.calculate_request_cost(&ModelAlias::Sonnet, Some("claude-opus-4-5-20251101"), &usage);
+        assert!((opus.input_cost - 0.5).abs() < 0.01);
+    }
+
     #[test]
     fn test_estimate_cost() {
         let calc = CostCalculator::with_default_pricing();
@@ -0,0 +1,272 @@
+//! Daily and monthly spend caps
+//!
+//! Caps apply to the current project (its `.doodoori` cost history) or to
+//! all projects (the user-level cost ledger). The cost of a running task is
+//! not recorded until it finishes, so checks add it on top.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::format_cost;
+use super::history::{CostHistory, CostHistoryManager};
+use super::ledger::{history_of, GlobalCostLedger};
+
+/// Spend caps from the `[cost]` section of doodoori.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpendLimits {
+    /// Maximum spend per day in this project (USD, UTC days)
+    pub daily_budget_usd: Option<f64>,
+    /// Maximum spend per calendar month in this project (USD)
+    pub monthly_budget_usd: Option<f64>,
+    /// Maximum spend per day across all projects (USD)
+    pub global_daily_budget_usd: Option<f64>,
+    /// Maximum spend per calendar month across all projects (USD)
+    pub global_monthly_budget_usd: Option<f64>,
+    /// Fraction of a cap at which to warn (0.8 = at 80%)
+    pub warn_threshold: f64,
+}
+
+impl Default for SpendLimits {
+    fn default() -> Self {
+        Self {
+            daily_budget_usd: None,
+            monthly_budget_usd: None,
+            global_daily_budget_usd: None,
+            global_monthly_budget_usd: None,
+            warn_threshold: 0.8,
+        }
+    }
+}
+
+impl SpendLimits {
+    /// Whether any cap is set
+    pub fn is_enabled(&self) -> bool {
+        self.has_caps(false) || self.has_caps(true)
+    }
+
+    /// Project caps, or the caps across all projects
+    fn caps(&self, global: bool) -> [(SpendPeriod, Option<f64>); 2] {
+        if global {
+            [
+                (SpendPeriod::Day, self.global_daily_budget_usd),
+                (SpendPeriod::Month, self.global_monthly_budget_usd),
+            ]
+        } else {
+            [(SpendPeriod::Day, self.daily_budget_usd), (SpendPeriod::Month, self.monthly_budget_usd)]
+        }
+    }
+
+    fn has_caps(&self, global: bool) -> bool {
+        self.caps(global).iter().any(|(_, limit)| limit.is_some())
+    }
+}
+
+/// Period a cap covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpendPeriod {
+    Day,
+    Month,
+}
+
+/// Spend measured against one cap
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendCap {
+    /// True for caps across all projects
+    pub global: bool,
+    pub period: SpendPeriod,
+    pub spent_usd: f64,
+    pub limit_usd: f64,
+}
+
+impl SpendCap {
+    /// Identifies the cap regardless of the amounts
+    pub fn key(&self) -> (bool, SpendPeriod) {
+        (self.global, self.period)
+    }
+}
+
+impl fmt::Display for SpendCap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let period = match self.period {
+            SpendPeriod::Day => "Daily",
+            SpendPeriod::Month => "Monthly",
+        };
+        let scope = if self.global { "all projects" } else { "this project" };
+        write!(
+            f,
+            "{} spend of {} is {} of {}",
+            period,
+            scope,
+            format_cost(self.spent_usd),
+            format_cost(self.limit_usd)
+        )
+    }
+}
+
+/// Outcome of a spend check
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpendCheck {
+    /// Caps that are reached
+    pub exceeded: Vec<SpendCap>,
+    /// Caps past the warning threshold but not reached
+    pub warnings: Vec<SpendCap>,
+}
+
+impl SpendCheck {
+    pub fn is_exceeded(&self) -> bool {
+        !self.exceeded.is_empty()
+    }
+}
+
+/// Checks recorded spend against [`SpendLimits`]
+#[derive(Debug, Clone)]
+pub struct SpendGuard {
+    limits: SpendLimits,
+    project_dir: PathBuf,
+    ledger: Option<PathBuf>,
+}
+
+impl SpendGuard {
+    /// Guard for a project; global caps are checked against `ledger`
+    pub fn new(limits: SpendLimits, project_dir: &Path, ledger: Option<PathBuf>) -> Self {
+        Self {
+            limits,
+            project_dir: project_dir.to_path_buf(),
+            ledger,
+        }
+    }
+
+    pub fn limits(&self) -> &SpendLimits {
+        &self.limits
+    }
+
+    /// Compare spend so far, plus `pending_usd` not yet recorded, with the caps
+    pub fn check(&self, pending_usd: f64) -> Result<SpendCheck> {
+        let mut check = SpendCheck::default();
+        if self.limits.has_caps(false) {
+            let history = CostHistoryManager::for_project(&self.project_dir)?;
+            self.measure(&mut check, false, history.history(), pending_usd);
+        }
+        if self.limits.has_caps(true) {
+            match self.ledger {
+                Some(ref path) => {
+                    let history = history_of(&GlobalCostLedger::new(path).entries()?);
+                    self.measure(&mut check, true, &history, pending_usd);
+                }
+                None => tracing::warn!("Global spend caps need the global cost ledger ([cost] global_ledger)"),
+            }
+        }
+        Ok(check)
+    }
+
+    fn measure(&self, check: &mut SpendCheck, global: bool, history: &CostHistory, pending_usd: f64) {
+        for (period, limit) in self.limits.caps(global) {
+            let Some(limit_usd) = limit else {
+                continue;
+            };
+            let recorded = match period {
+                SpendPeriod::Day => history.get_today_summary().map(|s| s.total_cost_usd).unwrap_or(0.0),
+                SpendPeriod::Month => history.get_monthly_total(),
+            };
+            let cap = SpendCap {
+                global,
+                period,
+                spent_usd: recorded + pending_usd,
+                limit_usd,
+            };
+            if cap.spent_usd >= limit_usd {
+                check.exceeded.push(cap);
+            } else if cap.spent_usd >= limit_usd * self.limits.warn_threshold {
+                check.warnings.push(cap);
+            }
+        }
+    }
+
+    /// Fail if a cap is already reached (used before starting work)
+    pub fn ensure_available(&self) -> Result<SpendCheck> {
+        let check = self.check(0.0)?;
+        if let Some(cap) = check.exceeded.first() {
+            anyhow::bail!("Spend cap reached: {}", cap);
+        }
+        Ok(check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::CostEntry;
+    use chrono::Utc;
+    use tempfile::tempdir;
+
+    fn entry(cost: f64) -> CostEntry {
+        CostEntry {
+            task_id: "task-1".to_string(),
+            timestamp: Utc::now(),
+            model: "sonnet".to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: cost,
+            status: "Completed".to_string(),
+            description: None,
+            iterations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_spend_guard_project_caps() {
+        let dir = tempdir().unwrap();
+        let mut costs = CostHistoryManager::for_project(dir.path()).unwrap();
+        costs.record_entry(entry(4.0)).unwrap();
+
+        let limits = SpendLimits {
+            daily_budget_usd: Some(5.0),
+            monthly_budget_usd: Some(100.0),
+            ..Default::default()
+        };
+        let guard = SpendGuard::new(limits, dir.path(), None);
+        assert!(guard.limits().is_enabled());
+        assert!(!SpendLimits::default().is_enabled());
+
+        // $4 of $5 is past the 80% threshold
+        let check = guard.ensure_available().unwrap();
+        assert_eq!(check.warnings.len(), 1);
+        assert_eq!(check.warnings[0].period, SpendPeriod::Day);
+        assert_eq!(check.warnings[0].to_string(), "Daily spend of this project is $4.00 of $5.00");
+
+        // The running task's cost counts too
+        let check = guard.check(1.5).unwrap();
+        assert!(check.is_exceeded());
+        assert!(check.warnings.is_empty());
+
+        costs.record_entry(entry(1.0)).unwrap();
+        let err = guard.ensure_available().unwrap_err();
+        assert!(err.to_string().contains("Spend cap reached: Daily spend of this project"));
+    }
+
+    #[test]
+    fn test_spend_guard_global_caps() {
+        let dir = tempdir().unwrap();
+        let ledger_path = dir.path().join("cost_ledger.jsonl");
+        let ledger = GlobalCostLedger::new(&ledger_path);
+        ledger.record(&dir.path().join("other"), &entry(30.0)).unwrap();
+
+        let limits = SpendLimits {
+            global_monthly_budget_usd: Some(25.0),
+            ..Default::default()
+        };
+        let project = dir.path().join("project");
+        let check = SpendGuard::new(limits.clone(), &project, Some(ledger_path)).check(0.0).unwrap();
+        assert_eq!(check.exceeded.len(), 1);
+        assert!(check.exceeded[0].global);
+        assert_eq!(check.exceeded[0].key(), (true, SpendPeriod::Month));
+
+        // Without a ledger global caps cannot be checked
+        assert!(!SpendGuard::new(limits, &project, None).check(0.0).unwrap().is_exceeded());
+    }
+}
@@ -0,0 +1,230 @@
+//! Updating prices from a pricing source
+//!
+//! `doodoori price --update` fetches a price.toml document from a URL or a
+//! local file, checks it against the [`ModelPricing`] schema and installs it
+//! as the user-level override of the embedded prices.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::{format_cost, ModelPricing, PricingConfig};
+use crate::utils::fs::atomic_write;
+
+/// Where `price --update` fetches prices from by default
+pub const DEFAULT_PRICE_SOURCE: &str = "https://raw.githubusercontent.com/devlikebear/doodoori/main/price.toml";
+
+/// Location of a price.toml document
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceSource {
+    Url(String),
+    File(PathBuf),
+}
+
+impl PriceSource {
+    /// `http(s)://` sources are fetched, anything else is read as a file
+    pub fn parse(source: &str) -> Self {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            Self::Url(source.to_string())
+        } else {
+            Self::File(PathBuf::from(source.strip_prefix("file://").unwrap_or(source)))
+        }
+    }
+
+    /// Fetch the document
+    pub async fn fetch(&self) -> Result<String> {
+        match self {
+            Self::Url(url) => fetch_url(url).await,
+            Self::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read price file: {}", path.display())),
+        }
+    }
+
+    /// Fetch the document and check that it is a usable price table
+    pub async fn load(&self) -> Result<(String, PricingConfig)> {
+        let content = self.fetch().await?;
+        let config = PricingConfig::from_str(&content).with_context(|| format!("Invalid prices from {}", self))?;
+        config.validate().with_context(|| format!("Invalid prices from {}", self))?;
+        Ok((content, config))
+    }
+}
+
+impl fmt::Display for PriceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Url(url) => write!(f, "{}", url),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[cfg(feature = "price-update")]
+async fn fetch_url(url: &str) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .unwrap_or_default();
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", url))?
+        .error_for_status()
+        .with_context(|| format!("Failed to fetch {}", url))?;
+    response.text().await.context("Failed to read price document")
+}
+
+#[cfg(not(feature = "price-update"))]
+async fn fetch_url(url: &str) -> Result<String> {
+    anyhow::bail!(
+        "Fetching prices from {} requires the price-update feature (cargo build --features price-update); \
+         pass a local file with --source instead",
+        url
+    )
+}
+
+/// Difference between two price tables
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceChange {
+    Added(String),
+    Removed(String),
+    Rate {
+        model: String,
+        rate: &'static str,
+        old: Option<f64>,
+        new: Option<f64>,
+    },
+}
+
+impl fmt::Display for PriceChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |value: &Option<f64>| value.map(format_cost).unwrap_or_else(|| "-".to_string());
+        match self {
+            Self::Added(model) => write!(f, "+ {}", model),
+            Self::Removed(model) => write!(f, "- {}", model),
+            Self::Rate { model, rate: name, old, new } => {
+                write!(f, "~ {} {}: {} -> {}", model, name, rate(old), rate(new))
+            }
+        }
+    }
+}
+
+/// Models added or removed and rates changed from `old` to `new`, by model ID
+pub fn diff_prices(old: &PricingConfig, new: &PricingConfig) -> Vec<PriceChange> {
+    let mut ids: Vec<&String> = old.models.keys().chain(new.models.keys()).collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut changes = Vec::new();
+    for id in ids {
+        match (old.models.get(id), new.models.get(id)) {
+            (None, Some(_)) => changes.push(PriceChange::Added(id.clone())),
+            (Some(_), None) => changes.push(PriceChange::Removed(id.clone())),
+            (Some(before), Some(after)) => changes.extend(rate_changes(id, before, after)),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn rate_changes(model: &str, before: &ModelPricing, after: &ModelPricing) -> Vec<PriceChange> {
+    before
+        .rates()
+        .into_iter()
+        .zip(after.rates())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((rate, old), (_, new))| PriceChange::Rate {
+            model: model.to_string(),
+            rate,
+            old,
+            new,
+        })
+        .collect()
+}
+
+/// Write a checked price document to `path`
+pub fn install_prices(content: &str, path: &Path) -> Result<()> {
+    atomic_write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Embedded prices with Sonnet 4.5 input raised and Haiku 3.5 dropped
+    fn updated_prices() -> String {
+        let mut content = include_str!("../../price.toml").replacen("input_per_mtok = 3.00", "input_per_mtok = 3.50", 1);
+        let start = content.find("[models.\"claude-3-5-haiku-20241022\"]").unwrap();
+        let end = start + content[start..].find("\n\n").unwrap();
+        content.replace_range(start..end, "");
+        content.replace("haiku_legacy = \"claude-3-5-haiku-20241022\"\n", "")
+    }
+
+    #[test]
+    fn test_price_source_parse() {
+        assert_eq!(
+            PriceSource::parse("https://example.com/price.toml"),
+            PriceSource::Url("https://example.com/price.toml".to_string())
+        );
+        assert_eq!(PriceSource::parse("file:///tmp/price.toml"), PriceSource::File(PathBuf::from("/tmp/price.toml")));
+        assert_eq!(PriceSource::parse("price.toml"), PriceSource::File(PathBuf::from("price.toml")));
+    }
+
+    #[tokio::test]
+    async fn test_update_from_file() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("price.toml");
+        std::fs::write(&source, updated_prices()).unwrap();
+
+        let (content, updated) = PriceSource::File(source).load().await.unwrap();
+        let changes = diff_prices(&PricingConfig::default_pricing(), &updated);
+        assert!(changes.contains(&PriceChange::Removed("claude-3-5-haiku-20241022".to_string())));
+        let change = changes.iter().find(|c| matches!(c, PriceChange::Rate { .. })).unwrap();
+        assert_eq!(change.to_string(), "~ claude-sonnet-4-5-20250929 input: $3.00 -> $3.50");
+
+        let target = dir.path().join("config").join("price.toml");
+        install_prices(&content, &target).unwrap();
+        let calculator = super::super::CostCalculator::from_price_file(Some(&target));
+        assert_eq!(calculator.get_rates(&crate::claude::ModelAlias::Sonnet), Some((3.5, 15.0)));
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_invalid_prices() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("price.toml");
+
+        std::fs::write(&source, "[meta]\nversion = \"1\"\n").unwrap();
+        assert!(PriceSource::File(source.clone()).load().await.is_err());
+
+        let negative = include_str!("../../price.toml").replacen("output_per_mtok = 15.00", "output_per_mtok = -1.0", 1);
+        std::fs::write(&source, negative).unwrap();
+        let err = PriceSource::File(source.clone()).load().await.unwrap_err();
+        assert!(format!("{:#}", err).contains("invalid output rate"));
+
+        // A broken price file falls back to the embedded prices
+        let calculator = super::super::CostCalculator::from_price_file(Some(&source));
+        assert_eq!(calculator.get_rates(&crate::claude::ModelAlias::Sonnet), Some((3.0, 15.0)));
+    }
+
+    #[cfg(feature = "price-update")]
+    #[tokio::test]
+    async fn test_update_from_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Local stand-in for the pricing server
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/price.toml", listener.local_addr().unwrap());
+        let body = updated_prices();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let response = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let (_, updated) = PriceSource::parse(&url).load().await.unwrap();
+        assert_eq!(updated.get_model_by_alias(&crate::claude::ModelAlias::Sonnet).unwrap().input_per_mtok, 3.5);
+    }
+}
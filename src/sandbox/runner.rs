@@ -7,6 +7,7 @@ use uuid::Uuid;
 
 use super::config::{NetworkMode, SandboxConfig};
 use super::container::{ContainerManager, ContainerState, ExecOutput};
+use crate::claude::{AgentBackend, AgentExecution, AgentRequest, CancelSignal, ClaudeRunner};
 
 /// Result of a sandbox execution
 #[derive(Debug)]
@@ -219,6 +220,40 @@ impl SandboxRunner {
     }
 }
 
+/// Runs Claude Code in the sandbox container with streamed output
+///
+/// Requires [`SandboxRunner::init`]. Commands run in the container's
+/// workspace, so the request's host working directory is not used.
+#[async_trait::async_trait]
+impl AgentBackend for SandboxRunner {
+    async fn execute(&self, request: AgentRequest, cancel: CancelSignal) -> Result<AgentExecution> {
+        let container_id = self
+            .container_id
+            .as_ref()
+            .context("Sandbox not initialized. Call init() first.")?;
+
+        let args = ClaudeRunner::new(request.config.clone()).build_args(&request.prompt);
+        tracing::debug!("Executing claude in sandbox with args: {:?}", args);
+
+        let child = tokio::process::Command::new("docker")
+            .arg("exec")
+            .arg(container_id)
+            .arg(&request.config.command.binary)
+            .args(&args)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .stdin(std::process::Stdio::null())
+            .spawn()
+            .context("Failed to execute docker exec")?;
+
+        crate::claude::stream_child(child, request.config.cost_meter(), cancel, request.task_id, None, request.transcript)
+    }
+
+    fn name(&self) -> &str {
+        "sandbox"
+    }
+}
+
 impl Drop for SandboxRunner {
     fn drop(&mut self) {
         // Note: Async cleanup cannot be done in Drop
@@ -1,14 +1,21 @@
 //! State management module for task persistence and resume functionality
+//!
+//! Each task keeps its files in `.doodoori/tasks/<task-id>/`, so several
+//! tasks can run in one project at the same time.
 #![allow(dead_code)]
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-use crate::claude::ExecutionUsage;
+use crate::claude::{ExecutionUsage, ToolStats};
+use crate::pricing::{format_cost, format_tokens};
+use crate::utils::format_duration;
+use crate::utils::fs::{atomic_write, FileLock};
 
 /// Task execution status
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -58,6 +65,144 @@ impl From<&ExecutionUsage> for TokenUsage {
     }
 }
 
+/// What one iteration did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationRecord {
+    /// 1-based iteration number
+    pub iteration: u32,
+    /// Model used for the iteration
+    pub model: String,
+    /// When the iteration started
+    #[serde(default)]
+    pub started_at: DateTime<Utc>,
+    /// When the iteration finished
+    #[serde(default)]
+    pub finished_at: DateTime<Utc>,
+    /// Token usage of the iteration
+    pub usage: TokenUsage,
+    /// Cost of the iteration in USD
+    pub cost_usd: f64,
+    /// Cost reported by the CLI (absent if it reported none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_cost_usd: Option<f64>,
+    /// Cost calculated from token usage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calculated_cost_usd: Option<f64>,
+    /// Whether the iteration completed the task
+    pub completed: bool,
+    /// Tool calls made during the iteration
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, ToolStats>,
+    /// Errors seen (failed tool calls, verification, execution errors)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Git HEAD after the iteration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_head: Option<String>,
+    /// Checkpoint commit recorded after the iteration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<String>,
+    /// Claude session the iteration ran in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl IterationRecord {
+    /// Record for an iteration that started at `started_at` and just finished
+    pub fn new(iteration: u32, model: impl Into<String>, started_at: DateTime<Utc>) -> Self {
+        Self {
+            iteration,
+            model: model.into(),
+            started_at,
+            finished_at: Utc::now(),
+            usage: TokenUsage::default(),
+            cost_usd: 0.0,
+            reported_cost_usd: None,
+            calculated_cost_usd: None,
+            completed: false,
+            tools: BTreeMap::new(),
+            errors: Vec::new(),
+            git_head: None,
+            checkpoint: None,
+            session_id: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: &ExecutionUsage) -> Self {
+        self.usage = TokenUsage::from(usage);
+        self.cost_usd = usage.total_cost_usd;
+        self.reported_cost_usd = usage.reported_cost_usd;
+        self.calculated_cost_usd = Some(usage.calculated_cost_usd);
+        self
+    }
+
+    pub fn with_completed(mut self, completed: bool) -> Self {
+        self.completed = completed;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: BTreeMap<String, ToolStats>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_errors(mut self, errors: Vec<String>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn with_git_head(mut self, head: Option<String>) -> Self {
+        self.git_head = head;
+        self
+    }
+
+    pub fn with_checkpoint(mut self, checkpoint: Option<String>) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    pub fn with_session_id(mut self, session_id: Option<String>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// Wall-clock duration of the iteration
+    pub fn duration_ms(&self) -> u64 {
+        (self.finished_at - self.started_at).num_milliseconds().max(0) as u64
+    }
+
+    /// One timeline line, e.g.
+    /// `#3  12:04:10  sonnet  42s  $0.0210  in 12.0K / out 1.5K  abc1234  completed`
+    pub fn timeline_line(&self) -> String {
+        let head = self.git_head.as_deref().map(|h| &h[..7.min(h.len())]).unwrap_or("-");
+        let outcome = if self.completed {
+            "completed"
+        } else if !self.errors.is_empty() {
+            "errors"
+        } else {
+            "continued"
+        };
+        // Costs the CLI did not report are marked as calculated from tokens
+        let estimated = if self.reported_cost_usd.is_none() && self.calculated_cost_usd.is_some() {
+            "~"
+        } else {
+            ""
+        };
+        format!(
+            "#{:<3} {}  {:<6}  {:>6}  {:>9}  in {} / out {}  {}  {}",
+            self.iteration,
+            self.started_at.format("%H:%M:%S"),
+            self.model,
+            format_duration(self.duration_ms()),
+            format!("{}{}", estimated, format_cost(self.cost_usd)),
+            format_tokens(self.usage.input_tokens),
+            format_tokens(self.usage.output_tokens),
+            head,
+            outcome,
+        )
+    }
+}
+
 /// Represents the state of a task execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskState {
@@ -91,6 +236,9 @@ pub struct TaskState {
     pub error: Option<String>,
     /// Final output if completed
     pub final_output: Option<String>,
+    /// Per-iteration history
+    #[serde(default)]
+    pub iterations: Vec<IterationRecord>,
 }
 
 impl TaskState {
@@ -113,6 +261,7 @@ impl TaskState {
             working_dir: None,
             error: None,
             final_output: None,
+            iterations: Vec::new(),
         }
     }
 
@@ -136,6 +285,25 @@ impl TaskState {
         self.updated_at = Utc::now();
     }
 
+    /// Usage recorded so far, as a starting total for a resumed run
+    pub fn execution_usage(&self) -> ExecutionUsage {
+        ExecutionUsage {
+            input_tokens: self.usage.input_tokens,
+            output_tokens: self.usage.output_tokens,
+            cache_creation_tokens: self.usage.cache_creation_tokens,
+            cache_read_tokens: self.usage.cache_read_tokens,
+            total_cost_usd: self.total_cost_usd,
+            duration_ms: self.duration_ms,
+            ..Default::default()
+        }
+    }
+
+    /// Record a finished iteration
+    pub fn record_iteration(&mut self, record: IterationRecord) {
+        self.iterations.push(record);
+        self.updated_at = Utc::now();
+    }
+
     /// Set session ID for resume
     pub fn set_session_id(&mut self, session_id: String) {
         self.session_id = Some(session_id);
@@ -176,7 +344,40 @@ impl TaskState {
     }
 }
 
+/// Directory under .doodoori holding one directory per task
+pub const TASKS_DIR: &str = "tasks";
+
+/// State file inside a task directory
+const STATE_FILE: &str = "state.json";
+
+/// Directory of a task's files (`.doodoori/tasks/<task-id>/`)
+pub fn task_dir(project_dir: &Path, task_id: &str) -> PathBuf {
+    project_dir.join(".doodoori").join(TASKS_DIR).join(task_id)
+}
+
+/// Whether a process with this ID exists
+pub fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // Signal 0 only checks that the process exists
+        let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+        result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
 /// State manager for persisting task states
+///
+/// Every task has its own directory, `.doodoori/tasks/<task-id>/`, holding
+/// its state (`state.json`), the log of its Claude runs (`task.log`), the
+/// console output of a detached run (`output.log`), the ID of the process
+/// running it (`pid`) and its transcripts, so any number of tasks can be
+/// active in one project. State files are replaced atomically under a file
+/// lock. Completed and failed tasks are archived to `.doodoori/history/`.
 pub struct StateManager {
     /// Base directory for state files (.doodoori)
     base_dir: PathBuf,
@@ -189,11 +390,23 @@ impl StateManager {
         fs::create_dir_all(&base_dir).context("Failed to create .doodoori directory")?;
         fs::create_dir_all(base_dir.join("history"))
             .context("Failed to create history directory")?;
+        fs::create_dir_all(base_dir.join(TASKS_DIR))
+            .context("Failed to create tasks directory")?;
         Ok(Self { base_dir })
     }
 
-    /// Get the path to the current state file
-    fn state_file_path(&self) -> PathBuf {
+    /// Directory of a task's files
+    pub fn task_dir(&self, task_id: &str) -> PathBuf {
+        self.base_dir.join(TASKS_DIR).join(task_id)
+    }
+
+    /// Get the path to a task's state file
+    fn state_file_path(&self, task_id: &str) -> PathBuf {
+        self.task_dir(task_id).join(STATE_FILE)
+    }
+
+    /// Single state file written by earlier versions
+    fn legacy_state_file_path(&self) -> PathBuf {
         self.base_dir.join("state.json")
     }
 
@@ -202,39 +415,147 @@ impl StateManager {
         self.base_dir.join("history").join(format!("{}.json", task_id))
     }
 
-    /// Save the current task state
+    fn pid_file_path(&self, task_id: &str) -> PathBuf {
+        self.task_dir(task_id).join("pid")
+    }
+
+    /// Log of a task's Claude runs
+    pub fn log_path(&self, task_id: &str) -> PathBuf {
+        self.task_dir(task_id).join("task.log")
+    }
+
+    /// Console output of a detached run
+    pub fn output_log_path(&self, task_id: &str) -> PathBuf {
+        self.task_dir(task_id).join("output.log")
+    }
+
+    /// Save a task's state
+    ///
+    /// While the task is running, the ID of the current process is recorded
+    /// next to it.
     pub fn save_state(&self, state: &TaskState) -> Result<()> {
-        let path = self.state_file_path();
+        let path = self.state_file_path(&state.task_id);
+        let _lock = FileLock::exclusive(&path).context("Failed to lock state file")?;
+        self.write_state(&path, state)
+    }
+
+    fn write_state(&self, path: &Path, state: &TaskState) -> Result<()> {
         let json = serde_json::to_string_pretty(state)?;
-        fs::write(&path, json).context("Failed to write state file")?;
+        atomic_write(path, json).context("Failed to write state file")?;
+
+        let pid_path = self.pid_file_path(&state.task_id);
+        if state.status == TaskStatus::Running {
+            atomic_write(&pid_path, std::process::id().to_string()).context("Failed to write pid file")?;
+        } else if pid_path.exists() {
+            fs::remove_file(&pid_path).ok();
+        }
+        self.remove_legacy_state(&state.task_id);
         tracing::debug!("Saved task state to {:?}", path);
         Ok(())
     }
 
-    /// Load the current task state
-    pub fn load_state(&self) -> Result<Option<TaskState>> {
-        let path = self.state_file_path();
+    /// Change a task's state while holding its lock
+    ///
+    /// Returns the updated state, or None if the task is not active.
+    pub fn update_state<F: FnOnce(&mut TaskState)>(&self, task_id: &str, update: F) -> Result<Option<TaskState>> {
+        let path = self.state_file_path(task_id);
+        let _lock = FileLock::exclusive(&path).context("Failed to lock state file")?;
+        let Some(mut state) = Self::read_state(&path)? else {
+            return Ok(None);
+        };
+        update(&mut state);
+        self.write_state(&path, &state)?;
+        Ok(Some(state))
+    }
+
+    fn read_state(path: &Path) -> Result<Option<TaskState>> {
         if !path.exists() {
             return Ok(None);
         }
-        let json = fs::read_to_string(&path).context("Failed to read state file")?;
-        let state: TaskState = serde_json::from_str(&json).context("Failed to parse state file")?;
+        let json = fs::read_to_string(path).context("Failed to read state file")?;
+        let state: TaskState = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse state file: {}", path.display()))?;
         Ok(Some(state))
     }
 
+    /// Remove `.doodoori/state.json` once its task is stored per task
+    fn remove_legacy_state(&self, task_id: &str) {
+        let path = self.legacy_state_file_path();
+        if let Ok(Some(state)) = Self::read_state(&path)
+            && state.task_id == task_id
+        {
+            fs::remove_file(&path).ok();
+        }
+    }
+
+    /// Load the most recently updated active task
+    pub fn load_state(&self) -> Result<Option<TaskState>> {
+        Ok(self.list_active_tasks()?.into_iter().next())
+    }
+
+    /// Load an active task by ID or unique prefix
+    pub fn load_task(&self, task_id: &str) -> Result<Option<TaskState>> {
+        let path = self.state_file_path(task_id);
+        if path.exists() {
+            return Self::read_state(&path);
+        }
+
+        let mut matches: Vec<TaskState> = self
+            .list_active_tasks()?
+            .into_iter()
+            .filter(|s| s.task_id.starts_with(task_id))
+            .collect();
+        match matches.len() {
+            0 => Ok(None),
+            1 => Ok(matches.pop()),
+            n => anyhow::bail!("Task ID '{}' is ambiguous ({} active tasks match)", task_id, n),
+        }
+    }
+
+    /// Load a task by ID or prefix, active tasks first, then history
+    pub fn find_task(&self, task_id: &str) -> Result<Option<TaskState>> {
+        match self.load_task(task_id)? {
+            Some(state) => Ok(Some(state)),
+            None => self.load_from_history(task_id),
+        }
+    }
+
+    /// List all active (not archived) tasks, most recently updated first
+    pub fn list_active_tasks(&self) -> Result<Vec<TaskState>> {
+        let mut tasks = Vec::new();
+
+        let tasks_dir = self.base_dir.join(TASKS_DIR);
+        if tasks_dir.exists() {
+            for entry in fs::read_dir(&tasks_dir)? {
+                let path = entry?.path().join(STATE_FILE);
+                match Self::read_state(&path) {
+                    Ok(Some(state)) => tasks.push(state),
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Skipping task state: {:#}", e),
+                }
+            }
+        }
+
+        if let Ok(Some(state)) = Self::read_state(&self.legacy_state_file_path())
+            && !tasks.iter().any(|t| t.task_id == state.task_id)
+        {
+            tasks.push(state);
+        }
+
+        // Sort by updated_at descending
+        tasks.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+        Ok(tasks)
+    }
+
     /// Archive a completed task to history
     pub fn archive_task(&self, state: &TaskState) -> Result<()> {
         let path = self.history_file_path(&state.task_id);
         let json = serde_json::to_string_pretty(state)?;
-        fs::write(&path, json).context("Failed to write history file")?;
+        atomic_write(&path, json).context("Failed to write history file")?;
         tracing::debug!("Archived task {} to history", state.short_id());
 
-        // Remove current state file
-        let state_path = self.state_file_path();
-        if state_path.exists() {
-            fs::remove_file(&state_path).ok();
-        }
-        Ok(())
+        // The task is no longer active; its logs and transcripts stay
+        self.clear_state(&state.task_id)
     }
 
     /// Load a task from history
@@ -265,16 +586,13 @@ impl StateManager {
         Ok(None)
     }
 
-    /// List all resumable tasks (current + interrupted in history)
+    /// List all resumable tasks (active + interrupted in history)
     pub fn list_resumable_tasks(&self) -> Result<Vec<TaskState>> {
-        let mut tasks = Vec::new();
-
-        // Check current state
-        if let Some(state) = self.load_state()? {
-            if state.can_resume() {
-                tasks.push(state);
-            }
-        }
+        let mut tasks: Vec<TaskState> = self
+            .list_active_tasks()?
+            .into_iter()
+            .filter(|state| state.can_resume())
+            .collect();
 
         // Check history for interrupted tasks
         let history_dir = self.base_dir.join("history");
@@ -284,7 +602,7 @@ impl StateManager {
                 if entry.path().extension().map_or(false, |e| e == "json") {
                     let json = fs::read_to_string(entry.path())?;
                     if let Ok(state) = serde_json::from_str::<TaskState>(&json) {
-                        if state.can_resume() {
+                        if state.can_resume() && !tasks.iter().any(|t| t.task_id == state.task_id) {
                             tasks.push(state);
                         }
                     }
@@ -320,15 +638,34 @@ impl StateManager {
         Ok(tasks)
     }
 
-    /// Clear current state
-    pub fn clear_state(&self) -> Result<()> {
-        let path = self.state_file_path();
-        if path.exists() {
-            fs::remove_file(&path)?;
+    /// Remove a task's active state and pid file
+    pub fn clear_state(&self, task_id: &str) -> Result<()> {
+        let path = self.state_file_path(task_id);
+        let _lock = FileLock::exclusive(&path).context("Failed to lock state file")?;
+        for path in [path.clone(), self.pid_file_path(task_id)] {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
         }
+        self.remove_legacy_state(task_id);
         Ok(())
     }
 
+    /// Process recorded for a task, if it is still alive
+    pub fn running_pid(&self, task_id: &str) -> Option<u32> {
+        let pid = self.recorded_pid(task_id)?;
+        process_alive(pid).then_some(pid)
+    }
+
+    /// Process ID recorded for a running task (alive or not)
+    pub fn recorded_pid(&self, task_id: &str) -> Option<u32> {
+        fs::read_to_string(self.pid_file_path(task_id))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
     /// Get the base directory path
     pub fn base_dir(&self) -> &Path {
         &self.base_dir
@@ -372,6 +709,50 @@ mod tests {
         assert_eq!(state.final_output, Some("Done".to_string()));
     }
 
+    #[test]
+    fn test_iteration_record_timeline() {
+        let started_at = Utc::now() - chrono::Duration::seconds(42);
+        let mut tools = BTreeMap::new();
+        tools.insert("Bash".to_string(), ToolStats { calls: 2, failures: 1, duration_ms: 300 });
+        let usage = ExecutionUsage {
+            input_tokens: 12_000,
+            output_tokens: 1_500,
+            total_cost_usd: 0.021,
+            reported_cost_usd: Some(0.021),
+            calculated_cost_usd: 0.02,
+            ..Default::default()
+        };
+
+        let record = IterationRecord::new(3, "sonnet", started_at)
+            .with_usage(&usage)
+            .with_completed(true)
+            .with_tools(tools)
+            .with_git_head(Some("abc1234def5678".to_string()));
+        assert!(record.duration_ms() >= 42_000);
+
+        let line = record.timeline_line();
+        assert!(line.starts_with("#3 "));
+        assert!(line.contains("sonnet"));
+        assert!(line.contains("42s"));
+        assert!(line.contains("$0.021"));
+        assert!(line.contains("in 12.0K / out 1.5K"));
+        assert!(line.contains("abc1234  completed"));
+        assert_eq!(record.calculated_cost_usd, Some(0.02));
+
+        // Costs the CLI did not report are marked
+        let unreported = ExecutionUsage {
+            reported_cost_usd: None,
+            ..usage
+        };
+        let record = IterationRecord::new(4, "sonnet", started_at).with_usage(&unreported);
+        assert!(record.timeline_line().contains("~$0.021"));
+
+        // Records written before timing and tool tracking still load
+        let old = r#"{"iteration":1,"model":"haiku","usage":{"input_tokens":1,"output_tokens":2,"cache_creation_tokens":0,"cache_read_tokens":0},"cost_usd":0.1,"completed":false}"#;
+        let record: IterationRecord = serde_json::from_str(old).unwrap();
+        assert!(record.tools.is_empty() && record.git_head.is_none());
+    }
+
     #[test]
     fn test_task_state_can_resume() {
         let mut state = TaskState::new("test".to_string(), "sonnet".to_string(), 50);
@@ -428,6 +809,89 @@ mod tests {
         assert_eq!(loaded.task_id, state.task_id);
     }
 
+    #[test]
+    fn test_state_manager_multiple_active_tasks() {
+        let dir = tempdir().unwrap();
+        let manager = StateManager::new(dir.path()).unwrap();
+
+        let mut first = TaskState::new("First".to_string(), "sonnet".to_string(), 10);
+        first.task_id = "aaaa-1111".to_string();
+        first.start();
+        let mut second = TaskState::new("Second".to_string(), "sonnet".to_string(), 10);
+        second.task_id = "bbbb-2222".to_string();
+        second.start();
+        manager.save_state(&first).unwrap();
+        manager.save_state(&second).unwrap();
+
+        // Both tasks are tracked, each with its own directory and process
+        assert_eq!(manager.list_active_tasks().unwrap().len(), 2);
+        assert_eq!(manager.list_resumable_tasks().unwrap().len(), 2);
+        assert!(manager.task_dir("aaaa-1111").join("state.json").exists());
+        assert_eq!(manager.running_pid("bbbb-2222"), Some(std::process::id()));
+        assert_eq!(manager.load_task("aaaa").unwrap().unwrap().prompt, "First");
+
+        let updated = manager
+            .update_state("aaaa-1111", |state| state.interrupt())
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.status, TaskStatus::Interrupted);
+        assert!(manager.recorded_pid("aaaa-1111").is_none());
+
+        second.complete(None);
+        manager.archive_task(&second).unwrap();
+        let active = manager.list_active_tasks().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].task_id, "aaaa-1111");
+        assert_eq!(manager.find_task("bbbb").unwrap().unwrap().status, TaskStatus::Completed);
+    }
+
+    #[test]
+    fn test_state_manager_concurrent_writers() {
+        let dir = tempdir().unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let project_dir = dir.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let manager = StateManager::new(&project_dir).unwrap();
+                    let mut state = TaskState::new(format!("Task {}", i), "sonnet".to_string(), 20);
+                    state.start();
+                    for iteration in 1..=20 {
+                        state.update_iteration(iteration);
+                        manager.save_state(&state).unwrap();
+                        // Another task's state is never seen half-written
+                        for task in manager.list_active_tasks().unwrap() {
+                            assert!(task.current_iteration <= 20);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let manager = StateManager::new(dir.path()).unwrap();
+        let tasks = manager.list_active_tasks().unwrap();
+        assert_eq!(tasks.len(), 4);
+        assert!(tasks.iter().all(|t| t.current_iteration == 20));
+    }
+
+    #[test]
+    fn test_state_manager_reads_legacy_state() {
+        let dir = tempdir().unwrap();
+        let manager = StateManager::new(dir.path()).unwrap();
+        let mut state = TaskState::new("Old".to_string(), "sonnet".to_string(), 10);
+        state.interrupt();
+        fs::write(dir.path().join(".doodoori/state.json"), serde_json::to_string(&state).unwrap()).unwrap();
+
+        assert_eq!(manager.load_state().unwrap().unwrap().task_id, state.task_id);
+
+        // Saving moves it into its task directory
+        manager.save_state(&state).unwrap();
+        assert!(!dir.path().join(".doodoori/state.json").exists());
+        assert_eq!(manager.list_active_tasks().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_state_manager_prefix_lookup() {
         let dir = tempdir().unwrap();
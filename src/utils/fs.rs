@@ -0,0 +1,175 @@
+//! Crash-safe file writes and advisory file locks
+#![allow(dead_code)]
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Replace a file's contents atomically
+///
+/// The data is written to a temporary file next to `path`, flushed to disk
+/// and renamed over `path`, so readers see either the old or the new
+/// contents, never a partial write.
+pub fn atomic_write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let dir = parent_dir(path);
+    fs::create_dir_all(dir)?;
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Append one line to a file and flush it to disk
+///
+/// If an earlier write was cut short and left the file without a trailing
+/// newline, the line is started on a fresh line so it stays readable.
+/// Callers appending from several processes should hold a [`FileLock`].
+pub fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    fs::create_dir_all(parent_dir(path))?;
+    let mut file = OpenOptions::new().create(true).append(true).read(true).open(path)?;
+
+    let mut data = String::with_capacity(line.len() + 2);
+    let len = file.metadata()?.len();
+    if len > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            data.push('\n');
+        }
+    }
+    data.push_str(line);
+    data.push('\n');
+    file.write_all(data.as_bytes())?;
+    file.sync_data()
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Advisory lock (`flock`) guarding a file, released when dropped
+///
+/// The lock is taken on a separate `<file>.lock` file, so it survives the
+/// guarded file being replaced by [`atomic_write`].
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Wait for an exclusive lock on `path`
+    pub fn exclusive(path: &Path) -> io::Result<Self> {
+        let file = Self::open(path)?;
+        file.lock()?;
+        Ok(Self { file })
+    }
+
+    /// Wait for a shared lock on `path`
+    pub fn shared(path: &Path) -> io::Result<Self> {
+        let file = Self::open(path)?;
+        file.lock_shared()?;
+        Ok(Self { file })
+    }
+
+    fn open(path: &Path) -> io::Result<File> {
+        fs::create_dir_all(parent_dir(path))?;
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(lock_path(path))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Lock file used for `path`
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/state.json");
+
+        atomic_write(&path, "{\"a\":1}").unwrap();
+        atomic_write(&path, "{\"a\":2}").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"a\":2}");
+        let names: Vec<String> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["state.json"]);
+    }
+
+    #[test]
+    fn test_append_line_after_torn_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+
+        append_line(&path, "one").unwrap();
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"tw").unwrap();
+        append_line(&path, "two").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntw\ntwo\n");
+    }
+
+    #[test]
+    fn test_file_lock_serializes_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counter");
+        atomic_write(&path, "0").unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        let _lock = FileLock::exclusive(&path).unwrap();
+                        let n: u32 = fs::read_to_string(&path).unwrap().parse().unwrap();
+                        atomic_write(&path, (n + 1).to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "100");
+        assert!(lock_path(&path).ends_with("counter.lock"));
+    }
+}
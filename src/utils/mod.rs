@@ -1 +1,66 @@
-// Utils module - stub
+//! Small helpers shared across modules
+
+pub mod fs;
+
+/// Truncate a string to a maximum number of characters (Unicode-safe),
+/// ending it with "..." when it was shortened
+pub fn truncate_str(s: &str, max_chars: usize) -> String {
+    if s.chars().count() > max_chars {
+        let truncated: String = s.chars().take(max_chars.saturating_sub(3)).collect();
+        format!("{}...", truncated)
+    } else {
+        s.to_string()
+    }
+}
+
+/// The last `max_chars` characters of a string (Unicode-safe)
+pub fn tail_str(s: &str, max_chars: usize) -> &str {
+    if max_chars == 0 {
+        return "";
+    }
+    match s.char_indices().nth_back(max_chars - 1) {
+        Some((start, _)) => &s[start..],
+        None => s,
+    }
+}
+
+/// Short human-readable duration (`42s`, `3m 5s`, `1h 2m`)
+pub fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_str() {
+        assert_eq!(truncate_str("short", 10), "short");
+        assert_eq!(truncate_str("abcdefghij", 8), "abcde...");
+        // Multi-byte characters are never split
+        assert_eq!(truncate_str("한국어 프롬프트입니다", 6), "한국어...");
+    }
+
+    #[test]
+    fn test_tail_str() {
+        assert_eq!(tail_str("hello", 10), "hello");
+        assert_eq!(tail_str("hello", 3), "llo");
+        assert_eq!(tail_str("hello", 0), "");
+        assert_eq!(tail_str("출력 끝부분", 3), "끝부분");
+        assert_eq!(tail_str("✓ done 🎉", 2), " 🎉");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42_500), "42s");
+        assert_eq!(format_duration(185_000), "3m 5s");
+        assert_eq!(format_duration(3_720_000), "1h 2m");
+    }
+}
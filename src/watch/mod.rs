@@ -6,9 +6,13 @@ use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
 
+use crate::claude::AgentBackend;
+use crate::utils::truncate_str;
+
 /// Events emitted by the file watcher
 #[derive(Debug, Clone)]
 pub enum WatchEvent {
@@ -221,6 +225,7 @@ pub struct WatchRunner {
     config: WatchConfig,
     task_prompt: String,
     task_config: WatchTaskConfig,
+    backend: Option<Arc<dyn AgentBackend>>,
 }
 
 /// Configuration for watch task execution
@@ -260,9 +265,16 @@ impl WatchRunner {
             config,
             task_prompt,
             task_config,
+            backend: None,
         }
     }
 
+    /// Use a different agent backend for task runs
+    pub fn with_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
     /// Run the watch loop
     pub async fn run(&self) -> Result<()> {
         
@@ -283,11 +295,7 @@ impl WatchRunner {
         );
         println!("  Patterns: {:?}", self.config.patterns);
         println!("  Directory: {}", self.config.base_dir.display());
-        println!("  Task: {}", if self.task_prompt.len() > 50 {
-            format!("{}...", &self.task_prompt[..47])
-        } else {
-            self.task_prompt.clone()
-        });
+        println!("  Task: {}", truncate_str(&self.task_prompt, 50));
         println!();
         println!("{} Waiting for file changes... (Ctrl+C to stop)", Emoji("⏳", "[WAIT]"));
 
@@ -386,21 +394,29 @@ impl WatchRunner {
     /// Execute the task
     async fn execute_task(&self) -> Result<()> {
         use crate::claude::ModelAlias;
-        use crate::loop_engine::{LoopConfig, LoopEngine, LoopEvent};
+        use crate::loop_engine::{CompletionStrategy, LoopConfig, LoopEngine, LoopEvent};
         use console::Emoji;
         use indicatif::{ProgressBar, ProgressStyle};
 
         // Get prompt from spec file or use direct prompt
-        let prompt = if let Some(ref spec_path) = self.task_config.spec_file {
+        let (prompt, completion_strategy, model_policy) = if let Some(ref spec_path) = self.task_config.spec_file {
             let spec = crate::instructions::SpecParser::parse_file(spec_path)?;
-            spec.to_prompt()
+            (
+                spec.to_prompt(),
+                CompletionStrategy::from_promise(&spec.effective_completion_promise()),
+                spec.model_policy,
+            )
         } else {
-            self.task_prompt.clone()
+            (self.task_prompt.clone(), CompletionStrategy::default(), None)
         };
 
         // Parse model
         let model: ModelAlias = self.task_config.model.parse().unwrap_or(ModelAlias::Sonnet);
 
+        // Refuse to start once a spend cap is reached
+        let doodoori_config = crate::config::DoodooriConfig::load().unwrap_or_default();
+        doodoori_config.cost.ensure_spend_available(&self.config.base_dir)?;
+
         // Build loop config
         let loop_config = LoopConfig {
             max_iterations: self.task_config.max_iterations,
@@ -409,10 +425,24 @@ impl WatchRunner {
             working_dir: Some(self.config.base_dir.clone()),
             yolo_mode: self.task_config.yolo_mode,
             readonly: self.task_config.readonly,
+            completion_strategy,
+            prompt_templates: doodoori_config.prompts.to_prompt_templates(&self.config.base_dir),
+            price_file: doodoori_config.pricing_file(),
+            agent_command: doodoori_config.agent,
+            record_transcripts: doodoori_config.logging.transcripts,
+            stall: doodoori_config.stall.clone(),
+            model_policy: model_policy.unwrap_or(doodoori_config.model_policy),
+            record_progress: doodoori_config.logging.journal,
+            checkpoints: doodoori_config.git.checkpoints,
+            cost_ledger: doodoori_config.cost.ledger_path(),
+            spend_limits: doodoori_config.cost.limits.clone(),
             ..Default::default()
         };
 
-        let engine = LoopEngine::new(loop_config);
+        let mut engine = LoopEngine::new(loop_config);
+        if let Some(ref backend) = self.backend {
+            engine = engine.with_backend(Arc::clone(backend));
+        }
 
         // Create progress bar
         let progress = ProgressBar::new(self.task_config.max_iterations as u64);
@@ -424,7 +454,7 @@ impl WatchRunner {
         );
 
         // Execute
-        let (mut rx, handle) = engine.execute(&prompt).await?;
+        let (mut rx, handle, _control) = engine.execute(&prompt).await?;
 
         let mut total_cost = 0.0f64;
 
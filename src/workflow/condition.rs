@@ -0,0 +1,240 @@
+//! `when:` conditions of workflow steps
+//!
+//! A condition tests the status or outputs of upstream steps:
+//!
+//! ```text
+//! steps.tests.status == 'failed'
+//! steps.backend_api.port != '' && steps.lint.status != 'skipped'
+//! steps.review.output contains 'LGTM' || steps.review.status == 'skipped'
+//! ```
+//!
+//! References use the same `steps.<step>.<field>` form as prompt templates.
+//! A bare reference is true when the value is non-empty and not `false`.
+//! `&&` binds tighter than `||`; there are no parentheses.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use super::outputs::step_key;
+
+/// A parsed `when:` condition, in disjunctive form
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// Alternatives joined by `||`, each a list of clauses joined by `&&`
+    any_of: Vec<Vec<Clause>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    negate: bool,
+    step: String,
+    field: String,
+    test: Test,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Test {
+    Truthy,
+    Eq(String),
+    Ne(String),
+    Contains(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ref(String, String),
+    Literal(String),
+    Eq,
+    Ne,
+    Contains,
+    Not,
+    And,
+    Or,
+}
+
+impl Condition {
+    /// Parse a condition expression
+    pub fn parse(expr: &str) -> Result<Self> {
+        let tokens = tokenize(expr).with_context(|| format!("Invalid condition: {}", expr))?;
+        let mut any_of = Vec::new();
+        for alternative in tokens.split(|t| *t == Token::Or) {
+            let mut clauses = Vec::new();
+            for clause in alternative.split(|t| *t == Token::And) {
+                clauses.push(parse_clause(clause).with_context(|| format!("Invalid condition: {}", expr))?);
+            }
+            any_of.push(clauses);
+        }
+        Ok(Self { any_of })
+    }
+
+    /// `(step key, field)` of every value the condition reads
+    pub fn references(&self) -> Vec<(String, String)> {
+        self.any_of
+            .iter()
+            .flatten()
+            .map(|c| (c.step.clone(), c.field.clone()))
+            .collect()
+    }
+
+    /// Evaluate against step values keyed by step key, then field
+    pub fn evaluate(&self, values: &HashMap<String, HashMap<String, String>>) -> bool {
+        self.any_of.iter().any(|clauses| {
+            clauses.iter().all(|clause| {
+                let value = values
+                    .get(&clause.step)
+                    .and_then(|step| step.get(&clause.field))
+                    .map(String::as_str)
+                    .unwrap_or("");
+                let result = match clause.test {
+                    Test::Truthy => !value.is_empty() && value != "false",
+                    Test::Eq(ref expected) => value == expected,
+                    Test::Ne(ref expected) => value != expected,
+                    Test::Contains(ref needle) => value.contains(needle.as_str()),
+                };
+                result != clause.negate
+            })
+        })
+    }
+}
+
+fn parse_clause(tokens: &[Token]) -> Result<Clause> {
+    let (negate, tokens) = match tokens.split_first() {
+        Some((Token::Not, rest)) => (true, rest),
+        _ => (false, tokens),
+    };
+    let (step, field) = match tokens.first() {
+        Some(Token::Ref(step, field)) => (step.clone(), field.clone()),
+        _ => anyhow::bail!("expected a steps.<step>.<field> reference"),
+    };
+    let test = match &tokens[1..] {
+        [] => Test::Truthy,
+        [Token::Eq, Token::Literal(value)] => Test::Eq(value.clone()),
+        [Token::Ne, Token::Literal(value)] => Test::Ne(value.clone()),
+        [Token::Contains, Token::Literal(value)] => Test::Contains(value.clone()),
+        _ => anyhow::bail!("expected ==, != or contains followed by a value after steps.{}.{}", step, field),
+    };
+    Ok(Clause { negate, step, field, test })
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => literal.push(ch),
+                        None => anyhow::bail!("unterminated string"),
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            '=' | '!' | '&' | '|' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let token = match (c, next) {
+                    ('=', Some('=')) => Token::Eq,
+                    ('!', Some('=')) => Token::Ne,
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    ('!', _) => {
+                        tokens.push(Token::Not);
+                        continue;
+                    }
+                    _ => anyhow::bail!("unexpected '{}'", c),
+                };
+                chars.next();
+                tokens.push(token);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "'\"=!&|".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(word_token(word)?);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn word_token(word: String) -> Result<Token> {
+    if word == "contains" {
+        return Ok(Token::Contains);
+    }
+    match word.strip_prefix("steps.").and_then(|rest| rest.rsplit_once('.')) {
+        Some((step, field)) if !step.is_empty() && !field.is_empty() => Ok(Token::Ref(step_key(step), field.to_string())),
+        _ if word.starts_with("steps.") => anyhow::bail!("expected steps.<step>.<field>, found '{}'", word),
+        _ => Ok(Token::Literal(word)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(entries: &[(&str, &str, &str)]) -> HashMap<String, HashMap<String, String>> {
+        let mut values: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (step, field, value) in entries {
+            values
+                .entry(step.to_string())
+                .or_default()
+                .insert(field.to_string(), value.to_string());
+        }
+        values
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let condition = Condition::parse("steps.Unit-Tests.status == 'failed' || !steps.lint.output").unwrap();
+        assert_eq!(
+            condition.references(),
+            vec![
+                ("unit_tests".to_string(), "status".to_string()),
+                ("lint".to_string(), "output".to_string()),
+            ]
+        );
+
+        assert!(Condition::parse("steps.tests.status ==").is_err());
+        assert!(Condition::parse("'failed' == steps.tests.status").is_err());
+        assert!(Condition::parse("steps.tests == failed").is_err());
+        assert!(Condition::parse("steps.tests.status == 'failed").is_err());
+        assert!(Condition::parse("steps.tests.status = failed").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_condition() {
+        let values = values(&[
+            ("tests", "status", "failed"),
+            ("review", "status", "completed"),
+            ("review", "output", "Looks good. LGTM"),
+            ("flags", "deploy", "false"),
+        ]);
+        let eval = |expr: &str| Condition::parse(expr).unwrap().evaluate(&values);
+
+        assert!(eval("steps.tests.status == 'failed'"));
+        assert!(eval("steps.tests.status == failed"));
+        assert!(!eval("steps.tests.status != \"failed\""));
+        assert!(eval("steps.review.output contains 'LGTM'"));
+        assert!(eval("steps.review.output && steps.tests.status == 'failed'"));
+        assert!(!eval("steps.flags.deploy"));
+        assert!(eval("!steps.flags.deploy"));
+        // Missing values are empty
+        assert!(!eval("steps.docs.output"));
+        // && binds tighter than ||
+        assert!(eval("steps.docs.output && steps.tests.status == 'failed' || steps.review.status == 'completed'"));
+    }
+}
@@ -5,12 +5,20 @@
 
 #![allow(dead_code)]
 
+mod condition;
+mod outputs;
+
+pub use condition::Condition;
+pub use outputs::{capture_outputs, render_prompt, step_key, StepOutputSpec};
+
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 
 use crate::claude::ModelAlias;
+use crate::hooks::{HookType, HooksConfig};
+use crate::loop_engine::{ModelPolicy, VerificationConfig};
 
 /// Global settings for a workflow
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -26,6 +34,15 @@ pub struct WorkflowGlobalSettings {
     pub completion_promise: String,
     /// Total budget for the workflow in USD
     pub budget_usd: Option<f64>,
+    /// Default verification command for steps without explicit `verify`
+    #[serde(default)]
+    pub verify: Option<String>,
+    /// Worktrees, integration branch and pull requests
+    #[serde(default)]
+    pub git: WorkflowGitSettings,
+    /// Workflow hooks, and loop hooks of every step (replacing doodoori.toml's)
+    #[serde(default)]
+    pub hooks: HooksConfig,
 }
 
 fn default_model() -> String {
@@ -47,10 +64,59 @@ impl Default for WorkflowGlobalSettings {
             max_parallel_workers: default_max_workers(),
             completion_promise: default_completion_promise(),
             budget_usd: None,
+            verify: None,
+            git: WorkflowGitSettings::default(),
+            hooks: HooksConfig::default(),
+        }
+    }
+}
+
+/// Git settings of a workflow
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WorkflowGitSettings {
+    /// Run steps in their own worktree and branch, merged into the
+    /// integration branch when they complete
+    #[serde(default)]
+    pub worktrees: bool,
+    /// Prefix of the integration and step branches
+    #[serde(default = "default_branch_prefix")]
+    pub branch_prefix: String,
+    /// Integration branch (default: the prefix followed by the workflow name)
+    #[serde(default)]
+    pub integration_branch: Option<String>,
+    /// Pull requests opened when the workflow completes
+    #[serde(default)]
+    pub pull_requests: PullRequestMode,
+}
+
+fn default_branch_prefix() -> String {
+    "workflow/".to_string()
+}
+
+impl Default for WorkflowGitSettings {
+    fn default() -> Self {
+        Self {
+            worktrees: false,
+            branch_prefix: default_branch_prefix(),
+            integration_branch: None,
+            pull_requests: PullRequestMode::default(),
         }
     }
 }
 
+/// Which pull requests a workflow opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullRequestMode {
+    /// No pull requests
+    #[default]
+    None,
+    /// One pull request from the integration branch
+    Workflow,
+    /// One pull request from each merged step branch
+    Step,
+}
+
 /// A step in a workflow
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WorkflowStep {
@@ -65,6 +131,9 @@ pub struct WorkflowStep {
     /// Model to use for this step
     #[serde(default)]
     pub model: Option<String>,
+    /// Per-iteration model rules for this step
+    #[serde(default)]
+    pub model_policy: Option<ModelPolicy>,
     /// Parallel group (steps in same group run concurrently)
     #[serde(default)]
     pub parallel_group: u32,
@@ -77,12 +146,110 @@ pub struct WorkflowStep {
     /// Budget limit for this step in USD
     #[serde(default)]
     pub budget_usd: Option<f64>,
+    /// Command that must succeed before the step is accepted as complete
+    #[serde(default)]
+    pub verify: Option<String>,
+    /// Named outputs captured when the step completes
+    #[serde(default)]
+    pub outputs: HashMap<String, StepOutputSpec>,
+    /// Condition on upstream steps; the step is skipped when it is false
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Retries when the step fails
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Step to run when this step fails
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    /// Keep running the workflow when this step fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// What to do when a dependency failed or was skipped
+    #[serde(default)]
+    pub on_dependency_failure: DependencyFailure,
+    /// Directory the step runs in, relative to the working tree
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    /// Run the step in its own worktree (default: `global.git.worktrees`)
+    #[serde(default)]
+    pub worktree: Option<bool>,
+    /// Skip all permissions (default: the `--yolo` flag)
+    #[serde(default)]
+    pub yolo: Option<bool>,
+    /// Only allow read-only tools
+    #[serde(default)]
+    pub readonly: bool,
+    /// Additional tools to allow (comma-separated, like `--allow`)
+    #[serde(default)]
+    pub allowed_tools: Option<String>,
+    /// System prompt file, relative to the directory the workflow runs in
+    #[serde(default)]
+    pub system_prompt: Option<PathBuf>,
+    /// Loop hooks of this step, replacing the global ones of the same type
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
 }
 
 fn default_max_iterations() -> u32 {
     50
 }
 
+/// Hooks run by the workflow rather than by a step's loop
+pub const WORKFLOW_HOOKS: [HookType; 3] = [
+    HookType::OnStepComplete,
+    HookType::OnWorkflowComplete,
+    HookType::OnWorkflowError,
+];
+
+/// Retries of a failed step
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// Seconds before the first retry, doubled for each further retry
+    #[serde(default = "default_retry_backoff")]
+    pub backoff_secs: u64,
+    /// Run each retry on the next larger model (haiku -> sonnet -> opus)
+    #[serde(default)]
+    pub escalate: bool,
+}
+
+fn default_retry_attempts() -> u32 {
+    2
+}
+
+fn default_retry_backoff() -> u64 {
+    10
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after failed attempt `attempt` (1-based)
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        std::time::Duration::from_secs(self.backoff_secs.saturating_mul(factor))
+    }
+
+    /// Model for attempt `attempt` (1-based) of a step that starts on `model`
+    pub fn model_for(&self, model: ModelAlias, attempt: u32) -> ModelAlias {
+        if !self.escalate {
+            return model;
+        }
+        (1..attempt).fold(model, |model, _| model.escalate().unwrap_or(model))
+    }
+}
+
+/// What happens to a step when one of its dependencies failed or was skipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyFailure {
+    /// Skip the step (and, in turn, its own dependents)
+    #[default]
+    Skip,
+    /// Fail the step
+    Fail,
+}
+
 /// Workflow definition loaded from YAML
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WorkflowDefinition {
@@ -136,6 +303,100 @@ impl WorkflowDefinition {
         // Check for circular dependencies
         self.check_circular_dependencies()?;
 
+        // Check failure handlers
+        for step in &self.steps {
+            let Some(ref handler_name) = step.on_failure else { continue };
+            let handler = self.get_step(handler_name).ok_or_else(|| {
+                anyhow::anyhow!("Step '{}' has unknown failure handler '{}'", step.name, handler_name)
+            })?;
+            if handler.name == step.name {
+                anyhow::bail!("Step '{}' cannot be its own failure handler", step.name);
+            }
+            if !handler.depends_on.is_empty() {
+                anyhow::bail!("Failure handler '{}' cannot have depends_on", handler.name);
+            }
+            if handler.on_failure.is_some() {
+                anyhow::bail!("Failure handler '{}' cannot have its own on_failure", handler.name);
+            }
+            if let Some(dependent) = self.steps.iter().find(|s| s.depends_on.contains(handler_name)) {
+                anyhow::bail!(
+                    "Step '{}' depends on failure handler '{}', which only runs when '{}' fails",
+                    dependent.name,
+                    handler.name,
+                    step.name
+                );
+            }
+        }
+
+        // Check retries and that prompts and conditions only reference upstream steps
+        for step in &self.steps {
+            for (name, spec) in &step.outputs {
+                spec.validate()
+                    .with_context(|| format!("Step '{}' output '{}'", step.name, name))?;
+            }
+
+            if step.retry.as_ref().is_some_and(|retry| retry.attempts == 0) {
+                anyhow::bail!("Step '{}' retry attempts must be at least 1", step.name);
+            }
+
+            let mut references = Vec::new();
+            if let Some(ref prompt) = step.prompt {
+                references.extend(outputs::references(prompt));
+            }
+            if let Some(ref when) = step.when {
+                let condition = Condition::parse(when)
+                    .with_context(|| format!("Step '{}' has an invalid when condition", step.name))?;
+                references.extend(condition.references());
+            }
+
+            for (key, output) in references {
+                let upstream = self
+                    .steps
+                    .iter()
+                    .find(|s| step_key(&s.name) == key)
+                    .ok_or_else(|| anyhow::anyhow!(
+                        "Step '{}' references outputs of unknown step '{}'",
+                        step.name,
+                        key
+                    ))?;
+                if !self.is_upstream(&upstream.name, step) {
+                    anyhow::bail!(
+                        "Step '{}' references outputs of '{}', which it does not depend on",
+                        step.name,
+                        upstream.name
+                    );
+                }
+                if !outputs::is_builtin(&output) && !upstream.outputs.contains_key(&output) {
+                    anyhow::bail!(
+                        "Step '{}' references output '{}' that step '{}' does not declare",
+                        step.name,
+                        output,
+                        upstream.name
+                    );
+                }
+            }
+        }
+
+        // Check working directories
+        for step in &self.steps {
+            let Some(ref dir) = step.working_dir else { continue };
+            let inside = dir.components().all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+            if !inside {
+                anyhow::bail!(
+                    "Step '{}' working_dir must be a relative path inside the working tree: {}",
+                    step.name,
+                    dir.display()
+                );
+            }
+        }
+
+        // Check permission overrides
+        for step in &self.steps {
+            if step.yolo == Some(true) && step.readonly {
+                anyhow::bail!("Step '{}' cannot be both yolo and readonly", step.name);
+            }
+        }
+
         // Warnings
         for step in &self.steps {
             if step.prompt.is_none() && step.spec.is_none() {
@@ -144,6 +405,24 @@ impl WorkflowDefinition {
                     step.name
                 ));
             }
+
+            let workflow_hooks: Vec<&str> = step
+                .hooks
+                .iter()
+                .flat_map(HooksConfig::configured)
+                .filter(|hook_type| WORKFLOW_HOOKS.contains(hook_type))
+                .map(|hook_type| hook_type.as_str())
+                .collect();
+            if !workflow_hooks.is_empty() {
+                warnings.push(format!(
+                    "Step '{}' hooks {} have no effect outside global hooks",
+                    step.name,
+                    workflow_hooks.join(", ")
+                ));
+            }
+        }
+        if self.global.git.pull_requests != PullRequestMode::None && !self.uses_worktrees() {
+            warnings.push("pull_requests has no effect unless steps run in worktrees".to_string());
         }
 
         Ok(warnings)
@@ -195,6 +474,44 @@ impl WorkflowDefinition {
         Ok(false)
     }
 
+    /// Whether `step` depends on `upstream`, directly or through other steps.
+    /// A failure handler depends on the steps it handles.
+    fn is_upstream(&self, upstream: &str, step: &WorkflowStep) -> bool {
+        let handled = self
+            .steps
+            .iter()
+            .filter(|s| s.on_failure.as_deref() == Some(step.name.as_str()))
+            .map(|s| &s.name);
+        step.depends_on.iter().chain(handled).any(|dep| {
+            dep == upstream || self.get_step(dep).is_some_and(|s| self.is_upstream(upstream, s))
+        })
+    }
+
+    /// Whether two steps may run at the same time, i.e. neither runs
+    /// downstream of the other
+    pub fn can_overlap(&self, step: &WorkflowStep, other: &WorkflowStep) -> bool {
+        step.name != other.name && !self.is_upstream(&step.name, other) && !self.is_upstream(&other.name, step)
+    }
+
+    /// Find a step by name
+    pub fn get_step(&self, name: &str) -> Option<&WorkflowStep> {
+        self.steps.iter().find(|s| s.name == name)
+    }
+
+    /// Whether a step only runs as another step's failure handler
+    pub fn is_failure_handler(&self, name: &str) -> bool {
+        self.steps.iter().any(|s| s.on_failure.as_deref() == Some(name))
+    }
+
+    /// Model for an attempt (1-based) of a step, escalated on retries if configured
+    pub fn get_attempt_model(&self, step: &WorkflowStep, attempt: u32) -> ModelAlias {
+        let model = self.get_step_model(step);
+        match step.retry {
+            Some(ref retry) => retry.model_for(model, attempt),
+            None => model,
+        }
+    }
+
     /// Get effective model for a step
     pub fn get_step_model(&self, step: &WorkflowStep) -> ModelAlias {
         let model_str = step
@@ -209,12 +526,61 @@ impl WorkflowDefinition {
             _ => ModelAlias::Sonnet,
         }
     }
+
+    /// Get effective verification command for a step
+    pub fn get_step_verification(&self, step: &WorkflowStep) -> Option<VerificationConfig> {
+        step.verify
+            .as_ref()
+            .or(self.global.verify.as_ref())
+            .filter(|cmd| !cmd.trim().is_empty())
+            .map(|cmd| VerificationConfig::new(cmd.trim()))
+    }
+
+    /// Hooks of a step: `base` (doodoori.toml's) with the global and the
+    /// step's own hooks in place of the ones they configure
+    pub fn get_step_hooks(&self, step: &WorkflowStep, base: HooksConfig) -> HooksConfig {
+        let hooks = base.merged_with(&self.global.hooks);
+        match step.hooks {
+            Some(ref overrides) => hooks.merged_with(overrides),
+            None => hooks,
+        }
+    }
+
+    /// Whether a step runs in its own worktree
+    pub fn uses_worktree(&self, step: &WorkflowStep) -> bool {
+        step.worktree.unwrap_or(self.global.git.worktrees)
+    }
+
+    /// Whether any step runs in its own worktree
+    pub fn uses_worktrees(&self) -> bool {
+        self.steps.iter().any(|step| self.uses_worktree(step))
+    }
+
+    /// Branch that worktree steps are merged into
+    pub fn integration_branch(&self) -> String {
+        self.global.git.integration_branch.clone().unwrap_or_else(|| {
+            format!("{}{}", self.global.git.branch_prefix, crate::git::sanitize_branch_name(&self.name))
+        })
+    }
+}
+
+/// What to do with a step whose dependencies have finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepDecision {
+    /// Run the step
+    Run,
+    /// Skip the step, with the reason
+    Skip(String),
+    /// Fail the step without running it, with the reason
+    Fail(String),
 }
 
 /// DAG-based scheduler for workflow execution
 pub struct DagScheduler {
     workflow: WorkflowDefinition,
     completed: HashSet<String>,
+    failed: HashSet<String>,
+    skipped: HashSet<String>,
     running: HashSet<String>,
 }
 
@@ -224,33 +590,77 @@ impl DagScheduler {
         Self {
             workflow,
             completed: HashSet::new(),
+            failed: HashSet::new(),
+            skipped: HashSet::new(),
             running: HashSet::new(),
         }
     }
 
-    /// Get the next batch of steps that can be executed
+    /// Get the steps whose dependencies have finished, lower parallel
+    /// groups first
+    ///
+    /// Failure handlers are never ready; they run when the step they
+    /// handle fails.
     pub fn get_ready_steps(&self) -> Vec<&WorkflowStep> {
-        self.workflow
+        let mut ready: Vec<&WorkflowStep> = self
+            .workflow
             .steps
             .iter()
+            .filter(|step| !self.workflow.is_failure_handler(&step.name))
             .filter(|step| {
-                // Not already completed or running
-                !self.completed.contains(&step.name) && !self.running.contains(&step.name)
+                // Not already finished or running
+                !self.is_finished(&step.name) && !self.running.contains(&step.name)
             })
             .filter(|step| {
-                // All dependencies are completed
+                // All dependencies have finished
                 step.depends_on
                     .iter()
-                    .all(|dep| self.completed.contains(dep))
+                    .all(|dep| self.is_finished(dep))
             })
-            .collect()
+            .collect();
+        ready.sort_by_key(|step| step.parallel_group);
+        ready
+    }
+
+    fn is_finished(&self, step_name: &str) -> bool {
+        self.completed.contains(step_name) || self.failed.contains(step_name) || self.skipped.contains(step_name)
     }
 
-    /// Get steps organized by parallel groups
+    /// Decide whether a step runs, from its `when` condition or, without
+    /// one, from the status of its dependencies
+    pub fn decide(&self, step: &WorkflowStep, state: &WorkflowState) -> StepDecision {
+        if let Some(ref when) = step.when {
+            return match Condition::parse(when) {
+                Ok(condition) if condition.evaluate(&state.step_outputs()) => StepDecision::Run,
+                Ok(_) => StepDecision::Skip(format!("condition not met: {}", when)),
+                Err(e) => StepDecision::Fail(format!("{:#}", e)),
+            };
+        }
+
+        let unfinished = step.depends_on.iter().find_map(|dep| {
+            let status = state.steps.get(dep).map(|s| &s.status);
+            (status != Some(&StepStatus::Completed))
+                .then(|| (dep, status.map_or("pending", StepStatus::as_str)))
+        });
+        match (unfinished, step.on_dependency_failure) {
+            (None, _) => StepDecision::Run,
+            (Some((dep, status)), DependencyFailure::Skip) => {
+                StepDecision::Skip(format!("dependency '{}' {}", dep, status))
+            }
+            (Some((dep, status)), DependencyFailure::Fail) => {
+                StepDecision::Fail(format!("dependency '{}' {}", dep, status))
+            }
+        }
+    }
+
+    /// Get steps organized by parallel groups, leaving out failure handlers
     pub fn get_execution_groups(&self) -> Vec<Vec<&WorkflowStep>> {
         let mut groups: HashMap<u32, Vec<&WorkflowStep>> = HashMap::new();
 
         for step in &self.workflow.steps {
+            if self.workflow.is_failure_handler(&step.name) {
+                continue;
+            }
             groups.entry(step.parallel_group).or_default().push(step);
         }
 
@@ -274,6 +684,13 @@ impl DagScheduler {
     /// Mark a step as failed
     pub fn mark_failed(&mut self, step_name: &str) {
         self.running.remove(step_name);
+        self.failed.insert(step_name.to_string());
+    }
+
+    /// Mark a step as skipped
+    pub fn mark_skipped(&mut self, step_name: &str) {
+        self.running.remove(step_name);
+        self.skipped.insert(step_name.to_string());
     }
 
     /// Check if all steps are completed
@@ -353,6 +770,21 @@ pub struct WorkflowState {
     pub started_at: chrono::DateTime<chrono::Utc>,
     /// Updated at
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Integration branch, when steps run in worktrees
+    #[serde(default)]
+    pub git: Option<WorkflowGitState>,
+}
+
+/// Branches and pull requests of a workflow whose steps run in worktrees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowGitState {
+    /// Branch completed steps are merged into
+    pub integration_branch: String,
+    /// Branch the integration branch was created from
+    pub base_branch: String,
+    /// URLs of the pull requests opened for the workflow
+    #[serde(default)]
+    pub pull_requests: Vec<String>,
 }
 
 /// Status of a workflow
@@ -372,7 +804,7 @@ pub struct StepState {
     pub status: StepStatus,
     /// Model used
     pub model: String,
-    /// Cost for this step
+    /// Cost for this step, over all its attempts
     pub cost_usd: f64,
     /// Error message if failed
     pub error: Option<String>,
@@ -380,6 +812,18 @@ pub struct StepState {
     pub started_at: Option<chrono::DateTime<chrono::Utc>>,
     /// Completed at
     pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Outputs captured when the step completed
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    /// Number of times the step was started
+    #[serde(default)]
+    pub attempts: u32,
+    /// Time spent running the step, over all attempts
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// Branch of the step's latest attempt, when it runs in a worktree
+    #[serde(default)]
+    pub branch: Option<String>,
 }
 
 /// Status of a step
@@ -392,6 +836,19 @@ pub enum StepStatus {
     Skipped,
 }
 
+impl StepStatus {
+    /// Lowercase name, as used in `when` conditions
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
 impl WorkflowState {
     /// Create a new workflow state
     pub fn new(workflow_id: String, name: String, workflow_file: PathBuf, steps: &[WorkflowStep]) -> Self {
@@ -408,6 +865,10 @@ impl WorkflowState {
                         error: None,
                         started_at: None,
                         completed_at: None,
+                        outputs: HashMap::new(),
+                        attempts: 0,
+                        duration_ms: 0,
+                        branch: None,
                     },
                 )
             })
@@ -423,6 +884,7 @@ impl WorkflowState {
             total_cost_usd: 0.0,
             started_at: now,
             updated_at: now,
+            git: None,
         }
     }
 
@@ -439,10 +901,11 @@ impl WorkflowState {
     pub fn update_step(&mut self, step_name: &str, status: StepStatus, cost: f64, error: Option<String>) {
         if let Some(step) = self.steps.get_mut(step_name) {
             step.status = status.clone();
-            step.cost_usd = cost;
+            step.cost_usd += cost;
             step.error = error;
             if status == StepStatus::Running {
                 step.started_at = Some(chrono::Utc::now());
+                step.attempts += 1;
             } else if matches!(status, StepStatus::Completed | StepStatus::Failed) {
                 step.completed_at = Some(chrono::Utc::now());
             }
@@ -451,6 +914,47 @@ impl WorkflowState {
         }
     }
 
+    /// Add the run time of a step attempt
+    pub fn add_step_duration(&mut self, step_name: &str, duration_ms: u64) {
+        if let Some(step) = self.steps.get_mut(step_name) {
+            step.duration_ms += duration_ms;
+        }
+    }
+
+    /// Record the branch a step attempt runs on
+    pub fn set_step_branch(&mut self, step_name: &str, branch: &str) {
+        if let Some(step) = self.steps.get_mut(step_name) {
+            step.branch = Some(branch.to_string());
+        }
+    }
+
+    /// Record the outputs captured from a completed step
+    pub fn set_step_outputs(&mut self, step_name: &str, outputs: HashMap<String, String>) {
+        if let Some(step) = self.steps.get_mut(step_name) {
+            step.outputs = outputs;
+        }
+    }
+
+    /// Status, error and captured outputs of every step by step key, for
+    /// rendering prompts and evaluating conditions
+    pub fn step_outputs(&self) -> HashMap<String, HashMap<String, String>> {
+        self.steps
+            .iter()
+            .map(|(name, state)| {
+                let mut values = if state.status == StepStatus::Completed {
+                    state.outputs.clone()
+                } else {
+                    HashMap::new()
+                };
+                values.insert("status".to_string(), state.status.as_str().to_string());
+                if let Some(ref error) = state.error {
+                    values.insert("error".to_string(), error.clone());
+                }
+                (step_key(name), values)
+            })
+            .collect()
+    }
+
     /// Check if the workflow can be resumed
     pub fn can_resume(&self) -> bool {
         matches!(self.status, WorkflowStatus::Failed | WorkflowStatus::Cancelled)
@@ -612,6 +1116,7 @@ impl Default for WorkflowStateManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hooks::HookDefinition;
 
     const SAMPLE_WORKFLOW: &str = r#"
 name: "Full Stack Development"
@@ -774,6 +1279,27 @@ steps:
         assert_eq!(state.steps["Project Setup"].status, StepStatus::Completed);
     }
 
+    #[test]
+    fn test_retried_step_cost() {
+        let workflow = WorkflowDefinition::parse(SAMPLE_WORKFLOW).unwrap();
+        let mut state = WorkflowState::new(
+            "wf-retry".to_string(),
+            workflow.name.clone(),
+            PathBuf::from("test.yaml"),
+            &workflow.steps,
+        );
+
+        state.update_step("Backend API", StepStatus::Running, 0.0, None);
+        state.update_step("Backend API", StepStatus::Failed, 0.25, Some("Error".to_string()));
+        state.update_step("Backend API", StepStatus::Running, 0.0, None);
+        state.update_step("Backend API", StepStatus::Completed, 0.5, None);
+
+        let step = &state.steps["Backend API"];
+        assert_eq!(step.attempts, 2);
+        assert_eq!(step.cost_usd, 0.75);
+        assert_eq!(state.total_cost_usd, 0.75);
+    }
+
     #[test]
     fn test_get_step_model() {
         let workflow = WorkflowDefinition::parse(SAMPLE_WORKFLOW).unwrap();
@@ -787,6 +1313,56 @@ steps:
         assert_eq!(workflow.get_step_model(frontend_step), ModelAlias::Sonnet);
     }
 
+    #[test]
+    fn test_get_step_verification() {
+        let yaml = r#"
+name: "Verified"
+global:
+  verify: "cargo test"
+steps:
+  - name: "Lint"
+    prompt: "Fix lints"
+    verify: "cargo clippy -- -D warnings"
+  - name: "Build"
+    prompt: "Fix the build"
+"#;
+        let workflow = WorkflowDefinition::parse(yaml).unwrap();
+
+        let lint = workflow.get_step_verification(&workflow.steps[0]).unwrap();
+        assert_eq!(lint.command, "cargo clippy -- -D warnings");
+
+        // Falls back to the global command
+        let build = workflow.get_step_verification(&workflow.steps[1]).unwrap();
+        assert_eq!(build.command, "cargo test");
+
+        let workflow = WorkflowDefinition::parse(SAMPLE_WORKFLOW).unwrap();
+        assert!(workflow.get_step_verification(&workflow.steps[0]).is_none());
+    }
+
+    #[test]
+    fn test_step_model_policy() {
+        let yaml = r#"
+name: "Plan then build"
+steps:
+  - name: "Build"
+    prompt: "Implement the plan"
+    model: sonnet
+    model_policy:
+      rules:
+        - opus until 1
+        - model: haiku
+          from_iteration: 2
+"#;
+        let workflow = WorkflowDefinition::parse(yaml).unwrap();
+        let policy = workflow.steps[0].model_policy.as_ref().unwrap();
+        assert_eq!(policy.rules[0].model, ModelAlias::Opus);
+        assert_eq!(policy.rules[0].until_iteration, Some(1));
+        assert_eq!(policy.rules[1].from_iteration, Some(2));
+
+        let workflow = WorkflowDefinition::parse(SAMPLE_WORKFLOW).unwrap();
+        assert!(workflow.steps[0].model_policy.is_none());
+    }
+
     #[test]
     fn test_workflow_state_manager() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -869,4 +1445,309 @@ steps:
         assert_eq!(completed.len(), 1);
         assert!(completed.contains(&"Project Setup".to_string()));
     }
+
+    const OUTPUTS_WORKFLOW: &str = r#"
+name: "Outputs"
+steps:
+  - name: "Backend API"
+    prompt: "Implement REST API"
+    outputs:
+      port:
+        regex: "port (\\d+)"
+  - name: "Frontend UI"
+    prompt: "Create a frontend for the API on port {{ steps.backend_api.port }}"
+    depends_on: ["Backend API"]
+  - name: "Docs"
+    prompt: "Document the app: {{ steps.frontend_ui.output }} {{ steps.backend_api.output }}"
+    depends_on: ["Frontend UI"]
+"#;
+
+    #[test]
+    fn test_validate_output_references() {
+        let workflow = WorkflowDefinition::parse(OUTPUTS_WORKFLOW).unwrap();
+        assert!(workflow.validate().is_ok());
+
+        // Not a dependency
+        let yaml = OUTPUTS_WORKFLOW.replace("    depends_on: [\"Backend API\"]\n", "");
+        let err = WorkflowDefinition::parse(&yaml).unwrap().validate().unwrap_err();
+        assert!(err.to_string().contains("does not depend on"));
+
+        // Undeclared output
+        let yaml = OUTPUTS_WORKFLOW.replace("backend_api.port", "backend_api.url");
+        let err = WorkflowDefinition::parse(&yaml).unwrap().validate().unwrap_err();
+        assert!(err.to_string().contains("output 'url'"));
+
+        // Unknown step
+        let yaml = OUTPUTS_WORKFLOW.replace("steps.frontend_ui", "steps.mobile_app");
+        assert!(WorkflowDefinition::parse(&yaml).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_step_outputs_survive_resume() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = WorkflowStateManager::with_base_dir(temp_dir.path().to_path_buf());
+        let workflow = WorkflowDefinition::parse(OUTPUTS_WORKFLOW).unwrap();
+        let mut state = WorkflowState::new(
+            "wf-outputs".to_string(),
+            workflow.name.clone(),
+            PathBuf::from("outputs.yaml"),
+            &workflow.steps,
+        );
+
+        let outputs = capture_outputs(&workflow.steps[0], Some("Listening on port 8080"), temp_dir.path()).unwrap();
+        state.set_step_outputs("Backend API", outputs);
+        state.update_step("Backend API", StepStatus::Completed, 0.5, None);
+        state.update_step("Frontend UI", StepStatus::Failed, 0.2, Some("Error".to_string()));
+        manager.save(&state).unwrap();
+
+        let resumed = manager.load("wf-outputs").unwrap().unwrap();
+        let prompt = render_prompt(workflow.steps[1].prompt.as_ref().unwrap(), &resumed.step_outputs()).unwrap();
+        assert_eq!(prompt, "Create a frontend for the API on port 8080");
+
+        // Outputs of steps that did not complete are not available
+        assert!(render_prompt(workflow.steps[2].prompt.as_ref().unwrap(), &resumed.step_outputs()).is_err());
+    }
+
+    const BRANCHING_WORKFLOW: &str = r#"
+name: "Branching"
+steps:
+  - name: "Tests"
+    prompt: "Run the test suite"
+    retry:
+      attempts: 3
+      backoff_secs: 5
+      escalate: true
+    on_failure: "Report"
+    continue_on_error: true
+  - name: "Fix"
+    prompt: "Fix the failing tests: {{ steps.tests.error }}"
+    depends_on: ["Tests"]
+    when: "steps.tests.status == 'failed'"
+  - name: "Deploy"
+    prompt: "Deploy"
+    depends_on: ["Tests"]
+    parallel_group: 1
+  - name: "Announce"
+    prompt: "Announce the release"
+    depends_on: ["Deploy"]
+    on_dependency_failure: fail
+    parallel_group: 2
+  - name: "Report"
+    prompt: "Report the failure: {{ steps.tests.error }}"
+"#;
+
+    #[test]
+    fn test_parse_branching_workflow() {
+        let workflow = WorkflowDefinition::parse(BRANCHING_WORKFLOW).unwrap();
+        assert!(workflow.validate().unwrap().is_empty());
+
+        let tests = &workflow.steps[0];
+        let retry = tests.retry.as_ref().unwrap();
+        assert_eq!(retry.attempts, 3);
+        assert_eq!(tests.on_failure.as_deref(), Some("Report"));
+        assert!(tests.continue_on_error);
+        assert_eq!(workflow.steps[2].on_dependency_failure, DependencyFailure::Skip);
+        assert_eq!(workflow.steps[3].on_dependency_failure, DependencyFailure::Fail);
+
+        // Failure handlers only run when the step they handle fails
+        assert!(workflow.is_failure_handler("Report"));
+        let scheduler = DagScheduler::new(workflow.clone());
+        let groups = scheduler.get_execution_groups();
+        assert!(groups.iter().flatten().all(|step| step.name != "Report"));
+        assert!(scheduler.get_ready_steps().iter().all(|step| step.name != "Report"));
+    }
+
+    #[test]
+    fn test_validate_branching_errors() {
+        let invalid = |from: &str, to: &str| {
+            let yaml = BRANCHING_WORKFLOW.replace(from, to);
+            WorkflowDefinition::parse(&yaml).unwrap().validate().unwrap_err().to_string()
+        };
+
+        assert!(invalid("on_failure: \"Report\"", "on_failure: \"Notify\"").contains("unknown failure handler"));
+        assert!(invalid("on_failure: \"Report\"", "on_failure: \"Tests\"").contains("its own failure handler"));
+        assert!(invalid("depends_on: [\"Deploy\"]", "depends_on: [\"Report\"]").contains("depends on failure handler"));
+        assert!(invalid("attempts: 3", "attempts: 0").contains("at least 1"));
+        assert!(invalid("'failed'\"", "'failed\"").contains("invalid when condition"));
+        assert!(invalid("steps.tests.status ==", "steps.deploy.status ==").contains("does not depend on"));
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let retry = RetryPolicy { attempts: 3, backoff_secs: 5, escalate: true };
+        assert_eq!(retry.delay(1), std::time::Duration::from_secs(5));
+        assert_eq!(retry.delay(2), std::time::Duration::from_secs(10));
+        assert_eq!(retry.model_for(ModelAlias::Haiku, 1), ModelAlias::Haiku);
+        assert_eq!(retry.model_for(ModelAlias::Haiku, 2), ModelAlias::Sonnet);
+        assert_eq!(retry.model_for(ModelAlias::Haiku, 4), ModelAlias::Opus);
+
+        let retry = RetryPolicy { escalate: false, ..retry };
+        assert_eq!(retry.model_for(ModelAlias::Haiku, 3), ModelAlias::Haiku);
+    }
+
+    #[test]
+    fn test_decide_step() {
+        let workflow = WorkflowDefinition::parse(BRANCHING_WORKFLOW).unwrap();
+        let scheduler = DagScheduler::new(workflow.clone());
+        let step = |name: &str| workflow.get_step(name).unwrap();
+        let mut state = WorkflowState::new(
+            "wf-branch".to_string(),
+            workflow.name.clone(),
+            PathBuf::from("branching.yaml"),
+            &workflow.steps,
+        );
+
+        state.update_step("Tests", StepStatus::Completed, 0.5, None);
+        assert!(matches!(scheduler.decide(step("Fix"), &state), StepDecision::Skip(_)));
+        assert_eq!(scheduler.decide(step("Deploy"), &state), StepDecision::Run);
+
+        state.update_step("Tests", StepStatus::Failed, 0.5, Some("2 tests failed".to_string()));
+        assert_eq!(scheduler.decide(step("Fix"), &state), StepDecision::Run);
+        assert_eq!(
+            scheduler.decide(step("Deploy"), &state),
+            StepDecision::Skip("dependency 'Tests' failed".to_string())
+        );
+        assert_eq!(state.step_outputs()["tests"]["error"], "2 tests failed");
+
+        state.update_step("Deploy", StepStatus::Skipped, 0.0, None);
+        assert_eq!(
+            scheduler.decide(step("Announce"), &state),
+            StepDecision::Fail("dependency 'Deploy' skipped".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ready_steps_after_failure() {
+        let workflow = WorkflowDefinition::parse(BRANCHING_WORKFLOW).unwrap();
+        let mut scheduler = DagScheduler::new(workflow);
+        scheduler.mark_failed("Tests");
+
+        // Dependents of a failed step are ready to be decided
+        let ready: Vec<&str> = scheduler.get_ready_steps().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(ready, vec!["Fix", "Deploy"]);
+
+        scheduler.mark_skipped("Deploy");
+        let ready: Vec<&str> = scheduler.get_ready_steps().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(ready, vec!["Fix", "Announce"]);
+    }
+
+    #[test]
+    fn test_steps_that_can_overlap() {
+        let workflow = WorkflowDefinition::parse(BRANCHING_WORKFLOW).unwrap();
+        let step = |name: &str| workflow.get_step(name).unwrap();
+
+        // Steps in different groups still overlap when neither waits for the other
+        assert!(workflow.can_overlap(step("Fix"), step("Deploy")));
+        assert!(workflow.can_overlap(step("Fix"), step("Announce")));
+        assert!(workflow.can_overlap(step("Report"), step("Deploy")));
+        assert!(!workflow.can_overlap(step("Deploy"), step("Announce")));
+        assert!(!workflow.can_overlap(step("Report"), step("Tests")));
+        assert!(!workflow.can_overlap(step("Fix"), step("Fix")));
+        assert!(workflow.steps.iter().all(|other| !workflow.can_overlap(step("Tests"), other)));
+    }
+
+    #[test]
+    fn test_parse_git_settings() {
+        let yaml = r#"
+name: "Full Stack"
+global:
+  git:
+    worktrees: true
+    pull_requests: step
+steps:
+  - name: "Backend API"
+    prompt: "Implement REST API"
+    working_dir: backend
+  - name: "Docs"
+    prompt: "Write docs"
+    worktree: false
+"#;
+        let workflow = WorkflowDefinition::parse(yaml).unwrap();
+        assert!(workflow.validate().unwrap().is_empty());
+        assert_eq!(workflow.global.git.pull_requests, PullRequestMode::Step);
+        assert_eq!(workflow.integration_branch(), "workflow/full-stack");
+        assert!(workflow.uses_worktree(&workflow.steps[0]));
+        assert!(!workflow.uses_worktree(&workflow.steps[1]));
+        assert_eq!(workflow.steps[0].working_dir, Some(PathBuf::from("backend")));
+
+        // Off by default, and pull requests need worktree steps
+        let workflow = WorkflowDefinition::parse(&yaml.replace("worktrees: true", "branch_prefix: \"wf/\"")).unwrap();
+        assert!(!workflow.uses_worktrees());
+        assert_eq!(workflow.integration_branch(), "wf/full-stack");
+        assert_eq!(workflow.validate().unwrap(), vec!["pull_requests has no effect unless steps run in worktrees"]);
+
+        for dir in ["../frontend", "/srv/backend"] {
+            let workflow = WorkflowDefinition::parse(&yaml.replace("working_dir: backend", &format!("working_dir: {}", dir))).unwrap();
+            assert!(workflow.validate().unwrap_err().to_string().contains("relative path inside the working tree"));
+        }
+    }
+
+    #[test]
+    fn test_parse_step_overrides() {
+        let yaml = r#"
+name: "Review"
+global:
+  hooks:
+    pre_run: scripts/setup.sh
+    on_workflow_complete: scripts/announce.sh
+steps:
+  - name: "Audit"
+    prompt: "Audit the code"
+    readonly: true
+    allowed_tools: "Bash(cargo audit)"
+    system_prompt: prompts/auditor.md
+    hooks:
+      pre_run: scripts/audit-setup.sh
+      on_complete:
+        command: scripts/report.sh
+        timeout_secs: 10
+  - name: "Fix"
+    prompt: "Fix the findings"
+    yolo: false
+    depends_on: [Audit]
+"#;
+        let workflow = WorkflowDefinition::parse(yaml).unwrap();
+        assert!(workflow.validate().unwrap().is_empty());
+        let (audit, fix) = (&workflow.steps[0], &workflow.steps[1]);
+        assert!(audit.readonly && audit.yolo.is_none());
+        assert_eq!(audit.allowed_tools.as_deref(), Some("Bash(cargo audit)"));
+        assert_eq!(
+            audit.system_prompt,
+            Some(PathBuf::from("prompts/auditor.md"))
+        );
+        assert_eq!(fix.yolo, Some(false));
+
+        // doodoori.toml's hooks, then the global ones, then the step's
+        let base = HooksConfig::new()
+            .with_post_run(HookDefinition::new("scripts/post.sh"))
+            .with_pre_run(HookDefinition::new("scripts/base.sh"));
+        let hooks = workflow.get_step_hooks(audit, base.clone());
+        assert_eq!(hooks.pre_run.unwrap().command, "scripts/audit-setup.sh");
+        assert_eq!(hooks.on_complete.unwrap().timeout_secs, 10);
+        assert_eq!(hooks.post_run.unwrap().command, "scripts/post.sh");
+        let hooks = workflow.get_step_hooks(fix, base);
+        assert_eq!(hooks.pre_run.unwrap().command, "scripts/setup.sh");
+        assert!(hooks.on_workflow_complete.is_some());
+
+        let workflow = WorkflowDefinition::parse(
+            &yaml.replace("yolo: false", "yolo: true\n    readonly: true"),
+        )
+        .unwrap();
+        assert!(
+            workflow
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("both yolo and readonly")
+        );
+
+        let workflow = WorkflowDefinition::parse(
+            &yaml.replace("pre_run: scripts/audit", "on_step_complete: scripts/audit"),
+        )
+        .unwrap();
+        assert_eq!(
+            workflow.validate().unwrap(),
+            vec!["Step 'Audit' hooks on_step_complete have no effect outside global hooks"]
+        );
+    }
 }
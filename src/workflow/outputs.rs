@@ -0,0 +1,266 @@
+//! Named outputs passed between workflow steps
+//!
+//! A step declares outputs that are captured when it completes, and steps
+//! downstream of it reference them in their prompts as
+//! `{{ steps.<step>.<output> }}`. Every step also has the built-in `output`
+//! (its final output), `status` and `error`. Captured values are kept in the
+//! workflow state, so a resumed workflow renders prompts with the outputs of
+//! steps it skips.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use super::WorkflowStep;
+
+/// Name of the built-in output holding a step's final output
+pub const FINAL_OUTPUT: &str = "output";
+
+/// Values every step has besides its declared outputs
+const BUILTIN_FIELDS: [&str; 3] = [FINAL_OUTPUT, "status", "error"];
+
+/// Whether a field is available on every step without declaring it
+pub fn is_builtin(field: &str) -> bool {
+    BUILTIN_FIELDS.contains(&field)
+}
+
+/// `{{ steps.<step>.<output> }}`
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*steps\.([A-Za-z0-9_-]+)\.([A-Za-z0-9_-]+)\s*\}\}").expect("valid reference pattern")
+});
+
+/// How a declared step output is captured
+///
+/// Written as `output`, `{ file: <path> }` or `{ regex: <pattern> }`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawOutputSpec", into = "RawOutputSpec")]
+pub enum StepOutputSpec {
+    /// The step's final output
+    Output,
+    /// Contents of a file the step wrote, relative to its working directory
+    File(PathBuf),
+    /// First capture group (or the whole match) of a regex over the final output
+    Regex(String),
+}
+
+/// YAML form of [`StepOutputSpec`]
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum RawOutputSpec {
+    Name(String),
+    Source(OutputSource),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct OutputSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regex: Option<String>,
+}
+
+impl TryFrom<RawOutputSpec> for StepOutputSpec {
+    type Error = String;
+
+    fn try_from(raw: RawOutputSpec) -> std::result::Result<Self, Self::Error> {
+        match raw {
+            RawOutputSpec::Name(name) if name == FINAL_OUTPUT => Ok(Self::Output),
+            RawOutputSpec::Name(name) => Err(format!("unknown output source '{}' (expected 'output')", name)),
+            RawOutputSpec::Source(OutputSource { file: Some(path), regex: None }) => Ok(Self::File(path)),
+            RawOutputSpec::Source(OutputSource { file: None, regex: Some(pattern) }) => Ok(Self::Regex(pattern)),
+            RawOutputSpec::Source(_) => Err("an output needs exactly one of 'file' or 'regex'".to_string()),
+        }
+    }
+}
+
+impl From<StepOutputSpec> for RawOutputSpec {
+    fn from(spec: StepOutputSpec) -> Self {
+        match spec {
+            StepOutputSpec::Output => Self::Name(FINAL_OUTPUT.to_string()),
+            StepOutputSpec::File(path) => Self::Source(OutputSource { file: Some(path), regex: None }),
+            StepOutputSpec::Regex(pattern) => Self::Source(OutputSource { file: None, regex: Some(pattern) }),
+        }
+    }
+}
+
+impl StepOutputSpec {
+    /// Check the spec without running the step
+    pub fn validate(&self) -> Result<()> {
+        if let Self::Regex(pattern) = self {
+            Regex::new(pattern).with_context(|| format!("Invalid output regex: {}", pattern))?;
+        }
+        Ok(())
+    }
+
+    /// Capture the value from a completed step
+    pub fn capture(&self, final_output: Option<&str>, working_dir: &Path) -> Result<String> {
+        match self {
+            Self::Output => final_output
+                .map(|output| output.trim().to_string())
+                .context("Step produced no final output"),
+            Self::File(path) => {
+                let path = working_dir.join(path);
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read output file: {}", path.display()))?;
+                Ok(content.trim().to_string())
+            }
+            Self::Regex(pattern) => {
+                let re = Regex::new(pattern).with_context(|| format!("Invalid output regex: {}", pattern))?;
+                let captures = final_output
+                    .and_then(|output| re.captures(output))
+                    .with_context(|| format!("Final output does not match /{}/", pattern))?;
+                let value = captures.get(1).or_else(|| captures.get(0)).map_or("", |m| m.as_str());
+                Ok(value.trim().to_string())
+            }
+        }
+    }
+}
+
+/// Key a step is referenced by: its name lowercased, with anything other
+/// than letters and digits replaced by `_` ("Backend API" -> `backend_api`)
+pub fn step_key(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+/// `(step key, output name)` of every output referenced in a prompt
+pub fn references(prompt: &str) -> Vec<(String, String)> {
+    REFERENCE
+        .captures_iter(prompt)
+        .map(|c| (step_key(&c[1]), c[2].to_string()))
+        .collect()
+}
+
+/// Capture the built-in and declared outputs of a completed step
+pub fn capture_outputs(
+    step: &WorkflowStep,
+    final_output: Option<&str>,
+    working_dir: &Path,
+) -> Result<HashMap<String, String>> {
+    let mut outputs = HashMap::new();
+    if let Some(output) = final_output {
+        outputs.insert(FINAL_OUTPUT.to_string(), output.trim().to_string());
+    }
+    for (name, spec) in &step.outputs {
+        let value = spec
+            .capture(final_output, working_dir)
+            .with_context(|| format!("Failed to capture output '{}' of step '{}'", name, step.name))?;
+        outputs.insert(name.clone(), value);
+    }
+    Ok(outputs)
+}
+
+/// Substitute output references in a prompt
+///
+/// `outputs` maps step keys to the outputs captured from those steps.
+pub fn render_prompt(prompt: &str, outputs: &HashMap<String, HashMap<String, String>>) -> Result<String> {
+    let mut rendered = String::with_capacity(prompt.len());
+    let mut last = 0;
+    for captures in REFERENCE.captures_iter(prompt) {
+        let whole = captures.get(0).expect("match");
+        let value = outputs
+            .get(&step_key(&captures[1]))
+            .and_then(|step| step.get(&captures[2]))
+            .with_context(|| format!("Step output not available: steps.{}.{}", &captures[1], &captures[2]))?;
+        rendered.push_str(&prompt[last..whole.start()]);
+        rendered.push_str(value);
+        last = whole.end();
+    }
+    rendered.push_str(&prompt[last..]);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn step_with_outputs(yaml: &str) -> WorkflowStep {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_parse_output_specs() {
+        let step = step_with_outputs(
+            r#"
+name: "Backend API"
+prompt: "Implement REST API"
+outputs:
+  summary: output
+  schema:
+    file: docs/openapi.yaml
+  port:
+    regex: "listening on port (\\d+)"
+"#,
+        );
+        assert_eq!(step.outputs["summary"], StepOutputSpec::Output);
+        assert_eq!(step.outputs["schema"], StepOutputSpec::File(PathBuf::from("docs/openapi.yaml")));
+        assert_eq!(step.outputs["port"], StepOutputSpec::Regex("listening on port (\\d+)".to_string()));
+
+        // Round-trips through the same YAML form
+        let yaml = serde_yaml::to_string(&step).unwrap();
+        assert_eq!(serde_yaml::from_str::<WorkflowStep>(&yaml).unwrap().outputs, step.outputs);
+
+        let invalid = "name: A\noutputs:\n  both:\n    file: a.txt\n    regex: x\n";
+        assert!(serde_yaml::from_str::<WorkflowStep>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_capture_outputs() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/openapi.yaml"), "openapi: 3.0.0\n").unwrap();
+
+        let step = step_with_outputs(
+            r#"
+name: "Backend API"
+outputs:
+  schema:
+    file: docs/openapi.yaml
+  port:
+    regex: "listening on port (\\d+)"
+"#,
+        );
+        let outputs = capture_outputs(&step, Some("Server listening on port 8080\nCOMPLETE\n"), dir.path()).unwrap();
+        assert_eq!(outputs[FINAL_OUTPUT], "Server listening on port 8080\nCOMPLETE");
+        assert_eq!(outputs["schema"], "openapi: 3.0.0");
+        assert_eq!(outputs["port"], "8080");
+
+        let err = capture_outputs(&step, Some("COMPLETE"), dir.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("output 'port' of step 'Backend API'"));
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        assert_eq!(step_key("Backend API"), "backend_api");
+
+        let prompt = "Call the API on port {{ steps.backend_api.port }}.\nNotes: {{steps.Backend-API.output}}";
+        assert_eq!(
+            references(prompt),
+            vec![
+                ("backend_api".to_string(), "port".to_string()),
+                ("backend_api".to_string(), "output".to_string()),
+            ]
+        );
+
+        let mut outputs = HashMap::new();
+        outputs.insert(
+            "backend_api".to_string(),
+            HashMap::from([
+                ("port".to_string(), "8080".to_string()),
+                (FINAL_OUTPUT.to_string(), "Done".to_string()),
+            ]),
+        );
+        assert_eq!(
+            render_prompt(prompt, &outputs).unwrap(),
+            "Call the API on port 8080.\nNotes: Done"
+        );
+        assert_eq!(render_prompt("No references", &outputs).unwrap(), "No references");
+        assert!(render_prompt("{{ steps.frontend_ui.output }}", &outputs).is_err());
+    }
+}